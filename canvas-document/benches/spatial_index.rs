@@ -0,0 +1,60 @@
+//! Query cost on a large board, compared to scanning every element.
+//!
+//! Run with `cargo bench -p canvas-document`.
+
+use canvas_document::{point, Document, Rect, Shape, Style};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const ELEMENT_COUNT: usize = 100_000;
+
+/// Squares laid out on a 316 x 316 grid with 40 unit spacing.
+fn large_board() -> Document {
+    let mut document = Document::new();
+    let columns = (ELEMENT_COUNT as f32).sqrt().ceil() as usize;
+    for i in 0..ELEMENT_COUNT {
+        let origin = point((i % columns) as f32 * 40.0, (i / columns) as f32 * 40.0);
+        document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(origin, point(30.0, 30.0)),
+            },
+            Style::default(),
+        );
+    }
+    document
+}
+
+fn spatial_queries(c: &mut Criterion) {
+    let document = large_board();
+    // roughly what a 1200x800 window shows at zoom 1
+    let viewport = Rect::from_origin_size(point(5_000.0, 5_000.0), point(1_200.0, 800.0));
+    let click = point(5_015.0, 5_015.0);
+
+    let mut group = c.benchmark_group("100k elements");
+    group.bench_function("viewport query (index)", |b| {
+        b.iter(|| document.elements_intersecting(black_box(&viewport)).len())
+    });
+    group.bench_function("viewport query (linear scan)", |b| {
+        b.iter(|| {
+            document
+                .iter()
+                .filter(|e| e.bounds().intersects(black_box(&viewport)))
+                .count()
+        })
+    });
+    group.bench_function("click pick (index)", |b| {
+        b.iter(|| document.element_at(black_box(click), 2.0))
+    });
+    group.bench_function("click pick (linear scan)", |b| {
+        b.iter(|| {
+            document
+                .iter()
+                .rev()
+                .find(|e| e.hit_test(black_box(click), 2.0))
+                .map(|e| e.id)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, spatial_queries);
+criterion_main!(benches);
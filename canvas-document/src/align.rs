@@ -0,0 +1,268 @@
+//! Aligning, distributing and tidying up selected elements.
+//!
+//! The layout functions work on world space bounds and return how far to move
+//! each of them, so they can be used without a document. [`align`],
+//! [`distribute`] and [`tidy_up`] apply them to elements as one undo step.
+
+use crate::document::Document;
+use crate::element::ElementId;
+use crate::geometry::{point, Point, Rect};
+use crate::grid::GRID_SIZE;
+use crate::history::History;
+
+/// Gap between the cells of a tidied up grid, in world units.
+pub const TIDY_GAP: f32 = GRID_SIZE;
+
+/// Edge or center the elements are lined up on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    /// centers on one vertical line
+    Center,
+    Right,
+    Top,
+    /// centers on one horizontal line
+    Middle,
+    Bottom,
+}
+
+/// Direction elements are spread along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    Horizontal,
+    Vertical,
+}
+
+impl Distribution {
+    fn along(self, p: Point) -> f32 {
+        match self {
+            Distribution::Horizontal => p.x,
+            Distribution::Vertical => p.y,
+        }
+    }
+
+    fn offset(self, amount: f32) -> Point {
+        match self {
+            Distribution::Horizontal => point(amount, 0.0),
+            Distribution::Vertical => point(0.0, amount),
+        }
+    }
+}
+
+/// Moves lining every rect up with the matching edge or center of all of them together.
+pub fn align_offsets(rects: &[Rect], alignment: Alignment) -> Vec<Point> {
+    let Some(bounds) = rects.iter().copied().reduce(|a, b| a.union(&b)) else {
+        return Vec::new();
+    };
+    rects
+        .iter()
+        .map(|rect| match alignment {
+            Alignment::Left => point(bounds.min.x - rect.min.x, 0.0),
+            Alignment::Center => point(bounds.center().x - rect.center().x, 0.0),
+            Alignment::Right => point(bounds.max.x - rect.max.x, 0.0),
+            Alignment::Top => point(0.0, bounds.min.y - rect.min.y),
+            Alignment::Middle => point(0.0, bounds.center().y - rect.center().y),
+            Alignment::Bottom => point(0.0, bounds.max.y - rect.max.y),
+        })
+        .collect()
+}
+
+/// Moves leaving equal gaps between the rects, in the order of their centers.
+/// The first and the last rect stay where they are, fewer than three don't move.
+pub fn distribute_offsets(rects: &[Rect], distribution: Distribution) -> Vec<Point> {
+    let mut offsets = vec![Point::ZERO; rects.len()];
+    if rects.len() < 3 {
+        return offsets;
+    }
+    let along = |p: Point| distribution.along(p);
+    let size = |rect: &Rect| along(rect.size());
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by(|&a, &b| along(rects[a].center()).total_cmp(&along(rects[b].center())));
+
+    let first = &rects[order[0]];
+    let last = &rects[order[order.len() - 1]];
+    let span = along(last.max) - along(first.min);
+    let occupied: f32 = rects.iter().map(size).sum();
+    let gap = (span - occupied) / (rects.len() - 1) as f32;
+
+    let mut position = along(first.min);
+    for &index in &order {
+        offsets[index] = distribution.offset(position - along(rects[index].min));
+        position += size(&rects[index]) + gap;
+    }
+    offsets
+}
+
+/// Moves putting the rects into a grid of about as many columns as rows,
+/// `gap` apart, starting at the top left of their bounds. Rects keep their
+/// reading order, top to bottom and left to right, and sit centered in
+/// cells as large as the largest rect.
+pub fn tidy_offsets(rects: &[Rect], gap: f32) -> Vec<Point> {
+    let Some(bounds) = rects.iter().copied().reduce(|a, b| a.union(&b)) else {
+        return Vec::new();
+    };
+    let columns = (rects.len() as f32).sqrt().ceil() as usize;
+    let cell = rects
+        .iter()
+        .fold(Point::ZERO, |cell, rect| cell.max(rect.size()));
+
+    // rows by height first, then left to right within each row
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by(|&a, &b| rects[a].center().y.total_cmp(&rects[b].center().y));
+    for row in order.chunks_mut(columns) {
+        row.sort_by(|&a, &b| rects[a].center().x.total_cmp(&rects[b].center().x));
+    }
+
+    let mut offsets = vec![Point::ZERO; rects.len()];
+    for (slot, &index) in order.iter().enumerate() {
+        let (row, column) = (slot / columns, slot % columns);
+        let center = bounds.min
+            + point(
+                column as f32 * (cell.x + gap) + cell.x * 0.5,
+                row as f32 * (cell.y + gap) + cell.y * 0.5,
+            );
+        offsets[index] = center - rects[index].center();
+    }
+    offsets
+}
+
+/// Moves every element by its offset as one undo step.
+fn apply(
+    history: &mut History,
+    document: &mut Document,
+    ids: &[ElementId],
+    layout: impl FnOnce(&[Rect]) -> Vec<Point>,
+) {
+    let ids: Vec<ElementId> = ids.iter().copied().filter(|&id| document.get(id).is_some()).collect();
+    let rects: Vec<Rect> = ids.iter().map(|&id| document.get(id).unwrap().geometry_bounds()).collect();
+    history.step(document, |history, document| {
+        for (id, offset) in ids.into_iter().zip(layout(&rects)) {
+            if offset != Point::ZERO {
+                history.translate(document, &[id], offset);
+            }
+        }
+    });
+}
+
+/// Lines the elements up, see [`align_offsets`].
+pub fn align(history: &mut History, document: &mut Document, ids: &[ElementId], alignment: Alignment) {
+    apply(history, document, ids, |rects| align_offsets(rects, alignment));
+}
+
+/// Spreads the elements out evenly, see [`distribute_offsets`].
+pub fn distribute(history: &mut History, document: &mut Document, ids: &[ElementId], distribution: Distribution) {
+    apply(history, document, ids, |rects| distribute_offsets(rects, distribution));
+}
+
+/// Puts the elements into a grid, see [`tidy_offsets`].
+pub fn tidy_up(history: &mut History, document: &mut Document, ids: &[ElementId]) {
+    apply(history, document, ids, |rects| tidy_offsets(rects, TIDY_GAP));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::Shape;
+    use crate::style::Style;
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect::from_origin_size(point(x, y), point(width, height))
+    }
+
+    fn moved(rects: &[Rect], offsets: &[Point]) -> Vec<Rect> {
+        rects.iter().zip(offsets).map(|(rect, &offset)| rect.translate(offset)).collect()
+    }
+
+    #[test]
+    fn align_lines_up_edges_and_centers() {
+        let rects = [rect(10.0, 0.0, 20.0, 10.0), rect(0.0, 30.0, 60.0, 20.0)];
+
+        let left = moved(&rects, &align_offsets(&rects, Alignment::Left));
+        assert_eq!((left[0].min.x, left[1].min.x), (0.0, 0.0));
+        let right = moved(&rects, &align_offsets(&rects, Alignment::Right));
+        assert_eq!((right[0].max.x, right[1].max.x), (60.0, 60.0));
+        let center = moved(&rects, &align_offsets(&rects, Alignment::Center));
+        assert_eq!((center[0].center().x, center[1].center().x), (30.0, 30.0));
+        // aligning horizontally leaves the vertical position alone
+        assert_eq!(center[0].min.y, 0.0);
+
+        let top = moved(&rects, &align_offsets(&rects, Alignment::Top));
+        assert_eq!((top[0].min.y, top[1].min.y), (0.0, 0.0));
+        let middle = moved(&rects, &align_offsets(&rects, Alignment::Middle));
+        assert_eq!((middle[0].center().y, middle[1].center().y), (25.0, 25.0));
+        let bottom = moved(&rects, &align_offsets(&rects, Alignment::Bottom));
+        assert_eq!((bottom[0].max.y, bottom[1].max.y), (50.0, 50.0));
+
+        assert!(align_offsets(&[], Alignment::Left).is_empty());
+    }
+
+    #[test]
+    fn distribute_leaves_equal_gaps() {
+        // out of order, with different widths
+        let rects = [
+            rect(100.0, 0.0, 20.0, 10.0),
+            rect(0.0, 5.0, 10.0, 10.0),
+            rect(30.0, 0.0, 40.0, 10.0),
+        ];
+        let spread = moved(&rects, &distribute_offsets(&rects, Distribution::Horizontal));
+        // the outer ones stay, 120 wide with 70 taken leaves two gaps of 25
+        assert_eq!(spread[1], rects[1]);
+        assert_eq!(spread[0], rects[0]);
+        assert_eq!(spread[2].min.x, 35.0);
+        assert_eq!(spread[2].min.y, 0.0);
+
+        let rects = [rect(0.0, 0.0, 10.0, 10.0), rect(0.0, 12.0, 10.0, 30.0), rect(0.0, 90.0, 10.0, 10.0)];
+        let spread = moved(&rects, &distribute_offsets(&rects, Distribution::Vertical));
+        assert_eq!(spread[1].min.y - spread[0].max.y, spread[2].min.y - spread[1].max.y);
+
+        // two elements have nothing to distribute
+        let pair = [rect(0.0, 0.0, 10.0, 10.0), rect(50.0, 0.0, 10.0, 10.0)];
+        assert_eq!(distribute_offsets(&pair, Distribution::Horizontal), vec![Point::ZERO; 2]);
+    }
+
+    #[test]
+    fn tidy_up_fills_a_grid_in_reading_order() {
+        let rects = [
+            rect(200.0, 0.0, 10.0, 10.0),
+            rect(0.0, 0.0, 20.0, 20.0),
+            rect(50.0, 300.0, 10.0, 40.0),
+            rect(100.0, 150.0, 10.0, 10.0),
+            rect(300.0, 160.0, 10.0, 10.0),
+        ];
+        let tidy = moved(&rects, &tidy_offsets(&rects, 10.0));
+        // three columns of cells 20 wide and 40 high, starting at the top left
+        assert_eq!(tidy[1].center(), point(10.0, 20.0));
+        assert_eq!(tidy[3].center(), point(40.0, 20.0));
+        assert_eq!(tidy[0].center(), point(70.0, 20.0));
+        assert_eq!(tidy[2].center(), point(10.0, 70.0));
+        assert_eq!(tidy[4].center(), point(40.0, 70.0));
+    }
+
+    #[test]
+    fn layout_commands_undo_in_one_step() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let ids: Vec<ElementId> = [0.0, 30.0, 100.0]
+            .into_iter()
+            .map(|x| {
+                document.add(
+                    Shape::Rectangle {
+                        rect: rect(x, x, 10.0, 10.0),
+                    },
+                    Style::default(),
+                )
+            })
+            .collect();
+        let before = document.clone();
+
+        align(&mut history, &mut document, &ids, Alignment::Top);
+        assert!(ids.iter().all(|&id| document.get(id).unwrap().geometry_bounds().min.y == 0.0));
+        distribute(&mut history, &mut document, &ids, Distribution::Horizontal);
+        assert_eq!(document.get(ids[1]).unwrap().geometry_bounds().min.x, 50.0);
+
+        assert!(history.undo(&mut document));
+        assert!(history.undo(&mut document));
+        assert_eq!(document, before);
+        assert!(!history.can_undo());
+    }
+}
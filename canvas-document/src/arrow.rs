@@ -0,0 +1,154 @@
+//! Arrowheads at the ends of lines.
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{point, Point};
+
+/// Length of a head along the line, relative to the stroke width.
+const HEAD_LENGTH_PER_WIDTH: f32 = 4.0;
+/// Heads of thin lines are still this long, in world units.
+const MIN_HEAD_LENGTH: f32 = 10.0;
+/// Angle between the line and each side of a head, 30 degrees.
+const HEAD_HALF_ANGLE: f32 = std::f32::consts::FRAC_PI_6;
+
+/// How one end of a line is decorated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Arrowhead {
+    #[default]
+    None,
+    /// Filled triangle.
+    Triangle,
+    /// Two strokes in a V.
+    Open,
+}
+
+impl Arrowhead {
+    pub fn is_none(&self) -> bool {
+        *self == Arrowhead::None
+    }
+
+    /// The next kind of head, for cycling through them with one key.
+    pub fn next(self) -> Arrowhead {
+        match self {
+            Arrowhead::None => Arrowhead::Triangle,
+            Arrowhead::Triangle => Arrowhead::Open,
+            Arrowhead::Open => Arrowhead::None,
+        }
+    }
+}
+
+/// Outline of one head, `filled` heads are closed polygons, open ones are
+/// polylines through the two barbs and the tip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadOutline {
+    pub points: Vec<Point>,
+    pub filled: bool,
+}
+
+/// What to paint for a line with heads: the shaft, shortened so round caps
+/// don't poke through filled tips, and the heads themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineGeometry {
+    pub start: Point,
+    pub end: Point,
+    pub heads: Vec<HeadOutline>,
+}
+
+pub fn head_length(stroke_width: f32) -> f32 {
+    (stroke_width * HEAD_LENGTH_PER_WIDTH).max(MIN_HEAD_LENGTH)
+}
+
+/// Head of the given kind with its tip at `tip`, pointing away from `from`.
+pub fn head_outline(head: Arrowhead, from: Point, tip: Point, stroke_width: f32) -> Option<HeadOutline> {
+    let d = tip - from;
+    let length = d.length();
+    if head.is_none() || length == 0.0 {
+        return None;
+    }
+    let back = d * (-1.0 / length) * head_length(stroke_width);
+    let (sin, cos) = HEAD_HALF_ANGLE.sin_cos();
+    let rotate = |v: Point, sin: f32| point(v.x * cos - v.y * sin, v.x * sin + v.y * cos);
+    let left = tip + rotate(back, sin);
+    let right = tip + rotate(back, -sin);
+    Some(match head {
+        Arrowhead::Triangle => HeadOutline {
+            points: vec![tip, left, right],
+            filled: true,
+        },
+        _ => HeadOutline {
+            points: vec![left, tip, right],
+            filled: false,
+        },
+    })
+}
+
+pub fn line_geometry(
+    start: Point,
+    end: Point,
+    start_head: Arrowhead,
+    end_head: Arrowhead,
+    stroke_width: f32,
+) -> LineGeometry {
+    let heads: Vec<HeadOutline> = [
+        head_outline(start_head, end, start, stroke_width),
+        head_outline(end_head, start, end, stroke_width),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    // a filled head covers the shaft up to the middle of its base
+    let inset = |head: Arrowhead, from: Point, tip: Point| {
+        let length = from.distance(tip);
+        if head != Arrowhead::Triangle || length == 0.0 {
+            return tip;
+        }
+        let depth = (head_length(stroke_width) * HEAD_HALF_ANGLE.cos()).min(length * 0.5);
+        tip.lerp(from, depth / length)
+    };
+    LineGeometry {
+        start: inset(start_head, end, start),
+        end: inset(end_head, start, end),
+        heads,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangle_heads_point_along_the_line() {
+        let geometry = line_geometry(point(0.0, 0.0), point(100.0, 0.0), Arrowhead::None, Arrowhead::Triangle, 2.0);
+        let [head] = geometry.heads.as_slice() else {
+            panic!("expected one head, got {:?}", geometry.heads);
+        };
+        assert!(head.filled);
+        assert_eq!(head.points[0], point(100.0, 0.0));
+        // barbs are behind the tip, one on each side
+        assert!(head.points[1].x < 100.0 && head.points[2].x < 100.0);
+        assert!((head.points[1].y + head.points[2].y).abs() < 1e-4);
+        assert!(geometry.end.x < 100.0);
+        assert_eq!(geometry.start, point(0.0, 0.0));
+    }
+
+    #[test]
+    fn open_heads_keep_the_shaft() {
+        let geometry = line_geometry(point(0.0, 0.0), point(0.0, 50.0), Arrowhead::Open, Arrowhead::Open, 2.0);
+        assert_eq!(geometry.heads.len(), 2);
+        assert!(geometry.heads.iter().all(|head| !head.filled && head.points[1] != point(0.0, 25.0)));
+        assert_eq!((geometry.start, geometry.end), (point(0.0, 0.0), point(0.0, 50.0)));
+        assert_eq!(geometry.heads[0].points[1], point(0.0, 0.0));
+    }
+
+    #[test]
+    fn degenerate_lines_have_no_heads() {
+        let p = point(3.0, 3.0);
+        assert!(line_geometry(p, p, Arrowhead::Triangle, Arrowhead::Open, 2.0).heads.is_empty());
+    }
+
+    #[test]
+    fn heads_cycle_through_every_kind() {
+        assert_eq!(Arrowhead::None.next().next().next(), Arrowhead::None);
+    }
+}
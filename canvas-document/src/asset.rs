@@ -0,0 +1,185 @@
+//! Files elements refer to, like the pictures of image elements, kept once
+//! per content and saved inside the canvas file.
+//!
+//! Assets are addressed by the SHA-256 of their bytes, so placing the same
+//! picture twice stores it once and ids stay valid across save and load.
+//! They are never removed while the canvas is open, undo can bring back an
+//! element that needs them, but only the ones still in use are saved.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+use crate::decode::{decode_image, DecodeError, ImageFormat};
+use crate::mipmap::MipChain;
+
+/// Hex SHA-256 of the asset's bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AssetId(String);
+
+impl AssetId {
+    pub fn of(bytes: &[u8]) -> Self {
+        let hash = Sha256::digest(bytes);
+        AssetId(hash.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The bytes of a file, saved as base64. Pictures are decoded the first
+/// time they are drawn and then kept with their mipmaps, clones share them.
+#[derive(Clone)]
+struct Asset {
+    bytes: Arc<[u8]>,
+    image: Arc<OnceLock<Option<MipChain>>>,
+}
+
+impl Asset {
+    fn new(bytes: Vec<u8>) -> Self {
+        Asset {
+            bytes: bytes.into(),
+            image: Arc::default(),
+        }
+    }
+}
+
+impl fmt::Debug for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Asset({} bytes)", self.bytes.len())
+    }
+}
+
+impl PartialEq for Asset {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Serialize for Asset {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(&self.bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for Asset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64.decode(encoded).map(Asset::new).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Every asset of a document by id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AssetStore {
+    assets: BTreeMap<AssetId, Asset>,
+}
+
+impl AssetStore {
+    /// Stores `bytes` unless the same bytes are stored already.
+    pub fn insert(&mut self, bytes: Vec<u8>) -> AssetId {
+        let id = AssetId::of(&bytes);
+        self.assets.entry(id.clone()).or_insert_with(|| Asset::new(bytes));
+        id
+    }
+
+    /// Stores the PNG or JPEG file `bytes`, failing when it can't be decoded.
+    pub fn insert_image(&mut self, bytes: Vec<u8>) -> Result<AssetId, DecodeError> {
+        let id = AssetId::of(&bytes);
+        if self.image(&id).is_none() {
+            let image = MipChain::new(decode_image(&bytes)?);
+            let asset = Asset::new(bytes);
+            let _ = asset.image.set(Some(image));
+            self.assets.insert(id.clone(), asset);
+        }
+        Ok(id)
+    }
+
+    pub fn get(&self, id: &AssetId) -> Option<&[u8]> {
+        self.assets.get(id).map(|asset| &*asset.bytes)
+    }
+
+    pub fn contains(&self, id: &AssetId) -> bool {
+        self.assets.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// The stored assets among `ids`, sharing their bytes with this store.
+    pub fn subset<'a>(&self, ids: impl IntoIterator<Item = &'a AssetId>) -> AssetStore {
+        let assets = ids
+            .into_iter()
+            .filter_map(|id| Some((id.clone(), self.assets.get(id)?.clone())))
+            .collect();
+        AssetStore { assets }
+    }
+
+    /// The asset decoded as a picture with its mipmaps, `None` when it is
+    /// missing or not a PNG or JPEG file.
+    pub fn image(&self, id: &AssetId) -> Option<&MipChain> {
+        let asset = self.assets.get(id)?;
+        asset
+            .image
+            .get_or_init(|| decode_image(&asset.bytes).ok().map(MipChain::new))
+            .as_ref()
+    }
+
+    pub fn format(&self, id: &AssetId) -> Option<ImageFormat> {
+        self.get(id).and_then(ImageFormat::sniff)
+    }
+
+    /// The asset as a `data:` URL, for SVG exports.
+    pub fn data_url(&self, id: &AssetId) -> Option<String> {
+        let bytes = self.get(id)?;
+        let format = ImageFormat::sniff(bytes)?;
+        Some(format!("data:{};base64,{}", format.mime_type(), BASE64.encode(bytes)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_bytes_are_stored_once() {
+        let mut store = AssetStore::default();
+        let a = store.insert(b"one".to_vec());
+        let b = store.insert(b"two".to_vec());
+        assert_eq!(store.insert(b"one".to_vec()), a);
+        assert_ne!(a, b);
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(&a), Some(&b"one"[..]));
+        assert_eq!(a.as_str(), "7692c3ad3540bb803c020b3aee66cd8887123234ea0c6e7143c0add73ff431ed");
+    }
+
+    #[test]
+    fn round_trips_as_base64() {
+        let mut store = AssetStore::default();
+        let id = store.insert(vec![0, 1, 2, 250]);
+        let json = serde_json::to_string(&store).unwrap();
+        assert_eq!(json, format!(r#"{{"{}":"AAEC+g=="}}"#, id.as_str()));
+        assert_eq!(serde_json::from_str::<AssetStore>(&json).unwrap(), store);
+    }
+
+    #[test]
+    fn undecodable_assets_have_no_image() {
+        let mut store = AssetStore::default();
+        let id = store.insert(b"not a picture".to_vec());
+        assert!(store.image(&id).is_none());
+        assert!(store.data_url(&id).is_none());
+    }
+}
@@ -0,0 +1,824 @@
+//! Connectors, lines whose ends stay attached to the elements they link.
+//!
+//! A connector keeps what its ends are bound to next to the routed points.
+//! [`Document`] routes a connector again whenever it, or an element one of
+//! its ends is bound to, changes. Moving, resizing or turning a box drags
+//! its connectors along, and undoing it brings them back.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::arrow::{line_geometry, Arrowhead, HeadOutline};
+use crate::document::Document;
+use crate::element::{Element, ElementId, Shape};
+use crate::geometry::{point, Point, Rect};
+use crate::render::Path;
+
+/// Room elbow routes leave around elements, also the length of the piece
+/// leaving a bound element straight out of its side.
+pub const ROUTE_MARGIN: f32 = 20.0;
+/// Extra length an elbow route accepts to save a bend.
+const BEND_COST: f32 = 2.0 * ROUTE_MARGIN;
+/// How many times as much a stretch of elbow route costs inside the margin
+/// of an element, where it only goes when elements stand too close.
+const CROWDED_COST: f32 = 4.0;
+/// How far around its ends an elbow route looks for elements to avoid.
+const SEARCH_MARGIN: f32 = 10.0 * ROUTE_MARGIN;
+/// How far the control points of a curve reach out, relative to the distance between its ends.
+const CURVE_REACH: f32 = 0.4;
+/// Grid lines closer than this are the same line.
+const EPSILON: f32 = 1e-3;
+
+/// Anchors offered for binding, the middle of each side in the element's
+/// own bounds: top, right, bottom, left.
+pub const SIDE_ANCHORS: [Point; 4] = [point(0.5, 0.0), point(1.0, 0.5), point(0.5, 1.0), point(0.0, 0.5)];
+
+/// The axis directions an elbow route moves in.
+const DIRECTIONS: [Point; 4] = [point(1.0, 0.0), point(-1.0, 0.0), point(0.0, 1.0), point(0.0, -1.0)];
+
+/// How a connector gets from one end to the other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Routing {
+    #[default]
+    Straight,
+    /// One cubic Bézier leaving and entering the bound elements head on.
+    Curved,
+    /// Horizontal and vertical pieces going around other elements.
+    Elbow,
+}
+
+impl Routing {
+    pub const ALL: [Routing; 3] = [Routing::Straight, Routing::Curved, Routing::Elbow];
+
+    pub fn is_default(&self) -> bool {
+        *self == Routing::default()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Routing::Straight => "Straight",
+            Routing::Curved => "Curved",
+            Routing::Elbow => "Elbow",
+        }
+    }
+}
+
+/// Where on an element a connector end attaches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    /// Wherever the outline faces the other end, moving as the elements do.
+    #[default]
+    Auto,
+    /// A point in the element's own bounds, from (0, 0) at the top left to
+    /// (1, 1) at the bottom right, so it follows resizing and rotation.
+    Fixed(Point),
+}
+
+impl Anchor {
+    pub fn is_auto(&self) -> bool {
+        *self == Anchor::Auto
+    }
+}
+
+/// What one end of a connector is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    pub element: ElementId,
+    #[serde(default, skip_serializing_if = "Anchor::is_auto")]
+    pub anchor: Anchor,
+}
+
+impl Binding {
+    pub fn new(element: ElementId, anchor: Anchor) -> Self {
+        Binding { element, anchor }
+    }
+}
+
+/// One end of a connector, ready for routing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct End {
+    point: Point,
+    /// Unit vector pointing out of the bound element's side, `None` for free ends.
+    direction: Option<Point>,
+    /// World bounds of the bound element.
+    bounds: Option<Rect>,
+}
+
+impl End {
+    fn free(point: Point) -> Self {
+        End {
+            point,
+            direction: None,
+            bounds: None,
+        }
+    }
+
+    /// Which way the connector leaves this end, a free end heads for `other`.
+    fn direction_to(&self, other: &End) -> Point {
+        self.direction.unwrap_or_else(|| unit(other.point - self.point))
+    }
+}
+
+fn unit(v: Point) -> Point {
+    let length = v.length();
+    if length > 0.0 {
+        v / length
+    } else {
+        point(1.0, 0.0)
+    }
+}
+
+/// `v` turned into the nearest of the four axis directions.
+fn axis_direction(v: Point) -> Point {
+    if v.x.abs() >= v.y.abs() {
+        point(v.x.signum(), 0.0)
+    } else {
+        point(0.0, v.y.signum())
+    }
+}
+
+/// World position of a point given relative to the element's own bounds.
+pub fn anchor_position(element: &Element, relative: Point) -> Point {
+    let rect = element.shape.bounds();
+    element
+        .transform
+        .apply(rect.min + point(relative.x * rect.width(), relative.y * rect.height()))
+}
+
+/// `p` relative to `rect`, the middle for sides of no length.
+fn relative_position(rect: &Rect, p: Point) -> Point {
+    let along = |offset: f32, size: f32| if size > 0.0 { offset / size } else { 0.5 };
+    point(along(p.x - rect.min.x, rect.width()), along(p.y - rect.min.y, rect.height()))
+}
+
+/// Outward normal of the side of the unit square closest to `relative`.
+fn side_normal(relative: Point) -> Point {
+    let sides = [
+        (relative.x, point(-1.0, 0.0)),
+        (1.0 - relative.x, point(1.0, 0.0)),
+        (relative.y, point(0.0, -1.0)),
+        (1.0 - relative.y, point(0.0, 1.0)),
+    ];
+    sides
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, normal)| normal)
+        .unwrap_or(point(1.0, 0.0))
+}
+
+/// Where the outline of `shape` meets the line from its middle to `target`,
+/// both in the shape's own coordinates.
+fn outline_toward(shape: &Shape, target: Point) -> Point {
+    let rect = shape.bounds();
+    let center = rect.center();
+    let half = rect.size() * 0.5;
+    let d = target - center;
+    if d.length() <= EPSILON {
+        return point(rect.max.x, center.y);
+    }
+    let t = match shape {
+        Shape::Ellipse { .. } if half.x > 0.0 && half.y > 0.0 => {
+            1.0 / ((d.x / half.x).powi(2) + (d.y / half.y).powi(2)).sqrt()
+        }
+        _ => {
+            let reach = |half: f32, d: f32| if d != 0.0 { half / d.abs() } else { f32::INFINITY };
+            reach(half.x, d.x).min(reach(half.y, d.y))
+        }
+    };
+    center + d * t
+}
+
+/// The end of a connector bound to `element`, with the other end at `toward`.
+fn bound_end(element: &Element, anchor: Anchor, toward: Point, routing: Routing) -> End {
+    let rect = element.shape.bounds();
+    let target = match element.transform.inverse() {
+        Some(inverse) => inverse.apply(toward),
+        None => toward,
+    };
+    let relative = match anchor {
+        Anchor::Fixed(relative) => relative,
+        // elbows leave from the middle of the side facing the other end
+        Anchor::Auto if routing == Routing::Elbow => {
+            let d = relative_position(&rect, target) - point(0.5, 0.5);
+            SIDE_ANCHORS[match d.x.abs() >= d.y.abs() {
+                true if d.x >= 0.0 => 1,
+                true => 3,
+                false if d.y >= 0.0 => 2,
+                false => 0,
+            }]
+        }
+        Anchor::Auto => relative_position(&rect, outline_toward(&element.shape, target)),
+    };
+    End {
+        point: anchor_position(element, relative),
+        direction: Some(unit(element.transform.apply_vector(side_normal(relative)))),
+        bounds: Some(element.geometry_bounds()),
+    }
+}
+
+/// Points from `start` to `end` for the given routing, going around
+/// `obstacles` when routing elbows. Curves are the start, two control points
+/// and the end.
+fn route(start: &End, end: &End, routing: Routing, obstacles: &[Rect]) -> Vec<Point> {
+    match routing {
+        Routing::Straight => vec![start.point, end.point],
+        Routing::Curved => {
+            let reach = start.point.distance(end.point) * CURVE_REACH;
+            vec![
+                start.point,
+                start.point + start.direction_to(end) * reach,
+                end.point + end.direction_to(start) * reach,
+                end.point,
+            ]
+        }
+        Routing::Elbow => elbow_route(start, end, obstacles),
+    }
+}
+
+/// Where a route leaving `end` in `direction` clears the margin around its element.
+fn stub(end: &End, direction: Point) -> Point {
+    let Some(bounds) = end.bounds else {
+        return end.point;
+    };
+    let clear = bounds.inflate(ROUTE_MARGIN);
+    match direction {
+        d if d.x > 0.0 => point(clear.max.x, end.point.y),
+        d if d.x < 0.0 => point(clear.min.x, end.point.y),
+        d if d.y > 0.0 => point(end.point.x, clear.max.y),
+        _ => point(end.point.x, clear.min.y),
+    }
+}
+
+fn elbow_route(start: &End, end: &End, obstacles: &[Rect]) -> Vec<Point> {
+    let leave = axis_direction(start.direction_to(end));
+    let enter = -axis_direction(end.direction_to(start));
+    let from = stub(start, leave);
+    let to = stub(end, -enter);
+    let middle = search_path(from, leave, to, enter, obstacles).unwrap_or_else(|| {
+        // boxed in, cross halfway between the ends
+        if leave.x != 0.0 {
+            let x = (from.x + to.x) * 0.5;
+            vec![from, point(x, from.y), point(x, to.y), to]
+        } else {
+            let y = (from.y + to.y) * 0.5;
+            vec![from, point(from.x, y), point(to.x, y), to]
+        }
+    });
+    let mut points = vec![start.point];
+    points.extend(middle);
+    points.push(end.point);
+    simplify(points)
+}
+
+/// Sorted coordinates with the near duplicates merged.
+fn grid_lines(mut values: Vec<f32>) -> Vec<f32> {
+    values.sort_by(f32::total_cmp);
+    values.dedup_by(|a, b| (*a - *b).abs() <= EPSILON);
+    values
+}
+
+fn strictly_inside(rect: &Rect, p: Point) -> bool {
+    p.x > rect.min.x + EPSILON && p.x < rect.max.x - EPSILON && p.y > rect.min.y + EPSILON && p.y < rect.max.y - EPSILON
+}
+
+/// A search state waiting in the queue, cheapest estimate first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Visit {
+    estimate: f32,
+    state: usize,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Cheapest horizontal and vertical path from `from` to `to` staying out of
+/// `obstacles`, leaving in direction `leave` and arriving in direction `enter`.
+///
+/// The path runs along the lines through both points and the edges of every
+/// obstacle and of its margin, which is enough to find the shortest way
+/// around them. An A* search over that grid counts every turn as
+/// [`BEND_COST`] of extra length, so among routes of about the same length
+/// the one with fewer bends wins.
+fn search_path(from: Point, leave: Point, to: Point, enter: Point, obstacles: &[Rect]) -> Option<Vec<Point>> {
+    let margins: Vec<Rect> = obstacles.iter().map(|rect| rect.inflate(ROUTE_MARGIN)).collect();
+    let mut xs = vec![from.x, to.x, (from.x + to.x) * 0.5];
+    let mut ys = vec![from.y, to.y, (from.y + to.y) * 0.5];
+    for rect in obstacles.iter().chain(&margins) {
+        xs.extend([rect.min.x, rect.max.x]);
+        ys.extend([rect.min.y, rect.max.y]);
+    }
+    let (xs, ys) = (grid_lines(xs), grid_lines(ys));
+    let find = |lines: &[f32], value: f32| lines.iter().position(|&line| (line - value).abs() <= EPSILON);
+    let node_at = |p: Point| Some(find(&ys, p.y)? * xs.len() + find(&xs, p.x)?);
+    let position = |node: usize| point(xs[node % xs.len()], ys[node / xs.len()]);
+    let open: Vec<bool> = (0..xs.len() * ys.len())
+        .map(|node| !obstacles.iter().any(|rect| strictly_inside(rect, position(node))))
+        .collect();
+    let (start, goal) = (node_at(from)?, node_at(to)?);
+    if !open[start] || !open[goal] {
+        return None;
+    }
+    let direction_index = |d: Point| DIRECTIONS.iter().position(|&candidate| candidate == d).unwrap_or(0);
+    let enter = direction_index(enter);
+    // the neighbouring grid node in a direction, if there is one
+    let step = |node: usize, direction: usize| {
+        let (column, row) = (node % xs.len(), node / xs.len());
+        let (column, row) = match direction {
+            0 => (column + 1, row),
+            1 => (column.checked_sub(1)?, row),
+            2 => (column, row + 1),
+            _ => (column, row.checked_sub(1)?),
+        };
+        (column < xs.len() && row < ys.len()).then_some(row * xs.len() + column)
+    };
+    let heuristic = |node: usize| {
+        let d = position(node) - to;
+        d.x.abs() + d.y.abs()
+    };
+
+    // a state is a node and the direction the path arrived in
+    let mut cost = vec![f32::INFINITY; open.len() * DIRECTIONS.len()];
+    let mut previous = vec![usize::MAX; cost.len()];
+    let mut queue = BinaryHeap::new();
+    let first = start * DIRECTIONS.len() + direction_index(leave);
+    cost[first] = 0.0;
+    queue.push(Visit {
+        estimate: heuristic(start),
+        state: first,
+    });
+    let mut best: Option<(f32, usize)> = None;
+    while let Some(Visit { estimate, state }) = queue.pop() {
+        if best.is_some_and(|(total, _)| estimate >= total) {
+            break;
+        }
+        let (node, direction) = (state / DIRECTIONS.len(), state % DIRECTIONS.len());
+        let here = cost[state];
+        if estimate > here + heuristic(node) + EPSILON {
+            // queued again with a lower cost since
+            continue;
+        }
+        if node == goal {
+            let total = here + if direction == enter { 0.0 } else { BEND_COST };
+            if best.is_none_or(|(cheapest, _)| total < cheapest) {
+                best = Some((total, state));
+            }
+            continue;
+        }
+        for next_direction in 0..DIRECTIONS.len() {
+            let Some(next) = step(node, next_direction) else {
+                continue;
+            };
+            let (a, b) = (position(node), position(next));
+            // grid lines run along every edge, so a piece between neighbours
+            // crosses an obstacle or a margin exactly when its middle is inside
+            let middle = a.lerp(b, 0.5);
+            if !open[next] || obstacles.iter().any(|rect| strictly_inside(rect, middle)) {
+                continue;
+            }
+            let crowded = margins.iter().any(|rect| strictly_inside(rect, middle));
+            let length = a.distance(b) * if crowded { CROWDED_COST } else { 1.0 };
+            let bend = if next_direction == direction { 0.0 } else { BEND_COST };
+            let next_cost = here + length + bend;
+            let next_state = next * DIRECTIONS.len() + next_direction;
+            if next_cost < cost[next_state] {
+                cost[next_state] = next_cost;
+                previous[next_state] = state;
+                queue.push(Visit {
+                    estimate: next_cost + heuristic(next),
+                    state: next_state,
+                });
+            }
+        }
+    }
+
+    let (_, mut state) = best?;
+    let mut path = vec![position(state / DIRECTIONS.len())];
+    while previous[state] != usize::MAX {
+        state = previous[state];
+        path.push(position(state / DIRECTIONS.len()));
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Drops repeated points and the middle one of three in a row on one axis.
+fn simplify(points: Vec<Point>) -> Vec<Point> {
+    let mut simple: Vec<Point> = Vec::with_capacity(points.len());
+    for p in points {
+        if simple.last().is_some_and(|last| last.distance(p) <= EPSILON) {
+            continue;
+        }
+        if let [.., a, b] = simple.as_slice() {
+            let same_x = (a.x - b.x).abs() <= EPSILON && (b.x - p.x).abs() <= EPSILON;
+            let same_y = (a.y - b.y).abs() <= EPSILON && (b.y - p.y).abs() <= EPSILON;
+            if same_x || same_y {
+                simple.pop();
+            }
+        }
+        simple.push(p);
+    }
+    simple
+}
+
+/// Bounds of every visible element near both ends for an elbow route to go
+/// around. Groups count as their children, connectors are left out.
+fn obstacles(document: &Document, start: &End, end: &End) -> Vec<Rect> {
+    fn collect(element: &Element, out: &mut Vec<Rect>) {
+        match &element.shape {
+            Shape::Group { children } | Shape::Frame { children, .. } => children
+                .iter()
+                .filter(|child| !child.hidden)
+                .for_each(|child| collect(child, out)),
+            Shape::Connector { .. } => {}
+            _ => out.push(element.geometry_bounds()),
+        }
+    }
+    let area = [start.bounds, end.bounds]
+        .into_iter()
+        .flatten()
+        .fold(Rect::from_corners(start.point, end.point), |area, bounds| area.union(&bounds))
+        .inflate(SEARCH_MARGIN);
+    let mut rects = Vec::new();
+    for element in document.elements_intersecting(&area) {
+        if document.is_visible(element.id) {
+            collect(element, &mut rects);
+        }
+    }
+    rects
+}
+
+/// Fresh points for `connector` following the elements its ends are bound
+/// to. Ends bound to elements that are gone stay where they were. `None`
+/// for other shapes.
+pub fn route_connector(document: &Document, connector: &Element) -> Option<Vec<Point>> {
+    let Shape::Connector {
+        points,
+        start,
+        end,
+        routing,
+        ..
+    } = &connector.shape
+    else {
+        return None;
+    };
+    let (first, last) = (*points.first()?, *points.last()?);
+    let bound = |binding: &Option<Binding>| {
+        let binding = binding.as_ref()?;
+        let element = document.get(binding.element)?;
+        (!matches!(element.shape, Shape::Connector { .. })).then_some((element, binding.anchor))
+    };
+    let (start, end) = (bound(start), bound(end));
+    // each end aims for the middle of the other
+    let aim = |bound: Option<(&Element, Anchor)>, free: Point| {
+        bound.map_or(free, |(element, _)| element.geometry_bounds().center())
+    };
+    let resolve = |bound: Option<(&Element, Anchor)>, free: Point, toward: Point| match bound {
+        Some((element, anchor)) => bound_end(element, anchor, toward, *routing),
+        None => End::free(free),
+    };
+    let start_end = resolve(start, first, aim(end, last));
+    let end_end = resolve(end, last, aim(start, first));
+    let obstacles = match routing {
+        Routing::Elbow => obstacles(document, &start_end, &end_end),
+        _ => Vec::new(),
+    };
+    Some(route(&start_end, &end_end, *routing, &obstacles))
+}
+
+/// Which connectors there are and what they are bound to, kept by the
+/// [`Document`] so a change only looks at the connectors it touches.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConnectorIndex {
+    /// every connector, nested ones included
+    connectors: HashSet<ElementId>,
+    /// connectors bound to each element
+    bound: HashMap<ElementId, HashSet<ElementId>>,
+}
+
+impl ConnectorIndex {
+    /// Adds the connectors among `element` and its descendants.
+    pub(crate) fn insert(&mut self, element: &Element) {
+        if let Shape::Connector { .. } = element.shape {
+            self.connectors.insert(element.id);
+            for binding in element.shape.bindings() {
+                self.bound.entry(binding.element).or_default().insert(element.id);
+            }
+        }
+        for child in element.children() {
+            self.insert(child);
+        }
+    }
+
+    /// Takes out what [`ConnectorIndex::insert`] added for `element`.
+    pub(crate) fn remove(&mut self, element: &Element) {
+        if let Shape::Connector { .. } = element.shape {
+            self.connectors.remove(&element.id);
+            for binding in element.shape.bindings() {
+                if let Some(connectors) = self.bound.get_mut(&binding.element) {
+                    connectors.remove(&element.id);
+                    if connectors.is_empty() {
+                        self.bound.remove(&binding.element);
+                    }
+                }
+            }
+        }
+        for child in element.children() {
+            self.remove(child);
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.connectors.is_empty()
+    }
+
+    /// The connectors among `ids` and the ones bound to any of them.
+    fn touching(&self, ids: &[ElementId]) -> BTreeSet<ElementId> {
+        let mut touched = BTreeSet::new();
+        for id in ids {
+            touched.extend(self.connectors.get(id));
+            touched.extend(self.bound.get(id).into_iter().flatten());
+        }
+        touched
+    }
+}
+
+/// Routes again every connector that is `root`, is inside it or is bound to
+/// anything in it, see [`Document`]. Returns the new points by connector.
+pub(crate) fn reroutes_after_change(document: &Document, root: ElementId) -> Vec<(ElementId, Vec<Point>)> {
+    let index = document.connector_index();
+    if index.is_empty() {
+        return Vec::new();
+    }
+    let Some(changed) = document.get(root) else {
+        return Vec::new();
+    };
+    let mut ids = changed.descendant_ids();
+    ids.push(root);
+    index
+        .touching(&ids)
+        .into_iter()
+        .filter_map(|id| document.get(id))
+        .filter_map(|connector| Some((connector.id, route_connector(document, connector)?)))
+        .collect()
+}
+
+/// Path along routed connector points, one cubic for curves.
+pub fn connector_path(points: &[Point], routing: Routing) -> Path {
+    match (routing, points) {
+        (Routing::Curved, &[start, control1, control2, end]) => {
+            let mut path = Path::new();
+            path.move_to(start).cubic_to(control1, control2, end);
+            path
+        }
+        _ => Path::polyline(points),
+    }
+}
+
+/// What to paint for a connector: the path, shortened under filled heads
+/// like a line's shaft, and the heads pointing along the first and last pieces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectorGeometry {
+    pub path: Path,
+    pub heads: Vec<HeadOutline>,
+}
+
+pub fn connector_geometry(
+    points: &[Point],
+    routing: Routing,
+    start_head: Arrowhead,
+    end_head: Arrowhead,
+    stroke_width: f32,
+) -> ConnectorGeometry {
+    let mut points = points.to_vec();
+    let n = points.len();
+    if n < 2 {
+        return ConnectorGeometry {
+            path: Path::polyline(&points),
+            heads: Vec::new(),
+        };
+    }
+    let first = line_geometry(points[0], points[1], start_head, Arrowhead::None, stroke_width);
+    let last = line_geometry(points[n - 2], points[n - 1], Arrowhead::None, end_head, stroke_width);
+    points[0] = first.start;
+    points[n - 1] = last.end;
+    ConnectorGeometry {
+        path: connector_path(&points, routing),
+        heads: first.heads.into_iter().chain(last.heads).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::Style;
+    use crate::History;
+
+    fn boxed(document: &mut Document, x: f32, y: f32) -> ElementId {
+        document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(x, y), point(40.0, 40.0)),
+            },
+            Style::default(),
+        )
+    }
+
+    fn connect(document: &mut Document, a: ElementId, b: ElementId, routing: Routing) -> ElementId {
+        document.add(
+            Shape::connector(
+                point(0.0, 0.0),
+                point(0.0, 0.0),
+                Some(Binding::new(a, Anchor::Auto)),
+                Some(Binding::new(b, Anchor::Auto)),
+                routing,
+            ),
+            Style::default(),
+        )
+    }
+
+    fn points_of(document: &Document, id: ElementId) -> Vec<Point> {
+        match &document.get(id).unwrap().shape {
+            Shape::Connector { points, .. } => points.clone(),
+            shape => panic!("not a connector: {shape:?}"),
+        }
+    }
+
+    fn assert_orthogonal(points: &[Point]) {
+        for pair in points.windows(2) {
+            let d = pair[1] - pair[0];
+            assert!(d.x.abs() < 1e-3 || d.y.abs() < 1e-3, "diagonal piece in {points:?}");
+        }
+    }
+
+    #[test]
+    fn straight_connectors_meet_the_facing_outlines() {
+        let mut document = Document::new();
+        let a = boxed(&mut document, 0.0, 0.0);
+        let b = boxed(&mut document, 200.0, 0.0);
+        let connector = connect(&mut document, a, b, Routing::Straight);
+        assert_eq!(points_of(&document, connector), vec![point(40.0, 20.0), point(200.0, 20.0)]);
+    }
+
+    #[test]
+    fn connectors_follow_moved_elements_and_undo() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let a = boxed(&mut document, 0.0, 0.0);
+        let b = boxed(&mut document, 200.0, 0.0);
+        let connector = connect(&mut document, a, b, Routing::Straight);
+        let before = points_of(&document, connector);
+
+        history.update(&mut document, b, |e| e.translate(point(0.0, 100.0)));
+        let moved = points_of(&document, connector);
+        // still on the line between the middles
+        assert!(moved[0].distance(point(40.0, 30.0)) < 1e-3);
+        assert!(moved[1].distance(point(200.0, 110.0)) < 1e-3);
+
+        history.undo(&mut document);
+        assert_eq!(points_of(&document, connector), before);
+        history.redo(&mut document);
+        assert_eq!(points_of(&document, connector), moved);
+    }
+
+    #[test]
+    fn only_connectors_bound_to_a_changed_element_are_routed() {
+        let mut document = Document::new();
+        let a = boxed(&mut document, 0.0, 0.0);
+        let b = boxed(&mut document, 200.0, 0.0);
+        let c = boxed(&mut document, 0.0, 200.0);
+        let ab = connect(&mut document, a, b, Routing::Straight);
+        let bc = connect(&mut document, b, c, Routing::Straight);
+        assert_eq!(document.connector_index().touching(&[a]), BTreeSet::from([ab]));
+        assert_eq!(document.connector_index().touching(&[b]), BTreeSet::from([ab, bc]));
+
+        // rebinding the end moves the connector's entry along
+        document.update(ab, |element| {
+            if let Shape::Connector { end, .. } = &mut element.shape {
+                *end = Some(Binding::new(c, Anchor::Auto));
+            }
+        });
+        assert_eq!(document.connector_index().touching(&[b]), BTreeSet::from([bc]));
+        assert_eq!(document.connector_index().touching(&[c]), BTreeSet::from([ab, bc]));
+
+        document.remove(bc);
+        assert_eq!(document.connector_index().touching(&[ab, bc]), BTreeSet::from([ab]));
+        assert!(document.connector_index().touching(&[b]).is_empty());
+        let routed = points_of(&document, ab);
+        document.update(b, |element| element.translate(point(0.0, 50.0)));
+        assert_eq!(points_of(&document, ab), routed);
+    }
+
+    #[test]
+    fn fixed_anchors_turn_with_their_element() {
+        let mut document = Document::new();
+        let a = boxed(&mut document, 0.0, 0.0);
+        let connector = document.add(
+            Shape::connector(
+                point(0.0, 0.0),
+                point(100.0, 100.0),
+                Some(Binding::new(a, Anchor::Fixed(point(1.0, 0.5)))),
+                None,
+                Routing::Straight,
+            ),
+            Style::default(),
+        );
+        assert_eq!(points_of(&document, connector)[0], point(40.0, 20.0));
+
+        let quarter = crate::Affine::around(point(20.0, 20.0), &crate::Affine::rotate(std::f32::consts::FRAC_PI_2));
+        document.update(a, |e| e.transform = quarter);
+        let points = points_of(&document, connector);
+        // the right side now faces down, the free end stays put
+        assert!(points[0].distance(point(20.0, 40.0)) < 1e-3);
+        assert_eq!(points[1], point(100.0, 100.0));
+    }
+
+    #[test]
+    fn curves_leave_their_elements_head_on() {
+        let mut document = Document::new();
+        let a = boxed(&mut document, 0.0, 0.0);
+        let b = boxed(&mut document, 200.0, 100.0);
+        let connector = connect(&mut document, a, b, Routing::Curved);
+        let [start, control1, control2, end] = points_of(&document, connector)[..] else {
+            panic!("a curve has two control points");
+        };
+        assert!(control1.x > start.x && (control1.y - start.y).abs() < 1e-3);
+        assert!(control2.x < end.x && (control2.y - end.y).abs() < 1e-3);
+    }
+
+    #[test]
+    fn elbows_connect_facing_sides() {
+        let mut document = Document::new();
+        let a = boxed(&mut document, 0.0, 0.0);
+        let b = boxed(&mut document, 200.0, 100.0);
+        let connector = connect(&mut document, a, b, Routing::Elbow);
+        let points = points_of(&document, connector);
+        assert_orthogonal(&points);
+        assert_eq!(points.first(), Some(&point(40.0, 20.0)));
+        assert_eq!(points.last(), Some(&point(200.0, 120.0)));
+        // one turn down and one back, nothing more
+        assert_eq!(points.len(), 4);
+    }
+
+    #[test]
+    fn elbows_go_around_elements_in_the_way() {
+        let mut document = Document::new();
+        let a = boxed(&mut document, 0.0, 0.0);
+        let b = boxed(&mut document, 300.0, 0.0);
+        let wall = document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(130.0, -60.0), point(40.0, 160.0)),
+            },
+            Style::default(),
+        );
+        let connector = connect(&mut document, a, b, Routing::Elbow);
+        let points = points_of(&document, connector);
+        assert_orthogonal(&points);
+        assert_eq!((points[0], points[points.len() - 1]), (point(40.0, 20.0), point(300.0, 20.0)));
+        let wall = document.get(wall).unwrap().geometry_bounds();
+        for pair in points.windows(2) {
+            for t in [0.25, 0.5, 0.75] {
+                assert!(!wall.contains(pair[0].lerp(pair[1], t)), "{points:?} crosses the wall");
+            }
+        }
+    }
+
+    #[test]
+    fn boxed_in_elbows_still_connect() {
+        let start = End {
+            point: point(0.0, 0.0),
+            direction: Some(point(1.0, 0.0)),
+            bounds: None,
+        };
+        let end = End::free(point(100.0, 50.0));
+        // an obstacle covering both ends leaves no way through
+        let cover = Rect::from_corners(point(-50.0, -50.0), point(150.0, 100.0));
+        let points = route(&start, &end, Routing::Elbow, &[cover]);
+        assert_orthogonal(&points);
+        assert_eq!(points, vec![point(0.0, 0.0), point(50.0, 0.0), point(50.0, 50.0), point(100.0, 50.0)]);
+    }
+
+    #[test]
+    fn heads_point_along_the_last_piece() {
+        let points = [point(0.0, 0.0), point(50.0, 0.0), point(50.0, 50.0)];
+        let geometry = connector_geometry(&points, Routing::Elbow, Arrowhead::None, Arrowhead::Triangle, 2.0);
+        let [head] = geometry.heads.as_slice() else {
+            panic!("expected one head");
+        };
+        assert_eq!(head.points[0], point(50.0, 50.0));
+        assert!(head.points[1].y < 50.0 && head.points[2].y < 50.0);
+    }
+}
@@ -0,0 +1,217 @@
+//! PNG and JPEG files turned into RGBA pixels, in pure Rust so it works the
+//! same natively and in the browser.
+
+use std::fmt;
+
+use crate::render::Image;
+
+/// Images wider or taller than this are rejected instead of decoded.
+pub const MAX_IMAGE_DIMENSION: u32 = 16_384;
+/// Images with more pixels than this are rejected too, so a file can't make
+/// the decoder allocate gigabytes while staying within the dimension limit.
+pub const MAX_IMAGE_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Image file formats that can be placed on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    /// Tells the format from the first bytes of the file.
+    pub fn sniff(bytes: &[u8]) -> Option<ImageFormat> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    /// Neither a PNG nor a JPEG file.
+    UnknownFormat,
+    Png(png::DecodingError),
+    Jpeg(jpeg_decoder::Error),
+    /// Decoded, but without pixels to show.
+    Empty,
+    /// Larger than [`MAX_IMAGE_DIMENSION`] or [`MAX_IMAGE_PIXELS`].
+    TooLarge { width: u32, height: u32 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnknownFormat => write!(f, "not a PNG or JPEG image"),
+            DecodeError::Png(err) => write!(f, "invalid PNG image: {}", err),
+            DecodeError::Jpeg(err) => write!(f, "invalid JPEG image: {}", err),
+            DecodeError::Empty => write!(f, "image has no pixels"),
+            DecodeError::TooLarge { width, height } => write!(
+                f,
+                "image is {}x{} pixels, at most {} on a side and {} in total are supported",
+                width, height, MAX_IMAGE_DIMENSION, MAX_IMAGE_PIXELS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<png::DecodingError> for DecodeError {
+    fn from(err: png::DecodingError) -> Self {
+        DecodeError::Png(err)
+    }
+}
+
+impl From<jpeg_decoder::Error> for DecodeError {
+    fn from(err: jpeg_decoder::Error) -> Self {
+        DecodeError::Jpeg(err)
+    }
+}
+
+/// Decodes a PNG or JPEG file into straight RGBA8 pixels. The size is checked
+/// from the header, before any pixels are decoded.
+pub fn decode_image(bytes: &[u8]) -> Result<Image, DecodeError> {
+    let (width, height, pixels) = match ImageFormat::sniff(bytes) {
+        Some(ImageFormat::Png) => decode_png(bytes)?,
+        Some(ImageFormat::Jpeg) => decode_jpeg(bytes)?,
+        None => return Err(DecodeError::UnknownFormat),
+    };
+    if width == 0 || height == 0 {
+        return Err(DecodeError::Empty);
+    }
+    Ok(Image::from_rgba(width, height, pixels))
+}
+
+fn check_size(width: u32, height: u32) -> Result<(), DecodeError> {
+    if width > MAX_IMAGE_DIMENSION || height > MAX_IMAGE_DIMENSION || width as u64 * height as u64 > MAX_IMAGE_PIXELS {
+        return Err(DecodeError::TooLarge { width, height });
+    }
+    Ok(())
+}
+
+fn decode_png(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), DecodeError> {
+    let mut decoder = png::Decoder::new(bytes);
+    // palettes and low bit depths are expanded, 16 bit channels cut to 8
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    check_size(reader.info().width, reader.info().height)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer)?;
+    let samples = &buffer[..frame.buffer_size()];
+    let pixels = match frame.color_type {
+        png::ColorType::Rgba => samples.to_vec(),
+        png::ColorType::Rgb => samples.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => samples.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => samples.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        // expanded to RGB by the transformations
+        png::ColorType::Indexed => return Err(DecodeError::Empty),
+    };
+    Ok((frame.width, frame.height, pixels))
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), DecodeError> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    decoder.read_info()?;
+    let header = decoder.info().ok_or(DecodeError::Empty)?;
+    check_size(header.width as u32, header.height as u32)?;
+    // four bytes per pixel for CMYK, the most any pixel format takes
+    decoder.set_max_decoding_buffer_size(MAX_IMAGE_PIXELS as usize * 4);
+    let samples = decoder.decode()?;
+    let info = decoder.info().ok_or(DecodeError::Empty)?;
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => samples.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        jpeg_decoder::PixelFormat::L8 => samples.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        // big endian, the high byte is enough
+        jpeg_decoder::PixelFormat::L16 => samples.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], 255]).collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => samples
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let ink = |c: u8| ((255 - c as u32) * (255 - p[3] as u32) / 255) as u8;
+                [ink(p[0]), ink(p[1]), ink(p[2]), 255]
+            })
+            .collect(),
+    };
+    Ok((info.width as u32, info.height as u32, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2 by 1 PNG, red and half transparent blue.
+    fn png_file() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 255, 0, 0, 255, 128]).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn decodes_png() {
+        let bytes = png_file();
+        assert_eq!(ImageFormat::sniff(&bytes), Some(ImageFormat::Png));
+        let image = decode_image(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixels(), &[255, 0, 0, 255, 0, 0, 255, 128]);
+    }
+
+    #[test]
+    fn decodes_jpeg() {
+        let mut bytes = Vec::new();
+        let encoder = jpeg_encoder::Encoder::new(&mut bytes, 100);
+        encoder.encode(&[200; 8 * 4 * 3], 8, 4, jpeg_encoder::ColorType::Rgb).unwrap();
+        assert_eq!(ImageFormat::sniff(&bytes), Some(ImageFormat::Jpeg));
+        let image = decode_image(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 4));
+        assert!(image.pixels().chunks(4).all(|p| p[0].abs_diff(200) <= 2 && p[3] == 255));
+    }
+
+    #[test]
+    fn rejects_huge_images_from_their_header() {
+        // the header and an empty data chunk, the pixels are never reached
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 70_000, 200);
+        encoder.set_color(png::ColorType::Rgba);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_chunk(png::chunk::IDAT, &[]).unwrap();
+        drop(writer);
+        assert!(matches!(
+            decode_image(&bytes),
+            Err(DecodeError::TooLarge { width: 70_000, height: 200 })
+        ));
+
+        let mut jpeg = Vec::new();
+        let encoder = jpeg_encoder::Encoder::new(&mut jpeg, 100);
+        encoder.encode(&[200; 8 * 4 * 3], 8, 4, jpeg_encoder::ColorType::Rgb).unwrap();
+        // the start of frame segment holds the height and width
+        let frame = jpeg.windows(2).position(|marker| marker == [0xff, 0xc0]).unwrap();
+        jpeg[frame + 5..frame + 9].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        assert!(matches!(
+            decode_image(&jpeg),
+            Err(DecodeError::TooLarge { width: 65_535, height: 65_535 })
+        ));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(matches!(decode_image(b"GIF89a"), Err(DecodeError::UnknownFormat)));
+        let mut truncated = png_file();
+        truncated.truncate(20);
+        assert!(matches!(decode_image(&truncated), Err(DecodeError::Png(_))));
+    }
+}
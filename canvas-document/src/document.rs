@@ -0,0 +1,586 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::asset::{AssetId, AssetStore};
+use crate::connector::{reroutes_after_change, ConnectorIndex};
+use crate::element::{Element, ElementId, Shape};
+use crate::format::FormatError;
+use crate::geometry::{Point, Rect};
+use crate::layer::{Layer, LayerId};
+use crate::spatial::SpatialIndex;
+use crate::style::Style;
+
+/// Everything drawn on a canvas. Elements are kept in paint order,
+/// the last element is drawn on top, which also keeps the elements of each
+/// layer together in the order of the layers. Group children are found by
+/// id like top level elements, but only top level elements have a paint
+/// order position here and an entry in the spatial index.
+///
+/// Elements can only be changed through the methods here so that the
+/// spatial index always matches the element geometry, and so that
+/// connectors are routed again when the elements they link change.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "DocumentData")]
+pub struct Document {
+    elements: Vec<Element>,
+    next_id: u64,
+    /// bottom to top, never empty
+    layers: Vec<Layer>,
+    /// where new elements go
+    active_layer: LayerId,
+    /// pictures and other files elements refer to, also the ones of removed
+    /// elements so undo can bring them back
+    assets: AssetStore,
+    /// paint order position of every element
+    positions: HashMap<ElementId, usize>,
+    /// top level group holding every nested element
+    roots: HashMap<ElementId, ElementId>,
+    index: SpatialIndex,
+    /// connectors and what they are bound to
+    connectors: ConnectorIndex,
+}
+
+/// The serialized part of a document, the lookups are rebuilt on load.
+#[derive(Deserialize)]
+struct DocumentData {
+    elements: Vec<Element>,
+    next_id: u64,
+    #[serde(default = "default_layers")]
+    layers: Vec<Layer>,
+    #[serde(default)]
+    active_layer: LayerId,
+    #[serde(default)]
+    assets: AssetStore,
+}
+
+/// What gets written for a document, only the assets some element still
+/// refers to are saved.
+#[derive(Serialize)]
+struct SavedDocument<'a> {
+    elements: &'a [Element],
+    next_id: u64,
+    layers: &'a [Layer],
+    active_layer: LayerId,
+    #[serde(skip_serializing_if = "AssetStore::is_empty")]
+    assets: AssetStore,
+}
+
+impl Serialize for Document {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let referenced: Vec<&AssetId> = self
+            .iter_nested()
+            .into_iter()
+            .filter_map(|element| match &element.shape {
+                Shape::Image { asset, .. } => Some(asset),
+                _ => None,
+            })
+            .collect();
+        SavedDocument {
+            elements: &self.elements,
+            next_id: self.next_id,
+            layers: &self.layers,
+            active_layer: self.active_layer,
+            assets: self.assets.subset(referenced),
+        }
+        .serialize(serializer)
+    }
+}
+
+fn default_layers() -> Vec<Layer> {
+    vec![Layer::new(LayerId::default(), "Layer 1")]
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Document {
+            elements: Vec::new(),
+            next_id: 0,
+            layers: default_layers(),
+            active_layer: LayerId::default(),
+            assets: AssetStore::default(),
+            positions: HashMap::new(),
+            roots: HashMap::new(),
+            index: SpatialIndex::default(),
+            connectors: ConnectorIndex::default(),
+        }
+    }
+}
+
+impl TryFrom<DocumentData> for Document {
+    type Error = FormatError;
+
+    /// Rebuilds the lookups. Files edited by hand or merged may reuse ids or
+    /// count `next_id` too low, the ids handed out later must not clash.
+    fn try_from(data: DocumentData) -> Result<Self, FormatError> {
+        let mut seen = HashSet::new();
+        let mut largest = None;
+        for element in &data.elements {
+            for id in std::iter::once(element.id).chain(element.descendant_ids()) {
+                if !seen.insert(id) {
+                    return Err(FormatError::DuplicateId(id));
+                }
+                largest = largest.max(Some(id.0));
+            }
+        }
+        let mut document = Document {
+            elements: data.elements,
+            next_id: data.next_id.max(largest.map_or(0, |id| id + 1)),
+            assets: data.assets,
+            ..Document::default()
+        };
+        if !data.layers.is_empty() {
+            document.layers = data.layers;
+        }
+        document.set_active_layer(data.active_layer);
+        document.sort_by_layer();
+        for position in 0..document.elements.len() {
+            document.register_descendants(position);
+            document.connectors.insert(&document.elements[position]);
+        }
+        document.index = SpatialIndex::bulk_load(document.elements.iter().map(|e| (e.id, e.bounds())));
+        Ok(document)
+    }
+}
+
+impl PartialEq for Document {
+    fn eq(&self, other: &Self) -> bool {
+        self.elements == other.elements
+            && self.next_id == other.next_id
+            && self.layers == other.layers
+            && self.assets == other.assets
+    }
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Document::default()
+    }
+
+    fn reindex_positions(&mut self, from: usize) {
+        for (position, element) in self.elements.iter().enumerate().skip(from) {
+            self.positions.insert(element.id, position);
+        }
+    }
+
+    /// Remembers the top level element of everything nested in the element at `position`.
+    fn register_descendants(&mut self, position: usize) {
+        let root = self.elements[position].id;
+        self.roots.retain(|_, r| *r != root);
+        for id in self.elements[position].descendant_ids() {
+            self.roots.insert(id, root);
+        }
+    }
+
+    /// Hands out an id for an element that is put into the document later,
+    /// like a group built around existing elements.
+    pub fn new_id(&mut self) -> ElementId {
+        let id = ElementId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    pub fn assets(&self) -> &AssetStore {
+        &self.assets
+    }
+
+    /// Assets can be added at any time, elements refer to them by id.
+    pub fn assets_mut(&mut self) -> &mut AssetStore {
+        &mut self.assets
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn layer(&self, id: LayerId) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.id == id)
+    }
+
+    /// Position of a layer bottom to top, unknown layers count as the top one.
+    fn layer_position(&self, id: LayerId) -> usize {
+        self.layers
+            .iter()
+            .position(|layer| layer.id == id)
+            .unwrap_or(self.layers.len() - 1)
+    }
+
+    /// Where the elements of `layer` are in paint order.
+    pub fn layer_range(&self, layer: LayerId) -> Range<usize> {
+        let position = self.layer_position(layer);
+        let start = self
+            .elements
+            .partition_point(|element| self.layer_position(element.layer) < position);
+        let end = self
+            .elements
+            .partition_point(|element| self.layer_position(element.layer) <= position);
+        start..end
+    }
+
+    /// The layer new elements are added to.
+    pub fn active_layer(&self) -> LayerId {
+        self.active_layer
+    }
+
+    /// Makes `id` the active layer, an unknown id picks the top layer.
+    pub fn set_active_layer(&mut self, id: LayerId) {
+        self.active_layer = match self.layer(id) {
+            Some(layer) => layer.id,
+            None => self.layers[self.layers.len() - 1].id,
+        };
+    }
+
+    /// Replaces the layer list, reordering elements to match. Prefer the
+    /// undoable functions in [`crate::layer`]. An empty list is ignored.
+    pub fn set_layers(&mut self, layers: Vec<Layer>) {
+        if layers.is_empty() {
+            return;
+        }
+        self.layers = layers;
+        self.set_active_layer(self.active_layer);
+        self.sort_by_layer();
+    }
+
+    /// Restores the paint order invariant after layers moved.
+    fn sort_by_layer(&mut self) {
+        let positions: HashMap<LayerId, usize> = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(position, layer)| (layer.id, position))
+            .collect();
+        let top = self.layers.len() - 1;
+        self.elements
+            .sort_by_key(|element| positions.get(&element.layer).copied().unwrap_or(top));
+        self.reindex_positions(0);
+    }
+
+    /// Whether the element is drawn: neither it, a group holding it nor its
+    /// layer are hidden.
+    pub fn is_visible(&self, id: ElementId) -> bool {
+        self.ancestry_allows(id, |element| !element.hidden, |layer| !layer.hidden)
+    }
+
+    /// Whether the element can be picked: visible, and neither it, a group
+    /// holding it nor its layer are locked.
+    pub fn is_pickable(&self, id: ElementId) -> bool {
+        self.is_visible(id) && self.ancestry_allows(id, |element| !element.locked, |layer| !layer.locked)
+    }
+
+    /// Checks the element, the groups holding it and the layer of the top one.
+    fn ancestry_allows(
+        &self,
+        id: ElementId,
+        element_ok: impl Fn(&Element) -> bool,
+        layer_ok: impl Fn(&Layer) -> bool,
+    ) -> bool {
+        let mut current = id;
+        loop {
+            let Some(element) = self.get(current) else {
+                return false;
+            };
+            if !element_ok(element) {
+                return false;
+            }
+            match self.parent_of(current) {
+                Some(parent) => current = parent.id,
+                None => return self.layer(element.layer).is_none_or(layer_ok),
+            }
+        }
+    }
+
+    /// Adds an element on top of the active layer and returns its new id.
+    pub fn add(&mut self, shape: Shape, style: Style) -> ElementId {
+        let id = self.new_id();
+        let mut element = Element::new(id, shape, style);
+        element.layer = self.active_layer;
+        let index = self.layer_range(self.active_layer).end;
+        self.index.insert(id, element.bounds());
+        self.connectors.insert(&element);
+        self.elements.insert(index, element);
+        self.reindex_positions(index);
+        self.reroute_connectors(id);
+        id
+    }
+
+    /// Puts an existing element back at `index` in paint order, keeping its id.
+    /// The index is kept within the element's layer. Used to restore removed
+    /// elements, new content should go through [`Document::add`].
+    pub fn insert(&mut self, index: usize, element: Element) {
+        self.next_id = self.next_id.max(element.id.0 + 1);
+        let range = self.layer_range(element.layer);
+        let index = index.clamp(range.start, range.end);
+        self.index.insert(element.id, element.bounds());
+        self.connectors.insert(&element);
+        self.elements.insert(index, element);
+        self.reindex_positions(index);
+        self.register_descendants(index);
+        self.reroute_connectors(self.elements[index].id);
+    }
+
+    /// Swaps in a new version of the top level element with the same id, returning the old one.
+    pub fn replace(&mut self, element: Element) -> Option<Element> {
+        let position = self.index_of(element.id)?;
+        self.index.insert(element.id, element.bounds());
+        let old = std::mem::replace(&mut self.elements[position], element);
+        self.connectors.remove(&old);
+        self.connectors.insert(&self.elements[position]);
+        self.register_descendants(position);
+        let id = self.elements[position].id;
+        if old.layer != self.elements[position].layer {
+            self.sort_by_layer();
+        }
+        self.reroute_connectors(id);
+        Some(old)
+    }
+
+    /// Changes an element in place, also one inside a group. Returns false
+    /// when there is no element with that id.
+    pub fn update(&mut self, id: ElementId, edit: impl FnOnce(&mut Element)) -> bool {
+        let Some(root) = self.root_of(id) else {
+            return false;
+        };
+        if !self.edit(id, edit) {
+            return false;
+        }
+        self.reroute_connectors(root);
+        true
+    }
+
+    /// [`Document::update`] without routing connectors again.
+    fn edit(&mut self, id: ElementId, edit: impl FnOnce(&mut Element)) -> bool {
+        let Some(root) = self.root_of(id) else {
+            return false;
+        };
+        let position = self.positions[&root];
+        let layer = self.elements[position].layer;
+        if self.elements[position].find(id).is_none() {
+            return false;
+        }
+        self.connectors.remove(&self.elements[position]);
+        let element = self.elements[position].find_mut(id).expect("found above");
+        edit(element);
+        // the id is what the lookups are keyed on
+        element.id = id;
+        let root = &self.elements[position];
+        self.index.insert(root.id, root.bounds());
+        self.connectors.insert(root);
+        let moved_layer = root.layer != layer;
+        if matches!(root.shape, Shape::Group { .. } | Shape::Frame { .. }) {
+            self.register_descendants(position);
+        }
+        if moved_layer {
+            self.sort_by_layer();
+        }
+        true
+    }
+
+    /// Routes the connectors touched by a change to the top level element `root`.
+    fn reroute_connectors(&mut self, root: ElementId) {
+        for (id, routed) in reroutes_after_change(self, root) {
+            self.edit(id, |element| {
+                if let Shape::Connector { points, .. } = &mut element.shape {
+                    *points = routed;
+                }
+            });
+        }
+    }
+
+    pub(crate) fn connector_index(&self) -> &ConnectorIndex {
+        &self.connectors
+    }
+
+    /// Takes a top level element out, group children are removed by updating the group.
+    pub fn remove(&mut self, id: ElementId) -> Option<Element> {
+        let position = self.positions.remove(&id)?;
+        self.index.remove(id);
+        let element = self.elements.remove(position);
+        self.connectors.remove(&element);
+        self.roots.retain(|_, root| *root != id);
+        self.reindex_positions(position);
+        Some(element)
+    }
+
+    pub fn get(&self, id: ElementId) -> Option<&Element> {
+        match self.index_of(id) {
+            Some(position) => Some(&self.elements[position]),
+            None => self.get(*self.roots.get(&id)?)?.find(id),
+        }
+    }
+
+    /// The top level element that is or holds `id`.
+    pub fn root_of(&self, id: ElementId) -> Option<ElementId> {
+        match self.positions.contains_key(&id) {
+            true => Some(id),
+            false => self.roots.get(&id).copied(),
+        }
+    }
+
+    /// The group directly holding `id`, `None` for top level elements.
+    pub fn parent_of(&self, id: ElementId) -> Option<&Element> {
+        self.get(*self.roots.get(&id)?)?.parent_of(id)
+    }
+
+    /// Position of a top level element in paint order.
+    pub fn index_of(&self, id: ElementId) -> Option<usize> {
+        self.positions.get(&id).copied()
+    }
+
+    /// Elements from bottom to top.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Element> {
+        self.elements.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Looks up the ids and returns the elements in paint order.
+    fn in_paint_order(&self, ids: impl Iterator<Item = ElementId>) -> Vec<&Element> {
+        let mut positions: Vec<usize> = ids.filter_map(|id| self.index_of(id)).collect();
+        positions.sort_unstable();
+        positions.into_iter().map(|p| &self.elements[p]).collect()
+    }
+
+    /// Elements whose bounds touch `rect`, bottom to top. This is what needs
+    /// drawing when `rect` is the visible part of the canvas.
+    pub fn elements_intersecting(&self, rect: &Rect) -> Vec<&Element> {
+        self.in_paint_order(self.index.intersecting(rect))
+    }
+
+    /// Elements lying completely inside `rect`, bottom to top.
+    pub fn elements_within(&self, rect: &Rect) -> Vec<&Element> {
+        self.in_paint_order(self.index.contained_in(rect))
+    }
+
+    /// Topmost element under `p`, skipping hidden and locked ones.
+    pub fn element_at(&self, p: Point, tolerance: f32) -> Option<ElementId> {
+        self.in_paint_order(self.index.near_point(p, tolerance))
+            .into_iter()
+            .rev()
+            .filter(|e| self.is_pickable(e.id))
+            .find(|e| e.hit_test(p, tolerance))
+            .map(|e| e.id)
+    }
+
+    /// Every element including the children of groups, depth first, so
+    /// bottom to top.
+    pub fn iter_nested(&self) -> Vec<&Element> {
+        fn walk<'a>(elements: &'a [Element], out: &mut Vec<&'a Element>) {
+            for element in elements {
+                out.push(element);
+                walk(element.children(), out);
+            }
+        }
+        let mut all = Vec::new();
+        walk(&self.elements, &mut all);
+        all
+    }
+
+    /// Bounds of all content, `None` for an empty document.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.elements
+            .iter()
+            .filter(|element| self.is_visible(element.id))
+            .map(Element::bounds)
+            .reduce(|a, b| a.union(&b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::point;
+
+    fn rectangle(x: f32, y: f32) -> Shape {
+        Shape::Rectangle {
+            rect: Rect::from_origin_size(point(x, y), point(10.0, 10.0)),
+        }
+    }
+
+    #[test]
+    fn ids_are_not_reused_after_removal() {
+        let mut document = Document::new();
+        let first = document.add(rectangle(0.0, 0.0), Style::default());
+        let second = document.add(rectangle(20.0, 0.0), Style::default());
+        assert!(document.remove(second).is_some());
+        let third = document.add(rectangle(40.0, 0.0), Style::default());
+
+        assert_ne!(second, third);
+        assert_eq!(document.len(), 2);
+        assert!(document.get(first).is_some());
+        assert!(document.get(second).is_none());
+    }
+
+    #[test]
+    fn element_at_prefers_topmost() {
+        let mut document = Document::new();
+        let _below = document.add(rectangle(0.0, 0.0), Style::default());
+        let above = document.add(rectangle(5.0, 5.0), Style::default());
+
+        assert_eq!(document.element_at(point(7.0, 7.0), 0.0), Some(above));
+        assert_eq!(document.element_at(point(100.0, 100.0), 0.0), None);
+    }
+
+    #[test]
+    fn spatial_queries_follow_updates() {
+        let mut document = Document::new();
+        let a = document.add(rectangle(0.0, 0.0), Style::filled(crate::style::BLACK));
+        let b = document.add(rectangle(100.0, 0.0), Style::filled(crate::style::BLACK));
+        let view = Rect::from_corners(point(-5.0, -5.0), point(50.0, 50.0));
+        let ids = |elements: Vec<&Element>| elements.iter().map(|e| e.id).collect::<Vec<_>>();
+
+        assert_eq!(ids(document.elements_intersecting(&view)), vec![a]);
+        document.update(b, |e| e.shape.translate(point(-95.0, 0.0)));
+        assert_eq!(ids(document.elements_intersecting(&view)), vec![a, b]);
+        assert_eq!(document.element_at(point(7.0, 7.0), 0.0), Some(b));
+
+        document.remove(a);
+        assert_eq!(ids(document.elements_within(&view)), vec![b]);
+        assert_eq!(document.index_of(b), Some(0));
+    }
+
+    #[test]
+    fn lookups_are_rebuilt_after_deserializing() {
+        let mut document = Document::new();
+        let a = document.add(rectangle(0.0, 0.0), Style::default());
+        let json = serde_json::to_string(&document).unwrap();
+        let loaded: Document = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded, document);
+        assert_eq!(loaded.element_at(point(5.0, 5.0), 0.0), Some(a));
+    }
+
+    #[test]
+    fn loaded_ids_never_clash_with_new_ones() {
+        let mut document = Document::new();
+        document.add(rectangle(0.0, 0.0), Style::default());
+        let b = document.add(rectangle(20.0, 0.0), Style::default());
+        let mut value = serde_json::to_value(&document).unwrap();
+        value["next_id"] = 0.into();
+        let mut loaded: Document = serde_json::from_value(value.clone()).unwrap();
+        assert!(loaded.add(rectangle(40.0, 0.0), Style::default()) > b);
+
+        let copy = value["elements"][0].clone();
+        value["elements"].as_array_mut().unwrap().push(copy);
+        let err = serde_json::from_value::<Document>(value).unwrap_err();
+        assert!(err.to_string().contains("element id 0 is used more than once"), "{err}");
+    }
+
+    #[test]
+    fn bounds_cover_every_element() {
+        let mut document = Document::new();
+        assert_eq!(document.bounds(), None);
+        document.add(rectangle(0.0, 0.0), Style::filled(crate::style::BLACK));
+        document.add(rectangle(30.0, -10.0), Style::filled(crate::style::BLACK));
+        assert_eq!(
+            document.bounds(),
+            Some(Rect::from_corners(point(0.0, -10.0), point(40.0, 10.0)))
+        );
+    }
+}
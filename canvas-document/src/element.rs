@@ -0,0 +1,721 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arrow::Arrowhead;
+use crate::asset::AssetId;
+use crate::connector::{connector_path, Binding, Routing};
+use crate::geometry::{distance_to_segment, point, Affine, Point, Rect};
+use crate::image::{is_full_crop, FULL_CROP};
+use crate::layer::LayerId;
+use crate::sticky::{fit_text, StickyColor};
+use crate::style::Style;
+use crate::text::{Font, TextAlign, TextLayout};
+
+/// Stable identifier of an element. Ids are never reused within a document,
+/// so they stay valid across reordering and deletion of other elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ElementId(pub u64);
+
+/// World space geometry of an element.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Shape {
+    Rectangle { rect: Rect },
+    /// Ellipse inscribed in `rect`.
+    Ellipse { rect: Rect },
+    Line {
+        start: Point,
+        end: Point,
+        #[serde(default, skip_serializing_if = "Arrowhead::is_none")]
+        start_head: Arrowhead,
+        #[serde(default, skip_serializing_if = "Arrowhead::is_none")]
+        end_head: Arrowhead,
+    },
+    /// Freehand stroke as the polyline the pointer travelled. Pen input also
+    /// records the pressure at every point, which varies the stroke width.
+    Freehand {
+        points: Vec<Point>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pressure: Vec<f32>,
+    },
+    /// Text anchored at its top left corner. Without a `width` it grows with
+    /// its longest line, with one it wraps to stay that wide.
+    Text {
+        position: Point,
+        content: String,
+        #[serde(flatten)]
+        font: Font,
+        #[serde(default, skip_serializing_if = "TextAlign::is_default")]
+        align: TextAlign,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<f32>,
+    },
+    /// Line between two elements that follows them around, see
+    /// [`crate::connector`]. `points` are the routed path, the start, two
+    /// control points and the end for curves. Ends that aren't bound to an
+    /// element stay where they are.
+    Connector {
+        points: Vec<Point>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        start: Option<Binding>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        end: Option<Binding>,
+        #[serde(default, skip_serializing_if = "Routing::is_default")]
+        routing: Routing,
+        #[serde(default, skip_serializing_if = "Arrowhead::is_none")]
+        start_head: Arrowhead,
+        #[serde(default, skip_serializing_if = "Arrowhead::is_none")]
+        end_head: Arrowhead,
+    },
+    /// Sticky note in one of the palette colors, its text shrinks to fit.
+    Sticky {
+        rect: Rect,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        content: String,
+        #[serde(default, skip_serializing_if = "StickyColor::is_default")]
+        color: StickyColor,
+    },
+    /// Picture from the document's assets, see [`crate::image`]. `crop` is
+    /// the part of the picture shown, relative to its size, and `rect` is
+    /// where that part goes.
+    Image {
+        rect: Rect,
+        asset: AssetId,
+        #[serde(default = "full_crop", skip_serializing_if = "is_full_crop")]
+        crop: Rect,
+    },
+    /// Elements moved, turned and picked as one, bottom to top. Transforms
+    /// given to a group are passed on to its children, so groups themselves
+    /// stay at the identity.
+    Group { children: Vec<Element> },
+    /// Named area holding the elements placed on it, bottom to top, see
+    /// [`crate::frame`]. Children are clipped to `rect`, which resizing
+    /// changes without stretching them. Like groups, frames pass transforms
+    /// on to their children.
+    Frame {
+        rect: Rect,
+        name: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Element>,
+    },
+}
+
+fn full_crop() -> Rect {
+    FULL_CROP
+}
+
+/// Text of a shape as laid out, see [`Shape::text_block`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextBlock {
+    /// Top left corner of the layout in the shape's coordinates.
+    pub origin: Point,
+    pub font: Font,
+    pub layout: TextLayout,
+}
+
+impl Shape {
+    /// Straight line without arrowheads.
+    pub fn line(start: Point, end: Point) -> Shape {
+        Shape::Line {
+            start,
+            end,
+            start_head: Arrowhead::None,
+            end_head: Arrowhead::None,
+        }
+    }
+
+    /// Left aligned text in the default font that grows with its content.
+    pub fn text(position: Point, content: impl Into<String>, font_size: f32) -> Shape {
+        Shape::Text {
+            position,
+            content: content.into(),
+            font: Font::new(font_size),
+            align: TextAlign::Left,
+            width: None,
+        }
+    }
+
+    /// Connector from `start` to `end` with an arrowhead at the end. The
+    /// points are only a first guess until the document routes it.
+    pub fn connector(
+        start: Point,
+        end: Point,
+        start_binding: Option<Binding>,
+        end_binding: Option<Binding>,
+        routing: Routing,
+    ) -> Shape {
+        Shape::Connector {
+            points: vec![start, end],
+            start: start_binding,
+            end: end_binding,
+            routing,
+            start_head: Arrowhead::None,
+            end_head: Arrowhead::Triangle,
+        }
+    }
+
+    /// What the ends of a connector are bound to, nothing for other shapes.
+    pub fn bindings(&self) -> impl Iterator<Item = &Binding> {
+        let ends = match self {
+            Shape::Connector { start, end, .. } => [start.as_ref(), end.as_ref()],
+            _ => [None, None],
+        };
+        ends.into_iter().flatten()
+    }
+
+    /// The whole picture `asset` stretched over `rect`.
+    pub fn image(rect: Rect, asset: AssetId) -> Shape {
+        Shape::Image {
+            rect,
+            asset,
+            crop: FULL_CROP,
+        }
+    }
+
+    /// Empty frame called `name`.
+    pub fn frame(rect: Rect, name: impl Into<String>) -> Shape {
+        Shape::Frame {
+            rect,
+            name: name.into(),
+            children: Vec::new(),
+        }
+    }
+
+    /// What is written on text and sticky notes, `None` for other shapes.
+    pub fn text_content(&self) -> Option<&str> {
+        match self {
+            Shape::Text { content, .. } | Shape::Sticky { content, .. } => Some(content),
+            _ => None,
+        }
+    }
+
+    pub fn text_content_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Shape::Text { content, .. } | Shape::Sticky { content, .. } => Some(content),
+            _ => None,
+        }
+    }
+
+    /// Lines of text and sticky notes and where they go.
+    pub fn text_block(&self) -> Option<TextBlock> {
+        match self {
+            Shape::Text {
+                position,
+                content,
+                font,
+                align,
+                width,
+            } => Some(TextBlock {
+                origin: *position,
+                font: *font,
+                layout: TextLayout::new(content, font, *align, *width),
+            }),
+            Shape::Sticky { rect, content, .. } => Some(fit_text(rect, content)),
+            _ => None,
+        }
+    }
+
+    /// Axis aligned bounds of the geometry, not including stroke width.
+    pub fn bounds(&self) -> Rect {
+        match self {
+            Shape::Rectangle { rect }
+            | Shape::Ellipse { rect }
+            | Shape::Sticky { rect, .. }
+            | Shape::Image { rect, .. }
+            | Shape::Frame { rect, .. } => *rect,
+            Shape::Line { start, end, .. } => Rect::from_corners(*start, *end),
+            Shape::Freehand { points, .. } | Shape::Connector { points, .. } => {
+                Rect::from_points(points.iter().copied()).unwrap_or_default()
+            }
+            Shape::Text {
+                position,
+                content,
+                font,
+                align,
+                width,
+            } => Rect::from_origin_size(*position, TextLayout::new(content, font, *align, *width).size),
+            Shape::Group { children } => children
+                .iter()
+                .map(Element::geometry_bounds)
+                .reduce(|a, b| a.union(&b))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Whether `p` hits the geometry, `tolerance` widens thin shapes so they can be picked.
+    pub fn hit_test(&self, p: Point, tolerance: f32) -> bool {
+        match self {
+            Shape::Rectangle { rect }
+            | Shape::Sticky { rect, .. }
+            | Shape::Image { rect, .. }
+            | Shape::Frame { rect, .. } => rect.inflate(tolerance).contains(p),
+            Shape::Ellipse { rect } => {
+                let radius = rect.size() * 0.5 + point(tolerance, tolerance);
+                if radius.x <= 0.0 || radius.y <= 0.0 {
+                    return false;
+                }
+                let d = p - rect.center();
+                (d.x / radius.x).powi(2) + (d.y / radius.y).powi(2) <= 1.0
+            }
+            Shape::Line { start, end, .. } => distance_to_segment(p, *start, *end) <= tolerance,
+            Shape::Freehand { points, .. } => match points.as_slice() {
+                [] => false,
+                [only] => only.distance(p) <= tolerance,
+                _ => points
+                    .windows(2)
+                    .any(|w| distance_to_segment(p, w[0], w[1]) <= tolerance),
+            },
+            Shape::Text { .. } => self.bounds().inflate(tolerance).contains(p),
+            Shape::Connector { points, routing, .. } => connector_path(points, *routing)
+                .flatten(tolerance.max(0.5))
+                .iter()
+                .flat_map(|polyline| polyline.points.windows(2))
+                .any(|w| distance_to_segment(p, w[0], w[1]) <= tolerance),
+            Shape::Group { children } => children
+                .iter()
+                .any(|child| !child.hidden && child.hit_test(p, tolerance)),
+        }
+    }
+
+    /// Stretches the geometry so its bounds become `target`.
+    pub fn fit_to(&mut self, target: Rect) {
+        let current = self.bounds();
+        let scale = |size: f32, target: f32| if size > 0.0 { target / size } else { 1.0 };
+        let scale_x = scale(current.width(), target.width());
+        let scale_y = scale(current.height(), target.height());
+        let map = |p: Point| {
+            target.min + point((p.x - current.min.x) * scale_x, (p.y - current.min.y) * scale_y)
+        };
+        match self {
+            Shape::Rectangle { rect }
+            | Shape::Ellipse { rect }
+            | Shape::Sticky { rect, .. }
+            | Shape::Image { rect, .. }
+            | Shape::Frame { rect, .. } => *rect = target,
+            Shape::Line { start, end, .. } => {
+                *start = map(*start);
+                *end = map(*end);
+            }
+            Shape::Freehand { points, .. } | Shape::Connector { points, .. } => {
+                points.iter_mut().for_each(|p| *p = map(*p))
+            }
+            Shape::Text {
+                position, font, width, ..
+            } => {
+                *position = target.min;
+                if (scale_y - 1.0).abs() <= 1e-4 {
+                    // pulling a side only changes where the lines wrap
+                    *width = Some(target.width().max(font.size));
+                } else {
+                    // text keeps its proportions, it only grows with the height
+                    font.size *= scale_y;
+                    *width = width.map(|width| width * scale_y);
+                }
+            }
+            Shape::Group { children } => {
+                let stretch = Affine::rect_to_rect(&current, &target);
+                children.iter_mut().for_each(|child| child.transform_by(&stretch));
+            }
+        }
+    }
+
+    /// Moves the transform into the geometry where the shape can express it,
+    /// so strokes keep their width. Returns false, leaving the shape alone,
+    /// when it can't: rotated or skewed boxes, and stretched or mirrored text.
+    pub fn apply_transform(&mut self, transform: &Affine) -> bool {
+        match self {
+            Shape::Line { start, end, .. } => {
+                *start = transform.apply(*start);
+                *end = transform.apply(*end);
+            }
+            Shape::Freehand { points, .. } | Shape::Connector { points, .. } => {
+                points.iter_mut().for_each(|p| *p = transform.apply(*p))
+            }
+            Shape::Rectangle { rect } | Shape::Ellipse { rect } | Shape::Sticky { rect, .. } => {
+                if !transform.is_axis_aligned() {
+                    return false;
+                }
+                *rect = transform.transform_rect(rect);
+            }
+            Shape::Image { rect, .. } => {
+                // mirroring would be lost in the rect, the element transform keeps it
+                if !transform.is_axis_aligned() || transform.a < 0.0 || transform.d < 0.0 {
+                    return false;
+                }
+                *rect = transform.transform_rect(rect);
+            }
+            Shape::Text {
+                position, font, width, ..
+            } => {
+                let upright = transform.a > 0.0 && transform.b.abs() <= transform.a * 1e-5;
+                let uniform = (transform.a - transform.d).abs() <= transform.a * 1e-5;
+                if !transform.is_axis_aligned() || !upright || !uniform {
+                    return false;
+                }
+                *position = transform.apply(*position);
+                font.size *= transform.a;
+                *width = width.map(|width| width * transform.a);
+            }
+            Shape::Group { children } => children.iter_mut().for_each(|child| child.transform_by(transform)),
+            Shape::Frame { rect, children, .. } => {
+                // frames stay upright, around where their turned rect went
+                *rect = transform.transform_rect(rect);
+                children.iter_mut().for_each(|child| child.transform_by(transform));
+            }
+        }
+        true
+    }
+
+    pub fn translate(&mut self, delta: Point) {
+        match self {
+            Shape::Rectangle { rect }
+            | Shape::Ellipse { rect }
+            | Shape::Sticky { rect, .. }
+            | Shape::Image { rect, .. } => *rect = rect.translate(delta),
+            Shape::Line { start, end, .. } => {
+                *start += delta;
+                *end += delta;
+            }
+            Shape::Freehand { points, .. } | Shape::Connector { points, .. } => {
+                points.iter_mut().for_each(|p| *p += delta)
+            }
+            Shape::Text { position, .. } => *position += delta,
+            Shape::Group { children } => children.iter_mut().for_each(|child| child.translate(delta)),
+            Shape::Frame { rect, children, .. } => {
+                *rect = rect.translate(delta);
+                children.iter_mut().for_each(|child| child.translate(delta));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Element {
+    pub id: ElementId,
+    /// Geometry before `transform`.
+    pub shape: Shape,
+    pub style: Style,
+    /// Maps the shape into the world, rotating, flipping or skewing it. Kept
+    /// at the identity whenever the shape itself can hold the change, see
+    /// [`Shape::apply_transform`].
+    #[serde(default, skip_serializing_if = "Affine::is_identity")]
+    pub transform: Affine,
+    /// Layer of a top level element, group children go with their group.
+    #[serde(default, skip_serializing_if = "LayerId::is_default")]
+    pub layer: LayerId,
+    /// Not drawn, exported or picked.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    /// Drawn but can't be picked, so it can't be moved or edited by accident.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+}
+
+impl Element {
+    pub fn new(id: ElementId, shape: Shape, style: Style) -> Self {
+        Element {
+            id,
+            shape,
+            style,
+            transform: Affine::IDENTITY,
+            layer: LayerId::default(),
+            hidden: false,
+            locked: false,
+        }
+    }
+
+    /// An empty group to put elements into, see [`crate::group`].
+    pub fn group(id: ElementId, children: Vec<Element>) -> Self {
+        let style = Style {
+            fill: None,
+            stroke: None,
+            ..Style::default()
+        };
+        Element::new(id, Shape::Group { children }, style)
+    }
+
+    pub fn is_group(&self) -> bool {
+        matches!(self.shape, Shape::Group { .. })
+    }
+
+    /// Children of a group or frame, empty for everything else.
+    pub fn children(&self) -> &[Element] {
+        match &self.shape {
+            Shape::Group { children } | Shape::Frame { children, .. } => children,
+            _ => &[],
+        }
+    }
+
+    /// Children of a group or frame, `None` for elements that can't hold any.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<Element>> {
+        match &mut self.shape {
+            Shape::Group { children } | Shape::Frame { children, .. } => Some(children),
+            _ => None,
+        }
+    }
+
+    /// The element or one of its descendants with `id`.
+    pub fn find(&self, id: ElementId) -> Option<&Element> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children().iter().find_map(|child| child.find(id))
+    }
+
+    pub fn find_mut(&mut self, id: ElementId) -> Option<&mut Element> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children_mut()?.iter_mut().find_map(|child| child.find_mut(id))
+    }
+
+    /// The group or frame directly holding descendant `id`.
+    pub fn parent_of(&self, id: ElementId) -> Option<&Element> {
+        let children = self.children();
+        if children.iter().any(|child| child.id == id) {
+            return Some(self);
+        }
+        children.iter().find_map(|child| child.parent_of(id))
+    }
+
+    /// Ids of every element below this one, depth first.
+    pub fn descendant_ids(&self) -> Vec<ElementId> {
+        let mut ids = Vec::new();
+        for child in self.children() {
+            ids.push(child.id);
+            ids.extend(child.descendant_ids());
+        }
+        ids
+    }
+
+    /// Topmost child of a group or frame under the world point `p`, skipping
+    /// hidden and locked ones.
+    pub fn child_at(&self, p: Point, tolerance: f32) -> Option<ElementId> {
+        let inverse = self.transform.inverse()?;
+        let local_tolerance = tolerance / self.transform.scale_factor();
+        self.children()
+            .iter()
+            .rev()
+            .filter(|child| !child.hidden && !child.locked)
+            .find(|child| child.hit_test(inverse.apply(p), local_tolerance))
+            .map(|child| child.id)
+    }
+
+    pub(crate) fn half_stroke(&self) -> f32 {
+        match self.style.stroke {
+            Some(_) => self.style.stroke_width * 0.5,
+            None => 0.0,
+        }
+    }
+
+    /// World bounds of the geometry grown by `inflate` before the transform.
+    /// Group children bring their own stroke, `painted` says whether it counts.
+    fn transformed_bounds(&self, inflate: f32, painted: bool) -> Rect {
+        let t = &self.transform;
+        if let Shape::Group { children } = &self.shape {
+            return children
+                .iter()
+                .map(|child| if painted { child.bounds() } else { child.geometry_bounds() })
+                .map(|rect| t.transform_rect(&rect))
+                .reduce(|a, b| a.union(&b))
+                .unwrap_or_default();
+        }
+        if t.is_identity() {
+            return self.shape.bounds().inflate(inflate);
+        }
+        match &self.shape {
+            Shape::Line { start, end, .. } => Rect::from_corners(t.apply(*start), t.apply(*end))
+                .inflate(inflate * t.scale_factor()),
+            Shape::Freehand { points, .. } | Shape::Connector { points, .. } => {
+                Rect::from_points(points.iter().map(|&p| t.apply(p)))
+                    .unwrap_or_default()
+                    .inflate(inflate * t.scale_factor())
+            }
+            Shape::Ellipse { rect } => {
+                // the transformed ellipse touches its bounds where the
+                // images of the two semi axes add up the most
+                let rect = rect.inflate(inflate);
+                let u = t.apply_vector(point(rect.width() * 0.5, 0.0));
+                let v = t.apply_vector(point(0.0, rect.height() * 0.5));
+                let half = point(u.x.hypot(v.x), u.y.hypot(v.y));
+                let center = t.apply(rect.center());
+                Rect::from_corners(center - half, center + half)
+            }
+            Shape::Rectangle { .. }
+            | Shape::Text { .. }
+            | Shape::Sticky { .. }
+            | Shape::Image { .. }
+            | Shape::Group { .. }
+            | Shape::Frame { .. } => {
+                t.transform_rect(&self.shape.bounds().inflate(inflate))
+            }
+        }
+    }
+
+    /// World bounds of the geometry, not including stroke width.
+    pub fn geometry_bounds(&self) -> Rect {
+        self.transformed_bounds(0.0, false)
+    }
+
+    /// World bounds including half the stroke width, i.e. everything that gets painted.
+    pub fn bounds(&self) -> Rect {
+        self.transformed_bounds(self.half_stroke(), true)
+    }
+
+    /// Corners of the shape's own bounds in the world, following rotation.
+    pub fn outline(&self) -> [Point; 4] {
+        self.shape.bounds().corners().map(|p| self.transform.apply(p))
+    }
+
+    /// Whether the world point `p` hits the element, `tolerance` is in world units.
+    pub fn hit_test(&self, p: Point, tolerance: f32) -> bool {
+        if self.transform.is_identity() {
+            return self.shape.hit_test(p, tolerance + self.half_stroke());
+        }
+        let Some(inverse) = self.transform.inverse() else {
+            return false;
+        };
+        let local_tolerance = tolerance / self.transform.scale_factor();
+        self.shape.hit_test(inverse.apply(p), local_tolerance + self.half_stroke())
+    }
+
+    /// Moves the element by `delta` in world space.
+    pub fn translate(&mut self, delta: Point) {
+        if self.transform.is_identity() {
+            self.shape.translate(delta);
+        } else {
+            self.transform = self.transform.then(&Affine::translate(delta));
+        }
+    }
+
+    /// Applies `transform` in world space after the current one, folding it
+    /// into the shape when possible.
+    pub fn transform_by(&mut self, transform: &Affine) {
+        let combined = self.transform.then(transform);
+        let mut shape = self.shape.clone();
+        if shape.apply_transform(&combined) {
+            self.shape = shape;
+            self.transform = Affine::IDENTITY;
+        } else {
+            self.transform = combined;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ellipse_hit_test_ignores_corners() {
+        let shape = Shape::Ellipse {
+            rect: Rect::from_corners(point(0.0, 0.0), point(100.0, 50.0)),
+        };
+        assert!(shape.hit_test(point(50.0, 25.0), 0.0));
+        assert!(shape.hit_test(point(99.0, 25.0), 0.0));
+        assert!(!shape.hit_test(point(2.0, 2.0), 0.0));
+    }
+
+    #[test]
+    fn freehand_hit_test_uses_segments() {
+        let shape = Shape::Freehand {
+            points: vec![point(0.0, 0.0), point(10.0, 0.0), point(10.0, 10.0)],
+            pressure: Vec::new(),
+        };
+        assert!(shape.hit_test(point(5.0, 1.0), 2.0));
+        assert!(shape.hit_test(point(11.0, 5.0), 2.0));
+        assert!(!shape.hit_test(point(5.0, 5.0), 2.0));
+    }
+
+    #[test]
+    fn element_bounds_include_stroke() {
+        let element = Element::new(
+            ElementId(1),
+            Shape::line(point(0.0, 0.0), point(10.0, 0.0)),
+            Style::stroked(crate::style::BLACK, 4.0),
+        );
+        assert_eq!(element.bounds(), Rect::from_corners(point(-2.0, -2.0), point(12.0, 2.0)));
+    }
+
+    #[test]
+    fn fit_to_scales_points_into_target() {
+        let mut shape = Shape::line(point(0.0, 0.0), point(10.0, 20.0));
+        shape.fit_to(Rect::from_corners(point(5.0, 5.0), point(25.0, 15.0)));
+        assert_eq!(shape, Shape::line(point(5.0, 5.0), point(25.0, 15.0)));
+    }
+
+    #[test]
+    fn rotated_boxes_keep_their_transform() {
+        let mut element = Element::new(
+            ElementId(1),
+            Shape::Rectangle {
+                rect: Rect::from_corners(point(0.0, 0.0), point(20.0, 10.0)),
+            },
+            Style::filled(crate::style::BLACK),
+        );
+        let eighth = Affine::around(point(10.0, 5.0), &Affine::rotate(std::f32::consts::FRAC_PI_4));
+        element.transform_by(&eighth);
+        assert!(!element.transform.is_identity());
+        let half_diagonal = 15.0 / std::f32::consts::SQRT_2;
+        let bounds = element.bounds();
+        assert!(bounds.min.distance(point(10.0 - half_diagonal, 5.0 - half_diagonal)) < 1e-4);
+        // the corner of the original rect is now empty space
+        assert!(!element.hit_test(point(1.0, 9.0), 0.0));
+        assert!(element.hit_test(point(7.0, -4.0), 0.0));
+
+        // a quarter turn in total lines the box up with the axes again
+        element.transform_by(&eighth);
+        assert!(element.transform.is_identity());
+        let bounds = element.shape.bounds();
+        assert!(bounds.min.distance(point(5.0, -5.0)) < 1e-4);
+        assert!(bounds.max.distance(point(15.0, 15.0)) < 1e-4);
+    }
+
+    #[test]
+    fn lines_bake_any_transform() {
+        let mut element = Element::new(ElementId(1), Shape::line(point(0.0, 0.0), point(10.0, 0.0)), Style::default());
+        element.transform_by(&Affine::rotate(std::f32::consts::FRAC_PI_2));
+        assert!(element.transform.is_identity());
+        let Shape::Line { end, .. } = element.shape else { unreachable!() };
+        assert!(end.distance(point(0.0, 10.0)) < 1e-5);
+    }
+
+    #[test]
+    fn rotated_ellipse_bounds_are_tight() {
+        let mut element = Element::new(
+            ElementId(1),
+            Shape::Ellipse {
+                rect: Rect::from_corners(point(-20.0, -10.0), point(20.0, 10.0)),
+            },
+            Style::filled(crate::style::BLACK),
+        );
+        element.transform_by(&Affine::rotate(std::f32::consts::FRAC_PI_2));
+        let bounds = element.bounds();
+        assert!(bounds.min.distance(point(-10.0, -20.0)) < 1e-4);
+        assert!(bounds.max.distance(point(10.0, 20.0)) < 1e-4);
+    }
+
+    #[test]
+    fn mirrored_text_stays_transformed() {
+        let mut shape = Shape::text(point(0.0, 0.0), "hi", 10.0);
+        assert!(!shape.apply_transform(&Affine::scale_xy(-1.0, 1.0)));
+        assert!(shape.apply_transform(&Affine::scale(2.0)));
+        assert!(matches!(shape, Shape::Text { font, .. } if font.size == 20.0));
+    }
+
+    #[test]
+    fn translate_moves_every_point() {
+        let mut shape = Shape::Freehand {
+            points: vec![point(0.0, 0.0), point(1.0, 1.0)],
+            pressure: Vec::new(),
+        };
+        shape.translate(point(5.0, -5.0));
+        assert_eq!(
+            shape,
+            Shape::Freehand {
+                points: vec![point(5.0, -5.0), point(6.0, -4.0)],
+                pressure: Vec::new(),
+            }
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Erasing pen strokes with a round eraser dragged over the board.
+//!
+//! The eraser either deletes every stroke it touches or cuts away only the
+//! parts of the strokes under it, leaving the pieces on either side as
+//! strokes of their own. Other elements are never erased.
+
+use crate::document::Document;
+use crate::element::{Element, ElementId, Shape};
+use crate::geometry::{Affine, Point, Rect};
+use crate::history::History;
+
+/// What happens to the strokes the eraser touches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EraseMode {
+    /// they are deleted whole
+    #[default]
+    Stroke,
+    /// only the parts under the eraser are cut away
+    Precise,
+}
+
+/// The part of the segment from `a` to `b` inside the circle, as the
+/// fractions along the segment where it enters and leaves the circle.
+/// `None` when it stays outside or only grazes it.
+pub fn segment_inside_circle(a: Point, b: Point, center: Point, radius: f32) -> Option<(f32, f32)> {
+    let d = b - a;
+    let f = a - center;
+    let (qa, qb, qc) = (d.dot(d), 2.0 * f.dot(d), f.dot(f) - radius * radius);
+    if qa == 0.0 {
+        return (qc < 0.0).then_some((0.0, 1.0));
+    }
+    let discriminant = qb * qb - 4.0 * qa * qc;
+    if discriminant <= 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let enter = ((-qb - root) / (2.0 * qa)).max(0.0);
+    let leave = ((-qb + root) / (2.0 * qa)).min(1.0);
+    (enter < leave).then_some((enter, leave))
+}
+
+/// Part of a stroke being collected by [`cut_stroke`].
+#[derive(Default)]
+struct Piece {
+    points: Vec<Point>,
+    pressure: Vec<f32>,
+}
+
+impl Piece {
+    fn push(&mut self, p: Point, pressure: Option<f32>) {
+        self.points.push(p);
+        self.pressure.extend(pressure);
+    }
+
+    /// The piece as a stroke, dropping pieces too short to be one.
+    fn take(&mut self) -> Option<Shape> {
+        let piece = std::mem::take(self);
+        (piece.points.len() >= 2).then_some(Shape::Freehand {
+            points: piece.points,
+            pressure: piece.pressure,
+        })
+    }
+}
+
+/// The pieces of the stroke through `points` left outside the circle, with
+/// the pressure interpolated where they are cut. `None` when the circle
+/// doesn't reach the stroke, an empty list when it covers all of it.
+pub fn cut_stroke(points: &[Point], pressure: &[f32], center: Point, radius: f32) -> Option<Vec<Shape>> {
+    if let [only] = points {
+        return (only.distance(center) < radius).then(Vec::new);
+    }
+    let with_pressure = pressure.len() == points.len();
+    let pressure_at = |i: usize, t: f32| with_pressure.then(|| pressure[i] + (pressure[i + 1] - pressure[i]) * t);
+    let mut pieces = Vec::new();
+    let mut current = Piece::default();
+    let mut cut = false;
+    for (i, segment) in points.windows(2).enumerate() {
+        let (a, b) = (segment[0], segment[1]);
+        let inside = segment_inside_circle(a, b, center, radius);
+        let (enter, leave) = inside.unwrap_or((1.0, 1.0));
+        if enter > 0.0 {
+            if current.points.is_empty() {
+                current.push(a, pressure_at(i, 0.0));
+            }
+            current.push(a.lerp(b, enter), pressure_at(i, enter));
+        }
+        if inside.is_some() {
+            cut = true;
+            pieces.extend(current.take());
+            if leave < 1.0 {
+                current.push(a.lerp(b, leave), pressure_at(i, leave));
+                current.push(b, pressure_at(i, 1.0));
+            }
+        }
+    }
+    pieces.extend(current.take());
+    cut.then_some(pieces)
+}
+
+/// [`cut_stroke`] for a freehand shape, other shapes are never cut.
+fn cut_shape(shape: &Shape, center: Point, radius: f32) -> Option<Vec<Shape>> {
+    match shape {
+        Shape::Freehand { points, pressure } => cut_stroke(points, pressure, center, radius),
+        _ => None,
+    }
+}
+
+/// Eraser positions along a drag from `from` to `to`, close enough that
+/// the circles leave no gaps. `from` was erased at by the previous call.
+fn sweep(from: Point, to: Point, radius: f32) -> Vec<Point> {
+    let steps = (from.distance(to) / (radius * 0.5)).ceil().max(1.0) as usize;
+    (1..=steps).map(|i| from.lerp(to, i as f32 / steps as f32)).collect()
+}
+
+/// The visible and unlocked strokes in `element`, itself or inside it, with
+/// the transform from their coordinates to the world.
+fn collect_strokes(element: &Element, to_world: Affine, strokes: &mut Vec<(ElementId, Affine)>) {
+    let to_world = element.transform.then(&to_world);
+    match &element.shape {
+        Shape::Freehand { .. } => strokes.push((element.id, to_world)),
+        _ => {
+            for child in element.children().iter().filter(|child| !child.hidden && !child.locked) {
+                collect_strokes(child, to_world, strokes);
+            }
+        }
+    }
+}
+
+/// Puts `element` right above `sibling`, in the same group or frame.
+fn insert_above(history: &mut History, document: &mut Document, sibling: ElementId, element: Element) {
+    if let Some(parent) = document.parent_of(sibling).map(|parent| parent.id) {
+        history.update(document, parent, |parent| {
+            if let Some(children) = parent.children_mut() {
+                let index = children.iter().position(|child| child.id == sibling);
+                children.insert(index.map_or(children.len(), |i| i + 1), element);
+            }
+        });
+    } else if let Some(index) = document.index_of(sibling) {
+        history.insert(document, index + 1, element);
+    }
+}
+
+/// Erases the strokes an eraser of `radius` world units touches while it
+/// moves from `from` to `to`, also ones in groups and frames. Strokes cut
+/// in several pieces keep their style and stacking. Returns whether
+/// anything was erased. Call it inside a gesture so a drag is one undo step.
+pub fn erase(
+    history: &mut History,
+    document: &mut Document,
+    from: Point,
+    to: Point,
+    radius: f32,
+    mode: EraseMode,
+) -> bool {
+    let area = Rect::from_corners(from, to).inflate(radius);
+    let mut strokes = Vec::new();
+    for element in document.elements_intersecting(&area) {
+        if document.is_pickable(element.id) {
+            collect_strokes(element, Affine::IDENTITY, &mut strokes);
+        }
+    }
+    let centers = sweep(from, to, radius);
+    let mut erased = false;
+    for (id, to_world) in strokes {
+        let (Some(stroke), Some(to_local)) = (document.get(id).cloned(), to_world.inverse()) else {
+            continue;
+        };
+        // the eraser reaches the edge of the ink, not only its middle
+        let local_radius = radius / to_world.scale_factor() + stroke.half_stroke();
+        let local_centers: Vec<Point> = centers.iter().map(|&center| to_local.apply(center)).collect();
+        match mode {
+            EraseMode::Stroke => {
+                if local_centers.iter().any(|&center| stroke.shape.hit_test(center, local_radius)) {
+                    history.remove(document, id);
+                    erased = true;
+                }
+            }
+            EraseMode::Precise => {
+                let mut pieces = vec![stroke.shape.clone()];
+                let mut cut = false;
+                for &center in &local_centers {
+                    let mut left = Vec::new();
+                    for piece in pieces {
+                        match cut_shape(&piece, center, local_radius) {
+                            Some(rest) => {
+                                cut = true;
+                                left.extend(rest);
+                            }
+                            None => left.push(piece),
+                        }
+                    }
+                    pieces = left;
+                }
+                if !cut {
+                    continue;
+                }
+                erased = true;
+                let mut pieces = pieces.into_iter();
+                let Some(first) = pieces.next() else {
+                    history.remove(document, id);
+                    continue;
+                };
+                history.update(document, id, |element| element.shape = first);
+                // later pieces go on top of earlier ones, like the stroke drew them
+                let mut below = id;
+                for piece in pieces {
+                    let element = Element {
+                        id: document.new_id(),
+                        shape: piece,
+                        ..stroke.clone()
+                    };
+                    let above = element.id;
+                    insert_above(history, document, below, element);
+                    below = above;
+                }
+            }
+        }
+    }
+    erased
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::point;
+    use crate::style::{Style, BLACK};
+
+    fn stroke(points: &[Point]) -> Shape {
+        Shape::Freehand {
+            points: points.to_vec(),
+            pressure: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn segments_enter_and_leave_circles() {
+        let (a, b) = (point(0.0, 0.0), point(10.0, 0.0));
+        assert_eq!(segment_inside_circle(a, b, point(5.0, 0.0), 1.0), Some((0.4, 0.6)));
+        // starting inside, the part inside starts at the start
+        assert_eq!(segment_inside_circle(a, b, point(0.0, 0.0), 2.0), Some((0.0, 0.2)));
+        assert_eq!(segment_inside_circle(a, b, point(5.0, 0.0), 20.0), Some((0.0, 1.0)));
+        // missing, grazing and stopping short
+        assert_eq!(segment_inside_circle(a, b, point(5.0, 3.0), 2.0), None);
+        assert_eq!(segment_inside_circle(a, b, point(5.0, 1.0), 1.0), None);
+        assert_eq!(segment_inside_circle(a, b, point(14.0, 0.0), 2.0), None);
+        // a segment of no length is a point
+        assert_eq!(segment_inside_circle(a, a, point(0.5, 0.0), 1.0), Some((0.0, 1.0)));
+    }
+
+    #[test]
+    fn cutting_the_middle_leaves_two_pieces() {
+        let points = [point(0.0, 0.0), point(10.0, 0.0), point(20.0, 0.0)];
+        let pressure = [0.0, 0.5, 1.0];
+        let pieces = cut_stroke(&points, &pressure, point(10.0, 0.0), 2.0).unwrap();
+        assert_eq!(
+            pieces,
+            vec![
+                Shape::Freehand {
+                    points: vec![point(0.0, 0.0), point(8.0, 0.0)],
+                    pressure: vec![0.0, 0.4],
+                },
+                Shape::Freehand {
+                    points: vec![point(12.0, 0.0), point(20.0, 0.0)],
+                    pressure: vec![0.6, 1.0],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cutting_an_end_or_all_of_it() {
+        let points = [point(0.0, 0.0), point(10.0, 0.0), point(10.0, 10.0)];
+        let pieces = cut_stroke(&points, &[], point(0.0, 0.0), 5.0).unwrap();
+        assert_eq!(pieces, vec![stroke(&[point(5.0, 0.0), point(10.0, 0.0), point(10.0, 10.0)])]);
+
+        assert_eq!(cut_stroke(&points, &[], point(5.0, 5.0), 50.0), Some(Vec::new()));
+        assert_eq!(cut_stroke(&points, &[], point(-10.0, 10.0), 5.0), None);
+        assert_eq!(cut_stroke(&[point(1.0, 1.0)], &[], point(0.0, 0.0), 2.0), Some(Vec::new()));
+    }
+
+    #[test]
+    fn stroke_mode_deletes_what_the_drag_touches() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let crossed = document.add(stroke(&[point(50.0, -20.0), point(50.0, 20.0)]), Style::stroked(BLACK, 2.0));
+        let missed = document.add(stroke(&[point(0.0, 30.0), point(100.0, 30.0)]), Style::stroked(BLACK, 2.0));
+
+        // the eraser jumps over the first stroke between two frames
+        history.begin_gesture();
+        assert!(erase(&mut history, &mut document, point(0.0, 0.0), point(100.0, 0.0), 4.0, EraseMode::Stroke));
+        history.end_gesture(&document);
+        assert!(document.get(crossed).is_none());
+        assert!(document.get(missed).is_some());
+
+        history.undo(&mut document);
+        assert!(document.get(crossed).is_some());
+    }
+
+    #[test]
+    fn precise_mode_splits_strokes_in_groups() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let style = Style::stroked(BLACK, 2.0);
+        let line = document.add(stroke(&[point(0.0, 0.0), point(100.0, 0.0)]), style.clone());
+        let other = document.add(stroke(&[point(0.0, 50.0), point(10.0, 50.0)]), style.clone());
+        let group = crate::group::group_elements(&mut history, &mut document, &[line, other]).unwrap();
+
+        history.begin_gesture();
+        // 3 above the stroke, with the ink 2 wide the eraser reaches 5 down and
+        // cuts 4 to either side
+        assert!(erase(&mut history, &mut document, point(50.0, 3.0), point(50.0, 3.0), 4.0, EraseMode::Precise));
+        history.end_gesture(&document);
+
+        let children = document.get(group).unwrap().children();
+        assert_eq!(children.len(), 3);
+        assert_eq!(children[0].id, line);
+        let ends = |shape: &Shape| match shape {
+            Shape::Freehand { points, .. } => (points[0], points[points.len() - 1]),
+            _ => unreachable!(),
+        };
+        let (start, end) = ends(&children[0].shape);
+        assert_eq!(start, point(0.0, 0.0));
+        assert!((end.x - 46.0).abs() < 1e-3);
+        let (start, end) = ends(&children[1].shape);
+        assert!((start.x - 54.0).abs() < 1e-3);
+        assert_eq!(end, point(100.0, 0.0));
+        assert_eq!(children[1].style, style);
+        assert_eq!(children[2].id, other);
+
+        history.undo(&mut document);
+        assert_eq!(document.get(group).unwrap().children().len(), 2);
+    }
+}
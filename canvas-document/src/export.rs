@@ -0,0 +1,51 @@
+//! What goes into an exported image, shared by the SVG writer and the rasterizer.
+
+use crate::document::Document;
+use crate::element::{Element, ElementId, Shape};
+use crate::geometry::Rect;
+
+/// Which part of the board ends up in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportArea {
+    /// Every element, cropped to their combined bounds.
+    Board,
+    /// What the camera sees, given as a world space rect.
+    Viewport(Rect),
+    /// Only these elements, cropped to their combined bounds.
+    Selection(Vec<ElementId>),
+    /// A frame and its content, cropped to the frame without padding.
+    Frame(ElementId),
+}
+
+impl ExportArea {
+    /// Visible elements to export in paint order and the world rect the
+    /// image covers, grown by `padding` on every side.
+    pub fn resolve<'a>(&self, document: &'a Document, padding: f32) -> (Vec<&'a Element>, Rect) {
+        let (mut elements, area): (Vec<&Element>, Rect) = match self {
+            ExportArea::Board => (document.iter().collect(), document.bounds().unwrap_or_default()),
+            ExportArea::Viewport(rect) => (document.elements_intersecting(rect), *rect),
+            ExportArea::Selection(ids) => {
+                // keep paint order, not selection order, and allow elements inside groups
+                let elements: Vec<&Element> = document
+                    .iter_nested()
+                    .into_iter()
+                    .filter(|e| ids.contains(&e.id) && document.is_visible(e.id))
+                    .collect();
+                let bounds = elements
+                    .iter()
+                    .map(|e| e.bounds())
+                    .reduce(|a, b| a.union(&b))
+                    .unwrap_or_default();
+                (elements, bounds)
+            }
+            ExportArea::Frame(id) => {
+                let frame = document.get(*id).filter(|e| matches!(e.shape, Shape::Frame { .. }));
+                let area = frame.map(|e| e.geometry_bounds()).unwrap_or_default();
+                let elements = frame.into_iter().filter(|e| document.is_visible(e.id)).collect();
+                return (elements, area);
+            }
+        };
+        elements.retain(|e| document.is_visible(e.id));
+        (elements, area.inflate(padding))
+    }
+}
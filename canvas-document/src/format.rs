@@ -0,0 +1,279 @@
+//! The on-disk format of a canvas.
+//!
+//! Files are JSON with a top level `version`. Fields added later are optional
+//! and fall back to their defaults, so older files parse as they are and the
+//! version stays put. Only changing what existing fields mean bumps it, along
+//! with a migration appended to [`MIGRATIONS`] that upgrades files written by
+//! older builds step by step before they are parsed.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::document::Document;
+use crate::element::ElementId;
+use crate::geometry::{Affine, Point, Rect};
+use crate::grid::BACKGROUND_COLOR;
+use crate::style::Color;
+
+/// Version written by this build.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` file to version `n + 2`.
+const MIGRATIONS: &[fn(&mut Value)] = &[];
+
+/// Where the camera was looking when the canvas was saved.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraState {
+    pub position: Point,
+    pub zoom: f32,
+}
+
+impl CameraState {
+    /// Maps world to screen coordinates, `position` ends up in the middle of a
+    /// surface `viewport` pixels large.
+    pub fn view_transform(&self, viewport: Point) -> Affine {
+        Affine::translate(-self.position)
+            .then(&Affine::scale(self.zoom))
+            .then(&Affine::translate(viewport * 0.5))
+    }
+
+    /// The part of the world visible on a surface `viewport` pixels large.
+    pub fn visible_rect(&self, viewport: Point) -> Rect {
+        let half = viewport * (0.5 / self.zoom);
+        Rect::from_corners(self.position - half, self.position + half)
+    }
+}
+
+impl Default for CameraState {
+    fn default() -> Self {
+        CameraState {
+            position: Point::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanvasSettings {
+    pub background: Color,
+    pub show_grid: bool,
+    /// dragged shapes snap to the grid dots
+    #[serde(default = "snap_by_default")]
+    pub snap_to_grid: bool,
+}
+
+fn snap_by_default() -> bool {
+    true
+}
+
+impl Default for CanvasSettings {
+    fn default() -> Self {
+        CanvasSettings {
+            background: BACKGROUND_COLOR,
+            show_grid: true,
+            snap_to_grid: snap_by_default(),
+        }
+    }
+}
+
+/// Everything that gets written to a canvas file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedCanvas {
+    pub document: Document,
+    pub camera: CameraState,
+    pub settings: CanvasSettings,
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Json(serde_json::Error),
+    MissingVersion,
+    /// The file was written by a newer build than this one.
+    UnsupportedVersion(u32),
+    /// Two elements share an id.
+    DuplicateId(ElementId),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Json(err) => write!(f, "invalid canvas file: {}", err),
+            FormatError::MissingVersion => write!(f, "canvas file has no version"),
+            FormatError::UnsupportedVersion(version) => write!(
+                f,
+                "canvas file version {} is newer than the supported version {}",
+                version, FORMAT_VERSION
+            ),
+            FormatError::DuplicateId(id) => write!(f, "element id {} is used more than once", id.0),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<serde_json::Error> for FormatError {
+    fn from(err: serde_json::Error) -> Self {
+        FormatError::Json(err)
+    }
+}
+
+impl SavedCanvas {
+    pub fn to_json(&self) -> Result<String, FormatError> {
+        let mut value = serde_json::to_value(self)?;
+        value["version"] = FORMAT_VERSION.into();
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+
+    /// Parses a canvas file, migrating it first if it was written by an older build.
+    pub fn from_json(json: &str) -> Result<Self, FormatError> {
+        let mut value: Value = serde_json::from_str(json)?;
+        let version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or(FormatError::MissingVersion)? as u32;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            migrate(&mut value);
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::Shape;
+    use crate::geometry::{point, Rect};
+    use crate::style::{Style, BLACK};
+    use crate::text::{Font, FontFamily, TextAlign};
+
+    fn sample() -> SavedCanvas {
+        let mut document = Document::new();
+        document.add(
+            Shape::Rectangle {
+                rect: Rect::from_corners(point(0.0, 0.0), point(40.0, 20.0)),
+            },
+            Style::filled(Color::new(0.2, 0.4, 0.6, 0.5)),
+        );
+        document.add(
+            Shape::Freehand {
+                points: vec![point(1.0, 2.0), point(3.5, -4.25)],
+                pressure: Vec::new(),
+            },
+            Style::stroked(BLACK, 3.0),
+        );
+        document.add(
+            Shape::text(point(-10.0, 5.0), "hello\nworld", 18.0),
+            Style::filled(BLACK),
+        );
+        document.add(
+            Shape::Text {
+                position: point(0.0, 40.0),
+                content: "wrapped and centered".to_string(),
+                font: Font {
+                    family: FontFamily::Serif,
+                    bold: true,
+                    italic: true,
+                    ..Font::new(12.0)
+                },
+                align: TextAlign::Center,
+                width: Some(80.0),
+            },
+            Style::filled(BLACK),
+        );
+        // the store doesn't care whether the bytes decode
+        let asset = document.assets_mut().insert(vec![0x89, b'P', b'N', b'G', 1, 2, 3]);
+        let mut image = Shape::image(Rect::from_corners(point(50.0, 0.0), point(90.0, 30.0)), asset);
+        crate::image::crop_to(&mut image, Rect::from_corners(point(60.0, 0.0), point(90.0, 30.0)));
+        document.add(image, Style::default());
+        SavedCanvas {
+            document,
+            camera: CameraState {
+                position: point(120.0, -30.0),
+                zoom: 2.5,
+            },
+            settings: CanvasSettings::default(),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let saved = sample();
+        let json = saved.to_json().unwrap();
+        assert_eq!(SavedCanvas::from_json(&json).unwrap(), saved);
+    }
+
+    #[test]
+    fn writes_current_version() {
+        let json = sample().to_json().unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], FORMAT_VERSION);
+    }
+
+    #[test]
+    fn reads_plain_text_written_before_fonts() {
+        let json = r#"{"type": "text", "position": {"x": 1.0, "y": 2.0}, "content": "hi", "font_size": 14.0}"#;
+        let shape: Shape = serde_json::from_str(json).unwrap();
+        assert_eq!(shape, Shape::text(point(1.0, 2.0), "hi", 14.0));
+        let value = serde_json::to_value(&shape).unwrap();
+        assert_eq!(value.as_object().unwrap().len(), 4, "defaults are left out: {value}");
+    }
+
+    #[test]
+    fn pictures_are_saved_with_the_document() {
+        let json = sample().to_json().unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        let assets = value["document"]["assets"].as_object().unwrap();
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets.values().next().unwrap(), "iVBORwECAw==");
+    }
+
+    #[test]
+    fn pictures_of_deleted_images_are_not_saved() {
+        let mut document = Document::new();
+        let mut history = crate::history::History::default();
+        let asset = document.assets_mut().insert(vec![0x89, b'P', b'N', b'G', 1, 2, 3]);
+        let image = Shape::image(Rect::from_corners(point(0.0, 0.0), point(40.0, 30.0)), asset);
+        let id = history.add(&mut document, image, Style::default());
+        history.remove(&mut document, id);
+        // kept in memory for undo
+        assert_eq!(document.assets().len(), 1);
+
+        let saved = SavedCanvas {
+            document,
+            ..SavedCanvas::default()
+        };
+        let value: Value = serde_json::from_str(&saved.to_json().unwrap()).unwrap();
+        assert!(value["document"].get("assets").is_none(), "{}", value["document"]);
+        let loaded = SavedCanvas::from_json(&saved.to_json().unwrap()).unwrap();
+        assert!(loaded.document.assets().is_empty());
+    }
+
+    #[test]
+    fn ids_keep_counting_after_load() {
+        let saved = sample();
+        let mut loaded = SavedCanvas::from_json(&saved.to_json().unwrap()).unwrap();
+        let id = loaded.document.add(
+            Shape::line(Point::ZERO, point(1.0, 1.0)),
+            Style::default(),
+        );
+        assert!(saved.document.get(id).is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        assert!(matches!(
+            SavedCanvas::from_json(r#"{"document": {}}"#),
+            Err(FormatError::MissingVersion)
+        ));
+        let future = format!(r#"{{"version": {}}}"#, FORMAT_VERSION + 1);
+        assert!(matches!(
+            SavedCanvas::from_json(&future),
+            Err(FormatError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+//! Frames, named areas of the board that own what is placed on them.
+//!
+//! A frame holds its children like a group, see [`Shape::Frame`], but keeps
+//! its own rect, clips the children to it and lets them be picked directly.
+//! Elements become children by being dropped on a frame and leave it again by
+//! being dragged off, so frames move their content along.
+
+use crate::document::Document;
+use crate::element::{ElementId, Shape};
+use crate::geometry::{point, Point, Rect};
+use crate::history::History;
+
+/// Width and height of a frame placed with a click, in world units.
+pub const FRAME_SIZE: Point = point(400.0, 300.0);
+
+pub fn is_frame(document: &Document, id: ElementId) -> bool {
+    document.get(id).is_some_and(|element| matches!(element.shape, Shape::Frame { .. }))
+}
+
+/// "Frame 3" for the third frame on the board.
+pub fn next_frame_name(document: &Document) -> String {
+    let frames = document.iter().filter(|element| matches!(element.shape, Shape::Frame { .. })).count();
+    format!("Frame {}", frames + 1)
+}
+
+/// Topmost visible frame whose rect holds `p`.
+pub fn frame_at(document: &Document, p: Point) -> Option<ElementId> {
+    document
+        .elements_intersecting(&Rect::from_corners(p, p))
+        .into_iter()
+        .rev()
+        .filter(|element| document.is_visible(element.id))
+        .find(|element| matches!(element.shape, Shape::Frame { rect, .. } if rect.contains(p)))
+        .map(|element| element.id)
+}
+
+/// Whether an element can be put into a frame: frames and connectors stay
+/// at the top level, and group children go with their group.
+fn can_be_placed(document: &Document, id: ElementId) -> bool {
+    let Some(element) = document.get(id) else {
+        return false;
+    };
+    let parent_is_frame = match document.parent_of(id) {
+        Some(parent) => matches!(parent.shape, Shape::Frame { .. }),
+        None => true,
+    };
+    parent_is_frame && !matches!(element.shape, Shape::Frame { .. } | Shape::Connector { .. })
+}
+
+/// Moves `id` into `frame`, or out to the top level above the frame it is in.
+fn move_to_frame(history: &mut History, document: &mut Document, id: ElementId, frame: Option<ElementId>) {
+    let parent = document.parent_of(id).map(|parent| (parent.id, parent.layer));
+    let Some(mut element) = history.remove(document, id) else {
+        return;
+    };
+    match frame {
+        Some(frame) => {
+            history.update(document, frame, |frame| {
+                if let Some(children) = frame.children_mut() {
+                    children.push(element);
+                }
+            });
+        }
+        None => {
+            let (index, layer) = match parent {
+                Some((parent, layer)) => (document.index_of(parent).map_or(usize::MAX, |index| index + 1), layer),
+                None => (usize::MAX, element.layer),
+            };
+            element.layer = layer;
+            history.insert(document, index, element);
+        }
+    }
+}
+
+/// Puts each of `ids` into the frame its middle was dropped on, and takes
+/// children dragged off their frame back out to the top level.
+pub fn place_in_frames(history: &mut History, document: &mut Document, ids: &[ElementId]) {
+    for &id in ids {
+        if !can_be_placed(document, id) {
+            continue;
+        }
+        let Some(center) = document.get(id).map(|element| element.geometry_bounds().center()) else {
+            continue;
+        };
+        let current = document.parent_of(id).map(|parent| parent.id);
+        let target = frame_at(document, center);
+        if target != current {
+            move_to_frame(history, document, id, target);
+        }
+    }
+}
+
+/// Moves the top level elements lying completely inside `frame` into it,
+/// keeping their paint order, e.g. after drawing a frame around them.
+pub fn adopt_contained(history: &mut History, document: &mut Document, frame: ElementId) {
+    let Some(Shape::Frame { rect, .. }) = document.get(frame).map(|element| &element.shape) else {
+        return;
+    };
+    let contained: Vec<ElementId> = document
+        .elements_within(rect)
+        .into_iter()
+        .filter(|element| element.id != frame && document.is_pickable(element.id))
+        .map(|element| element.id)
+        .filter(|&id| can_be_placed(document, id))
+        .collect();
+    for id in contained {
+        move_to_frame(history, document, id, Some(frame));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::Style;
+    use crate::test_util::square;
+
+    fn frame(document: &mut Document, history: &mut History) -> ElementId {
+        let shape = Shape::frame(Rect::from_origin_size(point(0.0, 0.0), point(100.0, 100.0)), "Frame 1");
+        history.add(document, shape, Style::default())
+    }
+
+    #[test]
+    fn dropped_elements_join_the_frame_and_leave_it_again() {
+        let (mut document, mut history) = (Document::new(), History::default());
+        let frame = frame(&mut document, &mut history);
+        let square = square(&mut document, 200.0, 0.0, 10.0);
+
+        history.translate(&mut document, &[square], point(-150.0, 20.0));
+        place_in_frames(&mut history, &mut document, &[square]);
+        assert_eq!(document.parent_of(square).map(|parent| parent.id), Some(frame));
+        assert_eq!(document.len(), 1);
+
+        // moving the frame takes the square along
+        history.translate(&mut document, &[frame], point(0.0, 50.0));
+        assert_eq!(document.get(square).unwrap().geometry_bounds().min, point(50.0, 70.0));
+
+        history.translate(&mut document, &[square], point(200.0, 0.0));
+        place_in_frames(&mut history, &mut document, &[square]);
+        assert_eq!(document.parent_of(square), None);
+        assert_eq!(document.index_of(square), Some(1));
+        assert!(document.get(frame).unwrap().children().is_empty());
+    }
+
+    #[test]
+    fn new_frames_adopt_what_they_cover() {
+        let (mut document, mut history) = (Document::new(), History::default());
+        let inside = square(&mut document, 10.0, 10.0, 10.0);
+        let across = square(&mut document, 95.0, 10.0, 10.0);
+        let frame = frame(&mut document, &mut history);
+        history.begin_gesture();
+        adopt_contained(&mut history, &mut document, frame);
+        history.end_gesture(&document);
+
+        assert_eq!(document.parent_of(inside).map(|parent| parent.id), Some(frame));
+        assert_eq!(document.parent_of(across), None);
+        history.undo(&mut document);
+        assert_eq!(document.parent_of(inside), None);
+        assert_eq!(document.len(), 3);
+    }
+}
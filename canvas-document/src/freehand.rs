@@ -0,0 +1,455 @@
+//! Pen strokes: capturing pointer samples, simplifying what gets stored and
+//! smoothing what gets drawn.
+//!
+//! Tolerances are given in screen pixels and divided by the zoom, so a stroke
+//! drawn zoomed in keeps its detail and one drawn zoomed out doesn't store
+//! points closer together than anyone could see.
+//!
+//! Strokes drawn with a pen also store the pressure of every sample. Those are
+//! painted as a filled outline whose width follows the pressure, see
+//! [`outline_polygon`].
+
+use crate::document::Document;
+use crate::element::{ElementId, Shape};
+use crate::geometry::{distance_to_segment, point, Point};
+use crate::history::History;
+use crate::style::Style;
+
+/// Pointer samples closer than this to the previous one are dropped.
+pub const MIN_POINT_SPACING_PX: f32 = 1.5;
+/// Largest deviation simplification may introduce.
+pub const SIMPLIFY_TOLERANCE_PX: f32 = 0.75;
+/// Target length of the straight pieces of a smoothed stroke.
+pub const SMOOTH_SPACING_PX: f32 = 3.0;
+/// Largest change in pressure simplification may introduce.
+pub const SIMPLIFY_PRESSURE_TOLERANCE: f32 = 0.1;
+/// Share of the stroke width left at zero pressure, so light strokes don't vanish.
+pub const MIN_PRESSURE_WIDTH: f32 = 0.2;
+/// Number of pieces each round end cap of an outline is made of.
+const CAP_SEGMENTS: usize = 8;
+
+/// Ramer–Douglas–Peucker: drops points that deviate less than `tolerance`
+/// from the line through their neighbours. Endpoints are always kept.
+pub fn simplify(points: &[Point], tolerance: f32) -> Vec<Point> {
+    simplify_indices(points, tolerance)
+        .into_iter()
+        .map(|i| points[i])
+        .collect()
+}
+
+/// Like [`simplify`] but returns the indices of the kept points, so data stored
+/// alongside the points can be filtered the same way.
+pub fn simplify_indices(points: &[Point], tolerance: f32) -> Vec<usize> {
+    simplify_by(points.len(), |i, start, end| {
+        distance_to_segment(points[i], points[start], points[end]) / tolerance
+    })
+}
+
+/// [`simplify_indices`] for a stroke with pressure. A point is also kept when
+/// its pressure differs by more than `pressure_tolerance` from the pressure
+/// interpolated between its neighbours, so a straight stroke pressed harder
+/// in the middle keeps its middle.
+pub fn simplify_with_pressure(
+    points: &[Point],
+    pressure: &[f32],
+    tolerance: f32,
+    pressure_tolerance: f32,
+) -> Vec<usize> {
+    if pressure.len() != points.len() {
+        return simplify_indices(points, tolerance);
+    }
+    simplify_by(points.len(), |i, start, end| {
+        let t = (i - start) as f32 / (end - start) as f32;
+        let interpolated = pressure[start] + (pressure[end] - pressure[start]) * t;
+        let distance = distance_to_segment(points[i], points[start], points[end]) / tolerance;
+        distance.max((pressure[i] - interpolated).abs() / pressure_tolerance)
+    })
+}
+
+/// Ramer–Douglas–Peucker over `len` samples. `deviation(i, start, end)` is how
+/// far sample `i` is from the span between `start` and `end`, in multiples of
+/// the tolerance, so the farthest sample is kept when it is above 1.
+fn simplify_by(len: usize, deviation: impl Fn(usize, usize, usize) -> f32) -> Vec<usize> {
+    if len < 3 {
+        return (0..len).collect();
+    }
+    let mut keep = vec![false; len];
+    keep[0] = true;
+    keep[len - 1] = true;
+
+    // explicit stack instead of recursion, long strokes have thousands of points
+    let mut ranges = vec![(0, len - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let (farthest, distance) = (start + 1..end)
+            .map(|i| (i, deviation(i, start, end)))
+            .fold((start, 0.0), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+        if distance > 1.0 {
+            keep[farthest] = true;
+            ranges.push((start, farthest));
+            ranges.push((farthest, end));
+        }
+    }
+
+    (0..len).filter(|&i| keep[i]).collect()
+}
+
+/// Catmull-Rom spline through every point, flattened into a polyline whose
+/// pieces are about `max_spacing` long. The result passes through the input points.
+pub fn smooth(points: &[Point], max_spacing: f32) -> Vec<Point> {
+    if points.len() < 3 || max_spacing <= 0.0 {
+        return points.to_vec();
+    }
+    let mut smoothed = vec![points[0]];
+    flatten_spline(points, max_spacing, |p, _, _| smoothed.push(p));
+    smoothed
+}
+
+/// [`smooth`] for a stroke with pressure, which is interpolated linearly
+/// between the input points.
+pub fn smooth_with_pressure(points: &[Point], pressure: &[f32], max_spacing: f32) -> (Vec<Point>, Vec<f32>) {
+    if points.len() < 3 || max_spacing <= 0.0 || pressure.len() != points.len() {
+        return (points.to_vec(), pressure.to_vec());
+    }
+    let mut smoothed = vec![points[0]];
+    let mut smoothed_pressure = vec![pressure[0]];
+    flatten_spline(points, max_spacing, |p, span, t| {
+        smoothed.push(p);
+        smoothed_pressure.push(pressure[span] + (pressure[span + 1] - pressure[span]) * t);
+    });
+    (smoothed, smoothed_pressure)
+}
+
+/// Calls `emit` with every point after the first of the flattened spline,
+/// together with the span it lies on and how far along that span it is.
+fn flatten_spline(points: &[Point], max_spacing: f32, mut emit: impl FnMut(Point, usize, f32)) {
+    let last = points.len() - 1;
+    for i in 0..last {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(last)];
+        let steps = (p1.distance(p2) / max_spacing).ceil().clamp(1.0, 64.0) as usize;
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            emit(catmull_rom(p0, p1, p2, p3, t), i, t);
+        }
+    }
+}
+
+fn catmull_rom(p0: Point, p1: Point, p2: Point, p3: Point, t: f32) -> Point {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p3 - p0 + p1 * 3.0 - p2 * 3.0) * t3)
+        * 0.5
+}
+
+/// Adds a pointer sample unless it is too close to the previous one.
+pub fn append_point(points: &mut Vec<Point>, p: Point, zoom: f32) {
+    match points.last() {
+        Some(last) if last.distance(p) < MIN_POINT_SPACING_PX / zoom => {}
+        _ => points.push(p),
+    }
+}
+
+/// Polyline to draw for stored stroke points at the given zoom.
+pub fn smooth_for_zoom(points: &[Point], zoom: f32) -> Vec<Point> {
+    smooth(points, SMOOTH_SPACING_PX / zoom)
+}
+
+/// Width of a stroke at a pressure between 0 and 1.
+pub fn width_at_pressure(stroke_width: f32, pressure: f32) -> f32 {
+    stroke_width * (MIN_PRESSURE_WIDTH + (1.0 - MIN_PRESSURE_WIDTH) * pressure.clamp(0.0, 1.0))
+}
+
+/// Smoothed centre line and the width at each of its points, for drawing a
+/// stroke with pressure at the given zoom.
+pub fn pressure_stroke_for_zoom(
+    points: &[Point],
+    pressure: &[f32],
+    stroke_width: f32,
+    zoom: f32,
+) -> (Vec<Point>, Vec<f32>) {
+    let (points, pressure) = smooth_with_pressure(points, pressure, SMOOTH_SPACING_PX / zoom);
+    let widths = pressure.iter().map(|&p| width_at_pressure(stroke_width, p)).collect();
+    (points, widths)
+}
+
+/// Closed outline of a stroke whose width varies along its centre line, with
+/// round caps at both ends. `widths` holds the full width at every point.
+///
+/// The outline runs along the left side, around the end cap, back along the
+/// right side and around the start cap. Sharp turns can make it overlap
+/// itself, so fill it with the nonzero rule.
+pub fn outline_polygon(points: &[Point], widths: &[f32]) -> Vec<Point> {
+    let count = points.len().min(widths.len());
+    if count == 0 {
+        return Vec::new();
+    }
+    let radius = |i: usize| widths[i] * 0.5;
+    if count == 1 {
+        return (0..CAP_SEGMENTS * 2)
+            .map(|step| {
+                let angle = std::f32::consts::TAU * step as f32 / (CAP_SEGMENTS * 2) as f32;
+                points[0] + point(angle.cos(), angle.sin()) * radius(0)
+            })
+            .collect();
+    }
+
+    let directions: Vec<Point> = (0..count)
+        .map(|i| direction(points[i.saturating_sub(1)], points[(i + 1).min(count - 1)]))
+        .collect();
+    let normal = |i: usize| point(-directions[i].y, directions[i].x);
+    // half turn around `center` starting at `center + start * r`, bulging towards `out`
+    let cap = |center: Point, start: Point, out: Point, r: f32| {
+        (1..CAP_SEGMENTS).map(move |step| {
+            let angle = std::f32::consts::PI * step as f32 / CAP_SEGMENTS as f32;
+            center + (start * angle.cos() + out * angle.sin()) * r
+        })
+    };
+
+    let last = count - 1;
+    let mut outline = Vec::with_capacity(count * 2 + CAP_SEGMENTS * 2);
+    outline.extend((0..count).map(|i| points[i] + normal(i) * radius(i)));
+    outline.extend(cap(points[last], normal(last), directions[last], radius(last)));
+    outline.extend((0..count).rev().map(|i| points[i] - normal(i) * radius(i)));
+    outline.extend(cap(points[0], -normal(0), -directions[0], radius(0)));
+    outline
+}
+
+/// Unit vector from `a` to `b`, pointing along x when they coincide.
+fn direction(a: Point, b: Point) -> Point {
+    let d = b - a;
+    let length = d.length();
+    if length > f32::EPSILON {
+        d / length
+    } else {
+        point(1.0, 0.0)
+    }
+}
+
+/// Starts a stroke at `p` as a new undo step, the stroke ends with [`finish_stroke`].
+/// `pressure` is only given for pen input, strokes started without it have a fixed width.
+pub fn begin_stroke(
+    history: &mut History,
+    document: &mut Document,
+    p: Point,
+    pressure: Option<f32>,
+    style: Style,
+) -> ElementId {
+    history.begin_gesture();
+    let shape = Shape::Freehand {
+        points: vec![p],
+        pressure: pressure.into_iter().collect(),
+    };
+    history.add(document, shape, style)
+}
+
+pub fn extend_stroke(
+    history: &mut History,
+    document: &mut Document,
+    id: ElementId,
+    p: Point,
+    sample_pressure: Option<f32>,
+    zoom: f32,
+) {
+    history.update(document, id, |element| {
+        if let Shape::Freehand { points, pressure } = &mut element.shape {
+            let count = points.len();
+            append_point(points, p, zoom);
+            if points.len() > count && !pressure.is_empty() {
+                pressure.push(sample_pressure.unwrap_or(pressure[count - 1]));
+            }
+        }
+    });
+}
+
+/// Simplifies the captured points and closes the undo step.
+pub fn finish_stroke(history: &mut History, document: &mut Document, id: ElementId, zoom: f32) {
+    history.update(document, id, |element| {
+        if let Shape::Freehand { points, pressure } = &mut element.shape {
+            let kept =
+                simplify_with_pressure(points, pressure, SIMPLIFY_TOLERANCE_PX / zoom, SIMPLIFY_PRESSURE_TOLERANCE);
+            if pressure.len() == points.len() {
+                *pressure = kept.iter().map(|&i| pressure[i]).collect();
+            }
+            *points = kept.iter().map(|&i| points[i]).collect();
+        }
+    });
+    history.end_gesture(document);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify_drops_collinear_points() {
+        let line: Vec<Point> = (0..=10).map(|i| point(i as f32, 0.0)).collect();
+        assert_eq!(simplify(&line, 0.1), vec![point(0.0, 0.0), point(10.0, 0.0)]);
+    }
+
+    #[test]
+    fn simplify_keeps_corners_above_tolerance() {
+        let points = [
+            point(0.0, 0.0),
+            point(5.0, 0.1),
+            point(10.0, 0.0),
+            point(10.0, 5.0),
+            point(10.0, 10.0),
+        ];
+        assert_eq!(
+            simplify(&points, 0.5),
+            vec![point(0.0, 0.0), point(10.0, 0.0), point(10.0, 10.0)]
+        );
+        // a looser tolerance cuts the corner entirely
+        assert_eq!(simplify(&points, 20.0), vec![point(0.0, 0.0), point(10.0, 10.0)]);
+    }
+
+    #[test]
+    fn simplify_leaves_short_strokes_alone() {
+        let points = [point(0.0, 0.0), point(1.0, 1.0)];
+        assert_eq!(simplify(&points, 5.0), points.to_vec());
+    }
+
+    #[test]
+    fn smooth_passes_through_input_points() {
+        let points = [point(0.0, 0.0), point(10.0, 10.0), point(20.0, 0.0), point(30.0, 10.0)];
+        let smoothed = smooth(&points, 1.0);
+        for p in points {
+            assert!(smoothed.iter().any(|s| s.distance(p) < 1e-4), "{:?} missing", p);
+        }
+        assert_eq!(smoothed.first(), points.first());
+        assert_eq!(smoothed.last(), points.last());
+        // spans are split by chord length, the curve itself is a little longer
+        assert!(smoothed.windows(2).all(|w| w[0].distance(w[1]) <= 1.5));
+        assert!(smoothed.len() > 40);
+    }
+
+    #[test]
+    fn append_point_spacing_depends_on_zoom() {
+        let mut points = vec![point(0.0, 0.0)];
+        append_point(&mut points, point(1.0, 0.0), 1.0);
+        assert_eq!(points.len(), 1);
+        append_point(&mut points, point(1.0, 0.0), 4.0);
+        assert_eq!(points.len(), 2);
+    }
+
+    #[test]
+    fn finished_stroke_is_simplified_and_one_undo_step() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let id = begin_stroke(&mut history, &mut document, point(0.0, 0.0), None, Style::default());
+        for i in 1..=20 {
+            extend_stroke(&mut history, &mut document, id, point(i as f32 * 2.0, 0.0), None, 1.0);
+        }
+        finish_stroke(&mut history, &mut document, id, 1.0);
+
+        assert_eq!(
+            document.get(id).unwrap().shape,
+            Shape::Freehand {
+                points: vec![point(0.0, 0.0), point(40.0, 0.0)],
+                pressure: Vec::new(),
+            }
+        );
+        history.undo(&mut document);
+        assert!(document.is_empty());
+    }
+
+    #[test]
+    fn simplify_keeps_pressure_aligned() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let id = begin_stroke(&mut history, &mut document, point(0.0, 0.0), Some(0.1), Style::default());
+        for i in 1..=10 {
+            let p = if i <= 5 { point(i as f32 * 2.0, 0.0) } else { point(10.0, (i - 5) as f32 * 2.0) };
+            extend_stroke(&mut history, &mut document, id, p, Some(i as f32 / 10.0), 1.0);
+        }
+        finish_stroke(&mut history, &mut document, id, 1.0);
+
+        let Shape::Freehand { points, pressure } = &document.get(id).unwrap().shape else {
+            panic!("not a stroke");
+        };
+        assert_eq!(points, &vec![point(0.0, 0.0), point(10.0, 0.0), point(10.0, 10.0)]);
+        assert_eq!(pressure, &vec![0.1, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn simplify_keeps_pressure_changes_along_straight_strokes() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        // pressed lightly, then hard in the middle, then lightly again
+        let ramp = |i: i32| 1.0 - (i - 20).abs() as f32 * 0.9 / 20.0;
+        let id = begin_stroke(&mut history, &mut document, point(0.0, 0.0), Some(ramp(0)), Style::default());
+        for i in 1..=40 {
+            extend_stroke(&mut history, &mut document, id, point(i as f32 * 2.0, 0.0), Some(ramp(i)), 1.0);
+        }
+        finish_stroke(&mut history, &mut document, id, 1.0);
+
+        let Shape::Freehand { points, pressure } = &document.get(id).unwrap().shape else {
+            panic!("not a stroke");
+        };
+        assert_eq!(points.len(), pressure.len());
+        assert_eq!(points, &vec![point(0.0, 0.0), point(40.0, 0.0), point(80.0, 0.0)]);
+        assert_eq!(pressure, &vec![ramp(0), ramp(20), ramp(40)]);
+    }
+
+    #[test]
+    fn mouse_strokes_have_no_pressure() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let id = begin_stroke(&mut history, &mut document, point(0.0, 0.0), None, Style::default());
+        extend_stroke(&mut history, &mut document, id, point(10.0, 0.0), Some(0.5), 1.0);
+        assert!(matches!(
+            &document.get(id).unwrap().shape,
+            Shape::Freehand { points, pressure } if points.len() == 2 && pressure.is_empty()
+        ));
+    }
+
+    #[test]
+    fn width_follows_pressure_with_a_floor() {
+        assert_eq!(width_at_pressure(10.0, 1.0), 10.0);
+        assert_eq!(width_at_pressure(10.0, 0.0), 10.0 * MIN_PRESSURE_WIDTH);
+        assert_eq!(width_at_pressure(10.0, 3.0), 10.0);
+        assert!(width_at_pressure(10.0, 0.5) < width_at_pressure(10.0, 0.8));
+    }
+
+    #[test]
+    fn smoothed_pressure_is_interpolated() {
+        let points = [point(0.0, 0.0), point(10.0, 0.0), point(20.0, 0.0)];
+        let (smoothed, pressure) = smooth_with_pressure(&points, &[0.0, 1.0, 0.0], 1.0);
+        assert_eq!(smoothed.len(), pressure.len());
+        let middle = smoothed.iter().position(|p| p.distance(point(10.0, 0.0)) < 1e-4).unwrap();
+        assert_eq!(pressure[middle], 1.0);
+        assert!((pressure[middle / 2] - 0.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn outline_offsets_by_half_the_width() {
+        let points = [point(0.0, 0.0), point(10.0, 0.0), point(20.0, 0.0)];
+        let widths = [2.0, 6.0, 4.0];
+        let outline = outline_polygon(&points, &widths);
+        assert_eq!(outline.len(), 6 + 2 * (CAP_SEGMENTS - 1));
+        // left side forward, then right side backward after the end cap
+        assert_eq!(&outline[..3], &[point(0.0, 1.0), point(10.0, 3.0), point(20.0, 2.0)]);
+        let right = 3 + CAP_SEGMENTS - 1;
+        assert_eq!(
+            &outline[right..right + 3],
+            &[point(20.0, -2.0), point(10.0, -3.0), point(0.0, -1.0)]
+        );
+        // the caps bulge past the ends
+        assert!(outline.iter().any(|p| p.x > 21.9));
+        assert!(outline.iter().any(|p| p.x < -0.9));
+        assert!(outline.iter().all(|p| p.x <= 22.0 + 1e-4 && p.x >= -1.0 - 1e-4));
+    }
+
+    #[test]
+    fn outline_of_a_single_point_is_a_circle() {
+        let outline = outline_polygon(&[point(5.0, 5.0)], &[4.0]);
+        assert!(outline.len() >= 8);
+        assert!(outline.iter().all(|p| (p.distance(point(5.0, 5.0)) - 2.0).abs() < 1e-4));
+        assert!(outline_polygon(&[], &[]).is_empty());
+    }
+}
@@ -0,0 +1,556 @@
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+/// A position or offset on the infinite canvas, in world units.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+pub const fn point(x: f32, y: f32) -> Point {
+    Point { x, y }
+}
+
+impl Point {
+    pub const ZERO: Point = point(0.0, 0.0);
+
+    pub fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    pub fn distance(self, other: Point) -> f32 {
+        (self - other).length()
+    }
+
+    pub fn dot(self, other: Point) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn min(self, other: Point) -> Point {
+        point(self.x.min(other.x), self.y.min(other.y))
+    }
+
+    pub fn max(self, other: Point) -> Point {
+        point(self.x.max(other.x), self.y.max(other.y))
+    }
+
+    pub fn lerp(self, other: Point, t: f32) -> Point {
+        self + (other - self) * t
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+    fn add(self, rhs: Point) -> Point {
+        point(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, rhs: Point) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+    fn sub(self, rhs: Point) -> Point {
+        point(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl SubAssign for Point {
+    fn sub_assign(&mut self, rhs: Point) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<f32> for Point {
+    type Output = Point;
+    fn mul(self, rhs: f32) -> Point {
+        point(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Div<f32> for Point {
+    type Output = Point;
+    fn div(self, rhs: f32) -> Point {
+        point(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl Neg for Point {
+    type Output = Point;
+    fn neg(self) -> Point {
+        point(-self.x, -self.y)
+    }
+}
+
+/// Axis aligned rectangle in world space, always kept normalized so `min <= max`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Rect {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Rect {
+    /// Builds a rect from any two opposite corners.
+    pub fn from_corners(a: Point, b: Point) -> Self {
+        Rect {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    pub fn from_origin_size(origin: Point, size: Point) -> Self {
+        Rect::from_corners(origin, origin + size)
+    }
+
+    /// Smallest rect containing every point, `None` when there are no points.
+    pub fn from_points<I: IntoIterator<Item = Point>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Rect::from_corners(first, first), |rect, p| {
+            rect.union(&Rect::from_corners(p, p))
+        }))
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max.y - self.min.y
+    }
+
+    pub fn size(&self) -> Point {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Point {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x && p.y >= self.min.y && p.y <= self.max.y
+    }
+
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        self.contains(other.min) && self.contains(other.max)
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Corners clockwise on screen, starting at the top left.
+    pub fn corners(&self) -> [Point; 4] {
+        [
+            self.min,
+            point(self.max.x, self.min.y),
+            self.max,
+            point(self.min.x, self.max.y),
+        ]
+    }
+
+    /// Overlap of the two rects, `None` when they don't touch.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        self.intersects(other).then(|| Rect {
+            min: self.min.max(other.min),
+            max: self.max.min(other.max),
+        })
+    }
+
+    /// Grows the rect by `amount` on every side.
+    pub fn inflate(&self, amount: f32) -> Rect {
+        Rect::from_corners(self.min - point(amount, amount), self.max + point(amount, amount))
+    }
+
+    pub fn translate(&self, delta: Point) -> Rect {
+        Rect {
+            min: self.min + delta,
+            max: self.max + delta,
+        }
+    }
+}
+
+/// Shortest distance from `p` to the segment `a`-`b`.
+pub fn distance_to_segment(p: Point, a: Point, b: Point) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.dot(ab);
+    if length_squared == 0.0 {
+        return p.distance(a);
+    }
+    let t = ((p - a).dot(ab) / length_squared).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+/// Moves `p` so the rect spanned from `anchor` becomes a square, keeping the
+/// longer side and the direction of the drag.
+pub fn constrain_square(anchor: Point, p: Point) -> Point {
+    let d = p - anchor;
+    let side = d.x.abs().max(d.y.abs());
+    anchor + point(side.copysign(d.x), side.copysign(d.y))
+}
+
+/// Rotates `p` around `anchor` to the nearest multiple of `step` radians,
+/// keeping its distance.
+pub fn constrain_angle(anchor: Point, p: Point, step: f32) -> Point {
+    let d = p - anchor;
+    let angle = (d.y.atan2(d.x) / step).round() * step;
+    anchor + point(angle.cos(), angle.sin()) * d.length()
+}
+
+/// Polygon approximating the ellipse inscribed in `rect`, useful for backends without ellipses.
+pub fn ellipse_outline(rect: &Rect, segments: usize) -> Vec<Point> {
+    let center = rect.center();
+    let radius = rect.size() * 0.5;
+    (0..segments)
+        .map(|i| {
+            let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+            center + point(radius.x * angle.cos(), radius.y * angle.sin())
+        })
+        .collect()
+}
+
+/// 2D affine transform mapping `p` to `(a·x + c·y + e, b·x + d·y + f)`, the
+/// same six numbers the browser canvas `setTransform` takes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Affine {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Affine::IDENTITY
+    }
+}
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine {
+        a: 1.0,
+        b: 0.0,
+        c: 0.0,
+        d: 1.0,
+        e: 0.0,
+        f: 0.0,
+    };
+
+    pub fn translate(delta: Point) -> Self {
+        Affine {
+            e: delta.x,
+            f: delta.y,
+            ..Affine::IDENTITY
+        }
+    }
+
+    pub fn scale(factor: f32) -> Self {
+        Affine::scale_xy(factor, factor)
+    }
+
+    /// Scales each axis separately, a negative factor mirrors that axis.
+    pub fn scale_xy(sx: f32, sy: f32) -> Self {
+        Affine {
+            a: sx,
+            d: sy,
+            ..Affine::IDENTITY
+        }
+    }
+
+    /// Counterclockwise on paper, which is clockwise on screen since y points down.
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Affine {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Affine::IDENTITY
+        }
+    }
+
+    /// `transform` applied with `center` as the origin, e.g. rotating or
+    /// flipping something in place.
+    pub fn around(center: Point, transform: &Affine) -> Self {
+        Affine::translate(-center)
+            .then(transform)
+            .then(&Affine::translate(center))
+    }
+
+    /// Stretches `from` onto `to`. A side of zero length keeps its scale.
+    pub fn rect_to_rect(from: &Rect, to: &Rect) -> Self {
+        let ratio = |size: f32, target: f32| if size > 0.0 { target / size } else { 1.0 };
+        Affine::translate(-from.min)
+            .then(&Affine::scale_xy(ratio(from.width(), to.width()), ratio(from.height(), to.height())))
+            .then(&Affine::translate(to.min))
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Affine::IDENTITY
+    }
+
+    /// Maps horizontal and vertical lines onto horizontal and vertical lines,
+    /// so axis aligned rects stay axis aligned. Quarter turns and flips count,
+    /// give or take rounding.
+    pub fn is_axis_aligned(&self) -> bool {
+        let tolerance = 1e-5 * (self.a.abs() + self.b.abs() + self.c.abs() + self.d.abs());
+        let tiny = |v: f32| v.abs() <= tolerance;
+        (tiny(self.b) && tiny(self.c)) || (tiny(self.a) && tiny(self.d))
+    }
+
+    /// Angle the x axis is turned by, in radians.
+    pub fn rotation(&self) -> f32 {
+        self.b.atan2(self.a)
+    }
+
+    /// Transform applying `self` first and `next` after it.
+    pub fn then(&self, next: &Affine) -> Affine {
+        Affine {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    pub fn apply(&self, p: Point) -> Point {
+        point(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    /// Maps a direction or size, ignoring the translation.
+    pub fn apply_vector(&self, v: Point) -> Point {
+        point(self.a * v.x + self.c * v.y, self.b * v.x + self.d * v.y)
+    }
+
+    /// `None` when the transform squashes everything onto a line or point.
+    pub fn inverse(&self) -> Option<Affine> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let a = self.d / det;
+        let b = -self.b / det;
+        let c = -self.c / det;
+        let d = self.a / det;
+        Some(Affine {
+            a,
+            b,
+            c,
+            d,
+            e: -(a * self.e + c * self.f),
+            f: -(b * self.e + d * self.f),
+        })
+    }
+
+    /// How much lengths grow on average, used for line widths and flattening tolerances.
+    pub fn scale_factor(&self) -> f32 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    /// Axis aligned bounds of the transformed rect.
+    pub fn transform_rect(&self, rect: &Rect) -> Rect {
+        Rect::from_points(rect.corners().map(|p| self.apply(p))).unwrap()
+    }
+}
+
+/// Splits a simple polygon into triangles by ear clipping, returning indices
+/// into `polygon`. Convex polygons become a fan. Self intersecting input
+/// still yields triangles, they just won't match the nonzero fill exactly.
+pub fn triangulate(polygon: &[Point]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+    if n < 3 {
+        return Vec::new();
+    }
+    let area: f32 = (0..n)
+        .map(|i| {
+            let (p, q) = (polygon[i], polygon[(i + 1) % n]);
+            p.x * q.y - q.x * p.y
+        })
+        .sum();
+    // positive when the corner turns the same way as the polygon winds
+    let turn = |a: Point, b: Point, c: Point| {
+        let cross = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        if area >= 0.0 {
+            cross
+        } else {
+            -cross
+        }
+    };
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let is_convex = (0..n).all(|i| turn(polygon[i], polygon[(i + 1) % n], polygon[(i + 2) % n]) >= 0.0);
+    if is_convex {
+        return (1..n - 1).map(|i| [0, i, i + 1]).collect();
+    }
+
+    let mut triangles = Vec::with_capacity(n - 2);
+    let mut k = 0;
+    let mut since_last_ear = 0;
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let corner = |k: usize| {
+            (
+                polygon[remaining[(k + len - 1) % len]],
+                polygon[remaining[k]],
+                polygon[remaining[(k + 1) % len]],
+            )
+        };
+        let (a, b, c) = corner(k);
+        // only reflex corners can poke into a candidate ear
+        let is_ear = turn(a, b, c) > 0.0
+            && !(0..len).any(|other| {
+                let (before, p, after) = corner(other);
+                (other + 1) % len != k
+                    && other != k
+                    && (k + 1) % len != other
+                    && turn(before, p, after) <= 0.0
+                    && turn(a, b, p) >= 0.0
+                    && turn(b, c, p) >= 0.0
+                    && turn(c, a, p) >= 0.0
+            });
+        // a broken polygon may have no ear left, clip anyway so this ends
+        if is_ear || since_last_ear > len {
+            triangles.push([remaining[(k + len - 1) % len], remaining[k], remaining[(k + 1) % len]]);
+            remaining.remove(k);
+            since_last_ear = 0;
+        } else {
+            k += 1;
+            since_last_ear += 1;
+        }
+        k %= remaining.len();
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_from_corners_normalizes() {
+        let rect = Rect::from_corners(point(10.0, -5.0), point(-2.0, 4.0));
+        assert_eq!(rect.min, point(-2.0, -5.0));
+        assert_eq!(rect.max, point(10.0, 4.0));
+        assert_eq!(rect.size(), point(12.0, 9.0));
+    }
+
+    #[test]
+    fn rect_from_points_covers_all() {
+        let rect = Rect::from_points([point(1.0, 1.0), point(-3.0, 2.0), point(0.0, 7.0)]).unwrap();
+        assert_eq!(rect, Rect::from_corners(point(-3.0, 1.0), point(1.0, 7.0)));
+        assert!(Rect::from_points(std::iter::empty()).is_none());
+    }
+
+    #[test]
+    fn rect_intersection_is_the_overlap() {
+        let a = Rect::from_corners(point(0.0, 0.0), point(10.0, 10.0));
+        let b = Rect::from_corners(point(5.0, -5.0), point(20.0, 5.0));
+        assert_eq!(a.intersection(&b), Some(Rect::from_corners(point(5.0, 0.0), point(10.0, 5.0))));
+        assert_eq!(a.intersection(&b.translate(point(20.0, 0.0))), None);
+    }
+
+    #[test]
+    fn constraints_keep_the_drag_direction() {
+        let anchor = point(10.0, 10.0);
+        assert_eq!(constrain_square(anchor, point(0.0, 14.0)), point(0.0, 20.0));
+        let snapped = constrain_angle(anchor, point(20.0, 11.0), std::f32::consts::FRAC_PI_4);
+        assert!(snapped.distance(point(10.0 + 101f32.sqrt(), 10.0)) < 1e-4);
+        let diagonal = constrain_angle(anchor, point(0.0, 1.0), std::f32::consts::FRAC_PI_4);
+        assert!((diagonal.x - diagonal.y).abs() < 1e-4 && diagonal.x < 10.0);
+    }
+
+    #[test]
+    fn segment_distance_clamps_to_endpoints() {
+        let a = point(0.0, 0.0);
+        let b = point(10.0, 0.0);
+        assert_eq!(distance_to_segment(point(5.0, 3.0), a, b), 3.0);
+        assert_eq!(distance_to_segment(point(13.0, 4.0), a, b), 5.0);
+        assert_eq!(distance_to_segment(point(3.0, 4.0), a, a), 5.0);
+    }
+
+    #[test]
+    fn affine_composes_in_order() {
+        let shift_then_zoom = Affine::translate(point(10.0, 0.0)).then(&Affine::scale(2.0));
+        assert_eq!(shift_then_zoom.apply(point(1.0, 1.0)), point(22.0, 2.0));
+        let inverse = shift_then_zoom.inverse().unwrap();
+        assert_eq!(inverse.apply(point(22.0, 2.0)), point(1.0, 1.0));
+        assert_eq!(shift_then_zoom.scale_factor(), 2.0);
+        assert!(Affine::scale(0.0).inverse().is_none());
+    }
+
+    #[test]
+    fn affine_rotates_and_maps_rects() {
+        let quarter = Affine::around(point(10.0, 10.0), &Affine::rotate(std::f32::consts::FRAC_PI_2));
+        assert!(quarter.apply(point(20.0, 10.0)).distance(point(10.0, 20.0)) < 1e-5);
+        assert!((quarter.rotation() - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        assert!(quarter.is_axis_aligned());
+        assert!(!Affine::rotate(0.3).is_axis_aligned());
+
+        let from = Rect::from_corners(point(0.0, 0.0), point(10.0, 0.0));
+        let to = Rect::from_corners(point(5.0, 5.0), point(25.0, 5.0));
+        let stretch = Affine::rect_to_rect(&from, &to);
+        assert_eq!(stretch.apply(point(10.0, 0.0)), point(25.0, 5.0));
+        // the zero height keeps its scale
+        assert_eq!(stretch.d, 1.0);
+    }
+
+    fn triangle_area(polygon: &[Point], triangles: &[[usize; 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let (a, b, c) = (polygon[a], polygon[b], polygon[c]);
+                ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)).abs() * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn triangulates_concave_polygons() {
+        // an L shape, area 3
+        let polygon = [
+            point(0.0, 0.0),
+            point(2.0, 0.0),
+            point(2.0, 1.0),
+            point(1.0, 1.0),
+            point(1.0, 2.0),
+            point(0.0, 2.0),
+        ];
+        let triangles = triangulate(&polygon);
+        assert_eq!(triangles.len(), 4);
+        assert!((triangle_area(&polygon, &triangles) - 3.0).abs() < 1e-5);
+
+        let mut reversed = polygon;
+        reversed.reverse();
+        assert!((triangle_area(&reversed, &triangulate(&reversed)) - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn convex_polygons_become_a_fan() {
+        let square = [point(0.0, 0.0), point(1.0, 0.0), point(1.0, 1.0), point(0.0, 1.0)];
+        assert_eq!(triangulate(&square), vec![[0, 1, 2], [0, 2, 3]]);
+        assert!(triangulate(&square[..2]).is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+//! Layout of the dot grid drawn behind the board.
+//!
+//! Shared by every renderer so the window, exports and snapshots agree on
+//! where the dots are.
+
+use crate::geometry::{point, Point, Rect};
+use crate::style::Color;
+
+/// Distance between dots in world units at zoom 1.
+pub const GRID_SIZE: f32 = 20.0;
+/// Every this many steps a dot is drawn darker.
+pub const EMPHASIS_EVERY: i32 = 4;
+/// Zoomed out, a darker dot this close in screen pixels is snapped to rather
+/// than a nearer normal one.
+pub const EMPHASIS_PULL: f32 = 16.0;
+pub const NORMAL_DOT_COLOR: Color = Color::new(0.7, 0.9, 1.0, 1.0);
+pub const EMPHASIZED_DOT_COLOR: Color = Color::new(0.4, 0.7, 0.9, 1.0);
+pub const BACKGROUND_COLOR: Color = Color::new(0.95, 0.96, 0.98, 1.0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridDot {
+    pub position: Point,
+    pub emphasized: bool,
+}
+
+impl GridDot {
+    pub fn color(&self) -> Color {
+        if self.emphasized {
+            EMPHASIZED_DOT_COLOR
+        } else {
+            NORMAL_DOT_COLOR
+        }
+    }
+}
+
+/// World distance between dots. Zoomed out the grid gets sparser so the dots
+/// don't turn into a solid fill.
+pub fn grid_step(zoom: f32) -> i32 {
+    (GRID_SIZE * (1.0 / zoom).max(1.0)).round() as i32
+}
+
+/// Nearest grid coordinate to `value` along one axis, for the grid drawn at
+/// `zoom`. Zoomed out, the darker dots pull from further away so shapes line
+/// up with the coarse grid that stands out on screen.
+pub fn snap_to_grid(value: f32, zoom: f32) -> f32 {
+    let step = grid_step(zoom) as f32;
+    if zoom < 1.0 {
+        let coarse = step * EMPHASIS_EVERY as f32;
+        let snapped = (value / coarse).round() * coarse;
+        if (snapped - value).abs() * zoom <= EMPHASIS_PULL {
+            return snapped;
+        }
+    }
+    (value / step).round() * step
+}
+
+/// Dot radius in screen pixels.
+pub fn dot_radius(zoom: f32) -> f32 {
+    zoom.clamp(0.5, 2.0)
+}
+
+/// Dots covering `visible`, a world space rect, at the given zoom.
+pub fn grid_dots(visible: &Rect, zoom: f32) -> Vec<GridDot> {
+    let step = grid_step(zoom);
+    let start_x = (visible.min.x / step as f32).floor() as i32 * step;
+    let start_y = (visible.min.y / step as f32).floor() as i32 * step;
+    let end_x = (visible.max.x / step as f32).ceil() as i32 * step;
+    let end_y = (visible.max.y / step as f32).ceil() as i32 * step;
+
+    let mut dots = Vec::new();
+    for x in (start_x..=end_x).step_by(step as usize) {
+        for y in (start_y..=end_y).step_by(step as usize) {
+            dots.push(GridDot {
+                position: point(x as f32, y as f32),
+                emphasized: (x / step) % EMPHASIS_EVERY == 0 && (y / step) % EMPHASIS_EVERY == 0,
+            });
+        }
+    }
+    dots
+}
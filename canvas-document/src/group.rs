@@ -0,0 +1,204 @@
+//! Grouping elements so they are picked, moved and turned as one.
+//!
+//! A group is an element holding its children, see [`Shape::Group`]. Groups
+//! nest, and both operations here work inside a group as well as at the top
+//! level, as long as the elements share the same parent.
+
+use crate::document::Document;
+use crate::element::{Element, ElementId};
+use crate::history::History;
+
+/// Puts the elements with `ids` into a new group where the topmost of them
+/// was. Returns the group, or `None` unless there are at least two elements
+/// with the same parent.
+pub fn group_elements(history: &mut History, document: &mut Document, ids: &[ElementId]) -> Option<ElementId> {
+    let parent = document.parent_of(*ids.first()?).map(|parent| parent.id);
+    let siblings: Vec<ElementId> = match parent {
+        Some(parent) => document.get(parent)?.children().iter().map(|child| child.id).collect(),
+        None => document.iter().map(|element| element.id).collect(),
+    };
+    // paint order positions among the siblings
+    let mut positions = Vec::with_capacity(ids.len());
+    for &id in ids {
+        positions.push(siblings.iter().position(|&sibling| sibling == id)?);
+    }
+    positions.sort_unstable();
+    positions.dedup();
+    if positions.len() < 2 {
+        return None;
+    }
+    // the group takes the place of the topmost element once the others are gone
+    let index = positions[positions.len() - 1] + 1 - positions.len();
+    let id = document.new_id();
+
+    history.step(document, |history, document| {
+        match parent {
+            Some(parent) => {
+                history.update(document, parent, |group| {
+                    let Some(children) = group.children_mut() else {
+                        return;
+                    };
+                    let mut grouped = Vec::with_capacity(positions.len());
+                    for &position in positions.iter().rev() {
+                        grouped.push(children.remove(position));
+                    }
+                    grouped.reverse();
+                    children.insert(index, Element::group(id, grouped));
+                });
+            }
+            None => {
+                let mut grouped = Vec::with_capacity(positions.len());
+                for &position in positions.iter().rev() {
+                    grouped.extend(history.remove(document, siblings[position]));
+                }
+                grouped.reverse();
+                // the group lives on the layer of the topmost member
+                let mut group = Element::group(id, grouped);
+                group.layer = group.children().last().map(|child| child.layer).unwrap_or_default();
+                history.insert(document, index, group);
+            }
+        }
+    });
+    Some(id)
+}
+
+/// Replaces the group `id` by its children, in its place in paint order.
+/// Returns the children, empty when `id` is not a group.
+pub fn ungroup(history: &mut History, document: &mut Document, id: ElementId) -> Vec<ElementId> {
+    let Some(group) = document.get(id).filter(|element| element.is_group()) else {
+        return Vec::new();
+    };
+    let children: Vec<Element> = group
+        .children()
+        .iter()
+        .map(|child| {
+            let mut child = child.clone();
+            child.layer = group.layer;
+            if !group.transform.is_identity() {
+                child.transform_by(&group.transform);
+            }
+            child
+        })
+        .collect();
+    let ids = children.iter().map(|child| child.id).collect();
+    let parent = document.parent_of(id).map(|parent| parent.id);
+
+    history.step(document, |history, document| {
+        match parent {
+            Some(parent) => {
+                history.update(document, parent, |parent| {
+                    let Some(siblings) = parent.children_mut() else {
+                        return;
+                    };
+                    if let Some(index) = siblings.iter().position(|sibling| sibling.id == id) {
+                        siblings.splice(index..=index, children);
+                    }
+                });
+            }
+            None => {
+                let index = document.index_of(id).unwrap_or(document.len());
+                history.remove(document, id);
+                for (offset, child) in children.into_iter().enumerate() {
+                    history.insert(document, index + offset, child);
+                }
+            }
+        }
+    });
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Affine, Rect};
+    use crate::test_util::square;
+
+    fn top_level(document: &Document) -> Vec<ElementId> {
+        document.iter().map(|element| element.id).collect()
+    }
+
+    #[test]
+    fn grouping_during_a_drag_leaves_the_drag_open() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let a = square(&mut document, 0.0, 0.0, 10.0);
+        let b = square(&mut document, 20.0, 0.0, 10.0);
+
+        history.begin_gesture();
+        history.translate(&mut document, &[a], point(5.0, 0.0));
+        let group = group_elements(&mut history, &mut document, &[a, b]).unwrap();
+        assert!(history.is_in_gesture());
+        history.translate(&mut document, &[group], point(5.0, 0.0));
+        history.end_gesture(&document);
+
+        // the drag and the grouping are undone together
+        history.undo(&mut document);
+        assert_eq!(top_level(&document), vec![a, b]);
+        assert_eq!(document.get(a).unwrap().geometry_bounds().min, point(0.0, 0.0));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn group_takes_the_place_of_the_topmost_member() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let ids: Vec<ElementId> = (0..4).map(|i| square(&mut document, i as f32 * 20.0, 0.0, 10.0)).collect();
+
+        let group = group_elements(&mut history, &mut document, &[ids[2], ids[0]]).unwrap();
+        assert_eq!(top_level(&document), vec![ids[1], group, ids[3]]);
+        let children: Vec<ElementId> = document.get(group).unwrap().children().iter().map(|c| c.id).collect();
+        assert_eq!(children, vec![ids[0], ids[2]]);
+        assert_eq!(document.parent_of(ids[2]).map(|p| p.id), Some(group));
+        // a click on a member picks the whole group
+        assert_eq!(document.element_at(point(45.0, 5.0), 0.0), Some(group));
+
+        assert!(history.undo(&mut document));
+        assert_eq!(top_level(&document), ids);
+        assert!(document.parent_of(ids[2]).is_none());
+        assert!(history.redo(&mut document));
+        assert_eq!(top_level(&document), vec![ids[1], group, ids[3]]);
+    }
+
+    #[test]
+    fn nested_groups_pass_transforms_down_and_ungroup_in_place() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let ids: Vec<ElementId> = (0..3).map(|i| square(&mut document, i as f32 * 20.0, 0.0, 10.0)).collect();
+        let outer = group_elements(&mut history, &mut document, &ids).unwrap();
+        let inner = group_elements(&mut history, &mut document, &[ids[0], ids[1]]).unwrap();
+        assert_eq!(document.parent_of(inner).map(|p| p.id), Some(outer));
+        assert_eq!(document.parent_of(ids[0]).map(|p| p.id), Some(inner));
+
+        history.transform(&mut document, &[outer], &Affine::translate(point(0.0, 100.0)));
+        assert!(document.get(outer).unwrap().transform.is_identity());
+        assert_eq!(document.get(ids[0]).unwrap().bounds().min, point(-1.0, 99.0));
+        assert_eq!(
+            document.get(outer).unwrap().geometry_bounds(),
+            Rect::from_corners(point(0.0, 100.0), point(50.0, 110.0))
+        );
+
+        // a nested element can be edited and deleted on its own
+        history.translate(&mut document, &[ids[1]], point(5.0, 0.0));
+        assert_eq!(document.get(ids[1]).unwrap().shape.bounds().min, point(25.0, 100.0));
+        history.remove(&mut document, ids[2]);
+        assert_eq!(document.get(outer).unwrap().children().len(), 1);
+
+        assert_eq!(ungroup(&mut history, &mut document, inner), vec![ids[0], ids[1]]);
+        let children: Vec<ElementId> = document.get(outer).unwrap().children().iter().map(|c| c.id).collect();
+        assert_eq!(children, vec![ids[0], ids[1]]);
+        assert_eq!(ungroup(&mut history, &mut document, outer), vec![ids[0], ids[1]]);
+        assert_eq!(top_level(&document), vec![ids[0], ids[1]]);
+        assert!(ungroup(&mut history, &mut document, ids[0]).is_empty());
+    }
+
+    #[test]
+    fn members_need_the_same_parent() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let ids: Vec<ElementId> = (0..3).map(|i| square(&mut document, i as f32 * 20.0, 0.0, 10.0)).collect();
+        let group = group_elements(&mut history, &mut document, &[ids[0], ids[1]]).unwrap();
+        assert_eq!(group_elements(&mut history, &mut document, &[ids[0], ids[2]]), None);
+        assert_eq!(group_elements(&mut history, &mut document, &[ids[2]]), None);
+        assert!(group_elements(&mut history, &mut document, &[group, ids[2]]).is_some());
+    }
+}
@@ -0,0 +1,413 @@
+//! Undo and redo.
+//!
+//! Every mutation of a [`Document`] that should be undoable goes through
+//! [`History`], which records it as an invertible [`Operation`]. Operations
+//! made between [`History::begin_gesture`] and [`History::end_gesture`] are
+//! undone as one step, so a whole drag is a single undo.
+
+use std::collections::VecDeque;
+
+use crate::document::Document;
+use crate::element::{Element, ElementId, Shape};
+use crate::geometry::{Affine, Point};
+use crate::layer::Layer;
+use crate::style::Style;
+
+pub const DEFAULT_UNDO_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Insert { index: usize, element: Element },
+    Remove { index: usize, element: Element },
+    Replace { before: Element, after: Element },
+    Layers { before: Vec<Layer>, after: Vec<Layer> },
+}
+
+impl Operation {
+    /// The top level element the operation is about, if any.
+    fn id(&self) -> Option<ElementId> {
+        match self {
+            Operation::Insert { element, .. } | Operation::Remove { element, .. } => Some(element.id),
+            Operation::Replace { before, .. } => Some(before.id),
+            Operation::Layers { .. } => None,
+        }
+    }
+
+    fn inverse(&self) -> Operation {
+        match self.clone() {
+            Operation::Insert { index, element } => Operation::Remove { index, element },
+            Operation::Remove { index, element } => Operation::Insert { index, element },
+            Operation::Replace { before, after } => Operation::Replace {
+                before: after,
+                after: before,
+            },
+            Operation::Layers { before, after } => Operation::Layers {
+                before: after,
+                after: before,
+            },
+        }
+    }
+
+    fn apply(&self, document: &mut Document) {
+        match self {
+            Operation::Insert { index, element } => document.insert(*index, element.clone()),
+            Operation::Remove { element, .. } => {
+                document.remove(element.id);
+            }
+            Operation::Replace { after, .. } => {
+                document.replace(after.clone());
+            }
+            Operation::Layers { after, .. } => document.set_layers(after.clone()),
+        }
+    }
+}
+
+/// Operations that are undone and redone together.
+type Change = Vec<Operation>;
+
+#[derive(Debug)]
+pub struct History {
+    undo_stack: VecDeque<Change>,
+    redo_stack: Vec<Change>,
+    limit: usize,
+    /// change being built by the current gesture
+    gesture: Option<Change>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(DEFAULT_UNDO_LIMIT)
+    }
+}
+
+impl History {
+    /// History remembering at most `limit` undo steps.
+    pub fn new(limit: usize) -> Self {
+        History {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            limit,
+            gesture: None,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || self.gesture.as_ref().is_some_and(|g| !g.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.gesture = None;
+    }
+
+    /// Starts collecting operations into a single undo step.
+    pub fn begin_gesture(&mut self) {
+        if self.gesture.is_none() {
+            self.gesture = Some(Vec::new());
+        }
+    }
+
+    /// Finishes the current gesture. Elements touched during the gesture are
+    /// only snapshotted here, so mouse move updates stay cheap.
+    pub fn end_gesture(&mut self, document: &Document) {
+        let Some(mut change) = self.gesture.take() else {
+            return;
+        };
+        for op in change.iter_mut() {
+            match op {
+                Operation::Insert { element, .. } | Operation::Replace { after: element, .. } => {
+                    if let Some(current) = document.get(element.id) {
+                        *element = current.clone();
+                    }
+                }
+                Operation::Remove { .. } | Operation::Layers { .. } => {}
+            }
+        }
+        self.commit(change);
+    }
+
+    /// Runs `edit` as one undo step. During a gesture that is already going
+    /// on, like a drag a shortcut was pressed in, the edits join it and the
+    /// gesture is left open for whoever started it.
+    pub fn step<T>(&mut self, document: &mut Document, edit: impl FnOnce(&mut History, &mut Document) -> T) -> T {
+        let opened = !self.is_in_gesture();
+        self.begin_gesture();
+        let result = edit(self, document);
+        if opened {
+            self.end_gesture(document);
+        }
+        result
+    }
+
+    pub fn is_in_gesture(&self) -> bool {
+        self.gesture.is_some()
+    }
+
+    fn record(&mut self, op: Operation) {
+        match &mut self.gesture {
+            Some(change) => change.push(op),
+            None => self.commit(vec![op]),
+        }
+    }
+
+    fn commit(&mut self, change: Change) {
+        if change.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        self.undo_stack.push_back(change);
+        while self.undo_stack.len() > self.limit {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// Creates an element.
+    pub fn add(&mut self, document: &mut Document, shape: Shape, style: Style) -> ElementId {
+        let id = document.add(shape, style);
+        let index = document.index_of(id).unwrap();
+        let element = document.get(id).cloned().unwrap();
+        self.record(Operation::Insert { index, element });
+        id
+    }
+
+    /// Puts `element` at `index` in paint order, e.g. a group around elements
+    /// that were just removed. Its id should come from [`Document::new_id`].
+    /// The index is kept within the element's layer.
+    pub fn insert(&mut self, document: &mut Document, index: usize, element: Element) {
+        let id = element.id;
+        document.insert(index, element.clone());
+        let index = document.index_of(id).unwrap();
+        self.record(Operation::Insert { index, element });
+    }
+
+    /// Deletes an element. Deleting the last child of a group deletes the
+    /// group too, frames stay when they are emptied.
+    pub fn remove(&mut self, document: &mut Document, id: ElementId) -> Option<Element> {
+        if let Some(parent) = document.parent_of(id) {
+            let (parent, removed) = (parent.id, document.get(id).cloned());
+            self.update(document, parent, |group| {
+                if let Some(children) = group.children_mut() {
+                    children.retain(|child| child.id != id);
+                }
+            });
+            if document.get(parent).is_some_and(|group| group.is_group() && group.children().is_empty()) {
+                self.remove(document, parent);
+            }
+            return removed;
+        }
+        let index = document.index_of(id)?;
+        let element = document.remove(id)?;
+        self.record(Operation::Remove {
+            index,
+            element: element.clone(),
+        });
+        Some(element)
+    }
+
+    /// Changes an element in place, covering moves, resizes, restyles and text edits.
+    /// Changes to group children are recorded on the top level group.
+    /// Returns false when there is no element with that id.
+    pub fn update(
+        &mut self,
+        document: &mut Document,
+        id: ElementId,
+        edit: impl FnOnce(&mut Element),
+    ) -> bool {
+        let Some(root) = document.root_of(id) else {
+            return false;
+        };
+        let already_recorded = self.gesture.as_ref().is_some_and(|change| {
+            change.iter().rev().find(|op| op.id() == Some(root)).is_some_and(|op| {
+                matches!(op, Operation::Insert { .. } | Operation::Replace { .. })
+            })
+        });
+        let before = (!already_recorded).then(|| document.get(root).cloned().unwrap());
+        document.update(id, edit);
+        // inside a gesture `after` is filled in by `end_gesture`
+        if let Some(before) = before {
+            let after = document.get(root).cloned().unwrap();
+            self.record(Operation::Replace { before, after });
+        }
+        true
+    }
+
+    pub fn translate(&mut self, document: &mut Document, ids: &[ElementId], delta: Point) {
+        for &id in ids {
+            self.update(document, id, |element| element.translate(delta));
+        }
+    }
+
+    /// Rotates, flips or scales elements in world space, see [`Element::transform_by`].
+    pub fn transform(&mut self, document: &mut Document, ids: &[ElementId], transform: &Affine) {
+        for &id in ids {
+            self.update(document, id, |element| element.transform_by(transform));
+        }
+    }
+
+    /// Replaces the layer list, see [`Document::set_layers`].
+    pub fn set_layers(&mut self, document: &mut Document, layers: Vec<Layer>) {
+        let before = document.layers().to_vec();
+        if layers.is_empty() || before == layers {
+            return;
+        }
+        document.set_layers(layers.clone());
+        self.record(Operation::Layers { before, after: layers });
+    }
+
+    pub fn set_style(&mut self, document: &mut Document, id: ElementId, style: Style) -> bool {
+        self.update(document, id, |element| element.style = style)
+    }
+
+    /// Reverts the last change. Returns false when there was nothing to undo.
+    pub fn undo(&mut self, document: &mut Document) -> bool {
+        self.end_gesture(document);
+        let Some(change) = self.undo_stack.pop_back() else {
+            return false;
+        };
+        for op in change.iter().rev() {
+            op.inverse().apply(document);
+        }
+        self.redo_stack.push(change);
+        true
+    }
+
+    /// Re-applies the last undone change. Returns false when there was nothing to redo.
+    pub fn redo(&mut self, document: &mut Document) -> bool {
+        self.end_gesture(document);
+        let Some(change) = self.redo_stack.pop() else {
+            return false;
+        };
+        for op in change.iter() {
+            op.apply(document);
+        }
+        self.undo_stack.push_back(change);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{point, Rect};
+
+    fn square() -> Shape {
+        Shape::Rectangle {
+            rect: Rect::from_origin_size(Point::ZERO, point(10.0, 10.0)),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_creation() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let id = history.add(&mut document, square(), Style::default());
+
+        assert!(history.undo(&mut document));
+        assert!(document.get(id).is_none());
+        assert!(history.redo(&mut document));
+        assert!(document.get(id).is_some());
+        assert!(!history.redo(&mut document));
+    }
+
+    #[test]
+    fn undo_delete_restores_paint_order() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let bottom = history.add(&mut document, square(), Style::default());
+        let middle = history.add(&mut document, square(), Style::default());
+        let top = history.add(&mut document, square(), Style::default());
+
+        history.remove(&mut document, middle);
+        history.undo(&mut document);
+
+        let order: Vec<ElementId> = document.iter().map(|e| e.id).collect();
+        assert_eq!(order, vec![bottom, middle, top]);
+    }
+
+    #[test]
+    fn drag_gesture_is_one_undo_step() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let id = history.add(&mut document, square(), Style::default());
+        let original = document.get(id).cloned().unwrap();
+
+        history.begin_gesture();
+        for _ in 0..10 {
+            history.translate(&mut document, &[id], point(1.0, 2.0));
+        }
+        history.end_gesture(&document);
+        let moved = document.get(id).cloned().unwrap();
+        assert_eq!(moved.shape.bounds().min, point(10.0, 20.0));
+
+        history.undo(&mut document);
+        assert_eq!(document.get(id), Some(&original));
+        history.redo(&mut document);
+        assert_eq!(document.get(id), Some(&moved));
+        history.undo(&mut document);
+        history.undo(&mut document);
+        assert!(document.is_empty());
+    }
+
+    #[test]
+    fn element_created_during_gesture_redoes_final_state() {
+        let mut document = Document::new();
+        let mut history = History::default();
+
+        history.begin_gesture();
+        let id = history.add(
+            &mut document,
+            Shape::Freehand {
+                points: vec![Point::ZERO],
+                pressure: Vec::new(),
+            },
+            Style::default(),
+        );
+        for i in 1..5 {
+            history.update(&mut document, id, |element| {
+                if let Shape::Freehand { points, .. } = &mut element.shape {
+                    points.push(point(i as f32, 0.0));
+                }
+            });
+        }
+        history.end_gesture(&document);
+        let drawn = document.get(id).cloned().unwrap();
+
+        history.undo(&mut document);
+        assert!(document.is_empty());
+        history.redo(&mut document);
+        assert_eq!(document.get(id), Some(&drawn));
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let id = history.add(&mut document, square(), Style::default());
+        history.set_style(&mut document, id, Style::filled(crate::style::WHITE));
+        history.undo(&mut document);
+        assert!(history.can_redo());
+
+        history.translate(&mut document, &[id], point(5.0, 0.0));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn depth_is_bounded() {
+        let mut document = Document::new();
+        let mut history = History::new(3);
+        for _ in 0..5 {
+            history.add(&mut document, square(), Style::default());
+        }
+        let mut undone = 0;
+        while history.undo(&mut document) {
+            undone += 1;
+        }
+        assert_eq!(undone, 3);
+        assert_eq!(document.len(), 2);
+    }
+}
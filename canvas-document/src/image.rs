@@ -0,0 +1,131 @@
+//! Image elements: pictures from the document's [`AssetStore`](crate::asset::AssetStore),
+//! cropped to a part of them and stretched over their rect.
+//!
+//! The crop is relative to the picture, from (0, 0) at its top left corner to
+//! (1, 1) at its bottom right, so it doesn't change when the image is
+//! resized. Cropping moves the edges of the rect over the picture, which
+//! stays where it is.
+
+use crate::decode::DecodeError;
+use crate::document::Document;
+use crate::element::{ElementId, Shape};
+use crate::geometry::{point, Point, Rect};
+use crate::history::History;
+use crate::style::Style;
+
+/// The whole picture.
+pub const FULL_CROP: Rect = Rect {
+    min: point(0.0, 0.0),
+    max: point(1.0, 1.0),
+};
+
+/// Images never get cropped smaller than this, relative to the picture.
+const MIN_CROP: f32 = 0.01;
+
+pub fn is_image(document: &Document, id: ElementId) -> bool {
+    document.get(id).is_some_and(|element| matches!(element.shape, Shape::Image { .. }))
+}
+
+pub fn is_full_crop(crop: &Rect) -> bool {
+    *crop == FULL_CROP
+}
+
+/// Where the whole picture of an image showing `crop` over `rect` lies.
+pub fn placement(rect: &Rect, crop: &Rect) -> Rect {
+    let scale = point(rect.width() / crop.width(), rect.height() / crop.height());
+    let min = rect.min - point(crop.min.x * scale.x, crop.min.y * scale.y);
+    Rect::from_origin_size(min, point(scale.x, scale.y))
+}
+
+/// Shows the part of the picture under `target`, kept within the picture.
+/// Other shapes are left alone.
+pub fn crop_to(shape: &mut Shape, target: Rect) {
+    let Shape::Image { rect, crop, .. } = shape else {
+        return;
+    };
+    let picture = placement(rect, crop);
+    let Some(target) = target.intersection(&picture) else {
+        return;
+    };
+    let relative = |p: Point| {
+        point(
+            (p.x - picture.min.x) / picture.width(),
+            (p.y - picture.min.y) / picture.height(),
+        )
+    };
+    let cropped = Rect::from_corners(relative(target.min), relative(target.max));
+    if cropped.width() < MIN_CROP || cropped.height() < MIN_CROP {
+        return;
+    }
+    *rect = target;
+    *crop = cropped;
+}
+
+/// Adds the PNG or JPEG file `bytes` as an image centered on `center`, one
+/// picture pixel per `pixel_size` world units but no larger than `max_size`.
+pub fn add_image(
+    history: &mut History,
+    document: &mut Document,
+    bytes: Vec<u8>,
+    center: Point,
+    pixel_size: f32,
+    max_size: Point,
+) -> Result<ElementId, DecodeError> {
+    let asset = document.assets_mut().insert_image(bytes)?;
+    let picture = document.assets().image(&asset).ok_or(DecodeError::Empty)?.image();
+    let size = point(picture.width() as f32, picture.height() as f32) * pixel_size;
+    let fit = (max_size.x / size.x).min(max_size.y / size.y).min(1.0);
+    let rect = Rect::from_origin_size(center - size * (fit * 0.5), size * fit);
+    let style = Style {
+        fill: None,
+        stroke: None,
+        ..Style::default()
+    };
+    Ok(history.add(document, Shape::image(rect, asset), style))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetId;
+
+    fn image(rect: Rect) -> Shape {
+        Shape::image(rect, AssetId::of(b""))
+    }
+
+    #[test]
+    fn cropping_keeps_the_picture_in_place() {
+        let mut shape = image(Rect::from_origin_size(point(0.0, 0.0), point(200.0, 100.0)));
+        crop_to(&mut shape, Rect::from_corners(point(50.0, 0.0), point(150.0, 50.0)));
+        let Shape::Image { rect, crop, .. } = &shape else {
+            unreachable!()
+        };
+        assert_eq!(*crop, Rect::from_corners(point(0.25, 0.0), point(0.75, 0.5)));
+        assert_eq!(
+            placement(rect, crop),
+            Rect::from_origin_size(point(0.0, 0.0), point(200.0, 100.0))
+        );
+
+        // growing again stops at the edges of the picture
+        crop_to(&mut shape, Rect::from_corners(point(-100.0, 0.0), point(150.0, 50.0)));
+        let Shape::Image { rect, crop, .. } = &shape else {
+            unreachable!()
+        };
+        assert_eq!(rect.min, point(0.0, 0.0));
+        assert_eq!(crop.min, point(0.0, 0.0));
+    }
+
+    #[test]
+    fn resized_crops_scale_with_the_image() {
+        let mut shape = image(Rect::from_origin_size(point(0.0, 0.0), point(100.0, 100.0)));
+        crop_to(&mut shape, Rect::from_corners(point(0.0, 0.0), point(50.0, 100.0)));
+        shape.fit_to(Rect::from_origin_size(point(0.0, 0.0), point(100.0, 200.0)));
+        let Shape::Image { rect, crop, .. } = &shape else {
+            unreachable!()
+        };
+        assert_eq!(
+            placement(rect, crop),
+            Rect::from_origin_size(point(0.0, 0.0), point(200.0, 200.0))
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Lasso picking: which elements a free-form loop drawn over the board
+//! holds or touches.
+//!
+//! Elements are compared by their outline in world coordinates, the points
+//! of strokes and lines and the edges of everything else, so a loop drawn
+//! tightly around a stroke picks it even where its bounding box sticks out.
+
+use crate::connector::connector_path;
+use crate::element::{Element, Shape};
+use crate::geometry::{ellipse_outline, Point};
+use crate::selection::MarqueeMode;
+
+/// Number of corners of the polygon standing in for an ellipse.
+const ELLIPSE_SEGMENTS: usize = 32;
+/// How closely curved connectors are followed, in world units.
+const CURVE_TOLERANCE: f32 = 0.5;
+
+/// Whether `p` lies inside the polygon, which is closed from its last point
+/// back to its first. Parts of a loop crossing itself count as outside
+/// when they are enclosed twice.
+pub fn point_in_polygon(p: Point, polygon: &[Point]) -> bool {
+    let mut inside = false;
+    let mut previous = match polygon.last() {
+        Some(&last) => last,
+        None => return false,
+    };
+    for &current in polygon {
+        // edges crossing the horizontal ray to the right of `p`
+        if (current.y > p.y) != (previous.y > p.y) {
+            let x = current.x + (p.y - current.y) / (previous.y - current.y) * (previous.x - current.x);
+            if p.x < x {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+    inside
+}
+
+/// Whether the segments `a`-`b` and `c`-`d` cross or touch.
+pub fn segments_intersect(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let cross = |o: Point, p: Point, q: Point| (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x);
+    let within = |o: Point, p: Point, q: Point| {
+        q.x >= o.x.min(p.x) && q.x <= o.x.max(p.x) && q.y >= o.y.min(p.y) && q.y <= o.y.max(p.y)
+    };
+    let (d1, d2) = (cross(c, d, a), cross(c, d, b));
+    let (d3, d4) = (cross(a, b, c), cross(a, b, d));
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0)) {
+        return true;
+    }
+    // collinear cases, one end lies on the other segment
+    (d1 == 0.0 && within(c, d, a))
+        || (d2 == 0.0 && within(c, d, b))
+        || (d3 == 0.0 && within(a, b, c))
+        || (d4 == 0.0 && within(a, b, d))
+}
+
+/// Edges of a polygon, the closing one included, or of an open polyline.
+fn edges(points: &[Point], closed: bool) -> impl Iterator<Item = (Point, Point)> + '_ {
+    let closing = match (closed, points) {
+        (true, [first, .., last]) => Some((*last, *first)),
+        _ => None,
+    };
+    points.windows(2).map(|w| (w[0], w[1])).chain(closing)
+}
+
+/// Whether the polyline through `points`, a polygon when `closed`, crosses
+/// the edge of the polygon `lasso`.
+fn crosses(points: &[Point], closed: bool, lasso: &[Point]) -> bool {
+    edges(points, closed).any(|(a, b)| edges(lasso, true).any(|(c, d)| segments_intersect(a, b, c, d)))
+}
+
+/// The element's outline in the world and whether it encloses an area.
+/// Groups are handled by [`element_in_lasso`], frames are their rect.
+fn outline(element: &Element) -> (Vec<Point>, bool) {
+    let (points, closed) = match &element.shape {
+        Shape::Freehand { points, .. } => (points.clone(), false),
+        Shape::Line { start, end, .. } => (vec![*start, *end], false),
+        Shape::Connector { points, routing, .. } => {
+            let flattened = connector_path(points, *routing).flatten(CURVE_TOLERANCE);
+            (flattened.into_iter().flat_map(|polyline| polyline.points).collect(), false)
+        }
+        Shape::Ellipse { rect } => (ellipse_outline(rect, ELLIPSE_SEGMENTS), true),
+        _ => (element.shape.bounds().corners().to_vec(), true),
+    };
+    (points.into_iter().map(|p| element.transform.apply(p)).collect(), closed)
+}
+
+/// Whether the loop `lasso` picks `element` in `mode`: holds all of it, or
+/// touches any of it. A group counts as held when all of its visible children
+/// are and as touched when any of them is; one without visible children is
+/// never picked.
+pub fn element_in_lasso(element: &Element, lasso: &[Point], mode: MarqueeMode) -> bool {
+    if lasso.len() < 3 {
+        return false;
+    }
+    if let Shape::Group { children } = &element.shape {
+        // children are placed in the group's coordinates
+        let picked: Vec<bool> = children
+            .iter()
+            .filter(|child| !child.hidden)
+            .map(|child| {
+                let mut placed = child.clone();
+                placed.transform = child.transform.then(&element.transform);
+                element_in_lasso(&placed, lasso, mode)
+            })
+            .collect();
+        return match mode {
+            MarqueeMode::Contained => !picked.is_empty() && picked.iter().all(|&held| held),
+            MarqueeMode::Intersecting => picked.contains(&true),
+        };
+    }
+    let (points, closed) = outline(element);
+    let Some(&first) = points.first() else {
+        return false;
+    };
+    match mode {
+        MarqueeMode::Contained => {
+            points.iter().all(|&p| point_in_polygon(p, lasso)) && !crosses(&points, closed, lasso)
+        }
+        MarqueeMode::Intersecting => {
+            point_in_polygon(first, lasso)
+                || crosses(&points, closed, lasso)
+                // a loop drawn inside a shape
+                || (closed && point_in_polygon(lasso[0], &points))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::ElementId;
+    use crate::geometry::{point, Affine, Rect};
+    use crate::style::Style;
+
+    fn square(min: f32, max: f32) -> Vec<Point> {
+        vec![point(min, min), point(max, min), point(max, max), point(min, max)]
+    }
+
+    fn element(shape: Shape) -> Element {
+        Element::new(ElementId(0), shape, Style::default())
+    }
+
+    #[test]
+    fn points_inside_polygons() {
+        let polygon = square(0.0, 10.0);
+        assert!(point_in_polygon(point(5.0, 5.0), &polygon));
+        assert!(!point_in_polygon(point(15.0, 5.0), &polygon));
+        // a U shape, the gap between its arms is outside
+        let u = [
+            point(0.0, 0.0),
+            point(3.0, 0.0),
+            point(3.0, 7.0),
+            point(7.0, 7.0),
+            point(7.0, 0.0),
+            point(10.0, 0.0),
+            point(10.0, 10.0),
+            point(0.0, 10.0),
+        ];
+        assert!(!point_in_polygon(point(5.0, 3.0), &u));
+        assert!(point_in_polygon(point(5.0, 8.0), &u));
+        assert!(point_in_polygon(point(1.0, 3.0), &u));
+        assert!(!point_in_polygon(point(5.0, 5.0), &[point(0.0, 0.0), point(10.0, 10.0)]));
+    }
+
+    #[test]
+    fn segments_cross_touch_or_miss() {
+        let (a, b) = (point(0.0, 0.0), point(10.0, 10.0));
+        assert!(segments_intersect(a, b, point(0.0, 10.0), point(10.0, 0.0)));
+        assert!(segments_intersect(a, b, point(10.0, 10.0), point(20.0, 0.0)));
+        assert!(segments_intersect(a, b, point(5.0, 5.0), point(20.0, 20.0)));
+        assert!(!segments_intersect(a, b, point(0.0, 1.0), point(9.0, 10.0)));
+        assert!(!segments_intersect(a, b, point(11.0, 11.0), point(20.0, 20.0)));
+        assert!(!segments_intersect(a, b, point(6.0, 0.0), point(20.0, 0.0)));
+    }
+
+    #[test]
+    fn strokes_are_picked_by_their_points() {
+        // an L shaped stroke whose bounds reach the corner the loop leaves out
+        let stroke = element(Shape::Freehand {
+            points: vec![point(1.0, 2.0), point(1.0, 9.0), point(8.0, 9.0)],
+            pressure: Vec::new(),
+        });
+        let triangle = [point(0.0, 0.0), point(0.0, 10.0), point(10.0, 10.0)];
+        assert!(element_in_lasso(&stroke, &triangle, MarqueeMode::Contained));
+
+        let half = square(0.0, 5.0);
+        assert!(!element_in_lasso(&stroke, &half, MarqueeMode::Contained));
+        assert!(element_in_lasso(&stroke, &half, MarqueeMode::Intersecting));
+        assert!(!element_in_lasso(&stroke, &square(20.0, 30.0), MarqueeMode::Intersecting));
+    }
+
+    #[test]
+    fn groups_without_visible_children_are_never_held() {
+        let mut hidden = element(Shape::Rectangle {
+            rect: Rect::from_corners(point(100.0, 100.0), point(110.0, 110.0)),
+        });
+        hidden.hidden = true;
+        let group = element(Shape::Group { children: vec![hidden] });
+        assert!(!element_in_lasso(&group, &square(0.0, 10.0), MarqueeMode::Contained));
+        assert!(!element_in_lasso(&group, &square(90.0, 120.0), MarqueeMode::Contained));
+        assert!(!element_in_lasso(&group, &square(90.0, 120.0), MarqueeMode::Intersecting));
+    }
+
+    #[test]
+    fn shapes_follow_their_transform_and_concave_loops() {
+        let mut rect = element(Shape::Rectangle {
+            rect: Rect::from_corners(point(-2.0, -2.0), point(2.0, 2.0)),
+        });
+        let diamond = [point(0.0, -3.0), point(3.0, 0.0), point(0.0, 3.0), point(-3.0, 0.0)];
+        assert!(!element_in_lasso(&rect, &diamond, MarqueeMode::Contained));
+        rect.transform = Affine::rotate(std::f32::consts::FRAC_PI_4);
+        assert!(element_in_lasso(&rect, &diamond, MarqueeMode::Contained));
+
+        // every corner is inside the U, but its top edge crosses the gap
+        let wide = element(Shape::Rectangle {
+            rect: Rect::from_corners(point(1.0, 1.0), point(9.0, 9.0)),
+        });
+        let u = [
+            point(0.0, 0.0),
+            point(3.0, 0.0),
+            point(3.0, 5.0),
+            point(7.0, 5.0),
+            point(7.0, 0.0),
+            point(10.0, 0.0),
+            point(10.0, 10.0),
+            point(0.0, 10.0),
+        ];
+        assert!(!element_in_lasso(&wide, &u, MarqueeMode::Contained));
+        assert!(element_in_lasso(&wide, &u, MarqueeMode::Intersecting));
+
+        // a loop drawn inside a big shape touches it
+        let big = element(Shape::Ellipse {
+            rect: Rect::from_corners(point(-50.0, -50.0), point(50.0, 50.0)),
+        });
+        assert!(element_in_lasso(&big, &square(-1.0, 1.0), MarqueeMode::Intersecting));
+    }
+}
@@ -0,0 +1,355 @@
+//! Layers and paint order.
+//!
+//! Every top level element belongs to a [`Layer`]. Layers are painted bottom
+//! to top and elements within a layer in their own order, which [`arrange`]
+//! changes. Hiding a layer or an element keeps it from being drawn, exported
+//! and picked, locking it only keeps it from being picked.
+//!
+//! Everything here goes through [`History`], so it can be undone.
+
+use serde::{Deserialize, Serialize};
+
+use crate::document::Document;
+use crate::element::ElementId;
+use crate::history::History;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LayerId(pub u64);
+
+impl LayerId {
+    /// The layer of documents written before there were layers.
+    pub fn is_default(&self) -> bool {
+        *self == LayerId::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    pub id: LayerId,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+}
+
+impl Layer {
+    pub fn new(id: LayerId, name: impl Into<String>) -> Self {
+        Layer {
+            id,
+            name: name.into(),
+            hidden: false,
+            locked: false,
+        }
+    }
+}
+
+/// Ways to change the paint order of elements within their layer or group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZOrder {
+    /// One step up, above the next element.
+    Forward,
+    /// One step down, below the previous element.
+    Backward,
+    /// Above everything else.
+    Front,
+    /// Below everything else.
+    Back,
+}
+
+/// `block` bottom to top after moving the `selected` elements. Selected
+/// elements keep their order among themselves.
+pub fn arranged(block: &[ElementId], selected: &[ElementId], order: ZOrder) -> Vec<ElementId> {
+    let is_selected = |id: &ElementId| selected.contains(id);
+    let mut result = block.to_vec();
+    match order {
+        ZOrder::Front | ZOrder::Back => {
+            let (picked, rest): (Vec<ElementId>, Vec<ElementId>) = block.iter().partition(|id| is_selected(id));
+            result = match order {
+                ZOrder::Front => [rest, picked].concat(),
+                _ => [picked, rest].concat(),
+            };
+        }
+        // going against the direction of travel moves runs of selected elements together
+        ZOrder::Forward => {
+            for i in (0..result.len().saturating_sub(1)).rev() {
+                if is_selected(&result[i]) && !is_selected(&result[i + 1]) {
+                    result.swap(i, i + 1);
+                }
+            }
+        }
+        ZOrder::Backward => {
+            for i in 1..result.len() {
+                if is_selected(&result[i]) && !is_selected(&result[i - 1]) {
+                    result.swap(i, i - 1);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Moves the elements with `ids` up or down among their siblings. Top level
+/// elements stay within their layer, group children within their group.
+pub fn arrange(history: &mut History, document: &mut Document, ids: &[ElementId], order: ZOrder) {
+    history.step(document, |history, document| {
+        // one block per group that has selected children
+        let mut parents: Vec<ElementId> = ids
+            .iter()
+            .filter_map(|&id| document.parent_of(id).map(|parent| parent.id))
+            .collect();
+        parents.sort_unstable();
+        parents.dedup();
+        for parent in parents {
+            history.update(document, parent, |group| {
+                if let Some(children) = group.children_mut() {
+                    let block: Vec<ElementId> = children.iter().map(|child| child.id).collect();
+                    let mut taken = std::mem::take(children);
+                    for id in arranged(&block, ids, order) {
+                        if let Some(index) = taken.iter().position(|child| child.id == id) {
+                            children.push(taken.swap_remove(index));
+                        }
+                    }
+                }
+            });
+        }
+
+        let layers: Vec<_> = document.layers().iter().map(|layer| layer.id).collect();
+        for layer in layers {
+            let range = document.layer_range(layer);
+            let block: Vec<ElementId> = document.iter().skip(range.start).take(range.len()).map(|e| e.id).collect();
+            if !block.iter().any(|id| ids.contains(id)) {
+                continue;
+            }
+            // put each element in place from the bottom up, the ones below are done
+            for (offset, id) in arranged(&block, ids, order).into_iter().enumerate() {
+                let index = range.start + offset;
+                if document.index_of(id) != Some(index) {
+                    if let Some(element) = history.remove(document, id) {
+                        history.insert(document, index, element);
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Applies `edit` to a copy of the layer list and records the change.
+fn edit_layers(history: &mut History, document: &mut Document, edit: impl FnOnce(&mut Vec<Layer>)) {
+    let mut layers = document.layers().to_vec();
+    edit(&mut layers);
+    if layers != document.layers() {
+        history.set_layers(document, layers);
+    }
+}
+
+/// Adds a layer above the active one and makes it active.
+pub fn add_layer(history: &mut History, document: &mut Document, name: &str) -> LayerId {
+    let id = LayerId(document.layers().iter().map(|layer| layer.id.0 + 1).max().unwrap_or(0));
+    let above = document
+        .layers()
+        .iter()
+        .position(|layer| layer.id == document.active_layer())
+        .map_or(document.layers().len(), |index| index + 1);
+    edit_layers(history, document, |layers| layers.insert(above, Layer::new(id, name)));
+    document.set_active_layer(id);
+    id
+}
+
+/// Deletes a layer with everything on it. The last layer can't be deleted.
+pub fn remove_layer(history: &mut History, document: &mut Document, id: LayerId) -> bool {
+    if document.layers().len() < 2 || document.layer(id).is_none() {
+        return false;
+    }
+    history.step(document, |history, document| {
+        let doomed: Vec<ElementId> = document.iter().filter(|e| e.layer == id).map(|e| e.id).collect();
+        for element in doomed {
+            history.remove(document, element);
+        }
+        edit_layers(history, document, |layers| layers.retain(|layer| layer.id != id));
+    });
+    true
+}
+
+pub fn rename_layer(history: &mut History, document: &mut Document, id: LayerId, name: &str) {
+    edit_layers(history, document, |layers| {
+        if let Some(layer) = layers.iter_mut().find(|layer| layer.id == id) {
+            layer.name = name.to_string();
+        }
+    });
+}
+
+pub fn set_layer_hidden(history: &mut History, document: &mut Document, id: LayerId, hidden: bool) {
+    edit_layers(history, document, |layers| {
+        if let Some(layer) = layers.iter_mut().find(|layer| layer.id == id) {
+            layer.hidden = hidden;
+        }
+    });
+}
+
+pub fn set_layer_locked(history: &mut History, document: &mut Document, id: LayerId, locked: bool) {
+    edit_layers(history, document, |layers| {
+        if let Some(layer) = layers.iter_mut().find(|layer| layer.id == id) {
+            layer.locked = locked;
+        }
+    });
+}
+
+/// Moves a layer to `index` in the bottom to top layer order.
+pub fn move_layer(history: &mut History, document: &mut Document, id: LayerId, index: usize) {
+    edit_layers(history, document, |layers| {
+        if let Some(from) = layers.iter().position(|layer| layer.id == id) {
+            let layer = layers.remove(from);
+            layers.insert(index.min(layers.len()), layer);
+        }
+    });
+}
+
+/// Puts top level elements on another layer, above what is already there.
+pub fn move_to_layer(history: &mut History, document: &mut Document, ids: &[ElementId], layer: LayerId) {
+    if document.layer(layer).is_none() {
+        return;
+    }
+    history.step(document, |history, document| {
+        for &id in ids {
+            if document.index_of(id).is_none() {
+                continue;
+            }
+            // removed and inserted rather than updated, so undo restores the old position
+            if let Some(mut element) = history.remove(document, id) {
+                element.layer = layer;
+                let index = document.layer_range(layer).end;
+                history.insert(document, index, element);
+            }
+        }
+    });
+}
+
+pub fn set_hidden(history: &mut History, document: &mut Document, ids: &[ElementId], hidden: bool) {
+    history.step(document, |history, document| {
+        for &id in ids {
+            history.update(document, id, |element| element.hidden = hidden);
+        }
+    });
+}
+
+pub fn set_locked(history: &mut History, document: &mut Document, ids: &[ElementId], locked: bool) {
+    history.step(document, |history, document| {
+        for &id in ids {
+            history.update(document, id, |element| element.locked = locked);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::point;
+    use crate::test_util::square;
+
+    fn order(document: &Document) -> Vec<ElementId> {
+        document.iter().map(|element| element.id).collect()
+    }
+
+    #[test]
+    fn arranged_moves_runs_together() {
+        let ids: Vec<ElementId> = (0..5).map(ElementId).collect();
+        let selected = [ids[1], ids[2]];
+        let moved = |order| arranged(&ids, &selected, order);
+        assert_eq!(moved(ZOrder::Forward), vec![ids[0], ids[3], ids[1], ids[2], ids[4]]);
+        assert_eq!(moved(ZOrder::Backward), vec![ids[1], ids[2], ids[0], ids[3], ids[4]]);
+        assert_eq!(moved(ZOrder::Front), vec![ids[0], ids[3], ids[4], ids[1], ids[2]]);
+        assert_eq!(moved(ZOrder::Back), vec![ids[1], ids[2], ids[0], ids[3], ids[4]]);
+        // already on top, nothing to do
+        assert_eq!(arranged(&ids, &[ids[4]], ZOrder::Forward), ids);
+    }
+
+    #[test]
+    fn z_order_stays_within_the_layer() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let bottom = square(&mut document, 0.0, 0.0, 10.0);
+        let other = square(&mut document, 0.0, 0.0, 10.0);
+        add_layer(&mut history, &mut document, "Top");
+        let top = square(&mut document, 0.0, 0.0, 10.0);
+        assert_eq!(order(&document), vec![bottom, other, top]);
+
+        arrange(&mut history, &mut document, &[bottom], ZOrder::Front);
+        assert_eq!(order(&document), vec![other, bottom, top]);
+        assert_eq!(document.element_at(point(5.0, 5.0), 0.0), Some(top));
+        assert!(history.undo(&mut document));
+        assert_eq!(order(&document), vec![bottom, other, top]);
+    }
+
+    #[test]
+    fn layer_order_decides_paint_order() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let base = document.active_layer();
+        let a = square(&mut document, 0.0, 0.0, 10.0);
+        let upper = add_layer(&mut history, &mut document, "Upper");
+        let b = square(&mut document, 0.0, 0.0, 10.0);
+        // new elements go on the active layer even when it is not on top
+        document.set_active_layer(base);
+        let c = square(&mut document, 0.0, 0.0, 10.0);
+        assert_eq!(order(&document), vec![a, c, b]);
+
+        move_layer(&mut history, &mut document, upper, 0);
+        assert_eq!(order(&document), vec![b, a, c]);
+        move_to_layer(&mut history, &mut document, &[a], upper);
+        assert_eq!(order(&document), vec![b, a, c]);
+        assert_eq!(document.get(a).unwrap().layer, upper);
+
+        assert!(history.undo(&mut document));
+        assert!(history.undo(&mut document));
+        assert_eq!(order(&document), vec![a, c, b]);
+    }
+
+    #[test]
+    fn hidden_and_locked_content_is_not_picked() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let below = square(&mut document, 0.0, 0.0, 10.0);
+        let above = square(&mut document, 0.0, 0.0, 10.0);
+        let p = point(5.0, 5.0);
+
+        set_locked(&mut history, &mut document, &[above], true);
+        assert_eq!(document.element_at(p, 0.0), Some(below));
+        assert!(document.is_visible(above));
+        set_hidden(&mut history, &mut document, &[below], true);
+        assert_eq!(document.element_at(p, 0.0), None);
+        assert!(!document.is_visible(below));
+
+        history.undo(&mut document);
+        history.undo(&mut document);
+        let layer = document.active_layer();
+        set_layer_hidden(&mut history, &mut document, layer, true);
+        assert_eq!(document.element_at(p, 0.0), None);
+        assert!(!document.is_visible(above));
+        set_layer_hidden(&mut history, &mut document, layer, false);
+        set_layer_locked(&mut history, &mut document, layer, true);
+        assert!(document.is_visible(above));
+        assert!(!document.is_pickable(above));
+    }
+
+    #[test]
+    fn removing_a_layer_takes_its_elements_and_undoes() {
+        let mut document = Document::new();
+        let mut history = History::default();
+        let kept = square(&mut document, 0.0, 0.0, 10.0);
+        let layer = add_layer(&mut history, &mut document, "Doomed");
+        square(&mut document, 20.0, 0.0, 10.0);
+        assert!(remove_layer(&mut history, &mut document, layer));
+        assert_eq!(order(&document), vec![kept]);
+        assert_eq!(document.layers().len(), 1);
+        assert_ne!(document.active_layer(), layer);
+        // the last layer stays
+        let last = document.active_layer();
+        assert!(!remove_layer(&mut history, &mut document, last));
+
+        assert!(history.undo(&mut document));
+        assert_eq!(document.len(), 2);
+        assert_eq!(document.layers().len(), 2);
+    }
+}
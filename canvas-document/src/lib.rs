@@ -0,0 +1,53 @@
+//! Platform neutral model of what is drawn on a canvas.
+//!
+//! Nothing in here knows about macroquad or the browser, the front ends
+//! (`rendering-engine` and the wasm packages) translate it into draw calls.
+
+pub mod align;
+pub mod arrow;
+pub mod asset;
+pub mod connector;
+pub mod decode;
+pub mod document;
+pub mod element;
+pub mod eraser;
+pub mod export;
+pub mod format;
+pub mod frame;
+pub mod freehand;
+pub mod geometry;
+pub mod grid;
+pub mod group;
+pub mod history;
+pub mod image;
+pub mod lasso;
+pub mod layer;
+pub mod mipmap;
+pub mod render;
+pub mod scene;
+pub mod selection;
+pub mod snap;
+pub mod spatial;
+pub mod sticky;
+pub mod style;
+pub mod svg;
+pub mod text;
+
+pub use document::Document;
+pub use element::{Element, ElementId, Shape};
+pub use format::{CameraState, CanvasSettings, SavedCanvas};
+pub use geometry::{point, Affine, Point, Rect};
+pub use history::History;
+pub use render::Renderer;
+pub use style::{Color, Style};
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use crate::{point, Document, ElementId, Rect, Shape, Style};
+
+    /// Adds a `size` square rectangle with its top left corner at (`x`, `y`).
+    pub(crate) fn square(document: &mut Document, x: f32, y: f32, size: f32) -> ElementId {
+        let rect = Rect::from_origin_size(point(x, y), point(size, size));
+        document.add(Shape::Rectangle { rect }, Style::default())
+    }
+}
@@ -0,0 +1,132 @@
+//! Downscaled copies of an image, so a picture shown small is drawn from a
+//! small texture instead of filtering all of its pixels every frame.
+
+use crate::render::Image;
+
+/// Levels stop halving once they are this small on their longer side.
+const SMALLEST_LEVEL: u32 = 16;
+
+/// An image and its copies at half, a quarter, ... of its size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MipChain {
+    /// largest first, `levels[0]` is the image itself
+    levels: Vec<Image>,
+}
+
+impl MipChain {
+    pub fn new(image: Image) -> Self {
+        let mut levels = vec![image];
+        loop {
+            let last = &levels[levels.len() - 1];
+            if last.width().max(last.height()) <= SMALLEST_LEVEL {
+                break;
+            }
+            let half = half_size(last);
+            levels.push(half);
+        }
+        MipChain { levels }
+    }
+
+    /// The full size image.
+    pub fn image(&self) -> &Image {
+        &self.levels[0]
+    }
+
+    pub fn levels(&self) -> &[Image] {
+        &self.levels
+    }
+
+    /// The smallest level at least `width` by `height` pixels, the image
+    /// is drawn that large on screen, so it never gets blurrier than it is.
+    pub fn level_for(&self, width: f32, height: f32) -> &Image {
+        self.levels
+            .iter()
+            .rev()
+            .find(|level| level.width() as f32 >= width && level.height() as f32 >= height)
+            .unwrap_or(&self.levels[0])
+    }
+}
+
+/// `image` halved until neither side is longer than `max_side`, for surfaces
+/// that can't hold it at full size. `None` when it already fits.
+pub fn fit_within(image: &Image, max_side: u32) -> Option<Image> {
+    let mut fitted: Option<Image> = None;
+    loop {
+        let current = fitted.as_ref().unwrap_or(image);
+        if current.width().max(current.height()) <= max_side.max(1) {
+            return fitted;
+        }
+        fitted = Some(half_size(current));
+    }
+}
+
+/// Every pixel the average of the two by two pixels under it. Colors are
+/// weighted by their alpha so transparent pixels don't darken the edges.
+fn half_size(image: &Image) -> Image {
+    let (width, height) = (image.width(), image.height());
+    let (half_width, half_height) = (width.div_ceil(2), height.div_ceil(2));
+    let source = image.pixels();
+    let mut pixels = Vec::with_capacity(half_width as usize * half_height as usize * 4);
+    for y in 0..half_height {
+        for x in 0..half_width {
+            let mut sum = [0u32; 4];
+            // odd sizes repeat the last row or column
+            for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let px = (x * 2 + sx).min(width - 1) as usize;
+                let py = (y * 2 + sy).min(height - 1) as usize;
+                let p = &source[(py * width as usize + px) * 4..][..4];
+                let alpha = p[3] as u32;
+                for channel in 0..3 {
+                    sum[channel] += p[channel] as u32 * alpha;
+                }
+                sum[3] += alpha;
+            }
+            let alpha = sum[3];
+            let color = |channel: usize| (sum[channel] + alpha / 2).checked_div(alpha).unwrap_or(0) as u8;
+            pixels.extend([color(0), color(1), color(2), ((alpha + 2) / 4) as u8]);
+        }
+    }
+    Image::from_rgba(half_width, half_height, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Image {
+        Image::from_rgba(width, height, rgba.repeat((width * height) as usize))
+    }
+
+    #[test]
+    fn halves_down_to_the_smallest_level() {
+        let chain = MipChain::new(solid(100, 40, [10, 20, 30, 255]));
+        let sizes: Vec<(u32, u32)> = chain.levels().iter().map(|level| (level.width(), level.height())).collect();
+        assert_eq!(sizes, [(100, 40), (50, 20), (25, 10), (13, 5)]);
+        assert!(chain.levels()[3].pixels().chunks(4).all(|p| p == [10, 20, 30, 255]));
+    }
+
+    #[test]
+    fn picks_the_smallest_sharp_level() {
+        let chain = MipChain::new(solid(64, 64, [0, 0, 0, 255]));
+        assert_eq!(chain.level_for(20.0, 20.0).width(), 32);
+        assert_eq!(chain.level_for(16.0, 16.0).width(), 16);
+        assert_eq!(chain.level_for(4.0, 4.0).width(), 16);
+        assert_eq!(chain.level_for(500.0, 500.0).width(), 64);
+    }
+
+    #[test]
+    fn fits_images_within_a_size() {
+        let image = solid(100, 40, [10, 20, 30, 255]);
+        assert!(fit_within(&image, 100).is_none());
+        let fitted = fit_within(&image, 30).unwrap();
+        assert_eq!((fitted.width(), fitted.height()), (25, 10));
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_darken() {
+        let mut pixels = [255, 255, 255, 255].repeat(2);
+        pixels.extend([0, 0, 0, 0].repeat(2));
+        let half = half_size(&Image::from_rgba(2, 2, pixels));
+        assert_eq!(half.pixels(), &[255, 255, 255, 128]);
+    }
+}
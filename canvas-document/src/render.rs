@@ -0,0 +1,418 @@
+//! The drawing interface every backend implements.
+//!
+//! Scene code (see [`crate::scene`]) only talks to a [`Renderer`], so the
+//! window, the browser canvas and the software rasterizer draw the same thing.
+//! [`RecordingRenderer`] keeps the calls instead of drawing them, for tests.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::geometry::{point, Affine, Point, Rect};
+use crate::style::Color;
+use crate::text::Font;
+
+/// Control point distance that makes four cubic Béziers approximate a circle.
+const KAPPA: f32 = 0.552_284_8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    MoveTo(Point),
+    LineTo(Point),
+    CubicTo {
+        control1: Point,
+        control2: Point,
+        to: Point,
+    },
+    Close,
+}
+
+/// Outline made of straight and curved pieces, possibly with several subpaths.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    pub segments: Vec<PathSegment>,
+}
+
+/// A flattened subpath.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<Point>,
+    pub closed: bool,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Path::default()
+    }
+
+    pub fn move_to(&mut self, p: Point) -> &mut Self {
+        self.segments.push(PathSegment::MoveTo(p));
+        self
+    }
+
+    pub fn line_to(&mut self, p: Point) -> &mut Self {
+        self.segments.push(PathSegment::LineTo(p));
+        self
+    }
+
+    pub fn cubic_to(&mut self, control1: Point, control2: Point, to: Point) -> &mut Self {
+        self.segments.push(PathSegment::CubicTo { control1, control2, to });
+        self
+    }
+
+    pub fn close(&mut self) -> &mut Self {
+        self.segments.push(PathSegment::Close);
+        self
+    }
+
+    pub fn rect(rect: Rect) -> Self {
+        Path::polygon(&[
+            rect.min,
+            point(rect.max.x, rect.min.y),
+            rect.max,
+            point(rect.min.x, rect.max.y),
+        ])
+    }
+
+    /// Ellipse inscribed in `rect`.
+    pub fn ellipse(rect: Rect) -> Self {
+        let c = rect.center();
+        let r = rect.size() * 0.5;
+        let k = r * KAPPA;
+        let mut path = Path::new();
+        path.move_to(point(c.x + r.x, c.y))
+            .cubic_to(point(c.x + r.x, c.y + k.y), point(c.x + k.x, c.y + r.y), point(c.x, c.y + r.y))
+            .cubic_to(point(c.x - k.x, c.y + r.y), point(c.x - r.x, c.y + k.y), point(c.x - r.x, c.y))
+            .cubic_to(point(c.x - r.x, c.y - k.y), point(c.x - k.x, c.y - r.y), point(c.x, c.y - r.y))
+            .cubic_to(point(c.x + k.x, c.y - r.y), point(c.x + r.x, c.y - k.y), point(c.x + r.x, c.y))
+            .close();
+        path
+    }
+
+    pub fn circle(center: Point, radius: f32) -> Self {
+        Path::ellipse(Rect::from_corners(
+            center - point(radius, radius),
+            center + point(radius, radius),
+        ))
+    }
+
+    /// Open path through the points.
+    pub fn polyline(points: &[Point]) -> Self {
+        let mut path = Path::new();
+        for (i, &p) in points.iter().enumerate() {
+            if i == 0 {
+                path.move_to(p);
+            } else {
+                path.line_to(p);
+            }
+        }
+        path
+    }
+
+    /// Closed path through the points.
+    pub fn polygon(points: &[Point]) -> Self {
+        let mut path = Path::polyline(points);
+        if !points.is_empty() {
+            path.close();
+        }
+        path
+    }
+
+    /// Splits the path into polylines, curves are cut into straight pieces
+    /// that stray at most about `tolerance` from the curve.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let mut polylines: Vec<Polyline> = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+        // a lone point has nothing to draw
+        let finish = |current: &mut Vec<Point>, closed: bool, polylines: &mut Vec<Polyline>| {
+            let points = std::mem::take(current);
+            if points.len() > 1 {
+                polylines.push(Polyline { points, closed });
+            }
+        };
+        for segment in &self.segments {
+            match *segment {
+                PathSegment::MoveTo(p) => {
+                    finish(&mut current, false, &mut polylines);
+                    current.push(p);
+                }
+                PathSegment::LineTo(p) => current.push(p),
+                PathSegment::CubicTo { control1, control2, to } => {
+                    let from = current.last().copied().unwrap_or(control1);
+                    let hull = from.distance(control1) + control1.distance(control2) + control2.distance(to);
+                    let steps = (hull / tolerance.max(1e-3)).sqrt().ceil().clamp(1.0, 100.0) as usize;
+                    for step in 1..=steps {
+                        let t = step as f32 / steps as f32;
+                        let u = 1.0 - t;
+                        current.push(
+                            from * (u * u * u)
+                                + control1 * (3.0 * u * u * t)
+                                + control2 * (3.0 * u * t * t)
+                                + to * (t * t * t),
+                        );
+                    }
+                }
+                PathSegment::Close => {
+                    let start = current.first().copied();
+                    finish(&mut current, true, &mut polylines);
+                    // drawing may continue from the start of the closed subpath
+                    current.extend(start);
+                }
+            }
+        }
+        finish(&mut current, false, &mut polylines);
+        polylines
+    }
+}
+
+/// Outline style, caps and joins are always round.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stroke {
+    pub color: Color,
+    pub width: f32,
+}
+
+/// Straight (not premultiplied) RGBA8 pixels. Cloning is cheap, and every
+/// image gets an id backends can key their texture caches by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    id: u64,
+    width: u32,
+    height: u32,
+    pixels: Arc<[u8]>,
+}
+
+impl Image {
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        assert_eq!(pixels.len(), width as usize * height as usize * 4, "expected RGBA8 pixels");
+        Image {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            width,
+            height,
+            pixels: pixels.into(),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+/// A drawing surface. Coordinates go through the current transform, which
+/// starts as the identity, i.e. surface pixels.
+pub trait Renderer {
+    /// Remembers the transform and clip until the matching [`Renderer::restore`].
+    fn save(&mut self);
+    fn restore(&mut self);
+    /// Applies `transform` to everything drawn after it, before the current transform.
+    fn transform(&mut self, transform: &Affine);
+    /// Limits drawing to `rect`, in current coordinates, until the next restore.
+    fn clip_rect(&mut self, rect: Rect);
+    /// Fills the whole surface, ignoring transform and clip.
+    fn clear(&mut self, color: Color);
+    /// Fills the path with the nonzero rule, open subpaths are closed implicitly.
+    fn fill_path(&mut self, path: &Path, color: Color);
+    fn stroke_path(&mut self, path: &Path, stroke: &Stroke);
+    /// One line of text with its top left corner at `position`. Glyphs go
+    /// where [`Font::glyphs`] puts them, so they line up with the layout.
+    fn draw_text(&mut self, text: &str, position: Point, font: &Font, color: Color);
+    /// Draws the image stretched over `dest`.
+    fn draw_image(&mut self, image: &Image, dest: Rect);
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.fill_path(&Path::rect(rect), color);
+    }
+
+    fn stroke_rect(&mut self, rect: Rect, stroke: &Stroke) {
+        self.stroke_path(&Path::rect(rect), stroke);
+    }
+
+    fn fill_ellipse(&mut self, rect: Rect, color: Color) {
+        self.fill_path(&Path::ellipse(rect), color);
+    }
+
+    fn stroke_ellipse(&mut self, rect: Rect, stroke: &Stroke) {
+        self.stroke_path(&Path::ellipse(rect), stroke);
+    }
+
+    fn fill_circle(&mut self, center: Point, radius: f32, color: Color) {
+        self.fill_path(&Path::circle(center, radius), color);
+    }
+
+    fn stroke_line(&mut self, start: Point, end: Point, stroke: &Stroke) {
+        self.stroke_path(&Path::polyline(&[start, end]), stroke);
+    }
+}
+
+/// One call made on a [`RecordingRenderer`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand {
+    Save,
+    Restore,
+    Transform(Affine),
+    ClipRect(Rect),
+    Clear(Color),
+    FillPath {
+        path: Path,
+        color: Color,
+    },
+    StrokePath {
+        path: Path,
+        stroke: Stroke,
+    },
+    Text {
+        text: String,
+        position: Point,
+        font: Font,
+        color: Color,
+    },
+    Image {
+        image_id: u64,
+        dest: Rect,
+    },
+}
+
+/// Backend that records what would have been drawn, so scene code can be
+/// asserted on without a window.
+#[derive(Debug, Default)]
+pub struct RecordingRenderer {
+    pub commands: Vec<DrawCommand>,
+}
+
+impl RecordingRenderer {
+    pub fn new() -> Self {
+        RecordingRenderer::default()
+    }
+
+    /// Commands that paint something, leaving out state changes.
+    pub fn painted(&self) -> impl Iterator<Item = &DrawCommand> {
+        self.commands.iter().filter(|command| {
+            matches!(
+                command,
+                DrawCommand::FillPath { .. }
+                    | DrawCommand::StrokePath { .. }
+                    | DrawCommand::Text { .. }
+                    | DrawCommand::Image { .. }
+            )
+        })
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    fn save(&mut self) {
+        self.commands.push(DrawCommand::Save);
+    }
+
+    fn restore(&mut self) {
+        self.commands.push(DrawCommand::Restore);
+    }
+
+    fn transform(&mut self, transform: &Affine) {
+        self.commands.push(DrawCommand::Transform(*transform));
+    }
+
+    fn clip_rect(&mut self, rect: Rect) {
+        self.commands.push(DrawCommand::ClipRect(rect));
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.commands.push(DrawCommand::Clear(color));
+    }
+
+    fn fill_path(&mut self, path: &Path, color: Color) {
+        self.commands.push(DrawCommand::FillPath {
+            path: path.clone(),
+            color,
+        });
+    }
+
+    fn stroke_path(&mut self, path: &Path, stroke: &Stroke) {
+        self.commands.push(DrawCommand::StrokePath {
+            path: path.clone(),
+            stroke: *stroke,
+        });
+    }
+
+    fn draw_text(&mut self, text: &str, position: Point, font: &Font, color: Color) {
+        self.commands.push(DrawCommand::Text {
+            text: text.to_string(),
+            position,
+            font: *font,
+            color,
+        });
+    }
+
+    fn draw_image(&mut self, image: &Image, dest: Rect) {
+        self.commands.push(DrawCommand::Image {
+            image_id: image.id(),
+            dest,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_splits_subpaths() {
+        let mut path = Path::polygon(&[point(0.0, 0.0), point(1.0, 0.0), point(1.0, 1.0)]);
+        path.move_to(point(5.0, 5.0)).line_to(point(6.0, 5.0));
+        let polylines = path.flatten(0.1);
+        assert_eq!(polylines.len(), 2);
+        assert!(polylines[0].closed);
+        assert_eq!(polylines[0].points.len(), 3);
+        assert_eq!(
+            polylines[1],
+            Polyline {
+                points: vec![point(5.0, 5.0), point(6.0, 5.0)],
+                closed: false
+            }
+        );
+    }
+
+    #[test]
+    fn flattened_ellipse_stays_on_the_curve() {
+        let rect = Rect::from_corners(point(-10.0, -5.0), point(10.0, 5.0));
+        let polylines = Path::ellipse(rect).flatten(0.05);
+        assert_eq!(polylines.len(), 1);
+        for p in &polylines[0].points {
+            let on_curve = (p.x / 10.0).powi(2) + (p.y / 5.0).powi(2);
+            assert!((on_curve - 1.0).abs() < 0.01, "{:?} is off the ellipse", p);
+        }
+        // finer tolerance, more points
+        assert!(Path::ellipse(rect).flatten(0.001)[0].points.len() > polylines[0].points.len());
+    }
+
+    #[test]
+    fn recording_keeps_call_order() {
+        let mut renderer = RecordingRenderer::new();
+        renderer.save();
+        renderer.transform(&Affine::scale(2.0));
+        renderer.fill_rect(Rect::from_corners(point(0.0, 0.0), point(1.0, 1.0)), crate::style::BLACK);
+        renderer.restore();
+        assert_eq!(renderer.commands.len(), 4);
+        assert_eq!(renderer.painted().count(), 1);
+        assert_eq!(renderer.commands[1], DrawCommand::Transform(Affine::scale(2.0)));
+    }
+
+    #[test]
+    fn images_get_distinct_ids() {
+        let a = Image::from_rgba(1, 1, vec![0; 4]);
+        let b = Image::from_rgba(1, 1, vec![0; 4]);
+        assert_ne!(a.id(), b.id());
+        assert_eq!(a.clone().id(), a.id());
+    }
+}
@@ -0,0 +1,452 @@
+//! What a board looks like, drawn through any [`Renderer`].
+
+use crate::arrow::{line_geometry, HeadOutline};
+use crate::asset::AssetStore;
+use crate::connector::connector_geometry;
+use crate::document::Document;
+use crate::element::{Element, Shape, TextBlock};
+use crate::format::{CameraState, CanvasSettings};
+use crate::freehand::{outline_polygon, pressure_stroke_for_zoom, smooth_for_zoom};
+use crate::geometry::{point, Point, Rect};
+use crate::grid::{dot_radius, grid_dots};
+use crate::image::{is_full_crop, placement};
+use crate::render::{Path, Renderer, Stroke};
+use crate::sticky;
+use crate::style::{Color, BLACK};
+
+const PLACEHOLDER_FILL: Color = Color::new(0.9, 0.9, 0.9, 1.0);
+const PLACEHOLDER_STROKE: Color = Color::new(0.6, 0.6, 0.6, 1.0);
+
+/// Background, grid and elements as seen through `camera` on a surface of
+/// `viewport` pixels.
+pub fn draw_board(
+    renderer: &mut impl Renderer,
+    document: &Document,
+    settings: &CanvasSettings,
+    camera: &CameraState,
+    viewport: Point,
+) {
+    renderer.clear(settings.background);
+    renderer.save();
+    renderer.transform(&camera.view_transform(viewport));
+    let visible = camera.visible_rect(viewport);
+    if settings.show_grid {
+        draw_grid(renderer, &visible, camera.zoom);
+    }
+    draw_document(renderer, document, &visible, camera.zoom);
+    renderer.restore();
+}
+
+/// Dots covering `visible`, in world coordinates. They keep the same size on
+/// screen at every zoom.
+pub fn draw_grid(renderer: &mut impl Renderer, visible: &Rect, zoom: f32) {
+    let radius = dot_radius(zoom) / zoom;
+    for dot in grid_dots(visible, zoom) {
+        renderer.fill_circle(dot.position, radius, dot.color());
+    }
+}
+
+/// Elements touching `visible` in paint order, in world coordinates.
+pub fn draw_document(renderer: &mut impl Renderer, document: &Document, visible: &Rect, zoom: f32) {
+    for element in document.elements_intersecting(visible) {
+        if document.is_visible(element.id) {
+            draw_element(renderer, element, document.assets(), zoom);
+        }
+    }
+}
+
+/// Draws one element in world coordinates, images come from `assets`.
+/// `zoom` only decides how finely curves are smoothed and which mipmap
+/// level pictures are drawn from.
+pub fn draw_element(renderer: &mut impl Renderer, element: &Element, assets: &AssetStore, zoom: f32) {
+    if element.transform.is_identity() {
+        draw_shape(renderer, element, assets, zoom);
+    } else {
+        renderer.save();
+        renderer.transform(&element.transform);
+        draw_shape(renderer, element, assets, zoom * element.transform.scale_factor());
+        renderer.restore();
+    }
+}
+
+/// Arrowheads in the stroke color, filled or outlined.
+fn draw_heads(renderer: &mut impl Renderer, heads: &[HeadOutline], stroke: &Stroke) {
+    for head in heads {
+        if head.filled {
+            renderer.fill_path(&Path::polygon(&head.points), stroke.color);
+        } else {
+            renderer.stroke_path(&Path::polyline(&head.points), stroke);
+        }
+    }
+}
+
+/// The element's shape in its own coordinates, before the transform.
+fn draw_shape(renderer: &mut impl Renderer, element: &Element, assets: &AssetStore, zoom: f32) {
+    let style = &element.style;
+    let fill = style.fill.map(|c| c.with_alpha(c.a * style.opacity));
+    let stroke = style.stroke.map(|c| Stroke {
+        color: c.with_alpha(c.a * style.opacity),
+        width: style.stroke_width,
+    });
+
+    match &element.shape {
+        Shape::Rectangle { rect } => {
+            if let Some(fill) = fill {
+                renderer.fill_rect(*rect, fill);
+            }
+            if let Some(stroke) = stroke {
+                renderer.stroke_rect(*rect, &stroke);
+            }
+        }
+        Shape::Ellipse { rect } => {
+            if let Some(fill) = fill {
+                renderer.fill_ellipse(*rect, fill);
+            }
+            if let Some(stroke) = stroke {
+                renderer.stroke_ellipse(*rect, &stroke);
+            }
+        }
+        Shape::Line {
+            start,
+            end,
+            start_head,
+            end_head,
+        } => {
+            let Some(stroke) = stroke else {
+                return;
+            };
+            let line = line_geometry(*start, *end, *start_head, *end_head, stroke.width);
+            renderer.stroke_line(line.start, line.end, &stroke);
+            draw_heads(renderer, &line.heads, &stroke);
+        }
+        Shape::Connector {
+            points,
+            routing,
+            start_head,
+            end_head,
+            ..
+        } => {
+            let Some(stroke) = stroke else {
+                return;
+            };
+            let connector = connector_geometry(points, *routing, *start_head, *end_head, stroke.width);
+            renderer.stroke_path(&connector.path, &stroke);
+            draw_heads(renderer, &connector.heads, &stroke);
+        }
+        Shape::Freehand { points, pressure } => {
+            let Some(stroke) = stroke else {
+                return;
+            };
+            if !pressure.is_empty() {
+                // the width varies along the stroke, so its outline is filled
+                let (centre, widths) = pressure_stroke_for_zoom(points, pressure, stroke.width, zoom);
+                renderer.fill_path(&Path::polygon(&outline_polygon(&centre, &widths)), stroke.color);
+            } else if let [only] = points.as_slice() {
+                renderer.fill_circle(*only, stroke.width * 0.5, stroke.color);
+            } else {
+                renderer.stroke_path(&Path::polyline(&smooth_for_zoom(points, zoom)), &stroke);
+            }
+        }
+        Shape::Text { content, .. } => {
+            let color = fill.or(stroke.map(|s| s.color)).unwrap_or(BLACK);
+            if let Some(block) = element.shape.text_block() {
+                draw_text_block(renderer, content, &block, color);
+            }
+        }
+        Shape::Sticky { rect, content, color } => {
+            let shadow = rect.translate(point(0.0, rect.height() * sticky::SHADOW_OFFSET));
+            renderer.fill_rect(shadow, sticky::SHADOW_COLOR.with_alpha(sticky::SHADOW_COLOR.a * style.opacity));
+            renderer.fill_rect(*rect, color.color().with_alpha(style.opacity));
+            if let Some(stroke) = stroke {
+                renderer.stroke_rect(*rect, &stroke);
+            }
+            if let Some(block) = element.shape.text_block() {
+                draw_text_block(renderer, content, &block, sticky::TEXT_COLOR.with_alpha(style.opacity));
+            }
+        }
+        Shape::Image { rect, asset, crop } => {
+            match assets.image(asset) {
+                Some(chain) => {
+                    let picture = placement(rect, crop);
+                    let level = chain.level_for(picture.width() * zoom, picture.height() * zoom);
+                    if is_full_crop(crop) {
+                        renderer.draw_image(level, picture);
+                    } else {
+                        renderer.save();
+                        renderer.clip_rect(*rect);
+                        renderer.draw_image(level, picture);
+                        renderer.restore();
+                    }
+                }
+                // missing or broken pictures still show where they are
+                None => {
+                    renderer.fill_rect(*rect, PLACEHOLDER_FILL.with_alpha(style.opacity));
+                    renderer.stroke_rect(
+                        *rect,
+                        &Stroke {
+                            color: PLACEHOLDER_STROKE.with_alpha(style.opacity),
+                            width: 1.0 / zoom,
+                        },
+                    );
+                }
+            }
+            if let Some(stroke) = stroke {
+                renderer.stroke_rect(*rect, &stroke);
+            }
+        }
+        Shape::Group { children } => {
+            for child in children.iter().filter(|child| !child.hidden) {
+                draw_element(renderer, child, assets, zoom);
+            }
+        }
+        Shape::Frame { rect, children, .. } => {
+            if let Some(fill) = fill {
+                renderer.fill_rect(*rect, fill);
+            }
+            renderer.save();
+            renderer.clip_rect(*rect);
+            for child in children.iter().filter(|child| !child.hidden) {
+                draw_element(renderer, child, assets, zoom);
+            }
+            renderer.restore();
+            if let Some(stroke) = stroke {
+                renderer.stroke_rect(*rect, &stroke);
+            }
+        }
+    }
+}
+
+/// Lines of laid out text, trailing spaces and empty lines are skipped.
+fn draw_text_block(renderer: &mut impl Renderer, content: &str, block: &TextBlock, color: Color) {
+    for line in &block.layout.lines {
+        let text = content[line.range.clone()].trim_end_matches(' ');
+        if !text.is_empty() {
+            renderer.draw_text(text, block.origin + point(line.x, line.y), &block.font, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{DrawCommand, RecordingRenderer};
+    use crate::style::{Color, Style};
+    use crate::text::{FontFamily, TextAlign};
+
+    #[test]
+    fn board_is_drawn_through_the_camera() {
+        let mut document = Document::new();
+        document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(0.0, 0.0), point(10.0, 10.0)),
+            },
+            Style::filled(BLACK),
+        );
+        // far outside the viewport, culled
+        document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(5000.0, 0.0), point(10.0, 10.0)),
+            },
+            Style::filled(BLACK),
+        );
+        let settings = CanvasSettings {
+            show_grid: false,
+            ..CanvasSettings::default()
+        };
+        let camera = CameraState {
+            position: point(5.0, 5.0),
+            zoom: 2.0,
+        };
+        let mut renderer = RecordingRenderer::new();
+        draw_board(&mut renderer, &document, &settings, &camera, point(100.0, 100.0));
+
+        assert_eq!(renderer.commands[0], DrawCommand::Clear(settings.background));
+        let DrawCommand::Transform(view) = renderer.commands[2] else {
+            panic!("expected the camera transform, got {:?}", renderer.commands[2]);
+        };
+        assert_eq!(view.apply(point(5.0, 5.0)), point(50.0, 50.0));
+        assert_eq!(renderer.painted().count(), 1);
+        assert_eq!(renderer.commands.last(), Some(&DrawCommand::Restore));
+    }
+
+    #[test]
+    fn hidden_elements_are_not_drawn() {
+        let mut document = Document::new();
+        let rect = Rect::from_origin_size(point(0.0, 0.0), point(10.0, 10.0));
+        let hidden = document.add(Shape::Rectangle { rect }, Style::filled(BLACK));
+        document.add(Shape::Ellipse { rect }, Style::filled(BLACK));
+        document.update(hidden, |element| element.hidden = true);
+
+        let mut renderer = RecordingRenderer::new();
+        draw_document(&mut renderer, &document, &rect, 1.0);
+        assert_eq!(renderer.painted().count(), 1);
+    }
+
+    #[test]
+    fn opacity_multiplies_into_colors() {
+        let element = Element::new(
+            crate::element::ElementId(0),
+            Shape::Ellipse {
+                rect: Rect::from_origin_size(point(0.0, 0.0), point(10.0, 5.0)),
+            },
+            Style {
+                fill: Some(Color::new(1.0, 0.0, 0.0, 0.8)),
+                stroke: Some(BLACK),
+                stroke_width: 3.0,
+                opacity: 0.5,
+            },
+        );
+        let mut renderer = RecordingRenderer::new();
+        draw_element(&mut renderer, &element, &AssetStore::default(), 1.0);
+        assert!(matches!(
+            &renderer.commands[..],
+            [
+                DrawCommand::FillPath { color: fill, .. },
+                DrawCommand::StrokePath { stroke, .. },
+            ] if (fill.a - 0.4).abs() < 1e-6 && stroke.color.a == 0.5 && stroke.width == 3.0
+        ));
+    }
+
+    #[test]
+    fn text_is_split_into_lines() {
+        let element = Element::new(
+            crate::element::ElementId(0),
+            Shape::text(point(10.0, 20.0), "one\ntwo", 10.0),
+            Style::filled(BLACK),
+        );
+        let mut renderer = RecordingRenderer::new();
+        draw_element(&mut renderer, &element, &AssetStore::default(), 1.0);
+        let tops: Vec<Point> = renderer
+            .commands
+            .iter()
+            .filter_map(|c| match c {
+                DrawCommand::Text { position, .. } => Some(*position),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tops, vec![point(10.0, 20.0), point(10.0, 32.0)]);
+    }
+
+    #[test]
+    fn wrapped_text_is_drawn_where_the_layout_puts_it() {
+        let mut shape = Shape::text(point(0.0, 0.0), "ab cd", 10.0);
+        if let Shape::Text { font, align, width, .. } = &mut shape {
+            font.family = FontFamily::Mono;
+            *align = TextAlign::Right;
+            *width = Some(24.0);
+        }
+        let element = Element::new(crate::element::ElementId(0), shape, Style::filled(BLACK));
+        let mut renderer = RecordingRenderer::new();
+        draw_element(&mut renderer, &element, &AssetStore::default(), 1.0);
+        let lines: Vec<(&str, Point)> = renderer
+            .commands
+            .iter()
+            .filter_map(|c| match c {
+                DrawCommand::Text { text, position, .. } => Some((text.as_str(), *position)),
+                _ => None,
+            })
+            .collect();
+        // two characters of 6 each, pushed against the right of a box 24 wide
+        assert_eq!(lines, vec![("ab", point(12.0, 0.0)), ("cd", point(12.0, 12.0))]);
+    }
+
+    #[test]
+    fn arrows_fill_triangle_heads_and_stroke_open_ones() {
+        let element = Element::new(
+            crate::element::ElementId(0),
+            Shape::Line {
+                start: point(0.0, 0.0),
+                end: point(100.0, 0.0),
+                start_head: crate::arrow::Arrowhead::Open,
+                end_head: crate::arrow::Arrowhead::Triangle,
+            },
+            Style::stroked(BLACK, 2.0),
+        );
+        let mut renderer = RecordingRenderer::new();
+        draw_element(&mut renderer, &element, &AssetStore::default(), 1.0);
+        assert!(matches!(
+            &renderer.commands[..],
+            [
+                DrawCommand::StrokePath { .. },
+                DrawCommand::StrokePath { .. },
+                DrawCommand::FillPath { color, .. },
+            ] if *color == BLACK
+        ));
+    }
+
+    #[test]
+    fn transformed_elements_draw_through_their_transform() {
+        let mut element = Element::new(
+            crate::element::ElementId(0),
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(0.0, 0.0), point(10.0, 5.0)),
+            },
+            Style::filled(BLACK),
+        );
+        element.transform = crate::geometry::Affine::rotate(0.5);
+        let mut renderer = RecordingRenderer::new();
+        draw_element(&mut renderer, &element, &AssetStore::default(), 1.0);
+        assert!(matches!(
+            &renderer.commands[..],
+            [
+                DrawCommand::Save,
+                DrawCommand::Transform(t),
+                DrawCommand::FillPath { .. },
+                DrawCommand::Restore,
+            ] if *t == element.transform
+        ));
+    }
+
+    #[test]
+    fn images_draw_a_mipmap_level_clipped_to_their_crop() {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 64, 64);
+        encoder.set_color(png::ColorType::Grayscale);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[128; 64 * 64]).unwrap();
+        writer.finish().unwrap();
+        let mut assets = AssetStore::default();
+        let asset = assets.insert_image(bytes).unwrap();
+
+        let mut shape = Shape::image(Rect::from_origin_size(point(0.0, 0.0), point(64.0, 64.0)), asset.clone());
+        crate::image::crop_to(&mut shape, Rect::from_origin_size(point(0.0, 0.0), point(32.0, 64.0)));
+        let element = Element::new(crate::element::ElementId(0), shape, Style::default());
+        let mut renderer = RecordingRenderer::new();
+        // a quarter of the size on screen, so the 16 pixel level is enough
+        draw_element(&mut renderer, &element, &assets, 0.25);
+        let small = assets.image(&asset).unwrap().levels()[2].id();
+        assert!(matches!(
+            &renderer.commands[..],
+            [
+                DrawCommand::Save,
+                DrawCommand::ClipRect(clip),
+                DrawCommand::Image { image_id, dest },
+                DrawCommand::Restore,
+                ..
+            ] if clip.width() == 32.0 && *image_id == small && dest.width() == 64.0
+        ));
+
+        // without its picture the image is still drawn, as a placeholder
+        let mut renderer = RecordingRenderer::new();
+        draw_element(&mut renderer, &element, &AssetStore::default(), 1.0);
+        assert!(matches!(
+            &renderer.commands[..],
+            [DrawCommand::FillPath { .. }, DrawCommand::StrokePath { .. }, ..]
+        ));
+    }
+
+    #[test]
+    fn grid_dots_keep_their_screen_size() {
+        let mut renderer = RecordingRenderer::new();
+        let visible = Rect::from_origin_size(point(0.0, 0.0), point(20.0, 20.0));
+        draw_grid(&mut renderer, &visible, 2.0);
+        let DrawCommand::FillPath { path, .. } = &renderer.commands[0] else {
+            panic!("expected a dot");
+        };
+        let outline = path.flatten(0.01);
+        let bounds = Rect::from_points(outline[0].points.iter().copied()).unwrap();
+        // radius 2 pixels at zoom 2 is 1 world unit
+        assert!((bounds.width() - 2.0).abs() < 1e-3);
+        assert!(bounds.center().distance(point(0.0, 0.0)) < 1e-3);
+    }
+}
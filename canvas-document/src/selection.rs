@@ -0,0 +1,584 @@
+//! Which elements are selected, and the geometry of editing them: marquee
+//! and lasso picking, the handles around the selection and what dragging them does.
+//!
+//! Everything here is in world coordinates. Handle sizes are given by the
+//! caller so they can stay constant on screen.
+
+use std::f32::consts::PI;
+
+use crate::document::Document;
+use crate::element::{Element, ElementId, Shape};
+use crate::geometry::{point, Affine, Point, Rect};
+use crate::lasso::element_in_lasso;
+
+/// Rotation snaps to multiples of this while Shift is held, 15 degrees.
+pub const ROTATION_SNAP: f32 = PI / 12.0;
+
+/// Selected elements in the order they were selected, and the groups
+/// entered to get at them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    ids: Vec<ElementId>,
+    /// entered groups, outermost first. Clicks and marquees pick the
+    /// children of the last one.
+    scope: Vec<ElementId>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Selection::default()
+    }
+
+    pub fn ids(&self) -> &[ElementId] {
+        &self.ids
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, id: ElementId) -> bool {
+        self.ids.contains(&id)
+    }
+
+    /// The selected element when exactly one is selected.
+    pub fn single(&self) -> Option<ElementId> {
+        match self.ids.as_slice() {
+            [only] => Some(*only),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.ids.clear();
+    }
+
+    /// Selects only `id`.
+    pub fn set(&mut self, id: ElementId) {
+        self.ids.clear();
+        self.ids.push(id);
+    }
+
+    pub fn add(&mut self, id: ElementId) {
+        if !self.contains(id) {
+            self.ids.push(id);
+        }
+    }
+
+    /// Adds `id` when it isn't selected, removes it when it is, like Shift-click.
+    pub fn toggle(&mut self, id: ElementId) {
+        match self.ids.iter().position(|&selected| selected == id) {
+            Some(index) => {
+                self.ids.remove(index);
+            }
+            None => self.ids.push(id),
+        }
+    }
+
+    /// The group being edited, if any.
+    pub fn scope(&self) -> Option<ElementId> {
+        self.scope.last().copied()
+    }
+
+    /// Starts editing inside `group`, like double-clicking it.
+    pub fn enter(&mut self, group: ElementId) {
+        self.scope.push(group);
+        self.ids.clear();
+    }
+
+    /// Stops editing the current group and selects it, like Escape.
+    /// Returns false when no group was entered.
+    pub fn leave(&mut self) -> bool {
+        match self.scope.pop() {
+            Some(group) => {
+                self.set(group);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets elements that are gone or can no longer be picked, e.g. after an undo,
+    /// and leaves groups that are gone or were ungrouped.
+    pub fn retain_existing(&mut self, document: &Document) {
+        if let Some(gone) = self
+            .scope
+            .iter()
+            .position(|&id| !document.get(id).is_some_and(|element| element.is_group()))
+        {
+            self.scope.truncate(gone);
+        }
+        self.ids.retain(|&id| document.is_pickable(id));
+    }
+
+    /// Topmost element under `p` that a click would pick: a top level
+    /// element, a child of the entered group, or what lies on a frame
+    /// before the frame itself.
+    pub fn element_at(&self, document: &Document, p: Point, tolerance: f32) -> Option<ElementId> {
+        match self.scope() {
+            Some(group) => document.get(group)?.child_at(p, tolerance),
+            None => {
+                let element = document.get(document.element_at(p, tolerance)?)?;
+                match element.shape {
+                    Shape::Frame { .. } => element.child_at(p, tolerance).or(Some(element.id)),
+                    _ => Some(element.id),
+                }
+            }
+        }
+    }
+
+    /// Union of the world geometry bounds of the selected elements.
+    pub fn bounds(&self, document: &Document) -> Option<Rect> {
+        self.ids
+            .iter()
+            .filter_map(|&id| document.get(id))
+            .map(|element| element.geometry_bounds())
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// The box the handles sit on. A single element keeps its own
+    /// orientation, several share their world bounds.
+    pub fn frame(&self, document: &Document) -> Option<Frame> {
+        if let Some(element) = self.single().and_then(|id| document.get(id)) {
+            return Some(Frame {
+                rect: element.shape.bounds(),
+                transform: element.transform,
+            });
+        }
+        self.bounds(document).map(|rect| Frame {
+            rect,
+            transform: Affine::IDENTITY,
+        })
+    }
+
+    /// Selects what the marquee `rect` picks, on top of `base` so Shift can
+    /// extend an existing selection while dragging.
+    pub fn select_marquee(&mut self, document: &Document, base: &[ElementId], rect: &Rect, mode: MarqueeMode) {
+        let picked = match (self.scope().and_then(|group| document.get(group)), mode) {
+            (Some(group), MarqueeMode::Contained) => {
+                group.children().iter().filter(|child| rect.contains_rect(&child.bounds())).collect()
+            }
+            (Some(group), MarqueeMode::Intersecting) => {
+                group.children().iter().filter(|child| rect.intersects(&child.bounds())).collect()
+            }
+            (None, MarqueeMode::Contained) => document.elements_within(rect),
+            (None, MarqueeMode::Intersecting) => document.elements_intersecting(rect),
+        };
+        self.ids = base.to_vec();
+        for element in picked.into_iter().filter(|element| document.is_pickable(element.id)) {
+            self.add(element.id);
+        }
+    }
+
+    /// Selects what the closed loop `lasso` picks, on top of `base` like
+    /// [`Selection::select_marquee`].
+    pub fn select_lasso(&mut self, document: &Document, base: &[ElementId], lasso: &[Point], mode: MarqueeMode) {
+        let candidates: Vec<&Element> = match (
+            self.scope().and_then(|group| document.get(group)),
+            Rect::from_points(lasso.iter().copied()),
+        ) {
+            (_, None) => Vec::new(),
+            (Some(group), _) => group.children().iter().collect(),
+            (None, Some(area)) => document.elements_intersecting(&area),
+        };
+        self.ids = base.to_vec();
+        for element in candidates {
+            if document.is_pickable(element.id) && element_in_lasso(element, lasso, mode) {
+                self.add(element.id);
+            }
+        }
+    }
+}
+
+/// Which elements a marquee or a lasso picks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarqueeMode {
+    /// Only elements lying completely inside.
+    Contained,
+    /// Every element it touches.
+    Intersecting,
+}
+
+impl MarqueeMode {
+    /// Dragging to the right picks contained elements, dragging to the left
+    /// picks everything touched.
+    pub fn for_drag(start: Point, end: Point) -> MarqueeMode {
+        if end.x >= start.x {
+            MarqueeMode::Contained
+        } else {
+            MarqueeMode::Intersecting
+        }
+    }
+}
+
+/// The handles around a selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handle {
+    TopLeft,
+    Top,
+    TopRight,
+    Right,
+    BottomRight,
+    Bottom,
+    BottomLeft,
+    Left,
+    /// Sits above the middle of the top edge.
+    Rotate,
+}
+
+impl Handle {
+    pub const RESIZE: [Handle; 8] = [
+        Handle::TopLeft,
+        Handle::Top,
+        Handle::TopRight,
+        Handle::Right,
+        Handle::BottomRight,
+        Handle::Bottom,
+        Handle::BottomLeft,
+        Handle::Left,
+    ];
+
+    /// Which way the handle moves the edges, -1 for left or top, 1 for right
+    /// or bottom and 0 when it leaves that axis alone.
+    fn direction(self) -> (f32, f32) {
+        match self {
+            Handle::TopLeft => (-1.0, -1.0),
+            Handle::Top => (0.0, -1.0),
+            Handle::TopRight => (1.0, -1.0),
+            Handle::Right => (1.0, 0.0),
+            Handle::BottomRight => (1.0, 1.0),
+            Handle::Bottom => (0.0, 1.0),
+            Handle::BottomLeft => (-1.0, 1.0),
+            Handle::Left => (-1.0, 0.0),
+            Handle::Rotate => (0.0, 0.0),
+        }
+    }
+
+    /// Center of the handle. The rotate handle is `rotate_offset` above the top edge.
+    pub fn position(self, bounds: &Rect, rotate_offset: f32) -> Point {
+        let center = bounds.center();
+        if self == Handle::Rotate {
+            return point(center.x, bounds.min.y - rotate_offset);
+        }
+        let (dx, dy) = self.direction();
+        let half = bounds.size() * 0.5;
+        point(center.x + dx * half.x, center.y + dy * half.y)
+    }
+}
+
+/// A rect and the transform placing it in the world, so handles follow
+/// rotated and flipped elements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub rect: Rect,
+    pub transform: Affine,
+}
+
+impl Frame {
+    pub fn corners(&self) -> [Point; 4] {
+        self.rect.corners().map(|p| self.transform.apply(p))
+    }
+
+    pub fn center(&self) -> Point {
+        self.transform.apply(self.rect.center())
+    }
+
+    /// `p` in the coordinates of `rect`.
+    pub fn to_local(&self, p: Point) -> Point {
+        self.transform.inverse().map_or(p, |inverse| inverse.apply(p))
+    }
+
+    /// World position of `handle`, `rotate_offset` is in world units.
+    pub fn handle_position(&self, handle: Handle, rotate_offset: f32) -> Point {
+        let local_offset = rotate_offset / self.transform.scale_factor();
+        self.transform.apply(handle.position(&self.rect, local_offset))
+    }
+
+    /// Like [`handle_at`], following the transform.
+    pub fn handle_at(&self, p: Point, radius: f32, rotate_offset: f32) -> Option<Handle> {
+        std::iter::once(Handle::Rotate)
+            .chain(Handle::RESIZE)
+            .find(|&handle| {
+                let d = self.handle_position(handle, rotate_offset) - p;
+                d.x.abs() <= radius && d.y.abs() <= radius
+            })
+    }
+}
+
+/// Handle within `radius` of `p`, the rotate handle wins over the others.
+pub fn handle_at(bounds: &Rect, p: Point, radius: f32, rotate_offset: f32) -> Option<Handle> {
+    std::iter::once(Handle::Rotate)
+        .chain(Handle::RESIZE)
+        .find(|handle| {
+            let d = handle.position(bounds, rotate_offset) - p;
+            d.x.abs() <= radius && d.y.abs() <= radius
+        })
+}
+
+/// Bounds after dragging `handle` of `original` to `pointer`. The opposite
+/// edge stays put, or the center does when `from_center` is set (Alt).
+/// `keep_aspect` (Shift) scales both axes by the same amount.
+pub fn resize_bounds(original: &Rect, handle: Handle, pointer: Point, from_center: bool, keep_aspect: bool) -> Rect {
+    let (dx, dy) = handle.direction();
+    let center = original.center();
+    if !keep_aspect {
+        // the dragged edge lands exactly on the pointer
+        let axis = |min: f32, max: f32, center: f32, d: f32, p: f32| {
+            if d == 0.0 {
+                return (min, max);
+            }
+            let fixed = match (from_center, d < 0.0) {
+                (true, _) => 2.0 * center - p,
+                (false, true) => max,
+                (false, false) => min,
+            };
+            (fixed.min(p), fixed.max(p))
+        };
+        let (min_x, max_x) = axis(original.min.x, original.max.x, center.x, dx, pointer.x);
+        let (min_y, max_y) = axis(original.min.y, original.max.y, center.y, dy, pointer.y);
+        return Rect {
+            min: point(min_x, min_y),
+            max: point(max_x, max_y),
+        };
+    }
+
+    let half = original.size() * 0.5;
+    let anchor = |c: f32, h: f32, d: f32| if from_center || d == 0.0 { c } else { c - d * h };
+    let anchor = point(anchor(center.x, half.x, dx), anchor(center.y, half.y, dy));
+    let grabbed = handle.position(original, 0.0);
+    let factor = |d: f32, from: f32, to: f32| (d != 0.0 && from != 0.0).then(|| to / from);
+    let sx = factor(dx, grabbed.x - anchor.x, pointer.x - anchor.x);
+    let sy = factor(dy, grabbed.y - anchor.y, pointer.y - anchor.y);
+    let (sx, sy) = match (sx, sy) {
+        (Some(sx), Some(sy)) => {
+            let uniform = sx.abs().max(sy.abs());
+            (uniform.copysign(sx), uniform.copysign(sy))
+        }
+        (Some(sx), None) => (sx, sx.abs()),
+        (None, Some(sy)) => (sy.abs(), sy),
+        (None, None) => (1.0, 1.0),
+    };
+    let scale = |p: Point| point(anchor.x + (p.x - anchor.x) * sx, anchor.y + (p.y - anchor.y) * sy);
+    Rect::from_corners(scale(original.min), scale(original.max))
+}
+
+/// Where `rect` ends up when `from` is stretched onto `to`, for resizing
+/// every selected element along with the selection bounds.
+pub fn map_rect(rect: &Rect, from: &Rect, to: &Rect) -> Rect {
+    Affine::rect_to_rect(from, to).transform_rect(rect)
+}
+
+/// Angle in radians the pointer has turned around `center` since `start`,
+/// snapped to [`ROTATION_SNAP`] when `snap` is set.
+pub fn rotation_angle(center: Point, start: Point, pointer: Point, snap: bool) -> f32 {
+    let angle_of = |p: Point| (p.y - center.y).atan2(p.x - center.x);
+    let angle = angle_of(pointer) - angle_of(start);
+    if snap {
+        (angle / ROTATION_SNAP).round() * ROTATION_SNAP
+    } else {
+        angle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::Style;
+
+    fn document_with_squares() -> (Document, Vec<ElementId>) {
+        let mut document = Document::new();
+        let ids = [0.0, 50.0, 100.0]
+            .into_iter()
+            .map(|x| {
+                document.add(
+                    Shape::Rectangle {
+                        rect: Rect::from_origin_size(point(x, 0.0), point(20.0, 20.0)),
+                    },
+                    Style::default(),
+                )
+            })
+            .collect();
+        (document, ids)
+    }
+
+    #[test]
+    fn toggle_adds_and_removes() {
+        let mut selection = Selection::new();
+        selection.set(ElementId(1));
+        selection.toggle(ElementId(2));
+        assert_eq!(selection.ids(), &[ElementId(1), ElementId(2)]);
+        selection.toggle(ElementId(1));
+        assert_eq!(selection.single(), Some(ElementId(2)));
+    }
+
+    #[test]
+    fn marquee_direction_picks_the_mode() {
+        let (document, ids) = document_with_squares();
+        // covers the first square and half of the second
+        let marquee = Rect::from_corners(point(-5.0, -5.0), point(60.0, 25.0));
+        let mut selection = Selection::new();
+
+        selection.select_marquee(&document, &[], &marquee, MarqueeMode::for_drag(marquee.min, marquee.max));
+        assert_eq!(selection.ids(), &ids[..1]);
+
+        selection.select_marquee(&document, &[], &marquee, MarqueeMode::for_drag(marquee.max, marquee.min));
+        assert_eq!(selection.ids(), &ids[..2]);
+
+        selection.select_marquee(&document, &[ids[2]], &marquee, MarqueeMode::Contained);
+        assert_eq!(selection.ids(), &[ids[2], ids[0]]);
+    }
+
+    #[test]
+    fn lasso_picks_what_the_loop_holds() {
+        let (document, ids) = document_with_squares();
+        // around the first square, then along under the second and up
+        // between the second and the third, cutting the second in half
+        let lasso = [
+            point(-5.0, -5.0),
+            point(25.0, -5.0),
+            point(25.0, 10.0),
+            point(60.0, 10.0),
+            point(60.0, 25.0),
+            point(-5.0, 25.0),
+        ];
+        let mut selection = Selection::new();
+        selection.select_lasso(&document, &[], &lasso, MarqueeMode::Contained);
+        assert_eq!(selection.ids(), &ids[..1]);
+
+        selection.select_lasso(&document, &[ids[2]], &lasso, MarqueeMode::Intersecting);
+        assert_eq!(selection.ids(), &[ids[2], ids[0], ids[1]]);
+    }
+
+    #[test]
+    fn clicks_on_a_frame_pick_what_lies_on_it() {
+        let (mut document, ids) = document_with_squares();
+        let mut history = crate::History::default();
+        let rect = Rect::from_corners(point(-10.0, -10.0), point(40.0, 40.0));
+        let frame = history.add(&mut document, Shape::frame(rect, "Frame 1"), Style::default());
+        crate::frame::adopt_contained(&mut history, &mut document, frame);
+
+        let selection = Selection::new();
+        assert_eq!(selection.element_at(&document, point(10.0, 10.0), 1.0), Some(ids[0]));
+        assert_eq!(selection.element_at(&document, point(30.0, 30.0), 1.0), Some(frame));
+    }
+
+    #[test]
+    fn selection_bounds_cover_every_element() {
+        let (mut document, ids) = document_with_squares();
+        let mut selection = Selection::new();
+        selection.set(ids[0]);
+        selection.add(ids[2]);
+        assert_eq!(
+            selection.bounds(&document),
+            Some(Rect::from_corners(point(0.0, 0.0), point(120.0, 20.0)))
+        );
+        document.remove(ids[2]);
+        selection.retain_existing(&document);
+        assert_eq!(selection.ids(), &ids[..1]);
+    }
+
+    #[test]
+    fn handles_are_found_with_the_rotate_handle_above() {
+        let bounds = Rect::from_corners(point(0.0, 0.0), point(100.0, 50.0));
+        assert_eq!(handle_at(&bounds, point(101.0, 49.0), 4.0, 20.0), Some(Handle::BottomRight));
+        assert_eq!(handle_at(&bounds, point(50.0, 0.0), 4.0, 20.0), Some(Handle::Top));
+        assert_eq!(handle_at(&bounds, point(50.0, -20.0), 4.0, 20.0), Some(Handle::Rotate));
+        assert_eq!(handle_at(&bounds, point(50.0, 25.0), 4.0, 20.0), None);
+    }
+
+    #[test]
+    fn entered_groups_scope_picking() {
+        let (mut document, ids) = document_with_squares();
+        let mut history = crate::history::History::default();
+        let group = crate::group::group_elements(&mut history, &mut document, &ids[..2]).unwrap();
+        let mut selection = Selection::new();
+        assert_eq!(selection.element_at(&document, point(55.0, 5.0), 0.0), Some(group));
+
+        selection.enter(group);
+        assert_eq!(selection.element_at(&document, point(55.0, 5.0), 0.0), Some(ids[1]));
+        assert_eq!(selection.element_at(&document, point(105.0, 5.0), 0.0), None);
+        let everything = Rect::from_corners(point(-5.0, -5.0), point(200.0, 25.0));
+        selection.select_marquee(&document, &[], &everything, MarqueeMode::Contained);
+        assert_eq!(selection.ids(), &ids[..2]);
+
+        assert!(selection.leave());
+        assert_eq!(selection.ids(), &[group]);
+        assert!(!selection.leave());
+
+        selection.enter(group);
+        crate::group::ungroup(&mut history, &mut document, group);
+        selection.retain_existing(&document);
+        assert_eq!(selection.scope(), None);
+    }
+
+    #[test]
+    fn single_rotated_element_keeps_its_frame() {
+        let (mut document, ids) = document_with_squares();
+        let quarter = Affine::around(point(10.0, 10.0), &Affine::rotate(PI / 4.0));
+        document.update(ids[0], |e| e.transform_by(&quarter));
+        let mut selection = Selection::new();
+        selection.set(ids[0]);
+        let frame = selection.frame(&document).unwrap();
+        assert_eq!(frame.rect, Rect::from_origin_size(point(0.0, 0.0), point(20.0, 20.0)));
+        // the top left corner turned onto the top of the diamond
+        let top = point(10.0, 10.0 - 10.0 * 2f32.sqrt());
+        assert!(frame.handle_position(Handle::TopLeft, 0.0).distance(top) < 1e-4);
+        assert_eq!(frame.handle_at(top + point(1.0, 1.0), 2.0, 20.0), Some(Handle::TopLeft));
+        assert!(frame.to_local(top).distance(point(0.0, 0.0)) < 1e-4);
+
+        selection.add(ids[1]);
+        let frame = selection.frame(&document).unwrap();
+        assert!(frame.transform.is_identity());
+        assert!((frame.rect.min.y - top.y).abs() < 1e-4);
+    }
+
+    #[test]
+    fn resize_keeps_the_opposite_edge() {
+        let original = Rect::from_corners(point(0.0, 0.0), point(100.0, 50.0));
+        assert_eq!(
+            resize_bounds(&original, Handle::Right, point(150.0, 999.0), false, false),
+            Rect::from_corners(point(0.0, 0.0), point(150.0, 50.0))
+        );
+        assert_eq!(
+            resize_bounds(&original, Handle::TopLeft, point(-10.0, 20.0), false, false),
+            Rect::from_corners(point(-10.0, 20.0), point(100.0, 50.0))
+        );
+    }
+
+    #[test]
+    fn resize_from_center_mirrors_the_change() {
+        let original = Rect::from_corners(point(0.0, 0.0), point(100.0, 50.0));
+        assert_eq!(
+            resize_bounds(&original, Handle::Right, point(110.0, 0.0), true, false),
+            Rect::from_corners(point(-10.0, 0.0), point(110.0, 50.0))
+        );
+    }
+
+    #[test]
+    fn resize_with_aspect_scales_both_axes() {
+        let original = Rect::from_corners(point(0.0, 0.0), point(100.0, 50.0));
+        let corner = resize_bounds(&original, Handle::BottomRight, point(200.0, 60.0), false, true);
+        assert_eq!(corner, Rect::from_corners(point(0.0, 0.0), point(200.0, 100.0)));
+        // an edge handle scales the other axis around its middle
+        let edge = resize_bounds(&original, Handle::Bottom, point(0.0, 100.0), false, true);
+        assert_eq!(edge, Rect::from_corners(point(-50.0, 0.0), point(150.0, 100.0)));
+    }
+
+    #[test]
+    fn mapped_rects_follow_the_selection() {
+        let from = Rect::from_corners(point(0.0, 0.0), point(100.0, 100.0));
+        let to = Rect::from_corners(point(0.0, 0.0), point(200.0, 50.0));
+        let inner = Rect::from_corners(point(50.0, 50.0), point(100.0, 100.0));
+        assert_eq!(map_rect(&inner, &from, &to), Rect::from_corners(point(100.0, 25.0), point(200.0, 50.0)));
+    }
+
+    #[test]
+    fn rotation_snaps_with_shift() {
+        let center = point(0.0, 0.0);
+        let start = point(10.0, 0.0);
+        let quarter = rotation_angle(center, start, point(0.0, 10.0), false);
+        assert!((quarter - PI / 2.0).abs() < 1e-5);
+        let snapped = rotation_angle(center, start, point(10.0, 1.0), true);
+        assert_eq!(snapped, 0.0);
+    }
+}
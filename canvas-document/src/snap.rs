@@ -0,0 +1,488 @@
+//! Snapping positions while shapes are drawn, moved and resized.
+//!
+//! Front ends build a [`Snapper`] for each pointer event and pass the dragged
+//! positions through it. Positions snap to the dot grid drawn at the current
+//! zoom and, with [`Snapper::with_guides`], to the edges, centers and spacing
+//! of the elements around them. What they snapped to comes back as [`Guide`]s
+//! to draw while the drag lasts.
+
+use crate::document::Document;
+use crate::element::ElementId;
+use crate::geometry::{point, Point, Rect};
+use crate::grid::snap_to_grid;
+
+/// How close an edge or center has to get to another element's to snap, in screen pixels.
+pub const GUIDE_SNAP_DISTANCE: f32 = 6.0;
+/// Positions closer than this in world units count as lined up.
+const ALIGNED: f32 = 1e-3;
+
+/// Something a snapped position lines up with, in world coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Guide {
+    /// Edges or centers lining up along the line from `from` to `to`.
+    Line { from: Point, to: Point },
+    /// The gap between two elements, `from` and `to` are on their facing edges.
+    Distance { from: Point, to: Point, distance: f32 },
+}
+
+/// A snapped position with the guides explaining it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapped<T> {
+    pub value: T,
+    pub guides: Vec<Guide>,
+}
+
+/// Snaps world positions to the grid and to nearby elements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapper {
+    zoom: f32,
+    grid: bool,
+    /// geometry bounds of the elements to line up with
+    targets: Vec<Rect>,
+}
+
+impl Snapper {
+    /// Snaps to the grid at `zoom` when `grid` is set. Nothing else yet, see
+    /// [`Snapper::with_guides`].
+    pub fn new(grid: bool, zoom: f32) -> Self {
+        Snapper {
+            zoom,
+            grid,
+            targets: Vec::new(),
+        }
+    }
+
+    /// Leaves every position as it is, e.g. while a key bypassing snapping is held.
+    pub fn disabled() -> Self {
+        Snapper::new(false, 1.0)
+    }
+
+    /// Also lines positions up with the visible top level elements in
+    /// `visible`, a world space rect, except the ones holding `ignored`
+    /// elements, which are usually the ones being dragged.
+    pub fn with_guides(mut self, document: &Document, visible: &Rect, ignored: &[ElementId]) -> Self {
+        let ignored: Vec<ElementId> = ignored.iter().filter_map(|&id| document.root_of(id)).collect();
+        self.targets = document
+            .elements_intersecting(visible)
+            .into_iter()
+            .filter(|element| !ignored.contains(&element.id) && document.is_visible(element.id))
+            .map(|element| element.geometry_bounds())
+            .collect();
+        self
+    }
+
+    /// World distance within which edges snap to each other.
+    fn tolerance(&self) -> f32 {
+        GUIDE_SNAP_DISTANCE / self.zoom
+    }
+
+    /// Grid coordinate closest to `value`, or `value` without the grid.
+    fn grid_value(&self, value: f32) -> f32 {
+        if self.grid {
+            snap_to_grid(value, self.zoom)
+        } else {
+            value
+        }
+    }
+
+    /// Where a dragged point like a corner or a line end lands.
+    pub fn point(&self, p: Point) -> Point {
+        self.snap_point(p).value
+    }
+
+    /// Like [`Snapper::point`], also returning the alignment guides.
+    pub fn snap_point(&self, p: Point) -> Snapped<Point> {
+        let snap = |axis: Axis| {
+            let value = axis.of(p);
+            let offset = self
+                .targets
+                .iter()
+                .flat_map(|target| features(axis.range(target)))
+                .map(|theirs| theirs - value)
+                .filter(|offset| offset.abs() <= self.tolerance())
+                .min_by(|a, b| a.abs().total_cmp(&b.abs()));
+            offset.map_or_else(|| self.grid_value(value), |offset| value + offset)
+        };
+        let snapped = point(snap(Axis::X), snap(Axis::Y));
+        let point_rect = Rect::from_corners(snapped, snapped);
+        let guides = [Axis::X, Axis::Y]
+            .into_iter()
+            .flat_map(|axis| self.alignment_lines(axis, &point_rect))
+            .collect();
+        Snapped { value: snapped, guides }
+    }
+
+    /// How far to move `rect` when it is dragged to have its top left corner
+    /// at `target`.
+    pub fn translation(&self, rect: &Rect, target: Point) -> Point {
+        self.snap_translation(rect, target).value
+    }
+
+    /// Like [`Snapper::translation`], also returning the guides. Edges and
+    /// centers line up with other elements or keep the same gaps as their
+    /// neighbours, and the top left corner falls back to the grid.
+    pub fn snap_translation(&self, rect: &Rect, target: Point) -> Snapped<Point> {
+        let moved = rect.translate(target - rect.min);
+        let snap = |axis: Axis| {
+            let value = axis.of(target);
+            let offset = [self.alignment_offset(axis, &moved), self.spacing_offset(axis, &moved)]
+                .into_iter()
+                .flatten()
+                .min_by(|a, b| a.abs().total_cmp(&b.abs()));
+            offset.map_or_else(|| self.grid_value(value), |offset| value + offset) - axis.of(rect.min)
+        };
+        let delta = point(snap(Axis::X), snap(Axis::Y));
+        Snapped {
+            value: delta,
+            guides: self.guides(&rect.translate(delta)),
+        }
+    }
+
+    /// Guides for `rect` where it is now: the elements it lines up with and
+    /// the equal gaps it keeps to its neighbours.
+    pub fn guides(&self, rect: &Rect) -> Vec<Guide> {
+        let mut guides = Vec::new();
+        for axis in [Axis::X, Axis::Y] {
+            guides.extend(self.alignment_lines(axis, rect));
+            // the gap to an element lined up with is measured across, unless equal gaps already are
+            let spacing = self.spacing_distances(axis.other(), rect);
+            if spacing.is_empty() {
+                guides.extend(self.alignment_distance(axis, rect));
+            }
+            guides.extend(spacing);
+        }
+        guides
+    }
+
+    /// Smallest move along `axis` lining an edge or the center of `rect` up with another element's.
+    fn alignment_offset(&self, axis: Axis, rect: &Rect) -> Option<f32> {
+        let ours = features(axis.range(rect));
+        self.targets
+            .iter()
+            .flat_map(|target| features(axis.range(target)))
+            .flat_map(|theirs| ours.map(|value| theirs - value))
+            .filter(|offset| offset.abs() <= self.tolerance())
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+    }
+
+    /// Smallest move along `axis` putting `rect` in the middle between two
+    /// elements, or next to a pair at the distance between the pair.
+    fn spacing_offset(&self, axis: Axis, rect: &Rect) -> Option<f32> {
+        let (min, max) = axis.range(rect);
+        let length = max - min;
+        let row = self.row(axis, rect);
+        let mut positions = Vec::new();
+        for pair in row.windows(2) {
+            let ((a_min, a_max), (b_min, b_max)) = (axis.range(pair[0]), axis.range(pair[1]));
+            let gap = b_min - a_max;
+            if gap <= 0.0 {
+                continue;
+            }
+            if gap > length {
+                positions.push(a_max + (gap - length) * 0.5);
+            }
+            positions.push(b_max + gap);
+            positions.push(a_min - gap - length);
+        }
+        positions
+            .into_iter()
+            .map(|position| position - min)
+            .filter(|offset| offset.abs() <= self.tolerance())
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+    }
+
+    /// Targets beside `rect` along `axis`, overlapping it across, in order along `axis`.
+    fn row(&self, axis: Axis, rect: &Rect) -> Vec<&Rect> {
+        let across = axis.other();
+        let (min, max) = across.range(rect);
+        let mut row: Vec<&Rect> = self
+            .targets
+            .iter()
+            .filter(|target| {
+                let (target_min, target_max) = across.range(target);
+                target_min < max && min < target_max
+            })
+            .collect();
+        row.sort_by(|a, b| axis.of(a.min).total_cmp(&axis.of(b.min)));
+        row
+    }
+
+    /// Targets sharing an edge or center position with `rect` along `axis`,
+    /// with the shared position.
+    fn aligned(&self, axis: Axis, rect: &Rect) -> Vec<(f32, &Rect)> {
+        let mut aligned = Vec::new();
+        for value in features(axis.range(rect)) {
+            for target in &self.targets {
+                if features(axis.range(target)).iter().any(|theirs| (theirs - value).abs() <= ALIGNED) {
+                    aligned.push((value, target));
+                }
+            }
+        }
+        aligned
+    }
+
+    /// A line across `axis` through every shared edge or center position,
+    /// long enough to reach all elements sharing it.
+    fn alignment_lines(&self, axis: Axis, rect: &Rect) -> Vec<Guide> {
+        let across = axis.other();
+        let aligned = self.aligned(axis, rect);
+        let mut lines: Vec<(f32, f32, f32)> = Vec::new();
+        for (value, target) in aligned {
+            let (min, max) = across.range(&rect.union(target));
+            match lines.iter_mut().find(|(line, ..)| *line == value) {
+                Some((_, from, to)) => (*from, *to) = (from.min(min), to.max(max)),
+                None => lines.push((value, min, max)),
+            }
+        }
+        lines
+            .into_iter()
+            .map(|(value, from, to)| Guide::Line {
+                from: axis.point(value, from),
+                to: axis.point(value, to),
+            })
+            .collect()
+    }
+
+    /// The gap to the nearest element `rect` lines up with along `axis`.
+    fn alignment_distance(&self, axis: Axis, rect: &Rect) -> Option<Guide> {
+        let across = axis.other();
+        let (min, max) = across.range(rect);
+        self.aligned(axis, rect)
+            .into_iter()
+            .filter_map(|(value, target)| {
+                let (target_min, target_max) = across.range(target);
+                if target_min >= max {
+                    Some((value, max, target_min))
+                } else if target_max <= min {
+                    Some((value, target_max, min))
+                } else {
+                    None
+                }
+            })
+            .min_by(|a, b| (a.2 - a.1).total_cmp(&(b.2 - b.1)))
+            .map(|(value, from, to)| Guide::Distance {
+                from: axis.point(value, from),
+                to: axis.point(value, to),
+                distance: to - from,
+            })
+    }
+
+    /// The gaps along `axis` that `rect` repeats: the ones to both of its
+    /// neighbours when they are equal, or the one between its neighbour
+    /// and the next element when `rect` keeps the same distance.
+    fn spacing_distances(&self, axis: Axis, rect: &Rect) -> Vec<Guide> {
+        let (min, max) = axis.range(rect);
+        let row = self.row(axis, rect);
+        let before: Vec<&Rect> = row.iter().copied().filter(|r| axis.range(r).1 <= min).collect();
+        let after: Vec<&Rect> = row.iter().copied().filter(|r| axis.range(r).0 >= max).collect();
+        let nearest_before = before.iter().copied().max_by(|a, b| axis.of(a.max).total_cmp(&axis.of(b.max)));
+        let nearest_after = after.iter().copied().min_by(|a, b| axis.of(a.min).total_cmp(&axis.of(b.min)));
+        let gap = |a: &Rect, b: &Rect| axis.range(b).0 - axis.range(a).1;
+        let equal = |a: f32, b: f32| (a - b).abs() <= ALIGNED.max(a.abs() * 1e-5);
+
+        let mut pairs: Vec<(&Rect, &Rect)> = Vec::new();
+        if let (Some(left), Some(right)) = (nearest_before, nearest_after) {
+            if equal(gap(left, rect), gap(rect, right)) {
+                pairs.extend([(left, rect), (rect, right)]);
+            }
+        }
+        if let Some(left) = nearest_before {
+            let next = before
+                .iter()
+                .copied()
+                .filter(|r| axis.range(r).1 <= axis.range(left).0)
+                .max_by(|a, b| axis.of(a.max).total_cmp(&axis.of(b.max)));
+            if let Some(next) = next.filter(|next| equal(gap(next, left), gap(left, rect))) {
+                pairs.extend([(next, left), (left, rect)]);
+            }
+        }
+        if let Some(right) = nearest_after {
+            let next = after
+                .iter()
+                .copied()
+                .filter(|r| axis.range(r).0 >= axis.range(right).1)
+                .min_by(|a, b| axis.of(a.min).total_cmp(&axis.of(b.min)));
+            if let Some(next) = next.filter(|next| equal(gap(rect, right), gap(right, next))) {
+                pairs.extend([(rect, right), (right, next)]);
+            }
+        }
+        pairs.dedup();
+
+        let across = axis.other();
+        pairs
+            .into_iter()
+            .map(|(a, b)| {
+                // measured through the middle of where the two overlap across
+                let (a_min, a_max) = across.range(a);
+                let (b_min, b_max) = across.range(b);
+                let middle = (a_min.max(b_min) + a_max.min(b_max)) * 0.5;
+                let (from, to) = (axis.range(a).1, axis.range(b).0);
+                Guide::Distance {
+                    from: axis.point(from, middle),
+                    to: axis.point(to, middle),
+                    distance: to - from,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    X,
+    Y,
+}
+
+impl Axis {
+    fn other(self) -> Axis {
+        match self {
+            Axis::X => Axis::Y,
+            Axis::Y => Axis::X,
+        }
+    }
+
+    fn of(self, p: Point) -> f32 {
+        match self {
+            Axis::X => p.x,
+            Axis::Y => p.y,
+        }
+    }
+
+    /// Point at `along` on this axis and `across` on the other one.
+    fn point(self, along: f32, across: f32) -> Point {
+        match self {
+            Axis::X => point(along, across),
+            Axis::Y => point(across, along),
+        }
+    }
+
+    fn range(self, rect: &Rect) -> (f32, f32) {
+        (self.of(rect.min), self.of(rect.max))
+    }
+}
+
+/// Both edges and the center of a range.
+fn features((min, max): (f32, f32)) -> [f32; 3] {
+    [min, (min + max) * 0.5, max]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::GRID_SIZE;
+    use crate::test_util::square;
+
+    fn everything() -> Rect {
+        Rect::from_corners(point(-1000.0, -1000.0), point(1000.0, 1000.0))
+    }
+
+    #[test]
+    fn points_snap_to_the_visible_step() {
+        let snapper = Snapper::new(true, 1.0);
+        assert_eq!(snapper.point(point(9.0, 11.0)), point(0.0, 20.0));
+        assert_eq!(snapper.point(point(-29.0, 51.0)), point(-20.0, 60.0));
+
+        // zoomed in the step stays the grid size
+        let zoomed_in = Snapper::new(true, 4.0);
+        assert_eq!(zoomed_in.point(point(31.0, 0.0)), point(GRID_SIZE * 2.0, 0.0));
+
+        assert_eq!(Snapper::new(false, 1.0).point(point(9.0, 11.0)), point(9.0, 11.0));
+        assert_eq!(Snapper::disabled().point(point(9.0, 11.0)), point(9.0, 11.0));
+    }
+
+    #[test]
+    fn zoomed_out_the_darker_dots_pull_harder() {
+        // at half zoom dots are 40 apart and every 160th is darker
+        let snapper = Snapper::new(true, 0.5);
+        // nearest dot is 120, but 160 is within 16 screen pixels
+        assert_eq!(snapper.point(point(135.0, 0.0)).x, 160.0);
+        // too far from 160 on screen, so the normal dot wins
+        assert_eq!(snapper.point(point(125.0, 0.0)).x, 120.0);
+        assert_eq!(snapper.point(point(-150.0, 0.0)).x, -160.0);
+    }
+
+    #[test]
+    fn moved_rects_land_on_the_grid() {
+        let snapper = Snapper::new(true, 1.0);
+        let rect = Rect::from_corners(point(3.0, 4.0), point(13.0, 14.0));
+        assert_eq!(snapper.translation(&rect, point(22.0, 38.0)), point(17.0, 36.0));
+        assert_eq!(Snapper::disabled().translation(&rect, point(22.0, 38.0)), point(19.0, 34.0));
+    }
+
+    #[test]
+    fn edges_and_centers_line_up_with_other_elements() {
+        let mut document = Document::new();
+        square(&mut document, 0.0, 0.0, 40.0);
+        let moving = square(&mut document, 100.0, 200.0, 20.0);
+        let snapper = Snapper::new(false, 1.0).with_guides(&document, &everything(), &[moving]);
+        let rect = document.get(moving).unwrap().geometry_bounds();
+
+        // the left edge is 3 units off the other square's right edge, the
+        // center 4 units off its center
+        let snapped = snapper.snap_translation(&rect, point(43.0, 14.0));
+        assert_eq!(snapped.value, point(-60.0, -190.0));
+        let moved = rect.translate(snapped.value);
+        assert_eq!(moved.min, point(40.0, 10.0));
+        assert!(snapped.guides.contains(&Guide::Line {
+            from: point(40.0, 0.0),
+            to: point(40.0, 40.0),
+        }));
+        assert!(snapped.guides.contains(&Guide::Line {
+            from: point(0.0, 20.0),
+            to: point(60.0, 20.0),
+        }));
+
+        // out of reach on screen, so it stays where it was dragged
+        assert_eq!(snapper.translation(&rect, point(50.0, 70.0)), point(-50.0, -130.0));
+        // zoomed out the same world distance is close enough
+        let zoomed_out = Snapper::new(false, 0.5).with_guides(&document, &everything(), &[moving]);
+        assert_eq!(zoomed_out.translation(&rect, point(45.0, 70.0)).x, -60.0);
+    }
+
+    #[test]
+    fn gaps_repeat_between_neighbours() {
+        let mut document = Document::new();
+        square(&mut document, 0.0, 0.0, 20.0);
+        square(&mut document, 50.0, 0.0, 20.0);
+        let moving = square(&mut document, 0.0, 300.0, 20.0);
+        let snapper = Snapper::new(false, 1.0).with_guides(&document, &everything(), &[moving]);
+        let rect = document.get(moving).unwrap().geometry_bounds();
+
+        // 30 apart like the pair, right of it
+        let snapped = snapper.snap_translation(&rect, point(98.0, 0.0));
+        assert_eq!(rect.translate(snapped.value).min, point(100.0, 0.0));
+        let distances: Vec<f32> = snapped
+            .guides
+            .iter()
+            .filter_map(|guide| match guide {
+                Guide::Distance { distance, .. } => Some(*distance),
+                Guide::Line { .. } => None,
+            })
+            .collect();
+        assert_eq!(distances, vec![30.0, 30.0]);
+
+        // in the middle between two that are further apart
+        let mut document = Document::new();
+        square(&mut document, 0.0, 0.0, 20.0);
+        square(&mut document, 80.0, 0.0, 20.0);
+        let moving = square(&mut document, 0.0, 300.0, 20.0);
+        let snapper = Snapper::new(false, 1.0).with_guides(&document, &everything(), &[moving]);
+        let rect = document.get(moving).unwrap().geometry_bounds();
+        assert_eq!(snapper.translation(&rect, point(37.0, 5.0)).x, 40.0);
+    }
+
+    #[test]
+    fn points_line_up_and_hidden_elements_are_ignored() {
+        let mut document = Document::new();
+        let target = square(&mut document, 0.0, 0.0, 40.0);
+        let snapper = Snapper::new(true, 1.0).with_guides(&document, &everything(), &[]);
+        let snapped = snapper.snap_point(point(62.0, 38.0));
+        // the grid takes over where there is nothing to line up with
+        assert_eq!(snapped.value, point(60.0, 40.0));
+        assert_eq!(snapped.guides.len(), 1);
+
+        document.update(target, |element| element.hidden = true);
+        let snapper = Snapper::new(false, 1.0).with_guides(&document, &everything(), &[]);
+        assert_eq!(snapper.point(point(62.0, 38.0)), point(62.0, 38.0));
+    }
+}
@@ -0,0 +1,151 @@
+//! R-tree over element bounds, so picking and culling don't have to walk every element.
+
+use std::collections::HashMap;
+
+use rstar::{RTree, RTreeObject, AABB};
+
+use crate::element::ElementId;
+use crate::geometry::{Point, Rect};
+
+#[derive(Debug, Clone, PartialEq)]
+struct IndexedBounds {
+    id: ElementId,
+    bounds: Rect,
+}
+
+impl RTreeObject for IndexedBounds {
+    type Envelope = AABB<[f32; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        to_aabb(&self.bounds)
+    }
+}
+
+fn to_aabb(rect: &Rect) -> AABB<[f32; 2]> {
+    AABB::from_corners([rect.min.x, rect.min.y], [rect.max.x, rect.max.y])
+}
+
+/// World space bounds of every element, keyed by id.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    tree: RTree<IndexedBounds>,
+    bounds: HashMap<ElementId, Rect>,
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        SpatialIndex::default()
+    }
+
+    /// Builds the index in one go, much faster than inserting one by one.
+    pub fn bulk_load(entries: impl IntoIterator<Item = (ElementId, Rect)>) -> Self {
+        let bounds: HashMap<ElementId, Rect> = entries.into_iter().collect();
+        let tree = RTree::bulk_load(
+            bounds
+                .iter()
+                .map(|(&id, &bounds)| IndexedBounds { id, bounds })
+                .collect(),
+        );
+        SpatialIndex { tree, bounds }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Adds the element or moves it to its new bounds.
+    pub fn insert(&mut self, id: ElementId, bounds: Rect) {
+        if let Some(old) = self.bounds.insert(id, bounds) {
+            if old == bounds {
+                return;
+            }
+            self.tree.remove(&IndexedBounds { id, bounds: old });
+        }
+        self.tree.insert(IndexedBounds { id, bounds });
+    }
+
+    pub fn remove(&mut self, id: ElementId) {
+        if let Some(bounds) = self.bounds.remove(&id) {
+            self.tree.remove(&IndexedBounds { id, bounds });
+        }
+    }
+
+    pub fn bounds(&self, id: ElementId) -> Option<Rect> {
+        self.bounds.get(&id).copied()
+    }
+
+    /// Ids of elements whose bounds touch `rect`, in no particular order.
+    pub fn intersecting(&self, rect: &Rect) -> impl Iterator<Item = ElementId> + '_ {
+        self.tree
+            .locate_in_envelope_intersecting(&to_aabb(rect))
+            .map(|entry| entry.id)
+    }
+
+    /// Ids of elements whose bounds lie completely inside `rect`, in no particular order.
+    pub fn contained_in(&self, rect: &Rect) -> impl Iterator<Item = ElementId> + '_ {
+        self.tree
+            .locate_in_envelope(&to_aabb(rect))
+            .map(|entry| entry.id)
+    }
+
+    /// Ids of elements whose bounds are within `tolerance` of `p`.
+    pub fn near_point(&self, p: Point, tolerance: f32) -> impl Iterator<Item = ElementId> + '_ {
+        let area = Rect::from_corners(p, p).inflate(tolerance);
+        self.intersecting(&area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::point;
+
+    fn square(x: f32, y: f32) -> Rect {
+        Rect::from_origin_size(point(x, y), point(10.0, 10.0))
+    }
+
+    fn sorted(ids: impl Iterator<Item = ElementId>) -> Vec<ElementId> {
+        let mut ids: Vec<ElementId> = ids.collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn queries_follow_moves_and_removals() {
+        let mut index = SpatialIndex::new();
+        index.insert(ElementId(0), square(0.0, 0.0));
+        index.insert(ElementId(1), square(100.0, 0.0));
+
+        let left = Rect::from_corners(point(-5.0, -5.0), point(50.0, 50.0));
+        assert_eq!(sorted(index.intersecting(&left)), vec![ElementId(0)]);
+
+        index.insert(ElementId(1), square(20.0, 0.0));
+        assert_eq!(sorted(index.intersecting(&left)), vec![ElementId(0), ElementId(1)]);
+
+        index.remove(ElementId(0));
+        assert_eq!(sorted(index.intersecting(&left)), vec![ElementId(1)]);
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn contained_excludes_partial_overlap() {
+        let index = SpatialIndex::bulk_load([
+            (ElementId(0), square(0.0, 0.0)),
+            (ElementId(1), square(15.0, 0.0)),
+        ]);
+        let area = Rect::from_corners(point(-1.0, -1.0), point(20.0, 20.0));
+        assert_eq!(sorted(index.contained_in(&area)), vec![ElementId(0)]);
+        assert_eq!(sorted(index.intersecting(&area)), vec![ElementId(0), ElementId(1)]);
+    }
+
+    #[test]
+    fn near_point_uses_tolerance() {
+        let index = SpatialIndex::bulk_load([(ElementId(3), square(0.0, 0.0))]);
+        assert_eq!(index.near_point(point(12.0, 5.0), 1.0).count(), 0);
+        assert_eq!(index.near_point(point(12.0, 5.0), 3.0).count(), 1);
+    }
+}
@@ -0,0 +1,152 @@
+//! Sticky notes: colored squares whose text shrinks to fit.
+
+use serde::{Deserialize, Serialize};
+
+use crate::element::TextBlock;
+use crate::geometry::{point, Rect};
+use crate::style::Color;
+use crate::text::{Font, TextAlign, TextLayout};
+
+/// Width and height of a note placed with a click, in world units.
+pub const STICKY_SIZE: f32 = 200.0;
+/// Space between the edge of a note and its text, relative to its shorter side.
+const PADDING: f32 = 0.08;
+/// Font size of short text, relative to the shorter side of the note.
+const MAX_FONT_SIZE: f32 = 0.16;
+/// Text never shrinks below this, relative to the largest size, it overflows instead.
+const MIN_FONT_SIZE: f32 = 0.1;
+/// Steps of the search for the largest font size that fits.
+const FIT_STEPS: usize = 12;
+/// Text on notes is dark whatever the note color.
+pub const TEXT_COLOR: Color = Color::new(0.13, 0.13, 0.13, 1.0);
+/// Shade under notes, offset down by `SHADOW_OFFSET` of their height.
+pub const SHADOW_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.12);
+pub const SHADOW_OFFSET: f32 = 0.02;
+
+/// The colors a note can have.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StickyColor {
+    #[default]
+    Yellow,
+    Orange,
+    Pink,
+    Purple,
+    Blue,
+    Green,
+}
+
+impl StickyColor {
+    pub const ALL: [StickyColor; 6] = [
+        StickyColor::Yellow,
+        StickyColor::Orange,
+        StickyColor::Pink,
+        StickyColor::Purple,
+        StickyColor::Blue,
+        StickyColor::Green,
+    ];
+
+    pub fn color(self) -> Color {
+        match self {
+            StickyColor::Yellow => Color::new(1.0, 0.92, 0.55, 1.0),
+            StickyColor::Orange => Color::new(1.0, 0.78, 0.5, 1.0),
+            StickyColor::Pink => Color::new(1.0, 0.71, 0.8, 1.0),
+            StickyColor::Purple => Color::new(0.82, 0.75, 1.0, 1.0),
+            StickyColor::Blue => Color::new(0.66, 0.84, 1.0, 1.0),
+            StickyColor::Green => Color::new(0.7, 0.92, 0.68, 1.0),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StickyColor::Yellow => "Yellow",
+            StickyColor::Orange => "Orange",
+            StickyColor::Pink => "Pink",
+            StickyColor::Purple => "Purple",
+            StickyColor::Blue => "Blue",
+            StickyColor::Green => "Green",
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == StickyColor::default()
+    }
+}
+
+/// Lays `content` out centered on the note `rect` in the largest font that
+/// fits, without breaking words. Text that doesn't fit even at the smallest
+/// size overflows at the bottom.
+pub fn fit_text(rect: &Rect, content: &str) -> TextBlock {
+    let side = rect.width().min(rect.height()).max(0.0);
+    let inner = rect.inflate(-side * PADDING);
+    let width = inner.width().max(0.0);
+    let layout_at = |size: f32| {
+        let font = Font::new(size);
+        (font, TextLayout::new(content, &font, TextAlign::Center, Some(width)))
+    };
+    let largest = side * MAX_FONT_SIZE;
+    let smallest = largest * MIN_FONT_SIZE;
+    // widths grow with the size, so the longest word caps it right away
+    let longest_word = content
+        .split([' ', '\n'])
+        .map(|word| Font::new(1.0).width(word))
+        .fold(0.0, f32::max);
+    let mut high = match longest_word > 0.0 {
+        true => largest.min(width / longest_word),
+        false => largest,
+    };
+    let fits = |size: f32| layout_at(size).1.size.y <= inner.height();
+    if !fits(high) {
+        let mut low = smallest;
+        for _ in 0..FIT_STEPS {
+            let middle = (low + high) * 0.5;
+            if fits(middle) {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        high = low;
+    }
+    let (font, layout) = layout_at(high.max(smallest));
+    let top = inner.min.y + ((inner.height() - layout.size.y) * 0.5).max(0.0);
+    TextBlock {
+        origin: point(inner.min.x, top),
+        font,
+        layout,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note() -> Rect {
+        Rect::from_origin_size(point(0.0, 0.0), point(STICKY_SIZE, STICKY_SIZE))
+    }
+
+    #[test]
+    fn short_text_is_large_and_centered() {
+        let text = fit_text(&note(), "Hi");
+        assert_eq!(text.font.size, STICKY_SIZE * MAX_FONT_SIZE);
+        assert_eq!(text.layout.lines.len(), 1);
+        let middle = text.origin.y + text.layout.size.y * 0.5;
+        assert!((middle - STICKY_SIZE * 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn long_text_shrinks_to_fit() {
+        let content = "a few words that will not fit on one line at the largest size ".repeat(4);
+        let text = fit_text(&note(), &content);
+        assert!(text.font.size < STICKY_SIZE * MAX_FONT_SIZE);
+        let inner = note().inflate(-STICKY_SIZE * PADDING);
+        assert!(text.origin.y >= inner.min.y && text.origin.y + text.layout.size.y <= inner.max.y + 1e-3);
+    }
+
+    #[test]
+    fn long_words_are_not_broken() {
+        let text = fit_text(&note(), "Incomprehensibilities");
+        assert_eq!(text.layout.lines.len(), 1);
+        assert!(text.layout.lines[0].width <= STICKY_SIZE * (1.0 - 2.0 * PADDING) + 1e-3);
+    }
+}
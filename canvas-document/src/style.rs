@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// RGBA color with channels in `0.0..=1.0`, same layout as macroquad's `Color`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r, g, b, a }
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Color { a, ..self }
+    }
+
+    /// `#rrggbb` form of the color, alpha is dropped.
+    pub fn to_hex(&self) -> String {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        format!("#{:02x}{:02x}{:02x}", channel(self.r), channel(self.g), channel(self.b))
+    }
+}
+
+pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+
+/// How an element is painted. `None` for fill or stroke means that part is not drawn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Style {
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    pub stroke_width: f32,
+    pub opacity: f32,
+}
+
+impl Style {
+    pub fn filled(color: Color) -> Self {
+        Style {
+            fill: Some(color),
+            stroke: None,
+            ..Style::default()
+        }
+    }
+
+    pub fn stroked(color: Color, width: f32) -> Self {
+        Style {
+            fill: None,
+            stroke: Some(color),
+            stroke_width: width,
+            ..Style::default()
+        }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            fill: None,
+            stroke: Some(BLACK),
+            stroke_width: 2.0,
+            opacity: 1.0,
+        }
+    }
+}
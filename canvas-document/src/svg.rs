@@ -0,0 +1,695 @@
+//! SVG export of a whole board, the visible part of it or a selection.
+//!
+//! The output is plain SVG 1.1 that opens in browsers, Inkscape and slide
+//! tools. Coordinates are world units, so one unit becomes one SVG pixel.
+
+use std::fmt::Write;
+
+use crate::arrow::{line_geometry, HeadOutline};
+use crate::asset::AssetStore;
+use crate::connector::connector_geometry;
+use crate::document::Document;
+use crate::element::{Element, Shape, TextBlock};
+pub use crate::export::ExportArea;
+use crate::freehand::{outline_polygon, pressure_stroke_for_zoom};
+use crate::geometry::{point, Point, Rect};
+use crate::image::{is_full_crop, placement};
+use crate::render::{Path, PathSegment};
+use crate::sticky;
+use crate::style::{Color, Style};
+use crate::text::TextAlign;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    pub area: ExportArea,
+    /// Space added around the exported area, in world units.
+    pub padding: f32,
+    /// Color painted behind everything, `None` leaves the background transparent.
+    pub background: Option<Color>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            area: ExportArea::Board,
+            padding: 16.0,
+            background: None,
+        }
+    }
+}
+
+/// Writes the elements chosen by `options` as an SVG document.
+pub fn export_svg(document: &Document, options: &SvgOptions) -> String {
+    let (elements, area) = options.area.resolve(document, options.padding);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="{} {} {} {}" width="{}" height="{}">"#,
+        num(area.min.x),
+        num(area.min.y),
+        num(area.width()),
+        num(area.height()),
+        num(area.width()),
+        num(area.height()),
+    );
+    if let Some(background) = options.background {
+        let _ = writeln!(
+            svg,
+            r#"  <rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+            num(area.min.x),
+            num(area.min.y),
+            num(area.width()),
+            num(area.height()),
+            paint("fill", Some(background)),
+        );
+    }
+    for element in elements {
+        write_element(&mut svg, element, document.assets());
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn write_element(svg: &mut String, element: &Element, assets: &AssetStore) {
+    if element.transform.is_identity() {
+        write_shape(svg, element, assets);
+        return;
+    }
+    let t = &element.transform;
+    let mut inner = String::new();
+    write_shape(&mut inner, element, assets);
+    let open = format!(
+        r#"<g transform="matrix({} {} {} {} {} {})">"#,
+        ratio(t.a),
+        ratio(t.b),
+        ratio(t.c),
+        ratio(t.d),
+        num(t.e),
+        num(t.f),
+    );
+    write_g(svg, &open, &inner);
+}
+
+/// `inner` wrapped in the `<g>` tag `open`, indented one more level.
+fn write_g(svg: &mut String, open: &str, inner: &str) {
+    let _ = writeln!(svg, "  {}", open);
+    for line in inner.lines() {
+        let _ = writeln!(svg, "  {}", line);
+    }
+    svg.push_str("  </g>\n");
+}
+
+/// The shape in its own coordinates, before the element transform.
+fn write_shape(svg: &mut String, element: &Element, assets: &AssetStore) {
+    let style = &element.style;
+    match &element.shape {
+        Shape::Rectangle { rect } => write_rect(svg, rect, style),
+        Shape::Ellipse { rect } => {
+            let center = rect.center();
+            let _ = writeln!(
+                svg,
+                r#"  <ellipse cx="{}" cy="{}" rx="{}" ry="{}"{}/>"#,
+                num(center.x),
+                num(center.y),
+                num(rect.width() * 0.5),
+                num(rect.height() * 0.5),
+                style_attributes(style),
+            );
+        }
+        Shape::Line {
+            start,
+            end,
+            start_head,
+            end_head,
+        } => {
+            let line = line_geometry(*start, *end, *start_head, *end_head, style.stroke_width);
+            let stroke_only = Style { fill: None, ..style.clone() };
+            let _ = writeln!(
+                svg,
+                r#"  <line x1="{}" y1="{}" x2="{}" y2="{}"{}/>"#,
+                num(line.start.x),
+                num(line.start.y),
+                num(line.end.x),
+                num(line.end.y),
+                style_attributes(&stroke_only),
+            );
+            write_heads(svg, &line.heads, style);
+        }
+        Shape::Connector {
+            points,
+            routing,
+            start_head,
+            end_head,
+            ..
+        } => {
+            let connector = connector_geometry(points, *routing, *start_head, *end_head, style.stroke_width);
+            let _ = writeln!(
+                svg,
+                r#"  <path d="{}"{}/>"#,
+                path_data(&connector.path),
+                style_attributes(&Style { fill: None, ..style.clone() }),
+            );
+            write_heads(svg, &connector.heads, style);
+        }
+        Shape::Freehand { points, pressure } if !pressure.is_empty() => {
+            // variable width, so the outline is filled with the stroke color
+            let (centre, widths) = pressure_stroke_for_zoom(points, pressure, style.stroke_width, 1.0);
+            let outline = outline_polygon(&centre, &widths);
+            let fill_only = Style {
+                fill: style.stroke,
+                stroke: None,
+                ..style.clone()
+            };
+            let _ = writeln!(
+                svg,
+                r#"  <path d="{}"{}/>"#,
+                polygon_path(&outline),
+                style_attributes(&fill_only),
+            );
+        }
+        Shape::Freehand { points, .. } => {
+            let _ = writeln!(
+                svg,
+                r#"  <path d="{}"{}/>"#,
+                stroke_path(points),
+                style_attributes(&Style { fill: None, ..style.clone() }),
+            );
+        }
+        Shape::Text { content, align, .. } => {
+            let color = style.fill.or(style.stroke).unwrap_or(crate::style::BLACK);
+            if let Some(block) = element.shape.text_block() {
+                write_text(svg, content, &block, *align, color, style.opacity);
+            }
+        }
+        Shape::Sticky { rect, content, color } => {
+            let shadow = rect.translate(point(0.0, rect.height() * sticky::SHADOW_OFFSET));
+            let shadow_style = Style {
+                opacity: style.opacity,
+                ..Style::filled(sticky::SHADOW_COLOR)
+            };
+            write_rect(svg, &shadow, &shadow_style);
+            write_rect(svg, rect, &Style { fill: Some(color.color()), ..style.clone() });
+            if let Some(block) = element.shape.text_block() {
+                write_text(svg, content, &block, TextAlign::Center, sticky::TEXT_COLOR, style.opacity);
+            }
+        }
+        Shape::Image { rect, asset, crop } => {
+            let Some(url) = assets.data_url(asset) else {
+                // missing pictures are left out, their outline still shows
+                if style.stroke.is_some() {
+                    write_rect(svg, rect, &Style { fill: None, ..style.clone() });
+                }
+                return;
+            };
+            let picture = placement(rect, crop);
+            let opacity = if style.opacity < 1.0 {
+                format!(r#" opacity="{}""#, num(style.opacity))
+            } else {
+                String::new()
+            };
+            let mut image = String::new();
+            let _ = writeln!(
+                image,
+                r#"  <image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none"{} xlink:href="{}"/>"#,
+                num(picture.min.x),
+                num(picture.min.y),
+                num(picture.width()),
+                num(picture.height()),
+                opacity,
+                url,
+            );
+            if is_full_crop(crop) {
+                svg.push_str(&image);
+            } else {
+                let clip = format!("image-{}", element.id.0);
+                write_clip_path(svg, &clip, rect);
+                write_g(svg, &format!(r#"<g clip-path="url(#{})">"#, clip), &image);
+            }
+            if style.stroke.is_some() {
+                write_rect(svg, rect, &Style { fill: None, ..style.clone() });
+            }
+        }
+        Shape::Group { children } => {
+            let mut inner = String::new();
+            for child in children.iter().filter(|child| !child.hidden) {
+                write_element(&mut inner, child, assets);
+            }
+            write_g(svg, "<g>", &inner);
+        }
+        Shape::Frame { rect, children, .. } => {
+            // the fill goes under the children and the outline over them
+            write_rect(svg, rect, &Style { stroke: None, ..style.clone() });
+            let clip = format!("frame-{}", element.id.0);
+            write_clip_path(svg, &clip, rect);
+            let mut inner = String::new();
+            for child in children.iter().filter(|child| !child.hidden) {
+                write_element(&mut inner, child, assets);
+            }
+            write_g(svg, &format!(r#"<g clip-path="url(#{})">"#, clip), &inner);
+            if style.stroke.is_some() {
+                write_rect(svg, rect, &Style { fill: None, ..style.clone() });
+            }
+        }
+    }
+}
+
+/// A `<clipPath>` named `id` that keeps what lies within `rect`.
+fn write_clip_path(svg: &mut String, id: &str, rect: &Rect) {
+    let _ = writeln!(svg, r#"  <clipPath id="{}">"#, id);
+    let mut clip_rect = String::new();
+    write_rect(&mut clip_rect, rect, &Style::filled(crate::style::BLACK));
+    let _ = write!(svg, "  {}", clip_rect);
+    svg.push_str("  </clipPath>\n");
+}
+
+fn write_rect(svg: &mut String, rect: &Rect, style: &Style) {
+    let _ = writeln!(
+        svg,
+        r#"  <rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+        num(rect.min.x),
+        num(rect.min.y),
+        num(rect.width()),
+        num(rect.height()),
+        style_attributes(style),
+    );
+}
+
+/// Laid out text as one `<text>` with a `<tspan>` per line.
+fn write_text(svg: &mut String, content: &str, block: &TextBlock, align: TextAlign, color: Color, opacity: f32) {
+    let TextBlock { origin, font, layout } = block;
+    let text_style = Style {
+        opacity,
+        ..Style::filled(color)
+    };
+    // anchored where the lines line up, so a viewer's font wider or
+    // narrower than the estimate stays aligned
+    let (anchor, anchor_x) = match align {
+        TextAlign::Left => ("start", 0.0),
+        TextAlign::Center => ("middle", layout.size.x * 0.5),
+        TextAlign::Right => ("end", layout.size.x),
+    };
+    let _ = write!(
+        svg,
+        r#"  <text font-family="{}" font-size="{}"{}{}{}{}>"#,
+        font.family.css(),
+        num(font.size),
+        if font.bold { r#" font-weight="bold""# } else { "" },
+        if font.italic { r#" font-style="italic""# } else { "" },
+        match align {
+            TextAlign::Left => String::new(),
+            _ => format!(r#" text-anchor="{anchor}""#),
+        },
+        style_attributes(&text_style),
+    );
+    for line in &layout.lines {
+        let text = content[line.range.clone()].trim_end_matches(' ');
+        if text.is_empty() {
+            continue;
+        }
+        let _ = write!(
+            svg,
+            r#"<tspan x="{}" y="{}">{}</tspan>"#,
+            num(origin.x + anchor_x),
+            // baselines sit one font size below the top of their line, like on screen
+            num(origin.y + line.y + font.size),
+            escape(text)
+        );
+    }
+    svg.push_str("</text>\n");
+}
+
+/// Arrowheads in the stroke color of `style`, filled or outlined.
+fn write_heads(svg: &mut String, heads: &[HeadOutline], style: &Style) {
+    for head in heads {
+        let (d, head_style) = if head.filled {
+            let fill_only = Style {
+                fill: style.stroke,
+                stroke: None,
+                ..style.clone()
+            };
+            (polygon_path(&head.points), fill_only)
+        } else {
+            (polyline_path(&head.points), Style { fill: None, ..style.clone() })
+        };
+        let _ = writeln!(svg, r#"  <path d="{}"{}/>"#, d, style_attributes(&head_style));
+    }
+}
+
+/// Path through the stroke points with the same Catmull-Rom curve the
+/// canvas draws, written as exact cubic Béziers.
+fn stroke_path(points: &[Point]) -> String {
+    let Some(first) = points.first() else {
+        return String::new();
+    };
+    let mut d = format!("M{} {}", num(first.x), num(first.y));
+    if points.len() == 1 {
+        // a zero length segment so the round cap shows a dot
+        let _ = write!(d, " L{} {}", num(first.x), num(first.y));
+        return d;
+    }
+    let last = points.len() - 1;
+    for i in 0..last {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(last)];
+        let c1 = p1 + (p2 - p0) / 6.0;
+        let c2 = p2 - (p3 - p1) / 6.0;
+        let _ = write!(
+            d,
+            " C{} {} {} {} {} {}",
+            num(c1.x),
+            num(c1.y),
+            num(c2.x),
+            num(c2.y),
+            num(p2.x),
+            num(p2.y)
+        );
+    }
+    d
+}
+
+fn path_data(path: &Path) -> String {
+    let mut d = String::new();
+    for segment in &path.segments {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        let _ = match *segment {
+            PathSegment::MoveTo(p) => write!(d, "M{} {}", num(p.x), num(p.y)),
+            PathSegment::LineTo(p) => write!(d, "L{} {}", num(p.x), num(p.y)),
+            PathSegment::CubicTo { control1, control2, to } => write!(
+                d,
+                "C{} {} {} {} {} {}",
+                num(control1.x),
+                num(control1.y),
+                num(control2.x),
+                num(control2.y),
+                num(to.x),
+                num(to.y)
+            ),
+            PathSegment::Close => write!(d, "Z"),
+        };
+    }
+    d
+}
+
+fn polyline_path(points: &[Point]) -> String {
+    let mut d = String::new();
+    for (i, p) in points.iter().enumerate() {
+        let command = if i == 0 { "M" } else { " L" };
+        let _ = write!(d, "{}{} {}", command, num(p.x), num(p.y));
+    }
+    d
+}
+
+fn polygon_path(points: &[Point]) -> String {
+    let mut d = polyline_path(points);
+    if !points.is_empty() {
+        d.push_str(" Z");
+    }
+    d
+}
+
+fn style_attributes(style: &Style) -> String {
+    let mut attributes = paint("fill", style.fill);
+    if let Some(stroke) = style.stroke {
+        attributes += &paint("stroke", Some(stroke));
+        let _ = write!(
+            attributes,
+            r#" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round""#,
+            num(style.stroke_width)
+        );
+    }
+    if style.opacity < 1.0 {
+        let _ = write!(attributes, r#" opacity="{}""#, num(style.opacity));
+    }
+    attributes
+}
+
+/// `fill` or `stroke` attribute, with the alpha as a separate opacity attribute.
+fn paint(attribute: &str, color: Option<Color>) -> String {
+    match color {
+        None => format!(r#" {}="none""#, attribute),
+        Some(color) if color.a < 1.0 => format!(
+            r#" {}="{}" {}-opacity="{}""#,
+            attribute,
+            color.to_hex(),
+            attribute,
+            num(color.a)
+        ),
+        Some(color) => format!(r#" {}="{}""#, attribute, color.to_hex()),
+    }
+}
+
+/// Numbers with at most two decimals and no trailing zeros, which keeps files
+/// small and the golden files stable.
+fn num(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        _ => text.to_string(),
+    }
+}
+
+/// Like [`num`] with four decimals, for matrix entries where two would
+/// visibly bend large elements.
+fn ratio(value: f32) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        _ => text.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrow::Arrowhead;
+    use crate::connector::{Anchor, Binding, Routing};
+    use crate::element::ElementId;
+    use crate::geometry::{point, Affine, Rect};
+    use crate::sticky::StickyColor;
+    use crate::style::{BLACK, WHITE};
+
+    /// Compares against `testdata/svg/<name>.svg`. Run the tests with
+    /// `UPDATE_GOLDEN=1` to rewrite the files after an intended change.
+    fn assert_golden(name: &str, svg: &str) {
+        let path = format!("{}/testdata/svg/{}.svg", env!("CARGO_MANIFEST_DIR"), name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, svg).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing {}", path));
+        assert_eq!(svg, expected, "{} differs from the golden file", name);
+    }
+
+    fn board() -> (Document, Vec<ElementId>) {
+        let mut document = Document::new();
+        let rect = document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(0.0, 0.0), point(120.0, 60.0)),
+            },
+            Style {
+                fill: Some(Color::new(0.2, 0.4, 0.8, 0.5)),
+                stroke: Some(BLACK),
+                stroke_width: 2.0,
+                opacity: 1.0,
+            },
+        );
+        let ellipse = document.add(
+            Shape::Ellipse {
+                rect: Rect::from_origin_size(point(150.0, 10.0), point(80.0, 40.0)),
+            },
+            Style {
+                opacity: 0.75,
+                ..Style::filled(Color::new(1.0, 0.5, 0.0, 1.0))
+            },
+        );
+        document.add(
+            Shape::line(point(0.0, 100.0), point(230.0, 100.0)),
+            Style::stroked(Color::new(0.5, 0.5, 0.5, 1.0), 1.5),
+        );
+        document.add(
+            Shape::Freehand {
+                points: vec![point(10.0, 130.0), point(40.0, 150.0), point(70.0, 130.0)],
+                pressure: Vec::new(),
+            },
+            Style::stroked(BLACK, 3.0),
+        );
+        document.add(
+            Shape::Freehand {
+                points: vec![point(100.0, 140.0), point(130.0, 140.0)],
+                pressure: vec![0.2, 1.0],
+            },
+            Style::stroked(BLACK, 8.0),
+        );
+        document.add(
+            Shape::text(point(150.0, 120.0), "Q3 <plan>\n& notes", 16.0),
+            Style::filled(BLACK),
+        );
+        document.add(
+            Shape::Line {
+                start: point(0.0, 190.0),
+                end: point(120.0, 190.0),
+                start_head: Arrowhead::Open,
+                end_head: Arrowhead::Triangle,
+            },
+            Style::stroked(BLACK, 2.0),
+        );
+        let rotated = document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(160.0, 170.0), point(60.0, 30.0)),
+            },
+            Style::stroked(BLACK, 2.0),
+        );
+        let turn = Affine::around(point(190.0, 185.0), &Affine::rotate(std::f32::consts::PI / 6.0));
+        document.update(rotated, |e| e.transform_by(&turn));
+        let grouped: Vec<ElementId> = [0.0, 30.0]
+            .into_iter()
+            .map(|x| {
+                document.add(
+                    Shape::Ellipse {
+                        rect: Rect::from_origin_size(point(x, 220.0), point(20.0, 20.0)),
+                    },
+                    Style::filled(BLACK),
+                )
+            })
+            .collect();
+        crate::group::group_elements(&mut crate::History::default(), &mut document, &grouped);
+        document.add(
+            Shape::connector(
+                point(0.0, 0.0),
+                point(0.0, 0.0),
+                Some(Binding::new(rect, Anchor::Auto)),
+                Some(Binding::new(ellipse, Anchor::Fixed(point(0.5, 1.0)))),
+                Routing::Elbow,
+            ),
+            Style::stroked(BLACK, 1.5),
+        );
+        (document, vec![rect, ellipse])
+    }
+
+    #[test]
+    fn whole_board() {
+        let (document, _) = board();
+        assert_golden("board", &export_svg(&document, &SvgOptions::default()));
+    }
+
+    #[test]
+    fn selection_with_background() {
+        let (document, selection) = board();
+        let options = SvgOptions {
+            area: ExportArea::Selection(selection),
+            padding: 0.0,
+            background: Some(WHITE),
+        };
+        assert_golden("selection", &export_svg(&document, &options));
+    }
+
+    #[test]
+    fn viewport_only_contains_visible_elements() {
+        let (document, _) = board();
+        let options = SvgOptions {
+            area: ExportArea::Viewport(Rect::from_origin_size(point(-10.0, 80.0), point(100.0, 100.0))),
+            padding: 0.0,
+            background: None,
+        };
+        assert_golden("viewport", &export_svg(&document, &options));
+    }
+
+    #[test]
+    fn frame_is_cropped_to_its_rect() {
+        let mut document = Document::new();
+        let mut history = crate::History::default();
+        let frame = history.add(
+            &mut document,
+            Shape::frame(Rect::from_origin_size(point(0.0, 0.0), point(300.0, 200.0)), "Ideas"),
+            Style {
+                fill: Some(WHITE),
+                stroke: Some(Color::new(0.8, 0.8, 0.8, 1.0)),
+                stroke_width: 1.0,
+                opacity: 1.0,
+            },
+        );
+        // hangs over the right edge, so it is clipped
+        let note = history.add(
+            &mut document,
+            Shape::Sticky {
+                rect: Rect::from_origin_size(point(200.0, 40.0), point(150.0, 150.0)),
+                content: "Ship it".to_string(),
+                color: StickyColor::Pink,
+            },
+            Style { stroke: None, ..Style::default() },
+        );
+        history.add(&mut document, Shape::text(point(400.0, 0.0), "outside", 16.0), Style::filled(BLACK));
+        crate::frame::adopt_contained(&mut history, &mut document, frame);
+        crate::frame::place_in_frames(&mut history, &mut document, &[note]);
+        assert_eq!(document.parent_of(note).map(|parent| parent.id), Some(frame));
+
+        let options = SvgOptions {
+            area: ExportArea::Frame(frame),
+            ..SvgOptions::default()
+        };
+        assert_golden("frame", &export_svg(&document, &options));
+    }
+
+    #[test]
+    fn images_are_embedded_and_cropped() {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 2);
+        encoder.set_color(png::ColorType::Rgb);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255]).unwrap();
+        writer.finish().unwrap();
+
+        let mut document = Document::new();
+        let mut history = crate::History::default();
+        let whole = crate::image::add_image(
+            &mut history,
+            &mut document,
+            bytes.clone(),
+            point(50.0, 50.0),
+            50.0,
+            point(1000.0, 1000.0),
+        )
+        .unwrap();
+        let cropped =
+            crate::image::add_image(&mut history, &mut document, bytes, point(200.0, 50.0), 50.0, point(1000.0, 1000.0))
+                .unwrap();
+        // the same picture twice is stored once
+        assert_eq!(document.assets().len(), 1);
+        document.update(cropped, |element| {
+            crate::image::crop_to(
+                &mut element.shape,
+                Rect::from_corners(point(150.0, 0.0), point(200.0, 100.0)),
+            )
+        });
+        document.update(whole, |element| element.style.opacity = 0.5);
+        assert_golden("image", &export_svg(&document, &SvgOptions::default()));
+    }
+
+    #[test]
+    fn empty_board_is_still_valid() {
+        let svg = export_svg(&Document::new(), &SvgOptions::default());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn numbers_are_trimmed() {
+        assert_eq!(num(1.0), "1");
+        assert_eq!(num(1.50), "1.5");
+        assert_eq!(num(-0.001), "0");
+        assert_eq!(num(2.345), "2.35");
+    }
+}
@@ -0,0 +1,682 @@
+//! Fonts, wrapping and caret movement for text elements.
+//!
+//! Glyph widths are estimated per character instead of read from font files,
+//! so text wraps the same way in every front end, in exports and in tests.
+//! Backends place each glyph at the offset [`Font::glyphs`] gives it, which
+//! keeps the caret and the selection lined up with what is drawn.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{point, Point, Rect};
+
+/// Distance between the tops of two lines of text, relative to the font size.
+pub const LINE_HEIGHT: f32 = 1.2;
+/// How much wider bold glyphs are than regular ones.
+const BOLD_WIDTH: f32 = 1.06;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FontFamily {
+    #[default]
+    Sans,
+    Serif,
+    Mono,
+}
+
+impl FontFamily {
+    pub const ALL: [FontFamily; 3] = [FontFamily::Sans, FontFamily::Serif, FontFamily::Mono];
+
+    /// Generic CSS family, for the browser and SVG.
+    pub fn css(self) -> &'static str {
+        match self {
+            FontFamily::Sans => "sans-serif",
+            FontFamily::Serif => "serif",
+            FontFamily::Mono => "monospace",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FontFamily::Sans => "Sans",
+            FontFamily::Serif => "Serif",
+            FontFamily::Mono => "Mono",
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == FontFamily::default()
+    }
+
+    /// Advance of `c` in ems.
+    fn em_width(self, c: char) -> f32 {
+        match self {
+            FontFamily::Mono => 0.6,
+            FontFamily::Sans => proportional_width(c),
+            // serifs run a little wider
+            FontFamily::Serif => proportional_width(c) * 1.04,
+        }
+    }
+}
+
+/// Rough advances of a typical proportional font, in ems.
+fn proportional_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.28,
+        'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '|' | '!' => 0.26,
+        'f' | 't' | 'r' | 'I' | '(' | ')' | '[' | ']' | '{' | '}' | '-' | '"' | '/' | '\\' => 0.36,
+        'm' | 'w' => 0.82,
+        'M' | 'W' | '@' => 0.9,
+        '\t' => 1.12,
+        c if c.is_ascii_uppercase() => 0.68,
+        c if c.is_ascii() => 0.54,
+        // CJK ideographs, kana and hangul take a full em
+        '\u{1100}'..='\u{11ff}' | '\u{2e80}'..='\u{a4cf}' => 1.0,
+        '\u{ac00}'..='\u{d7a3}' | '\u{f900}'..='\u{faff}' => 1.0,
+        _ => 0.6,
+    }
+}
+
+/// Typeface, size and style of a text element. The size is in world units,
+/// so text grows and shrinks with the zoom like every other shape.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Font {
+    #[serde(default, skip_serializing_if = "FontFamily::is_default")]
+    pub family: FontFamily,
+    #[serde(rename = "font_size")]
+    pub size: f32,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub italic: bool,
+}
+
+impl Font {
+    /// Regular sans serif text of `size`.
+    pub fn new(size: f32) -> Font {
+        Font {
+            family: FontFamily::Sans,
+            size,
+            bold: false,
+            italic: false,
+        }
+    }
+
+    /// How far the pen moves after drawing `c`.
+    pub fn advance(&self, c: char) -> f32 {
+        let width = self.family.em_width(c) * self.size;
+        if self.bold {
+            width * BOLD_WIDTH
+        } else {
+            width
+        }
+    }
+
+    pub fn width(&self, text: &str) -> f32 {
+        text.chars().map(|c| self.advance(c)).sum()
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.size * LINE_HEIGHT
+    }
+
+    /// Characters of `text` with their distance from the start of the line.
+    pub fn glyphs<'a>(&'a self, text: &'a str) -> impl Iterator<Item = (char, f32)> + 'a {
+        text.chars().scan(0.0, move |pen, c| {
+            let x = *pen;
+            *pen += self.advance(c);
+            Some((c, x))
+        })
+    }
+
+    /// CSS font shorthand, e.g. `italic bold 16px serif`.
+    pub fn css(&self) -> String {
+        format!(
+            "{}{}{}px {}",
+            if self.italic { "italic " } else { "" },
+            if self.bold { "bold " } else { "" },
+            self.size,
+            self.family.css(),
+        )
+    }
+}
+
+/// Horizontal alignment of the lines within the text box.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    pub fn is_default(&self) -> bool {
+        *self == TextAlign::default()
+    }
+}
+
+/// One line of laid out text, relative to the top left of the text box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    /// Bytes of the content on this line. Spaces where a line wraps stay on
+    /// the line before, the line break of a paragraph is left out.
+    pub range: Range<usize>,
+    pub x: f32,
+    pub y: f32,
+    /// Width without the spaces at the end.
+    pub width: f32,
+}
+
+/// Content broken into lines. Lines break at `\n` and, when the text has a
+/// fixed width, between words, or within words longer than the width.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayout {
+    pub lines: Vec<TextLine>,
+    /// Size of the text box: the fixed width, or that of the longest line, by
+    /// the height of all lines.
+    pub size: Point,
+    font: Font,
+}
+
+impl TextLayout {
+    /// Lays `content` out in a box `width` wide, or as wide as its longest
+    /// line when there is no width.
+    pub fn new(content: &str, font: &Font, align: TextAlign, width: Option<f32>) -> TextLayout {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for paragraph in content.split('\n') {
+            wrap(paragraph, start, font, width, &mut lines);
+            start += paragraph.len() + 1;
+        }
+        let box_width = width.unwrap_or_else(|| lines.iter().map(|line| line.width).fold(0.0, f32::max));
+        for (i, line) in lines.iter_mut().enumerate() {
+            line.y = i as f32 * font.line_height();
+            line.x = match align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (box_width - line.width) * 0.5,
+                TextAlign::Right => box_width - line.width,
+            };
+        }
+        TextLayout {
+            size: point(box_width, lines.len() as f32 * font.line_height()),
+            lines,
+            font: *font,
+        }
+    }
+
+    /// Line the caret at byte `index` is drawn on. Where a line wraps, the
+    /// caret goes to the start of the next line.
+    pub fn line_of(&self, index: usize) -> usize {
+        self.lines.partition_point(|line| line.range.start <= index).saturating_sub(1)
+    }
+
+    /// Top of the caret at byte `index` of `content`.
+    pub fn caret_position(&self, content: &str, index: usize) -> Point {
+        let line = &self.lines[self.line_of(index)];
+        let end = index.clamp(line.range.start, line.range.end);
+        point(line.x + self.font.width(&content[line.range.start..end]), line.y)
+    }
+
+    /// Caret index closest to `p`, relative to the top left of the text box.
+    pub fn index_at(&self, content: &str, p: Point) -> usize {
+        let row = (p.y / self.font.line_height()).floor().max(0.0) as usize;
+        self.index_in_line(content, row.min(self.lines.len() - 1), p.x)
+    }
+
+    /// Caret index on line `row` closest to `x`.
+    fn index_in_line(&self, content: &str, row: usize, x: f32) -> usize {
+        let line = &self.lines[row];
+        // the end of a wrapped line is drawn at the start of the next one
+        let wraps = self.lines.get(row + 1).is_some_and(|next| next.range.start == line.range.end);
+        let end = match wraps {
+            true => previous_boundary(content, line.range.end),
+            false => line.range.end,
+        };
+        let mut pen = line.x;
+        for (i, c) in content[line.range.start..end].char_indices() {
+            let advance = self.font.advance(c);
+            if x < pen + advance * 0.5 {
+                return line.range.start + i;
+            }
+            pen += advance;
+        }
+        end
+    }
+
+    /// Boxes covering the selected bytes `range`, one per line it touches.
+    pub fn selection_rects(&self, content: &str, range: Range<usize>) -> Vec<Rect> {
+        let mut rects = Vec::new();
+        if range.is_empty() {
+            return rects;
+        }
+        for line in &self.lines {
+            let start = range.start.max(line.range.start);
+            let end = range.end.min(line.range.end);
+            // a selected line break shows as a little extra width
+            let continues = range.end > line.range.end && range.start <= line.range.end;
+            if start > end || (start == end && !continues) {
+                continue;
+            }
+            let x = |index: usize| line.x + self.font.width(&content[line.range.start..index]);
+            let mut right = x(end);
+            if continues && content[line.range.end..].starts_with('\n') {
+                right += self.font.advance(' ');
+            }
+            rects.push(Rect::from_corners(
+                point(x(start), line.y),
+                point(right, line.y + self.font.line_height()),
+            ));
+        }
+        rects
+    }
+}
+
+/// Breaks one paragraph starting at byte `offset` into lines no wider than `width`.
+fn wrap(paragraph: &str, offset: usize, font: &Font, width: Option<f32>, lines: &mut Vec<TextLine>) {
+    let mut push = |range: Range<usize>, width: f32| {
+        lines.push(TextLine {
+            range: offset + range.start..offset + range.end,
+            x: 0.0,
+            y: 0.0,
+            width,
+        })
+    };
+    let Some(limit) = width else {
+        push(0..paragraph.len(), font.width(paragraph.trim_end_matches(' ')));
+        return;
+    };
+
+    let (mut line_start, mut pen, mut line_width) = (0, 0.0, 0.0);
+    let mut word_start = 0;
+    while word_start < paragraph.len() {
+        let word_end = paragraph[word_start..].find(' ').map_or(paragraph.len(), |n| word_start + n);
+        let next = paragraph[word_end..]
+            .find(|c| c != ' ')
+            .map_or(paragraph.len(), |n| word_end + n);
+        let word = &paragraph[word_start..word_end];
+        let word_width = font.width(word);
+
+        if pen + word_width > limit && line_start < word_start {
+            push(line_start..word_start, line_width);
+            (line_start, pen) = (word_start, 0.0);
+        }
+        if word_width > limit {
+            // too long for any line, break between its characters
+            for (i, c) in word.char_indices() {
+                let advance = font.advance(c);
+                if pen + advance > limit && line_start < word_start + i {
+                    push(line_start..word_start + i, pen);
+                    (line_start, pen) = (word_start + i, 0.0);
+                }
+                pen += advance;
+            }
+        } else {
+            pen += word_width;
+        }
+        if !word.is_empty() {
+            line_width = pen;
+        }
+        // spaces hang past the edge instead of wrapping
+        pen += font.width(&paragraph[word_end..next]);
+        word_start = next;
+    }
+    push(line_start..paragraph.len(), line_width);
+}
+
+fn previous_boundary(content: &str, index: usize) -> usize {
+    content[..index].char_indices().next_back().map_or(0, |(i, _)| i)
+}
+
+fn next_boundary(content: &str, index: usize) -> usize {
+    content[index..].chars().next().map_or(index, |c| index + c.len_utf8())
+}
+
+/// Start of the word before `index`, skipping the whitespace in between.
+fn word_start_before(content: &str, index: usize) -> usize {
+    let before = content[..index].trim_end();
+    before
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+/// End of the word after `index`, skipping the whitespace in between.
+fn word_end_after(content: &str, index: usize) -> usize {
+    let rest = &content[index..];
+    let word_start = rest.len() - rest.trim_start().len();
+    rest[word_start..]
+        .find(char::is_whitespace)
+        .map_or(content.len(), |n| index + word_start + n)
+}
+
+/// Word around `index`, or the run of whitespace there, for double clicks.
+pub fn word_range(content: &str, index: usize) -> Range<usize> {
+    let index = index.min(content.len());
+    let kind = |c: char| c.is_whitespace();
+    let Some(here) = content[index..].chars().next().or_else(|| content[..index].chars().next_back()) else {
+        return index..index;
+    };
+    let start = content[..index]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| kind(c) != kind(here) || c == '\n')
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let end = content[index..]
+        .char_indices()
+        .find(|&(_, c)| kind(c) != kind(here) || c == '\n')
+        .map_or(content.len(), |(i, _)| index + i);
+    start..end.max(start)
+}
+
+/// Where a key press moves the caret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Up,
+    Down,
+    LineStart,
+    LineEnd,
+    Start,
+    End,
+}
+
+/// Caret and selection in the content of a text element being edited, as
+/// byte indices on character boundaries.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextCursor {
+    pub caret: usize,
+    /// Other end of the selection, the same as `caret` when nothing is selected.
+    pub anchor: usize,
+    /// Where moving up and down tries to stay, so the caret doesn't drift
+    /// left over short lines.
+    goal_x: Option<f32>,
+}
+
+impl TextCursor {
+    pub fn new(index: usize) -> TextCursor {
+        TextCursor {
+            caret: index,
+            anchor: index,
+            goal_x: None,
+        }
+    }
+
+    /// Selects everything in `content`.
+    pub fn all(content: &str) -> TextCursor {
+        TextCursor {
+            anchor: 0,
+            ..TextCursor::new(content.len())
+        }
+    }
+
+    pub fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.caret != self.anchor
+    }
+
+    pub fn selected<'a>(&self, content: &'a str) -> &'a str {
+        &content[self.selection()]
+    }
+
+    /// Puts the caret at `index`, extending the selection or dropping it.
+    pub fn set(&mut self, index: usize, extend: bool) {
+        self.caret = index;
+        if !extend {
+            self.anchor = index;
+        }
+        self.goal_x = None;
+    }
+
+    /// Selects `range`, leaving the caret at its end.
+    pub fn select(&mut self, range: Range<usize>) {
+        self.anchor = range.start;
+        self.caret = range.end;
+        self.goal_x = None;
+    }
+
+    /// Keeps the cursor inside `content` after it changed underneath, e.g. by undo.
+    pub fn clamp(&mut self, content: &str) {
+        let clamp = |index: usize| {
+            let mut index = index.min(content.len());
+            while !content.is_char_boundary(index) {
+                index -= 1;
+            }
+            index
+        };
+        self.caret = clamp(self.caret);
+        self.anchor = clamp(self.anchor);
+    }
+
+    /// Index `motion` takes the caret to.
+    fn target(&mut self, content: &str, layout: &TextLayout, motion: Motion) -> usize {
+        let row = layout.line_of(self.caret);
+        match motion {
+            Motion::Left => previous_boundary(content, self.caret),
+            Motion::Right => next_boundary(content, self.caret),
+            Motion::WordLeft => word_start_before(content, self.caret),
+            Motion::WordRight => word_end_after(content, self.caret),
+            Motion::Up | Motion::Down => {
+                let x = *self
+                    .goal_x
+                    .get_or_insert_with(|| layout.caret_position(content, self.caret).x);
+                match (motion, row) {
+                    (Motion::Up, 0) => 0,
+                    (Motion::Up, _) => layout.index_in_line(content, row - 1, x),
+                    _ if row + 1 >= layout.lines.len() => content.len(),
+                    _ => layout.index_in_line(content, row + 1, x),
+                }
+            }
+            Motion::LineStart => layout.lines[row].range.start,
+            Motion::LineEnd => layout.index_in_line(content, row, f32::INFINITY),
+            Motion::Start => 0,
+            Motion::End => content.len(),
+        }
+    }
+
+    /// Moves the caret, extending the selection or dropping it. Left and
+    /// right without `extend` first collapse a selection to its ends.
+    pub fn move_caret(&mut self, content: &str, layout: &TextLayout, motion: Motion, extend: bool) {
+        let target = match motion {
+            Motion::Left if self.has_selection() && !extend => self.selection().start,
+            Motion::Right if self.has_selection() && !extend => self.selection().end,
+            _ => self.target(content, layout, motion),
+        };
+        let goal_x = self.goal_x.filter(|_| matches!(motion, Motion::Up | Motion::Down));
+        self.set(target, extend);
+        self.goal_x = goal_x;
+    }
+
+    /// Replaces the selection with `text` and puts the caret after it.
+    pub fn insert(&mut self, content: &mut String, text: &str) {
+        let range = self.selection();
+        content.replace_range(range.clone(), text);
+        self.set(range.start + text.len(), false);
+    }
+
+    /// Deletes the selection, or from the caret to where `motion` would take it.
+    pub fn delete(&mut self, content: &mut String, layout: &TextLayout, motion: Motion) {
+        if !self.has_selection() {
+            self.anchor = self.target(content, layout, motion);
+        }
+        self.insert(content, "");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Monospace, so every character is 6 wide and every line 12 high.
+    fn mono() -> Font {
+        Font {
+            family: FontFamily::Mono,
+            ..Font::new(10.0)
+        }
+    }
+
+    fn line_texts<'a>(content: &'a str, layout: &TextLayout) -> Vec<&'a str> {
+        layout.lines.iter().map(|line| &content[line.range.clone()]).collect()
+    }
+
+    #[test]
+    fn auto_width_only_breaks_at_newlines() {
+        let content = "one two three\nfour";
+        let layout = TextLayout::new(content, &mono(), TextAlign::Left, None);
+        assert_eq!(line_texts(content, &layout), ["one two three", "four"]);
+        assert_eq!(layout.size, point(78.0, 24.0));
+        assert_eq!(layout.lines[1].y, 12.0);
+
+        let empty = TextLayout::new("", &mono(), TextAlign::Left, None);
+        assert_eq!(empty.lines.len(), 1);
+        assert_eq!(empty.size, point(0.0, 12.0));
+    }
+
+    #[test]
+    fn fixed_width_wraps_between_words() {
+        // 48 wide fits eight characters
+        let content = "the quick brown fox";
+        let layout = TextLayout::new(content, &mono(), TextAlign::Left, Some(48.0));
+        assert_eq!(line_texts(content, &layout), ["the ", "quick ", "brown ", "fox"]);
+        // trailing spaces hang, they don't count towards the width
+        assert_eq!(layout.lines[1].width, 30.0);
+        assert_eq!(layout.size, point(48.0, 48.0));
+
+        let layout = TextLayout::new(content, &mono(), TextAlign::Left, Some(60.0));
+        assert_eq!(line_texts(content, &layout), ["the quick ", "brown fox"]);
+    }
+
+    #[test]
+    fn long_words_break_between_characters() {
+        let content = "a abcdefghij b";
+        let layout = TextLayout::new(content, &mono(), TextAlign::Left, Some(24.0));
+        assert_eq!(line_texts(content, &layout), ["a ", "abcd", "efgh", "ij b"]);
+    }
+
+    #[test]
+    fn alignment_offsets_lines() {
+        let content = "ab\nabcd";
+        let center = TextLayout::new(content, &mono(), TextAlign::Center, None);
+        assert_eq!((center.lines[0].x, center.lines[1].x), (6.0, 0.0));
+        let right = TextLayout::new(content, &mono(), TextAlign::Right, Some(30.0));
+        assert_eq!((right.lines[0].x, right.lines[1].x), (18.0, 6.0));
+    }
+
+    #[test]
+    fn caret_positions_follow_the_layout() {
+        let content = "hello world\nok";
+        let layout = TextLayout::new(content, &mono(), TextAlign::Left, Some(48.0));
+        assert_eq!(line_texts(content, &layout), ["hello ", "world", "ok"]);
+        assert_eq!(layout.caret_position(content, 2), point(12.0, 0.0));
+        // where the line wraps the caret starts the next line
+        assert_eq!(layout.caret_position(content, 6), point(0.0, 12.0));
+        // before and after the line break
+        assert_eq!(layout.caret_position(content, 11), point(30.0, 12.0));
+        assert_eq!(layout.caret_position(content, 12), point(0.0, 24.0));
+        assert_eq!(layout.caret_position(content, content.len()), point(12.0, 24.0));
+
+        assert_eq!(layout.index_at(content, point(13.0, 3.0)), 2);
+        assert_eq!(layout.index_at(content, point(16.0, 3.0)), 3);
+        // past the end of a wrapped line stays before its last space
+        assert_eq!(layout.index_at(content, point(100.0, 3.0)), 5);
+        assert_eq!(layout.index_at(content, point(100.0, 14.0)), 11);
+        assert_eq!(layout.index_at(content, point(-5.0, 100.0)), 12);
+    }
+
+    #[test]
+    fn selection_covers_every_line_it_touches() {
+        let content = "abc\ndef";
+        let layout = TextLayout::new(content, &mono(), TextAlign::Left, None);
+        let rects = layout.selection_rects(content, 1..6);
+        assert_eq!(
+            rects,
+            [
+                // the selected line break adds the width of a space
+                Rect::from_corners(point(6.0, 0.0), point(24.0, 12.0)),
+                Rect::from_corners(point(0.0, 12.0), point(12.0, 24.0)),
+            ]
+        );
+        assert!(layout.selection_rects(content, 2..2).is_empty());
+    }
+
+    #[test]
+    fn cursor_moves_by_characters_words_and_lines() {
+        let content = "one two\nthree\nfour five";
+        let layout = TextLayout::new(content, &mono(), TextAlign::Left, None);
+        let mut cursor = TextCursor::new(1);
+
+        cursor.move_caret(content, &layout, Motion::WordRight, false);
+        assert_eq!(cursor.caret, 3);
+        cursor.move_caret(content, &layout, Motion::WordRight, true);
+        assert_eq!((cursor.anchor, cursor.caret), (3, 7));
+        cursor.move_caret(content, &layout, Motion::Left, false);
+        assert_eq!(cursor.selection(), 3..3);
+
+        // down keeps the column over the shorter line in between
+        cursor.set(6, false);
+        cursor.move_caret(content, &layout, Motion::Down, false);
+        assert_eq!(cursor.caret, 13);
+        cursor.move_caret(content, &layout, Motion::Down, false);
+        assert_eq!(cursor.caret, 20);
+        cursor.move_caret(content, &layout, Motion::Down, false);
+        assert_eq!(cursor.caret, content.len());
+
+        cursor.move_caret(content, &layout, Motion::LineStart, false);
+        assert_eq!(cursor.caret, 14);
+        cursor.move_caret(content, &layout, Motion::WordLeft, false);
+        assert_eq!(cursor.caret, 8);
+        cursor.move_caret(content, &layout, Motion::Up, true);
+        assert_eq!(cursor.selected(content), "one two\n");
+    }
+
+    #[test]
+    fn editing_replaces_the_selection() {
+        let mut content = "héllo world".to_string();
+        let layout = TextLayout::new(&content, &mono(), TextAlign::Left, None);
+        let mut cursor = TextCursor::new(3);
+
+        cursor.delete(&mut content, &layout, Motion::Left);
+        assert_eq!((content.as_str(), cursor.caret), ("hllo world", 1));
+
+        cursor.select(word_range(&content, 8));
+        assert_eq!(cursor.selected(&content), "world");
+        cursor.insert(&mut content, "there\nfriend");
+        assert_eq!(content, "hllo there\nfriend");
+        assert_eq!(cursor.selection(), content.len()..content.len());
+
+        let layout = TextLayout::new(&content, &mono(), TextAlign::Left, None);
+        cursor.delete(&mut content, &layout, Motion::WordLeft);
+        assert_eq!(content, "hllo there\n");
+
+        let mut cursor = TextCursor::all(&content);
+        cursor.insert(&mut content, "x");
+        assert_eq!(content, "x");
+
+        let mut cursor = TextCursor::new(40);
+        cursor.clamp(&content);
+        assert_eq!(cursor.caret, 1);
+    }
+
+    #[test]
+    fn proportional_fonts_vary_in_width() {
+        let font = Font::new(10.0);
+        assert!(font.width("iii") < font.width("mmm"));
+        let bold = Font { bold: true, ..font };
+        assert!(bold.width("abc") > font.width("abc"));
+        let glyphs: Vec<(char, f32)> = mono().glyphs("ab").collect();
+        assert_eq!(glyphs, [('a', 0.0), ('b', 6.0)]);
+        assert_eq!(
+            Font { italic: true, bold: true, family: FontFamily::Serif, size: 16.0 }.css(),
+            "italic bold 16px serif"
+        );
+    }
+}
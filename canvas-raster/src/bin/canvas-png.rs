@@ -0,0 +1,54 @@
+//! Renders a saved canvas file to PNG, for thumbnails and CI artifacts.
+//!
+//! ```text
+//! canvas-png <canvas.json> <output.png> [--scale N | --dpi N] [--padding N] [--grid] [--transparent]
+//! ```
+
+use std::{env, error::Error, fs, process};
+
+use canvas_document::SavedCanvas;
+use canvas_raster::{render_png, scale_for_dpi, RasterOptions};
+
+const USAGE: &str =
+    "usage: canvas-png <canvas.json> <output.png> [--scale N | --dpi N] [--padding N] [--grid] [--transparent]";
+
+fn main() {
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut paths = Vec::new();
+    let mut options = RasterOptions::default();
+    let mut transparent = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut number = |name: &str| -> Result<f32, Box<dyn Error>> {
+            let value = args.next().ok_or_else(|| format!("{} needs a value", name))?;
+            Ok(value.parse()?)
+        };
+        match arg.as_str() {
+            "--scale" => options.scale = number("--scale")?,
+            "--dpi" => options.scale = scale_for_dpi(number("--dpi")?),
+            "--padding" => options.padding = number("--padding")?,
+            "--grid" => options.show_grid = true,
+            "--transparent" => transparent = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}\n{}", arg, USAGE).into()),
+            _ => paths.push(arg),
+        }
+    }
+    let [input, output] = paths.as_slice() else {
+        return Err(USAGE.into());
+    };
+
+    let saved = SavedCanvas::from_json(&fs::read_to_string(input)?)?;
+    options.background = (!transparent).then_some(saved.settings.background);
+    fs::write(output, render_png(&saved.document, &options)?)?;
+    Ok(())
+}
@@ -0,0 +1,613 @@
+//! CPU rasterizer for canvas documents.
+//!
+//! Draws a [`Document`] into an in-memory image with tiny-skia, without a
+//! window or a browser, for PNG exports, thumbnails and pixel snapshot tests.
+//! [`SkiaRenderer`] is the software [`Renderer`] backend, so the picture comes
+//! from the same scene code as the window and the browser.
+//! Text uses ProggyClean, the same font macroquad draws with, bundled in
+//! `assets/`.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    sync::OnceLock,
+};
+
+use canvas_document::{
+    export::ExportArea,
+    grid::BACKGROUND_COLOR,
+    point,
+    render::{Image, Path, PathSegment, Stroke},
+    scene,
+    text::Font as TextFont,
+    Affine, Color, Document, Point, Rect, Renderer,
+};
+use fontdue::{Font, FontSettings};
+use tiny_skia::{
+    ColorU8, FillRule, FilterQuality, LineCap, LineJoin, Mask, Paint, PathBuilder, Pixmap,
+    PixmapPaint, PremultipliedColorU8, Transform,
+};
+
+/// How far italic glyphs lean, horizontal offset per unit of height.
+const ITALIC_SLANT: f32 = 0.2;
+/// Largest width or height of an image, about 1 GB of pixels.
+pub const MAX_DIMENSION: u32 = 16_384;
+
+static FONT: OnceLock<Font> = OnceLock::new();
+
+fn glyph_font() -> &'static Font {
+    FONT.get_or_init(|| {
+        Font::from_bytes(
+            include_bytes!("../assets/ProggyClean.ttf") as &[u8],
+            FontSettings::default(),
+        )
+        .expect("bundled font is valid")
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterOptions {
+    pub area: ExportArea,
+    /// Space added around the exported area, in world units.
+    pub padding: f32,
+    /// Image pixels per world unit, see [`scale_for_dpi`].
+    pub scale: f32,
+    /// Color painted behind everything, `None` leaves the image transparent.
+    pub background: Option<Color>,
+    /// Draw the dot grid like the canvas does.
+    pub show_grid: bool,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        RasterOptions {
+            area: ExportArea::Board,
+            padding: 16.0,
+            scale: 1.0,
+            background: Some(BACKGROUND_COLOR),
+            show_grid: false,
+        }
+    }
+}
+
+/// Scale for a target resolution, one world unit is one CSS pixel at 96 DPI.
+pub fn scale_for_dpi(dpi: f32) -> f32 {
+    dpi / 96.0
+}
+
+#[derive(Debug)]
+pub enum RasterError {
+    /// The image would be wider or taller than [`MAX_DIMENSION`].
+    TooLarge {
+        width: f32,
+        height: f32,
+    },
+    Encode(String),
+}
+
+impl fmt::Display for RasterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RasterError::TooLarge { width, height } => write!(
+                f,
+                "image of {}x{} pixels is larger than the maximum of {} pixels per side",
+                width.ceil(),
+                height.ceil(),
+                MAX_DIMENSION
+            ),
+            RasterError::Encode(err) => write!(f, "could not encode png: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RasterError {}
+
+/// Draws the elements chosen by `options` into a new image.
+pub fn rasterize(document: &Document, options: &RasterOptions) -> Result<Pixmap, RasterError> {
+    let (elements, area) = options.area.resolve(document, options.padding);
+    let width = area.width() * options.scale;
+    let height = area.height() * options.scale;
+    if width.ceil() > MAX_DIMENSION as f32 || height.ceil() > MAX_DIMENSION as f32 {
+        return Err(RasterError::TooLarge { width, height });
+    }
+    let pixmap = Pixmap::new((width.ceil() as u32).max(1), (height.ceil() as u32).max(1))
+        .expect("size is checked above");
+
+    let mut renderer = SkiaRenderer::new(pixmap);
+    if let Some(background) = options.background {
+        renderer.clear(background);
+    }
+    renderer.transform(
+        &Affine::translate(point(-area.min.x, -area.min.y))
+            .then(&Affine::scale(options.scale)),
+    );
+    if options.show_grid {
+        // dots keep their on screen size, like in the window
+        scene::draw_grid(&mut renderer, &area, options.scale);
+    }
+    for element in elements {
+        scene::draw_element(&mut renderer, element, document.assets(), options.scale);
+    }
+    Ok(renderer.into_pixmap())
+}
+
+/// [`rasterize`] encoded as PNG.
+pub fn render_png(document: &Document, options: &RasterOptions) -> Result<Vec<u8>, RasterError> {
+    rasterize(document, options)?
+        .encode_png()
+        .map_err(|err| RasterError::Encode(err.to_string()))
+}
+
+/// [`Renderer`] drawing into a tiny-skia pixmap.
+pub struct SkiaRenderer {
+    pixmap: Pixmap,
+    transform: Affine,
+    clip: Option<Mask>,
+    saved: Vec<(Affine, Option<Mask>)>,
+    /// images converted to premultiplied pixmaps, by image id
+    images: HashMap<u64, Pixmap>,
+}
+
+impl SkiaRenderer {
+    pub fn new(pixmap: Pixmap) -> Self {
+        SkiaRenderer {
+            pixmap,
+            transform: Affine::IDENTITY,
+            clip: None,
+            saved: Vec::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    pub fn into_pixmap(self) -> Pixmap {
+        self.pixmap
+    }
+
+    fn skia_transform(&self) -> Transform {
+        let t = self.transform;
+        Transform::from_row(t.a, t.b, t.c, t.d, t.e, t.f)
+    }
+
+    /// Text turned, mirrored or slanted. Glyphs are rasterized at their size
+    /// on screen and drawn through the transform, filtered.
+    fn draw_transformed_text(&mut self, text: &str, position: Point, font: &TextFont, color: tiny_skia::Color) {
+        let scale = self.transform.scale_factor();
+        if scale <= 0.0 {
+            return;
+        }
+        let px = font.size * scale;
+        let paint = PixmapPaint {
+            quality: FilterQuality::Bilinear,
+            ..PixmapPaint::default()
+        };
+        let slant = if font.italic { ITALIC_SLANT } else { 0.0 };
+        // in world units from here on, the transform takes it to the image
+        let baseline = position.y + font.size;
+        for (c, offset) in font.glyphs(text) {
+            let (metrics, coverage) = glyph_font().rasterize(c, px);
+            if let Some(glyph) = glyph_pixmap(metrics.width, metrics.height, &coverage, color) {
+                let x = position.x + offset + metrics.xmin as f32 / scale;
+                let y = baseline - (metrics.height as f32 + metrics.ymin as f32) / scale;
+                for shift in bold_shifts(font, px) {
+                    // slanted glyphs lean right around the baseline
+                    let transform = self
+                        .skia_transform()
+                        .pre_translate(x + shift / scale + slant * (baseline - y), y)
+                        .pre_concat(Transform::from_row(1.0, 0.0, -slant, 1.0, 0.0, 0.0))
+                        .pre_scale(1.0 / scale, 1.0 / scale);
+                    self.pixmap
+                        .draw_pixmap(0, 0, glyph.as_ref(), &paint, transform, self.clip.as_ref());
+                }
+            }
+        }
+    }
+}
+
+/// Horizontal offsets in pixels to draw each glyph at. Bold draws it twice,
+/// a little apart, since the bundled font has no bold face.
+fn bold_shifts(font: &TextFont, px: f32) -> impl Iterator<Item = f32> {
+    let shift = (px * 0.06).max(1.0);
+    let passes = if font.bold { 2 } else { 1 };
+    (0..passes).map(move |pass| pass as f32 * shift)
+}
+
+impl Renderer for SkiaRenderer {
+    fn save(&mut self) {
+        self.saved.push((self.transform, self.clip.clone()));
+    }
+
+    fn restore(&mut self) {
+        if let Some((transform, clip)) = self.saved.pop() {
+            self.transform = transform;
+            self.clip = clip;
+        }
+    }
+
+    fn transform(&mut self, transform: &Affine) {
+        self.transform = transform.then(&self.transform);
+    }
+
+    fn clip_rect(&mut self, rect: Rect) {
+        let Some(path) = skia_path(&Path::rect(rect)) else {
+            return;
+        };
+        let transform = self.skia_transform();
+        match &mut self.clip {
+            Some(mask) => mask.intersect_path(&path, FillRule::Winding, true, transform),
+            None => {
+                let mut mask = Mask::new(self.pixmap.width(), self.pixmap.height())
+                    .expect("pixmap has a valid size");
+                mask.fill_path(&path, FillRule::Winding, true, transform);
+                self.clip = Some(mask);
+            }
+        }
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.pixmap.fill(skia_color(color));
+    }
+
+    fn fill_path(&mut self, path: &Path, color: Color) {
+        if let Some(path) = skia_path(path) {
+            let transform = self.skia_transform();
+            self.pixmap.fill_path(
+                &path,
+                &solid(color),
+                FillRule::Winding,
+                transform,
+                self.clip.as_ref(),
+            );
+        }
+    }
+
+    fn stroke_path(&mut self, path: &Path, stroke: &Stroke) {
+        if let Some(path) = skia_path(path) {
+            let transform = self.skia_transform();
+            let style = tiny_skia::Stroke {
+                width: stroke.width,
+                line_cap: LineCap::Round,
+                line_join: LineJoin::Round,
+                ..tiny_skia::Stroke::default()
+            };
+            self.pixmap.stroke_path(
+                &path,
+                &solid(stroke.color),
+                &style,
+                transform,
+                self.clip.as_ref(),
+            );
+        }
+    }
+
+    /// Glyphs are rasterized at their final pixel size, so text only follows
+    /// the translation and uniform scale of the transform. Every family is
+    /// drawn with the bundled font, spaced by the layout's advances.
+    fn draw_text(&mut self, text: &str, position: Point, font: &TextFont, color: Color) {
+        let color = skia_color(color);
+        let t = self.transform;
+        if font.italic || t.b != 0.0 || t.c != 0.0 || t.a <= 0.0 || t.d <= 0.0 {
+            self.draw_transformed_text(text, position, font, color);
+            return;
+        }
+        let scale = t.scale_factor();
+        let px = font.size * scale;
+        let origin = t.apply(position);
+        let baseline = origin.y + px;
+        for (c, offset) in font.glyphs(text) {
+            let (metrics, coverage) = glyph_font().rasterize(c, px);
+            if let Some(glyph) = glyph_pixmap(metrics.width, metrics.height, &coverage, color) {
+                let x = origin.x + offset * scale + metrics.xmin as f32;
+                let y = baseline - metrics.height as f32 - metrics.ymin as f32;
+                for shift in bold_shifts(font, px) {
+                    self.pixmap.draw_pixmap(
+                        (x + shift).round() as i32,
+                        y.round() as i32,
+                        glyph.as_ref(),
+                        &PixmapPaint::default(),
+                        Transform::identity(),
+                        self.clip.as_ref(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn draw_image(&mut self, image: &Image, dest: Rect) {
+        let transform = self.skia_transform();
+        let source = match self.images.entry(image.id()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match image_pixmap(image) {
+                Some(pixmap) => entry.insert(pixmap),
+                None => return,
+            },
+        };
+        let transform = transform
+            .pre_translate(dest.min.x, dest.min.y)
+            .pre_scale(
+                dest.width() / source.width() as f32,
+                dest.height() / source.height() as f32,
+            );
+        let paint = PixmapPaint {
+            quality: FilterQuality::Bilinear,
+            ..PixmapPaint::default()
+        };
+        self.pixmap
+            .draw_pixmap(0, 0, source.as_ref(), &paint, transform, self.clip.as_ref());
+    }
+}
+
+fn skia_color(color: Color) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba(
+        color.r.clamp(0.0, 1.0),
+        color.g.clamp(0.0, 1.0),
+        color.b.clamp(0.0, 1.0),
+        color.a.clamp(0.0, 1.0),
+    )
+    .unwrap_or(tiny_skia::Color::TRANSPARENT)
+}
+
+fn solid(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(skia_color(color));
+    paint.anti_alias = true;
+    paint
+}
+
+fn skia_path(path: &Path) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    for segment in &path.segments {
+        match *segment {
+            PathSegment::MoveTo(p) => builder.move_to(p.x, p.y),
+            PathSegment::LineTo(p) => builder.line_to(p.x, p.y),
+            PathSegment::CubicTo { control1, control2, to } => {
+                builder.cubic_to(control1.x, control1.y, control2.x, control2.y, to.x, to.y)
+            }
+            PathSegment::Close => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+/// Coverage bitmap of a glyph tinted with `color`.
+fn glyph_pixmap(
+    width: usize,
+    height: usize,
+    coverage: &[u8],
+    color: tiny_skia::Color,
+) -> Option<Pixmap> {
+    let mut glyph = Pixmap::new(width as u32, height as u32)?;
+    let color = color.premultiply().to_color_u8();
+    for (pixel, &cover) in glyph.pixels_mut().iter_mut().zip(coverage) {
+        let scale = |channel: u8| (channel as u16 * cover as u16 / 255) as u8;
+        *pixel = PremultipliedColorU8::from_rgba(
+            scale(color.red()),
+            scale(color.green()),
+            scale(color.blue()),
+            scale(color.alpha()),
+        )?;
+    }
+    Some(glyph)
+}
+
+/// The image's straight RGBA premultiplied, as tiny-skia expects.
+fn image_pixmap(image: &Image) -> Option<Pixmap> {
+    let mut pixmap = Pixmap::new(image.width(), image.height())?;
+    for (pixel, rgba) in pixmap.pixels_mut().iter_mut().zip(image.pixels().chunks_exact(4)) {
+        *pixel = ColorU8::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3]).premultiply();
+    }
+    Some(pixmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas_document::{style::BLACK, Shape, Style};
+
+    /// Compares against `testdata/<name>.png`, allowing for rounding
+    /// differences between SIMD backends. Run the tests with `UPDATE_GOLDEN=1`
+    /// to rewrite the files after an intended change.
+    fn assert_snapshot(name: &str, pixmap: &Pixmap) {
+        let path = format!("{}/testdata/{}.png", env!("CARGO_MANIFEST_DIR"), name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            pixmap.save_png(&path).unwrap();
+        }
+        let expected = Pixmap::load_png(&path).unwrap_or_else(|_| panic!("missing {}", path));
+        assert_eq!(
+            (pixmap.width(), pixmap.height()),
+            (expected.width(), expected.height()),
+            "{} has a different size",
+            name
+        );
+        let differing = pixmap
+            .data()
+            .iter()
+            .zip(expected.data())
+            .filter(|(a, b)| a.abs_diff(**b) > 2)
+            .count();
+        assert_eq!(
+            differing, 0,
+            "{} differs from the snapshot in {} channels",
+            name, differing
+        );
+    }
+
+    fn board() -> Document {
+        let mut document = Document::new();
+        document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(0.0, 0.0), point(120.0, 60.0)),
+            },
+            Style {
+                fill: Some(Color::new(0.2, 0.4, 0.8, 0.5)),
+                stroke: Some(BLACK),
+                stroke_width: 2.0,
+                opacity: 1.0,
+            },
+        );
+        document.add(
+            Shape::Ellipse {
+                rect: Rect::from_origin_size(point(150.0, 10.0), point(80.0, 40.0)),
+            },
+            Style::filled(Color::new(1.0, 0.5, 0.0, 0.75)),
+        );
+        document.add(
+            Shape::Freehand {
+                points: vec![point(10.0, 90.0), point(40.0, 110.0), point(70.0, 90.0)],
+                pressure: Vec::new(),
+            },
+            Style::stroked(BLACK, 3.0),
+        );
+        document.add(
+            Shape::Freehand {
+                points: vec![point(100.0, 100.0), point(140.0, 100.0)],
+                pressure: vec![0.2, 1.0],
+            },
+            Style::stroked(Color::new(0.8, 0.1, 0.1, 1.0), 8.0),
+        );
+        document.add(
+            Shape::text(point(160.0, 80.0), "Hi\nthere", 13.0),
+            Style::filled(BLACK),
+        );
+        document
+    }
+
+    #[test]
+    fn board_snapshot() {
+        let pixmap = rasterize(&board(), &RasterOptions::default()).unwrap();
+        assert_snapshot("board", &pixmap);
+    }
+
+    #[test]
+    fn board_with_grid_at_double_scale() {
+        let options = RasterOptions {
+            scale: 2.0,
+            show_grid: true,
+            ..RasterOptions::default()
+        };
+        let document = board();
+        let pixmap = rasterize(&document, &options).unwrap();
+        let (_, area) = options.area.resolve(&document, options.padding);
+        assert_eq!(pixmap.width(), (area.width() * 2.0).ceil() as u32);
+        assert_snapshot("board_grid_2x", &pixmap);
+    }
+
+    #[test]
+    fn transparent_background_stays_clear() {
+        let options = RasterOptions {
+            background: None,
+            ..RasterOptions::default()
+        };
+        let pixmap = rasterize(&board(), &options).unwrap();
+        assert_eq!(pixmap.pixel(0, 0).unwrap().alpha(), 0);
+    }
+
+    #[test]
+    fn grid_dots_are_drawn_on_an_empty_board() {
+        let options = RasterOptions {
+            area: ExportArea::Viewport(Rect::from_origin_size(
+                point(-10.0, -10.0),
+                point(40.0, 40.0),
+            )),
+            padding: 0.0,
+            show_grid: true,
+            ..RasterOptions::default()
+        };
+        let pixmap = rasterize(&Document::new(), &options).unwrap();
+        // the dot at the world origin is emphasized
+        let dot = pixmap.pixel(10, 10).unwrap().demultiply();
+        let background = pixmap.pixel(0, 5).unwrap().demultiply();
+        assert!(dot.blue() < background.blue() || dot.red() < background.red());
+        assert_ne!(dot, background);
+    }
+
+    #[test]
+    fn refuses_huge_images() {
+        let options = RasterOptions {
+            area: ExportArea::Viewport(Rect::from_origin_size(
+                point(0.0, 0.0),
+                point(100_000.0, 10.0),
+            )),
+            ..RasterOptions::default()
+        };
+        assert!(matches!(
+            rasterize(&Document::new(), &options),
+            Err(RasterError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn images_are_scaled_and_clipped() {
+        let mut renderer = SkiaRenderer::new(Pixmap::new(20, 20).unwrap());
+        // one opaque red pixel stretched over the left half
+        let red = Image::from_rgba(1, 1, vec![255, 0, 0, 255]);
+        renderer.save();
+        renderer.clip_rect(Rect::from_origin_size(point(0.0, 0.0), point(20.0, 10.0)));
+        renderer.draw_image(&red, Rect::from_origin_size(point(0.0, 0.0), point(10.0, 20.0)));
+        renderer.restore();
+        let pixmap = renderer.into_pixmap();
+        assert_eq!(pixmap.pixel(5, 5).unwrap().demultiply(), ColorU8::from_rgba(255, 0, 0, 255));
+        assert_eq!(pixmap.pixel(15, 5).unwrap().alpha(), 0);
+        assert_eq!(pixmap.pixel(5, 15).unwrap().alpha(), 0);
+    }
+
+    #[test]
+    fn rotated_elements_follow_their_transform() {
+        let mut document = Document::new();
+        let bar = document.add(
+            Shape::Rectangle {
+                rect: Rect::from_origin_size(point(0.0, 0.0), point(40.0, 10.0)),
+            },
+            Style::filled(BLACK),
+        );
+        let text = document.add(
+            Shape::text(point(0.0, 30.0), "MMMM", 20.0),
+            Style::filled(BLACK),
+        );
+        let eighth = Affine::around(point(20.0, 5.0), &Affine::rotate(std::f32::consts::FRAC_PI_4));
+        document.update(bar, |e| e.transform_by(&eighth));
+        let half_turn = Affine::around(point(24.0, 42.0), &Affine::rotate(std::f32::consts::PI));
+        document.update(text, |e| e.transform_by(&half_turn));
+        let options = RasterOptions {
+            area: ExportArea::Viewport(Rect::from_origin_size(point(-10.0, -20.0), point(60.0, 80.0))),
+            padding: 0.0,
+            background: None,
+            ..RasterOptions::default()
+        };
+        let pixmap = rasterize(&document, &options).unwrap();
+        let alpha = |x: f32, y: f32| pixmap.pixel((x + 10.0) as u32, (y + 20.0) as u32).unwrap().alpha();
+        assert_eq!(alpha(20.0, 5.0), 255);
+        assert_eq!(alpha(1.0, 1.0), 0, "the unrotated corner stays empty");
+        assert_eq!(alpha(10.0, -5.0), 255, "the bar reaches up along the diagonal");
+        let painted = |rows: std::ops::Range<u32>| {
+            rows.flat_map(|y| (0..pixmap.width()).map(move |x| (x, y)))
+                .filter(|&(x, y)| pixmap.pixel(x, y).unwrap().alpha() > 0)
+                .count()
+        };
+        // the text turned over its center still covers the same rows
+        assert_eq!(painted(45..50), 0);
+        assert!(painted(50..74) > 0);
+        assert_eq!(painted(74..80), 0);
+    }
+
+    #[test]
+    fn bold_and_italic_text_change_the_glyphs() {
+        let draw = |font: TextFont| {
+            let mut renderer = SkiaRenderer::new(Pixmap::new(120, 30).unwrap());
+            renderer.draw_text("Hello", point(4.0, 4.0), &font, BLACK);
+            renderer.into_pixmap()
+        };
+        let painted = |pixmap: &Pixmap| pixmap.pixels().iter().filter(|p| p.alpha() > 0).count();
+        let regular = draw(TextFont::new(16.0));
+        let bold = draw(TextFont { bold: true, ..TextFont::new(16.0) });
+        let italic = draw(TextFont { italic: true, ..TextFont::new(16.0) });
+        assert!(painted(&regular) > 0);
+        assert!(painted(&bold) > painted(&regular));
+        assert_ne!(italic.data(), regular.data());
+    }
+
+    #[test]
+    fn dpi_maps_to_scale() {
+        assert_eq!(scale_for_dpi(96.0), 1.0);
+        assert_eq!(scale_for_dpi(192.0), 2.0);
+    }
+}
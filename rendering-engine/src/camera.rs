@@ -1,3 +1,4 @@
+use canvas_document::{point, CameraState, Rect};
 use macroquad::{
     math::{vec2, Vec2},
     window::{screen_height, screen_width},
@@ -23,4 +24,27 @@ impl Camera {
     pub fn screen_to_world(&self, screen_pos: Vec2) -> Vec2 {
         (screen_pos - vec2(screen_width(), screen_height()) * 0.5) / self.zoom + self.position
     }
+
+    /// The part of the world currently on screen.
+    pub fn visible_world_rect(&self) -> Rect {
+        let top_left = self.screen_to_world(Vec2::ZERO);
+        let bottom_right = self.screen_to_world(vec2(screen_width(), screen_height()));
+        Rect::from_corners(
+            point(top_left.x, top_left.y),
+            point(bottom_right.x, bottom_right.y),
+        )
+    }
+
+    /// Snapshot of the camera for saving.
+    pub fn state(&self) -> CameraState {
+        CameraState {
+            position: point(self.position.x, self.position.y),
+            zoom: self.zoom,
+        }
+    }
+
+    pub fn restore(&mut self, state: &CameraState) {
+        self.position = vec2(state.position.x, state.position.y);
+        self.zoom = state.zoom;
+    }
 }
@@ -1,6 +1,85 @@
+use canvas_document::{
+    arrow::Arrowhead,
+    connector::{Binding, Routing},
+    selection::{Frame, Handle, Selection},
+    snap::Guide,
+    sticky::StickyColor,
+    text::TextCursor,
+    Element, ElementId, Point,
+};
 use macroquad::math::Vec2;
 
+/// What the left mouse button is currently doing to a shape.
+pub enum ShapeDrag {
+    None,
+    /// moving the selection, `grab_offset` is where it was grabbed relative to its top left corner
+    Moving { grab_offset: Vec2 },
+    /// dragging one of the eight handles around the selection `frame`, `elements` are the
+    /// selected elements as they were when the drag started
+    Resizing { handle: Handle, frame: Frame, elements: Vec<Element> },
+    /// dragging the rotation handle, turning the selection around `center`
+    Rotating { center: Point, start: Point, elements: Vec<Element> },
+    /// dragging a selection rectangle from `start`, `base` was selected before it
+    Marquee { start: Vec2, base: Vec<ElementId> },
+    /// drawing a lasso loop through `points` in world coordinates, `base` was selected before it
+    Lasso { points: Vec<Point>, base: Vec<ElementId> },
+    /// drawing a pen stroke
+    Drawing { id: ElementId },
+    /// erasing, the eraser was at `last` the frame before
+    Erasing { last: Point },
+    /// dragging out a new shape from `anchor`, the element is only added once the drag is long enough
+    Creating { anchor: Vec2, id: Option<ElementId> },
+    /// dragging out a connector from `anchor`, bound to `start` when it began on an element
+    Connecting { anchor: Point, start: Option<Binding>, id: Option<ElementId> },
+}
+
+/// Text element being typed into, in place on the canvas.
+pub struct TextEdit {
+    pub id: ElementId,
+    pub cursor: TextCursor,
+    /// dragging out a selection with the mouse
+    pub selecting: bool,
+    /// when the caret last moved, it only blinks while left alone
+    pub moved_at: f64,
+}
+
 pub struct CanvasState {
     pub is_dragging: bool,
     pub last_mouse_position: Vec2,
-}
\ No newline at end of file
+    pub selection: Selection,
+    pub shape_drag: ShapeDrag,
+    /// heads given to new arrows, at the start and the end
+    pub arrowheads: (Arrowhead, Arrowhead),
+    /// routing given to new connectors
+    pub routing: Routing,
+    /// element under the pointer a connector would bind to, its anchors are shown
+    pub connect_target: Option<ElementId>,
+    /// color given to new sticky notes
+    pub sticky_color: StickyColor,
+    /// time and screen position of the last click, to tell double clicks
+    pub last_click: Option<(f64, Vec2)>,
+    /// what the shape being dragged snapped to, shown until it is let go
+    pub guides: Vec<Guide>,
+    pub text_edit: Option<TextEdit>,
+    /// image whose handles crop it instead of resizing it
+    pub cropping: Option<ElementId>,
+}
+
+impl CanvasState {
+    pub fn new() -> Self {
+        CanvasState {
+            is_dragging: false,
+            last_mouse_position: Vec2::ZERO,
+            selection: Selection::new(),
+            shape_drag: ShapeDrag::None,
+            arrowheads: (Arrowhead::None, Arrowhead::Triangle),
+            routing: Routing::Elbow,
+            connect_target: None,
+            sticky_color: StickyColor::default(),
+            last_click: None,
+            guides: Vec::new(),
+            text_edit: None,
+            cropping: None,
+        }
+    }
+}
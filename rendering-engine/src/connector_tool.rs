@@ -0,0 +1,110 @@
+use canvas_document::{
+    connector::{anchor_position, Anchor, Binding, SIDE_ANCHORS},
+    style::BLACK,
+    Document, History, Point, Rect, Shape, Style,
+};
+use macroquad::input::{is_mouse_button_down, is_mouse_button_pressed, mouse_position, MouseButton};
+
+use crate::{
+    camera::Camera,
+    canvas_state::{CanvasState, ShapeDrag},
+    document_view::to_point,
+    shape_tools::{DRAG_THRESHOLD, LINE_WIDTH, PICK_TOLERANCE},
+};
+
+/// How close to an anchor of an element, in screen pixels, a connector end
+/// binds to that anchor instead of following the outline.
+pub const ANCHOR_SNAP: f32 = 8.0;
+
+/// What a connector end at `p` binds to: the topmost element under it that
+/// isn't a connector, at one of its side anchors when `p` is close to one.
+pub fn binding_at(camera: &Camera, document: &Document, p: Point) -> Option<Binding> {
+    let snap = ANCHOR_SNAP / camera.zoom;
+    let near = Rect::from_corners(p, p).inflate(snap);
+    document
+        .elements_intersecting(&near)
+        .into_iter()
+        .rev()
+        .filter(|element| document.is_pickable(element.id) && !matches!(element.shape, Shape::Connector { .. }))
+        .find_map(|element| {
+            let anchor = SIDE_ANCHORS
+                .into_iter()
+                .find(|&relative| anchor_position(element, relative).distance(p) <= snap);
+            match anchor {
+                Some(relative) => Some(Binding::new(element.id, Anchor::Fixed(relative))),
+                None => element
+                    .hit_test(p, PICK_TOLERANCE / camera.zoom)
+                    .then_some(Binding::new(element.id, Anchor::Auto)),
+            }
+        })
+}
+
+/// Dragging from one element to another links them with a connector that
+/// follows them around. Ends let go near an anchor dot stick to it, ends let
+/// go elsewhere on an element slide along its outline, and ends off any
+/// element stay where they are.
+pub fn handle_connect(camera: &Camera, state: &mut CanvasState, document: &mut Document, history: &mut History) {
+    let mouse_world = to_point(camera.screen_to_world(mouse_position().into()));
+    let target = binding_at(camera, document, mouse_world);
+    state.connect_target = target.map(|binding| binding.element);
+    if is_mouse_button_pressed(MouseButton::Left) {
+        state.shape_drag = ShapeDrag::Connecting {
+            anchor: mouse_world,
+            start: target,
+            id: None,
+        };
+    }
+    let ShapeDrag::Connecting { anchor, start, id } = state.shape_drag else {
+        return;
+    };
+
+    if is_mouse_button_down(MouseButton::Left) {
+        // an element doesn't link to itself
+        let end = target.filter(|end| start.is_none_or(|start| start.element != end.element));
+        match id {
+            Some(id) => {
+                let changed = document.get(id).is_some_and(|element| match &element.shape {
+                    Shape::Connector { points, end: bound, .. } => {
+                        *bound != end || (end.is_none() && points.last() != Some(&mouse_world))
+                    }
+                    _ => false,
+                });
+                if changed {
+                    history.update(document, id, |element| {
+                        if let Shape::Connector { points, end: bound, .. } = &mut element.shape {
+                            *bound = end;
+                            if let Some(last) = points.last_mut() {
+                                *last = mouse_world;
+                            }
+                        }
+                    });
+                }
+            }
+            None if anchor.distance(mouse_world) * camera.zoom >= DRAG_THRESHOLD => {
+                history.begin_gesture();
+                let shape = Shape::Connector {
+                    points: vec![anchor, mouse_world],
+                    start,
+                    end,
+                    routing: state.routing,
+                    start_head: state.arrowheads.0,
+                    end_head: state.arrowheads.1,
+                };
+                let id = history.add(document, shape, Style::stroked(BLACK, LINE_WIDTH));
+                state.selection.set(id);
+                state.shape_drag = ShapeDrag::Connecting {
+                    anchor,
+                    start,
+                    id: Some(id),
+                };
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    if id.is_some() {
+        history.end_gesture(document);
+    }
+    state.shape_drag = ShapeDrag::None;
+}
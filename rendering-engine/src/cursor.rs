@@ -1,10 +1,21 @@
-use macroquad::{color::WHITE, input::{mouse_position, show_mouse}, texture::{draw_texture, Texture2D}, window::{screen_height, screen_width}};
+use canvas_document::{
+    eraser::EraseMode,
+    point,
+    render::{Image, Stroke},
+    style::BLACK,
+    Rect, Renderer,
+};
+use macroquad::{
+    input::{mouse_position, show_mouse},
+    texture::load_image,
+    window::{screen_height, screen_width},
+};
 
-use crate::{canvas_state::CanvasState, user_action_mode::UserActionMode};
+use crate::{canvas_state::CanvasState, shape_tools::ERASER_RADIUS, user_action_mode::UserActionMode};
 
 
 
-pub fn handle_cursor(mode: &UserActionMode, state: &CanvasState, cursors: &Cursors) {
+pub fn handle_cursor(renderer: &mut impl Renderer, mode: &UserActionMode, state: &CanvasState, cursors: &Cursors) {
     let (mouse_x, mouse_y) = mouse_position();
     let window_width = screen_width();
     let window_height = screen_height();
@@ -12,34 +23,97 @@ pub fn handle_cursor(mode: &UserActionMode, state: &CanvasState, cursors: &Curso
     if mouse_x >= 0.0 && mouse_x < window_width && mouse_y >= 0.0 && mouse_y < window_height {
         // Mouse is inside the window
         show_mouse(false);
-        draw_cursor(mode, state, cursors);
+        draw_cursor(renderer, mode, state, cursors);
     } else {
         // Mouse is outside the window
         show_mouse(true);
     }
 }
 
-pub fn draw_cursor(mode: &UserActionMode, state: &CanvasState, cursors: &Cursors) {
+pub fn draw_cursor(renderer: &mut impl Renderer, mode: &UserActionMode, state: &CanvasState, cursors: &Cursors) {
     let (mouse_x, mouse_y) = mouse_position();
-    let cursor_texture = match mode {
-        UserActionMode::DRAG => {
+    let cursor_image = match mode {
+        UserActionMode::Drag => {
             if state.is_dragging {
                 &cursors.grab
             } else {
                 &cursors.hand
             }
         }
+        UserActionMode::Select
+        | UserActionMode::Lasso
+        | UserActionMode::Rectangle
+        | UserActionMode::Ellipse
+        | UserActionMode::Line
+        | UserActionMode::Arrow
+        | UserActionMode::Connector
+        | UserActionMode::Pen
+        | UserActionMode::Sticky
+        | UserActionMode::Frame => {
+            draw_crosshair(renderer, mouse_x, mouse_y);
+            return;
+        }
+        UserActionMode::Text => {
+            draw_text_beam(renderer, mouse_x, mouse_y);
+            return;
+        }
+        UserActionMode::Eraser(erase_mode) => {
+            draw_eraser(renderer, mouse_x, mouse_y, *erase_mode);
+            return;
+        }
     };
-    
-    draw_texture(
-        *cursor_texture,
-        mouse_x - cursor_texture.width() / 2.0,
-        mouse_y - cursor_texture.height() / 2.0,
-        WHITE,
+
+    let size = point(cursor_image.width() as f32, cursor_image.height() as f32);
+    let top_left = point(mouse_x - size.x / 2.0, mouse_y - size.y / 2.0);
+    renderer.draw_image(cursor_image, Rect::from_origin_size(top_left, size));
+}
+
+const CROSSHAIR_SIZE: f32 = 8.0;
+
+fn draw_crosshair(renderer: &mut impl Renderer, x: f32, y: f32) {
+    let stroke = Stroke { color: BLACK, width: 1.0 };
+    renderer.stroke_line(point(x - CROSSHAIR_SIZE, y), point(x + CROSSHAIR_SIZE, y), &stroke);
+    renderer.stroke_line(point(x, y - CROSSHAIR_SIZE), point(x, y + CROSSHAIR_SIZE), &stroke);
+}
+
+/// I-beam showing where text goes.
+fn draw_text_beam(renderer: &mut impl Renderer, x: f32, y: f32) {
+    let stroke = Stroke { color: BLACK, width: 1.0 };
+    let serif = CROSSHAIR_SIZE * 0.4;
+    renderer.stroke_line(point(x, y - CROSSHAIR_SIZE), point(x, y + CROSSHAIR_SIZE), &stroke);
+    for end in [y - CROSSHAIR_SIZE, y + CROSSHAIR_SIZE] {
+        renderer.stroke_line(point(x - serif, end), point(x + serif, end), &stroke);
+    }
+}
+
+/// The eraser's reach, filled when it deletes whole strokes.
+fn draw_eraser(renderer: &mut impl Renderer, x: f32, y: f32, mode: EraseMode) {
+    let reach = Rect::from_corners(
+        point(x - ERASER_RADIUS, y - ERASER_RADIUS),
+        point(x + ERASER_RADIUS, y + ERASER_RADIUS),
     );
+    if mode == EraseMode::Stroke {
+        renderer.fill_ellipse(reach, BLACK.with_alpha(0.15));
+    }
+    renderer.stroke_ellipse(reach, &Stroke { color: BLACK, width: 1.0 });
 }
 
 pub struct Cursors {
-    pub hand: Texture2D,
-    pub grab: Texture2D,
-}
\ No newline at end of file
+    pub hand: Image,
+    pub grab: Image,
+}
+
+impl Cursors {
+    /// Loads the cursor images, paths are given from the root Cargo.toml not package level.
+    pub async fn load() -> Cursors {
+        Cursors {
+            hand: load_cursor("rendering-engine/src/assets/hand_cursor.png").await,
+            grab: load_cursor("rendering-engine/src/assets/grab_cursor.png").await,
+        }
+    }
+}
+
+async fn load_cursor(path: &str) -> Image {
+    let image = load_image(path).await.unwrap();
+    Image::from_rgba(image.width as u32, image.height as u32, image.bytes)
+}
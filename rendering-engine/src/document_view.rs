@@ -0,0 +1,243 @@
+use canvas_document::{
+    connector::{anchor_position, SIDE_ANCHORS},
+    element::TextBlock,
+    image::placement,
+    render::{Path, Stroke},
+    selection::Handle,
+    snap::Guide,
+    style::{Color as DocumentColor, BLACK, WHITE},
+    text::Font,
+    Document, Point, Rect, Renderer, Shape,
+};
+use macroquad::{
+    color::Color,
+    input::mouse_position,
+    math::{vec2, Vec2},
+    time::get_time,
+};
+
+use crate::{
+    camera::Camera,
+    canvas_state::{CanvasState, ShapeDrag},
+    shape_tools::{HANDLE_SIZE, ROTATE_HANDLE_OFFSET},
+};
+
+const SELECTION_COLOR: DocumentColor = DocumentColor::new(0.9, 0.16, 0.22, 1.0);
+const GUIDE_COLOR: DocumentColor = DocumentColor::new(0.95, 0.2, 0.75, 1.0);
+/// Size of the distance labels in screen pixels.
+const GUIDE_LABEL_SIZE: f32 = 14.0;
+/// Length of the ticks at the ends of a measured distance, in screen pixels.
+const GUIDE_TICK: f32 = 4.0;
+const TEXT_SELECTION_COLOR: DocumentColor = DocumentColor::new(0.2, 0.45, 0.95, 0.3);
+/// Width of the text caret in screen pixels.
+const CARET_WIDTH: f32 = 1.5;
+/// How long the caret is shown, and then hidden, while it blinks, in seconds.
+const CARET_BLINK: f64 = 0.5;
+/// Radius of the anchor dots a connector can bind to, in screen pixels.
+const ANCHOR_DOT_RADIUS: f32 = 4.0;
+const FRAME_LABEL_COLOR: DocumentColor = DocumentColor::new(0.45, 0.45, 0.45, 1.0);
+/// Size of frame names in screen pixels, and their gap to the frame.
+const FRAME_LABEL_SIZE: f32 = 12.0;
+const FRAME_LABEL_GAP: f32 = 4.0;
+
+pub fn to_vec2(p: Point) -> Vec2 {
+    vec2(p.x, p.y)
+}
+
+pub fn to_point(v: Vec2) -> Point {
+    canvas_document::point(v.x, v.y)
+}
+
+pub fn to_color(color: DocumentColor, opacity: f32) -> Color {
+    Color::new(color.r, color.g, color.b, color.a * opacity)
+}
+
+/// Outlines of the selected elements, the selection bounds with its handles
+/// and the marquee or lasso being dragged, in screen space.
+pub fn draw_selection(renderer: &mut impl Renderer, document: &Document, state: &CanvasState, camera: &Camera) {
+    let to_screen = |p: Point| to_point(camera.world_to_screen(to_vec2(p)));
+    let thin = Stroke {
+        color: SELECTION_COLOR,
+        width: 1.0,
+    };
+
+    if let ShapeDrag::Marquee { start, .. } = state.shape_drag {
+        let marquee = Rect::from_corners(
+            to_point(camera.world_to_screen(start)),
+            canvas_document::point(mouse_position().0, mouse_position().1),
+        );
+        renderer.fill_rect(marquee, SELECTION_COLOR.with_alpha(0.08));
+        renderer.stroke_rect(marquee, &thin);
+    }
+    if let ShapeDrag::Lasso { points, .. } = &state.shape_drag {
+        let lasso = Path::polygon(&points.iter().map(|&p| to_screen(p)).collect::<Vec<_>>());
+        renderer.fill_path(&lasso, SELECTION_COLOR.with_alpha(0.08));
+        renderer.stroke_path(&lasso, &thin);
+    }
+
+    if let Some(group) = state.selection.scope().and_then(|id| document.get(id)) {
+        // the entered group stays visible as context
+        renderer.stroke_path(
+            &Path::polygon(&group.geometry_bounds().corners().map(to_screen)),
+            &Stroke {
+                color: SELECTION_COLOR.with_alpha(0.4),
+                width: 1.0,
+            },
+        );
+    }
+
+    if let Some(image) = state.cropping.and_then(|id| document.get(id)) {
+        if let Shape::Image { rect, crop, .. } = &image.shape {
+            // the whole picture, so it shows how far the crop can grow
+            let picture = placement(rect, crop).corners().map(|p| to_screen(image.transform.apply(p)));
+            renderer.stroke_path(
+                &Path::polygon(&picture),
+                &Stroke {
+                    color: SELECTION_COLOR.with_alpha(0.4),
+                    width: 1.0,
+                },
+            );
+        }
+    }
+
+    let Some(frame) = state.selection.frame(document) else {
+        return;
+    };
+    if state.selection.ids().len() > 1 {
+        for element in state.selection.ids().iter().filter_map(|&id| document.get(id)) {
+            renderer.stroke_path(&Path::polygon(&element.outline().map(to_screen)), &thin);
+        }
+    }
+    renderer.stroke_path(
+        &Path::polygon(&frame.corners().map(to_screen)),
+        &Stroke {
+            color: SELECTION_COLOR,
+            width: 2.0,
+        },
+    );
+
+    let handle_position = |handle: Handle| to_screen(frame.handle_position(handle, ROTATE_HANDLE_OFFSET / camera.zoom));
+    let top = handle_position(Handle::Top);
+    let rotate = handle_position(Handle::Rotate);
+    renderer.stroke_line(top, rotate, &thin);
+    renderer.fill_circle(rotate, HANDLE_SIZE * 0.5, WHITE);
+    renderer.stroke_ellipse(
+        Rect::from_corners(rotate - half_handle(), rotate + half_handle()),
+        &thin,
+    );
+    for handle in Handle::RESIZE {
+        let center = handle_position(handle);
+        let square = Rect::from_corners(center - half_handle(), center + half_handle());
+        renderer.fill_rect(square, WHITE);
+        renderer.stroke_rect(square, &thin);
+    }
+}
+
+/// Names of the visible frames above their top left corner, in screen space,
+/// so they stay readable at any zoom and don't end up in exports.
+pub fn draw_frame_labels(renderer: &mut impl Renderer, document: &Document, camera: &Camera) {
+    let font = Font::new(FRAME_LABEL_SIZE);
+    for element in document.elements_intersecting(&camera.visible_world_rect()) {
+        let Shape::Frame { name, .. } = &element.shape else {
+            continue;
+        };
+        if !document.is_visible(element.id) {
+            continue;
+        }
+        let corner = to_point(camera.world_to_screen(to_vec2(element.geometry_bounds().min)));
+        let position = corner - canvas_document::point(0.0, font.line_height() + FRAME_LABEL_GAP);
+        renderer.draw_text(name, position, &font, FRAME_LABEL_COLOR);
+    }
+}
+
+fn half_handle() -> Point {
+    canvas_document::point(HANDLE_SIZE * 0.5, HANDLE_SIZE * 0.5)
+}
+
+/// Smart guides of the shape being dragged: lines through what it lines up
+/// with and the measured gaps, in screen space.
+pub fn draw_guides(renderer: &mut impl Renderer, state: &CanvasState, camera: &Camera) {
+    let to_screen = |p: Point| to_point(camera.world_to_screen(to_vec2(p)));
+    let stroke = Stroke {
+        color: GUIDE_COLOR,
+        width: 1.0,
+    };
+    for guide in &state.guides {
+        match *guide {
+            Guide::Line { from, to } => renderer.stroke_line(to_screen(from), to_screen(to), &stroke),
+            Guide::Distance { from, to, distance } => {
+                let (from, to) = (to_screen(from), to_screen(to));
+                renderer.stroke_line(from, to, &stroke);
+                // ticks across the measured span
+                let direction = to - from;
+                let across =
+                    canvas_document::point(-direction.y, direction.x) * (GUIDE_TICK / direction.length().max(1.0));
+                for end in [from, to] {
+                    renderer.stroke_line(end - across, end + across, &stroke);
+                }
+                let label = format!("{}", distance.round());
+                let middle = from.lerp(to, 0.5);
+                renderer.draw_text(&label, middle + across * 1.5, &Font::new(GUIDE_LABEL_SIZE), GUIDE_COLOR);
+            }
+        }
+    }
+}
+
+/// Outline and anchor dots of the element a connector would bind to, in screen space.
+pub fn draw_connector_anchors(renderer: &mut impl Renderer, document: &Document, state: &CanvasState, camera: &Camera) {
+    let Some(element) = state.connect_target.and_then(|id| document.get(id)) else {
+        return;
+    };
+    let to_screen = |p: Point| to_point(camera.world_to_screen(to_vec2(p)));
+    let thin = Stroke {
+        color: SELECTION_COLOR,
+        width: 1.0,
+    };
+    renderer.stroke_path(&Path::polygon(&element.outline().map(to_screen)), &thin);
+    for relative in SIDE_ANCHORS {
+        let center = to_screen(anchor_position(element, relative));
+        renderer.fill_circle(center, ANCHOR_DOT_RADIUS, WHITE);
+        renderer.stroke_path(&Path::circle(center, ANCHOR_DOT_RADIUS), &thin);
+    }
+}
+
+/// Box, selected text and blinking caret of the text being edited, in screen space.
+pub fn draw_text_editing(renderer: &mut impl Renderer, document: &Document, state: &CanvasState, camera: &Camera) {
+    let Some(edit) = &state.text_edit else {
+        return;
+    };
+    let Some(element) = document.get(edit.id) else {
+        return;
+    };
+    let (Some(content), Some(TextBlock { origin, font, layout })) =
+        (element.shape.text_content(), element.shape.text_block())
+    else {
+        return;
+    };
+    let to_screen = |p: Point| to_point(camera.world_to_screen(to_vec2(element.transform.apply(origin + p))));
+
+    let text_box = Rect::from_origin_size(Point::ZERO, layout.size);
+    renderer.stroke_path(
+        &Path::polygon(&text_box.corners().map(to_screen)),
+        &Stroke {
+            color: SELECTION_COLOR.with_alpha(0.5),
+            width: 1.0,
+        },
+    );
+    for rect in layout.selection_rects(content, edit.cursor.selection()) {
+        renderer.fill_path(&Path::polygon(&rect.corners().map(to_screen)), TEXT_SELECTION_COLOR);
+    }
+    // solid while typing, blinking once left alone
+    if (get_time() - edit.moved_at) % (CARET_BLINK * 2.0) < CARET_BLINK {
+        let top = layout.caret_position(content, edit.cursor.caret);
+        let bottom = top + canvas_document::point(0.0, font.line_height());
+        renderer.stroke_line(
+            to_screen(top),
+            to_screen(bottom),
+            &Stroke {
+                color: BLACK,
+                width: CARET_WIDTH,
+            },
+        );
+    }
+}
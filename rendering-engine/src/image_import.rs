@@ -0,0 +1,96 @@
+use std::fs;
+
+use canvas_document::{image::add_image, point, Document, History};
+use egui_macroquad::egui;
+use macroquad::input::{is_key_pressed, KeyCode};
+
+use crate::camera::Camera;
+use crate::canvas_state::CanvasState;
+use crate::keyboard::is_ctrl_down;
+
+/// Largest share of the screen a newly placed image covers.
+const MAX_SCREEN_SHARE: f32 = 0.8;
+
+/// Window to place a PNG or JPEG file on the board, opened with Ctrl+I.
+/// miniquad doesn't report files dropped on the window on desktop, so the
+/// file is picked by its path.
+#[derive(Default)]
+pub struct ImageImport {
+    open: bool,
+    path: String,
+    /// why the last file could not be placed
+    error: Option<String>,
+}
+
+impl ImageImport {
+    pub fn new() -> Self {
+        ImageImport::default()
+    }
+
+    pub fn handle_shortcut(&mut self) {
+        if is_ctrl_down() && is_key_pressed(KeyCode::I) {
+            self.open = true;
+            self.error = None;
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        camera: &Camera,
+        state: &mut CanvasState,
+        document: &mut Document,
+        history: &mut History,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Insert image")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_pos([420.0, 200.0])
+            .show(ctx, |ui| {
+                ui.label("PNG or JPEG file");
+                let response = ui.text_edit_singleline(&mut self.path);
+                let entered = response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                if ui.button("Insert").clicked() || entered {
+                    match self.insert(camera, state, document, history) {
+                        Ok(()) => {
+                            self.open = false;
+                            self.error = None;
+                        }
+                        Err(err) => self.error = Some(err),
+                    }
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+        // closing with the window's button, inserting already closed it
+        self.open &= open;
+    }
+
+    /// Places the file at the center of the screen at its pixel size, scaled
+    /// down when it wouldn't fit, and selects it.
+    fn insert(
+        &self,
+        camera: &Camera,
+        state: &mut CanvasState,
+        document: &mut Document,
+        history: &mut History,
+    ) -> Result<(), String> {
+        let path = self.path.trim();
+        let bytes = fs::read(path).map_err(|err| format!("could not read {}: {}", path, err))?;
+        let visible = camera.visible_world_rect();
+        let id = add_image(
+            history,
+            document,
+            bytes,
+            point(camera.position.x, camera.position.y),
+            1.0 / camera.zoom,
+            point(visible.width(), visible.height()) * MAX_SCREEN_SHARE,
+        )
+        .map_err(|err| format!("could not insert {}: {}", path, err))?;
+        state.selection.set(id);
+        Ok(())
+    }
+}
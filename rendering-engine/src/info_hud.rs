@@ -1,17 +1,19 @@
-use macroquad::{color::BLACK, text::draw_text};
+use canvas_document::{point, style::BLACK, text::Font, CanvasSettings, Renderer};
 
 use crate::camera::Camera;
 
 
-pub fn display_hud(camera: &Camera) {
-    draw_text(
+pub fn display_hud(renderer: &mut impl Renderer, camera: &Camera, settings: &CanvasSettings) {
+    renderer.draw_text(
         &format!(
-            "Camera: ({:.2}, {:.2}), Zoom: {:.2}",
-            camera.position.x, camera.position.y, camera.zoom
+            "Camera: ({:.2}, {:.2}), Zoom: {:.2}, Snap: {}",
+            camera.position.x,
+            camera.position.y,
+            camera.zoom,
+            if settings.snap_to_grid { "on" } else { "off" }
         ),
-        10.0,
-        20.0,
-        20.0,
+        point(10.0, 0.0),
+        &Font::new(20.0),
         BLACK,
     );
-}
\ No newline at end of file
+}
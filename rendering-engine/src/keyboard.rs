@@ -0,0 +1,35 @@
+use canvas_document::{CanvasSettings, Document, History};
+use macroquad::input::{is_key_down, is_key_pressed, KeyCode};
+
+pub fn is_ctrl_down() -> bool {
+    is_key_down(KeyCode::LeftControl)
+        || is_key_down(KeyCode::RightControl)
+        || is_key_down(KeyCode::LeftSuper)
+        || is_key_down(KeyCode::RightSuper)
+}
+
+pub fn is_shift_down() -> bool {
+    is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift)
+}
+
+pub fn is_alt_down() -> bool {
+    is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt)
+}
+
+/// Ctrl+Z undoes, Ctrl+Shift+Z redoes.
+pub fn handle_history_shortcuts(history: &mut History, document: &mut Document) {
+    if is_ctrl_down() && is_key_pressed(KeyCode::Z) {
+        if is_shift_down() {
+            history.redo(document);
+        } else {
+            history.undo(document);
+        }
+    }
+}
+
+/// Ctrl+' turns snapping to the grid on and off.
+pub fn handle_snap_shortcut(settings: &mut CanvasSettings) {
+    if is_ctrl_down() && is_key_pressed(KeyCode::Apostrophe) {
+        settings.snap_to_grid = !settings.snap_to_grid;
+    }
+}
@@ -0,0 +1,188 @@
+use canvas_document::layer::{
+    add_layer, arrange, move_layer, move_to_layer, remove_layer, rename_layer, set_hidden, set_layer_hidden,
+    set_layer_locked, set_locked, LayerId, ZOrder,
+};
+use canvas_document::{Document, Element, ElementId, History, Shape};
+use egui_macroquad::egui;
+use macroquad::input::{is_key_pressed, KeyCode};
+
+use crate::canvas_state::CanvasState;
+use crate::keyboard::{is_ctrl_down, is_shift_down};
+
+/// Layer list top to bottom with their elements, like the layers panel of
+/// other drawing apps. Changes made here can be undone.
+#[derive(Default)]
+pub struct LayersPanel {
+    /// layer whose name is being edited and the edited name
+    renaming: Option<(LayerId, String)>,
+}
+
+impl LayersPanel {
+    pub fn new() -> Self {
+        LayersPanel::default()
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut CanvasState, document: &mut Document, history: &mut History) {
+        egui::Window::new("Layers")
+            .default_pos([940.0, 20.0])
+            .default_width(240.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("+ Layer").clicked() {
+                        let name = format!("Layer {}", document.layers().len() + 1);
+                        add_layer(history, document, &name);
+                    }
+                    let selected = state.selection.ids().to_vec();
+                    ui.add_enabled_ui(!selected.is_empty(), |ui| {
+                        if ui.button("⏶").on_hover_text("Bring forward (Ctrl+])").clicked() {
+                            arrange(history, document, &selected, ZOrder::Forward);
+                        }
+                        if ui.button("⏷").on_hover_text("Send backward (Ctrl+[)").clicked() {
+                            arrange(history, document, &selected, ZOrder::Backward);
+                        }
+                        if ui.button("⏫").on_hover_text("Bring to front (Ctrl+Shift+])").clicked() {
+                            arrange(history, document, &selected, ZOrder::Front);
+                        }
+                        if ui.button("⏬").on_hover_text("Send to back (Ctrl+Shift+[)").clicked() {
+                            arrange(history, document, &selected, ZOrder::Back);
+                        }
+                        if ui.button("→ active").on_hover_text("Move to the active layer").clicked() {
+                            move_to_layer(history, document, &selected, document.active_layer());
+                        }
+                    });
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let layers: Vec<LayerId> = document.layers().iter().rev().map(|layer| layer.id).collect();
+                    for layer in layers {
+                        self.layer_row(ui, state, document, history, layer);
+                    }
+                });
+            });
+    }
+
+    fn layer_row(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &mut CanvasState,
+        document: &mut Document,
+        history: &mut History,
+        id: LayerId,
+    ) {
+        let Some(layer) = document.layer(id).cloned() else {
+            return;
+        };
+        let position = document.layers().iter().position(|layer| layer.id == id).unwrap_or(0);
+        let count = document.layers().len();
+        ui.horizontal(|ui| {
+            if ui.selectable_label(!layer.hidden, "👁").on_hover_text("Show or hide").clicked() {
+                set_layer_hidden(history, document, id, !layer.hidden);
+            }
+            if ui.selectable_label(layer.locked, "🔒").on_hover_text("Lock or unlock").clicked() {
+                set_layer_locked(history, document, id, !layer.locked);
+            }
+            match &mut self.renaming {
+                Some((renaming, name)) if *renaming == id => {
+                    let response = ui.text_edit_singleline(name);
+                    if response.lost_focus() {
+                        rename_layer(history, document, id, name.trim());
+                        self.renaming = None;
+                    } else {
+                        response.request_focus();
+                    }
+                }
+                _ => {
+                    let response = ui.selectable_label(document.active_layer() == id, &layer.name);
+                    if response.clicked() {
+                        document.set_active_layer(id);
+                    }
+                    if response.double_clicked() {
+                        self.renaming = Some((id, layer.name.clone()));
+                    }
+                }
+            }
+            if ui.add_enabled(position + 1 < count, egui::Button::new("⏶")).clicked() {
+                move_layer(history, document, id, position + 1);
+            }
+            if ui.add_enabled(position > 0, egui::Button::new("⏷")).clicked() {
+                move_layer(history, document, id, position - 1);
+            }
+            if ui.add_enabled(count > 1, egui::Button::new("🗑")).clicked() {
+                remove_layer(history, document, id);
+            }
+        });
+
+        let range = document.layer_range(id);
+        let mut elements: Vec<Element> = document.iter().skip(range.start).take(range.len()).cloned().collect();
+        elements.reverse();
+        egui::CollapsingHeader::new(format!("{} elements", elements.len()))
+            .id_source(("layer", id.0))
+            .show(ui, |ui| {
+                for element in &elements {
+                    element_row(ui, state, document, history, element);
+                }
+            });
+    }
+}
+
+fn element_row(
+    ui: &mut egui::Ui,
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+    element: &Element,
+) {
+    ui.horizontal(|ui| {
+        if ui.selectable_label(!element.hidden, "👁").clicked() {
+            set_hidden(history, document, &[element.id], !element.hidden);
+        }
+        if ui.selectable_label(element.locked, "🔒").clicked() {
+            set_locked(history, document, &[element.id], !element.locked);
+        }
+        let selected = state.selection.contains(element.id);
+        if ui.selectable_label(selected, label(element)).clicked() && document.is_pickable(element.id) {
+            if is_shift_down() {
+                state.selection.toggle(element.id);
+            } else {
+                state.selection.set(element.id);
+            }
+        }
+    });
+}
+
+fn label(element: &Element) -> String {
+    let kind = match &element.shape {
+        Shape::Frame { name, .. } => return name.clone(),
+        Shape::Rectangle { .. } => "Rectangle",
+        Shape::Ellipse { .. } => "Ellipse",
+        Shape::Line { .. } => "Line",
+        Shape::Freehand { .. } => "Drawing",
+        Shape::Connector { .. } => "Connector",
+        Shape::Text { .. } => "Text",
+        Shape::Sticky { .. } => "Sticky note",
+        Shape::Image { .. } => "Image",
+        Shape::Group { .. } => "Group",
+    };
+    format!("{kind} #{}", element.id.0)
+}
+
+/// Ctrl+] and Ctrl+[ move the selection one step up or down, with Shift all
+/// the way. Ctrl+Shift+H hides and Ctrl+Shift+L locks the selection.
+pub fn handle_layer_shortcuts(state: &mut CanvasState, document: &mut Document, history: &mut History) {
+    if !is_ctrl_down() || state.selection.is_empty() {
+        return;
+    }
+    let selected: Vec<ElementId> = state.selection.ids().to_vec();
+    let shift = is_shift_down();
+    if is_key_pressed(KeyCode::RightBracket) {
+        arrange(history, document, &selected, if shift { ZOrder::Front } else { ZOrder::Forward });
+    } else if is_key_pressed(KeyCode::LeftBracket) {
+        arrange(history, document, &selected, if shift { ZOrder::Back } else { ZOrder::Backward });
+    } else if shift && is_key_pressed(KeyCode::H) {
+        set_hidden(history, document, &selected, true);
+        state.selection.clear();
+    } else if shift && is_key_pressed(KeyCode::L) {
+        set_locked(history, document, &selected, true);
+        state.selection.clear();
+    }
+}
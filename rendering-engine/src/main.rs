@@ -1,3 +1,4 @@
+use canvas_document::{grid::BACKGROUND_COLOR, scene::draw_board, CanvasSettings, Document, History};
 use canvas_state::CanvasState;
 use cursor::{draw_cursor, handle_cursor, Cursors};
 use macroquad::prelude::*;
@@ -5,10 +6,8 @@ use macroquad::prelude::*;
 mod camera;
 use camera::Camera;
 
-mod grid;
-use grid::draw_grid;
 use scrollbar::{draw_scrollbar, handle_scroll, ScrollBarConfig};
-use user_action_mode::{observe_user_action, UserActionMode};
+use user_action_mode::{handle_mode_shortcuts, observe_user_action, UserActionMode};
 
 mod scrollbar;
 mod user_action_mode;
@@ -16,40 +15,119 @@ mod canvas_state;
 mod cursor;
 mod info_hud;
 use info_hud::display_hud;
+mod document_view;
+use document_view::{draw_connector_anchors, draw_frame_labels, draw_guides, draw_selection, draw_text_editing};
+mod keyboard;
+use keyboard::{handle_history_shortcuts, handle_snap_shortcut};
+mod layers_panel;
+mod toolbar;
+use toolbar::{handle_align_shortcuts, show_toolbar};
+use layers_panel::{handle_layer_shortcuts, LayersPanel};
+mod persistence;
+mod image_import;
+use image_import::ImageImport;
+mod shape_tools;
+mod connector_tool;
+mod text_tool;
+use text_tool::{handle_text_editing, TextInput};
+use persistence::handle_file_shortcuts;
+use shape_tools::{handle_arrowhead_shortcuts, handle_group_shortcuts, handle_transform_shortcuts};
+mod renderer;
+use renderer::MacroquadRenderer;
 
 
 #[macroquad::main(window_conf)]
 async fn main() {
     let mut camera = Camera::new();
     let scroll_bar_config: ScrollBarConfig = ScrollBarConfig::new();
-    let mut canvas_state = CanvasState {
-        is_dragging: false,
-        last_mouse_position: Vec2::ZERO,
+    let mut canvas_state = CanvasState::new();
+    let mut current_user_action_mode = UserActionMode::Drag;
+    let mut document = Document::new();
+    let mut history = History::default();
+    let mut settings = CanvasSettings {
+        background: BACKGROUND_COLOR,
+        ..Default::default()
     };
-    let current_user_action_mode = UserActionMode::DRAG;
 
-    // Load cursor images, path is given from root Cargo.toml not package level
-    let cursors = Cursors {
-        hand: load_texture("rendering-engine/src/assets/hand_cursor.png").await.unwrap(),
-        grab: load_texture("rendering-engine/src/assets/grab_cursor.png").await.unwrap(),
-    };
+    let mut layers_panel = LayersPanel::new();
+    let mut image_import = ImageImport::new();
+    let text_input = TextInput::new();
+    let mut renderer = MacroquadRenderer::new();
+    let cursors = Cursors::load().await;
 
         // Hide the default system cursor
         show_mouse(false);
 
     loop {
-        clear_background(grid::BACKGROUND_COLOR);
-        draw_grid(&camera);
+        draw_board(&mut renderer, &document, &settings, &camera.state(), MacroquadRenderer::viewport());
+        draw_frame_labels(&mut renderer, &document, &camera);
+        draw_selection(&mut renderer, &document, &canvas_state, &camera);
+        draw_guides(&mut renderer, &canvas_state, &camera);
+        draw_text_editing(&mut renderer, &document, &canvas_state, &camera);
+        if matches!(current_user_action_mode, UserActionMode::Connector) {
+            draw_connector_anchors(&mut renderer, &document, &canvas_state, &camera);
+        }
         handle_scroll(&mouse_wheel(), &mut camera);
-        draw_scrollbar(&scroll_bar_config, &camera);
+        draw_scrollbar(&mut renderer, &scroll_bar_config, &camera);
+
+        // the panel takes the input it is used for, the canvas gets the rest
+        let (mut panel_has_pointer, mut panel_has_keyboard) = (false, false);
+        egui_macroquad::ui(|egui_ctx| {
+            show_toolbar(egui_ctx, &mut canvas_state, &mut document, &mut history);
+            layers_panel.show(egui_ctx, &mut canvas_state, &mut document, &mut history);
+            image_import.show(egui_ctx, &camera, &mut canvas_state, &mut document, &mut history);
+            panel_has_pointer = egui_ctx.wants_pointer_input() || egui_ctx.is_pointer_over_area();
+            panel_has_keyboard = egui_ctx.wants_keyboard_input();
+        });
 
-        canvas_state = observe_user_action(&mut camera, &current_user_action_mode, canvas_state);
+        if !panel_has_keyboard {
+            handle_file_shortcuts(
+                &mut document,
+                &mut history,
+                &mut camera,
+                &mut settings,
+                canvas_state.selection.ids(),
+            );
+            handle_history_shortcuts(&mut history, &mut document);
+            handle_snap_shortcut(&mut settings);
+        }
+        canvas_state.selection.retain_existing(&document);
+        // typing into text takes the keys, and the mouse while it is over the text
+        let typed = text_input.take();
+        let editing_text = handle_text_editing(
+            &camera,
+            &mut canvas_state,
+            &mut document,
+            &mut history,
+            if panel_has_keyboard { &[] } else { &typed },
+            !panel_has_pointer,
+        );
+        if !panel_has_keyboard && !editing_text {
+            handle_mode_shortcuts(&mut current_user_action_mode);
+            handle_arrowhead_shortcuts(&mut canvas_state, &mut document, &mut history);
+            handle_transform_shortcuts(&canvas_state, &mut document, &mut history);
+            handle_group_shortcuts(&mut canvas_state, &mut document, &mut history);
+            handle_layer_shortcuts(&mut canvas_state, &mut document, &mut history);
+            handle_align_shortcuts(&canvas_state, &mut document, &mut history);
+            image_import.handle_shortcut();
+        }
+        if !panel_has_pointer && !editing_text {
+            canvas_state = observe_user_action(
+                &mut camera,
+                &current_user_action_mode,
+                &settings,
+                canvas_state,
+                &mut document,
+                &mut history,
+            );
+        }
+        egui_macroquad::draw();
 
         // Draw the appropriate cursor
-        draw_cursor(&current_user_action_mode, &canvas_state, &cursors);
+        draw_cursor(&mut renderer, &current_user_action_mode, &canvas_state, &cursors);
         // Handle cursor visibility and drawing
-        handle_cursor(&current_user_action_mode, &canvas_state, &cursors);
-        display_hud(&camera);
+        handle_cursor(&mut renderer, &current_user_action_mode, &canvas_state, &cursors);
+        display_hud(&mut renderer, &camera, &settings);
 
         next_frame().await
     }
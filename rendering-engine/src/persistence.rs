@@ -0,0 +1,117 @@
+use std::{error::Error, fs};
+
+use canvas_document::{
+    frame::is_frame,
+    svg::{export_svg, ExportArea, SvgOptions},
+    CanvasSettings, Document, ElementId, History, SavedCanvas,
+};
+use canvas_raster::{render_png, RasterOptions};
+use macroquad::input::{is_key_down, is_key_pressed, KeyCode};
+
+use crate::{
+    camera::Camera,
+    keyboard::{is_ctrl_down, is_shift_down},
+};
+
+/// File used by Ctrl+S / Ctrl+O, relative to the working directory like the cursor assets.
+const CANVAS_FILE: &str = "canvas.json";
+/// File written by the Ctrl+E exports.
+const SVG_FILE: &str = "canvas.svg";
+/// File written by the Ctrl+P exports.
+const PNG_FILE: &str = "canvas.png";
+/// PNG exports are rendered at twice the world size so they stay sharp on high DPI screens.
+const PNG_SCALE: f32 = 2.0;
+
+/// Ctrl+S writes the canvas to disk, Ctrl+O replaces the current canvas with the saved one.
+/// Ctrl+E exports the board as SVG and Ctrl+P as PNG, with Shift only the selection, or the
+/// selected frame cropped to its edges, and with Alt only what is on screen.
+pub fn handle_file_shortcuts(
+    document: &mut Document,
+    history: &mut History,
+    camera: &mut Camera,
+    settings: &mut CanvasSettings,
+    selected: &[ElementId],
+) {
+    if !is_ctrl_down() {
+        return;
+    }
+    if is_key_pressed(KeyCode::S) {
+        match save_canvas(CANVAS_FILE, document, camera, settings) {
+            Ok(()) => println!("saved canvas to {}", CANVAS_FILE),
+            Err(err) => eprintln!("could not save {}: {}", CANVAS_FILE, err),
+        }
+    }
+    if is_key_pressed(KeyCode::O) {
+        match load_canvas(CANVAS_FILE) {
+            Ok(saved) => {
+                *document = saved.document;
+                history.clear();
+                camera.restore(&saved.camera);
+                *settings = saved.settings;
+            }
+            Err(err) => eprintln!("could not open {}: {}", CANVAS_FILE, err),
+        }
+    }
+    if is_key_pressed(KeyCode::E) {
+        let options = SvgOptions {
+            area: export_area(camera, document, selected),
+            background: Some(settings.background),
+            ..SvgOptions::default()
+        };
+        match fs::write(SVG_FILE, export_svg(document, &options)) {
+            Ok(()) => println!("exported {}", SVG_FILE),
+            Err(err) => eprintln!("could not export {}: {}", SVG_FILE, err),
+        }
+    }
+    if is_key_pressed(KeyCode::P) {
+        let options = RasterOptions {
+            area: export_area(camera, document, selected),
+            scale: PNG_SCALE,
+            background: Some(settings.background),
+            show_grid: settings.show_grid,
+            ..RasterOptions::default()
+        };
+        match export_png(PNG_FILE, document, &options) {
+            Ok(()) => println!("exported {}", PNG_FILE),
+            Err(err) => eprintln!("could not export {}: {}", PNG_FILE, err),
+        }
+    }
+}
+
+fn export_area(camera: &Camera, document: &Document, selected: &[ElementId]) -> ExportArea {
+    if is_shift_down() {
+        match selected {
+            [id] if is_frame(document, *id) => ExportArea::Frame(*id),
+            _ => ExportArea::Selection(selected.to_vec()),
+        }
+    } else if is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt) {
+        ExportArea::Viewport(camera.visible_world_rect())
+    } else {
+        ExportArea::Board
+    }
+}
+
+pub fn save_canvas(
+    path: &str,
+    document: &Document,
+    camera: &Camera,
+    settings: &CanvasSettings,
+) -> Result<(), Box<dyn Error>> {
+    let saved = SavedCanvas {
+        document: document.clone(),
+        camera: camera.state(),
+        settings: settings.clone(),
+    };
+    fs::write(path, saved.to_json()?)?;
+    Ok(())
+}
+
+pub fn load_canvas(path: &str) -> Result<SavedCanvas, Box<dyn Error>> {
+    let json = fs::read_to_string(path)?;
+    Ok(SavedCanvas::from_json(&json)?)
+}
+
+pub fn export_png(path: &str, document: &Document, options: &RasterOptions) -> Result<(), Box<dyn Error>> {
+    fs::write(path, render_png(document, options)?)?;
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use canvas_document::{
+    geometry::triangulate,
+    mipmap::fit_within,
+    render::{Image, Path, Stroke},
+    text::Font,
+    Affine, Color, Point, Rect, Renderer,
+};
+use macroquad::{
+    math::Vec2,
+    prelude::{clear_background, draw_texture_ex, DrawTextureParams, Texture2D, WHITE},
+    shapes::{draw_circle, draw_line, draw_triangle},
+    miniquad::gl::{glGetIntegerv, GL_MAX_TEXTURE_SIZE},
+    text::{draw_text_ex, TextParams},
+    window::{get_internal_gl, screen_height, screen_width},
+};
+
+use crate::document_view::{to_color, to_vec2};
+
+/// Largest distance in pixels a flattened curve may stray from the real one.
+const FLATTEN_TOLERANCE: f32 = 0.25;
+
+/// Text smaller than this many pixels isn't drawn at all.
+const MIN_TEXT_SIZE: f32 = 1.0;
+/// Largest pixel size glyphs are rasterized at.
+const MAX_GLYPH_SIZE: f32 = 256.0;
+/// Offset of the second pass of bold glyphs, relative to the font size.
+const BOLD_SHIFT: f32 = 0.06;
+/// Texture size assumed when the driver doesn't report its limit.
+const FALLBACK_TEXTURE_SIZE: u32 = 2048;
+
+/// Screen space scissor rect as x, y, width, height.
+type Scissor = (i32, i32, i32, i32);
+
+/// Draws through macroquad's immediate mode shapes. Paths are flattened and
+/// transformed on the CPU, fills are triangulated.
+pub struct MacroquadRenderer {
+    transform: Affine,
+    clip: Option<Scissor>,
+    saved: Vec<(Affine, Option<Scissor>)>,
+    textures: HashMap<u64, Texture2D>,
+    /// longest side of a texture the GPU takes, larger images are halved to fit
+    max_texture_size: u32,
+}
+
+impl MacroquadRenderer {
+    pub fn new() -> Self {
+        MacroquadRenderer {
+            transform: Affine::IDENTITY,
+            clip: None,
+            saved: Vec::new(),
+            textures: HashMap::new(),
+            max_texture_size: max_texture_size(),
+        }
+    }
+
+    pub fn viewport() -> Point {
+        canvas_document::point(screen_width(), screen_height())
+    }
+
+    fn to_screen(&self, p: Point) -> Vec2 {
+        to_vec2(self.transform.apply(p))
+    }
+
+    /// Rotation of the current transform and whether it mirrors, for the
+    /// macroquad calls that only know an angle and a flip.
+    fn orientation(&self) -> (f32, bool) {
+        let t = &self.transform;
+        if t.a * t.d - t.b * t.c < 0.0 {
+            // mirrored along the x axis first, then rotated
+            ((-t.b).atan2(-t.a), true)
+        } else {
+            (t.rotation(), false)
+        }
+    }
+
+    fn set_scissor(&self) {
+        unsafe { get_internal_gl() }.quad_gl.scissor(self.clip);
+    }
+}
+
+impl Renderer for MacroquadRenderer {
+    fn save(&mut self) {
+        self.saved.push((self.transform, self.clip));
+    }
+
+    fn restore(&mut self) {
+        if let Some((transform, clip)) = self.saved.pop() {
+            self.transform = transform;
+            self.clip = clip;
+            self.set_scissor();
+        }
+    }
+
+    fn transform(&mut self, transform: &Affine) {
+        self.transform = transform.then(&self.transform);
+    }
+
+    fn clip_rect(&mut self, rect: Rect) {
+        let mut screen = self.transform.transform_rect(&rect);
+        if let Some((x, y, w, h)) = self.clip {
+            let current = Rect::from_origin_size(
+                canvas_document::point(x as f32, y as f32),
+                canvas_document::point(w as f32, h as f32),
+            );
+            // nested clips only ever shrink, down to nothing
+            screen = screen.intersection(&current).unwrap_or(Rect {
+                min: screen.min,
+                max: screen.min,
+            });
+        }
+        self.clip = Some((
+            screen.min.x.floor() as i32,
+            screen.min.y.floor() as i32,
+            screen.width().ceil() as i32,
+            screen.height().ceil() as i32,
+        ));
+        self.set_scissor();
+    }
+
+    fn clear(&mut self, color: Color) {
+        clear_background(to_color(color, 1.0));
+    }
+
+    fn fill_path(&mut self, path: &Path, color: Color) {
+        let color = to_color(color, 1.0);
+        let tolerance = FLATTEN_TOLERANCE / self.transform.scale_factor().max(f32::EPSILON);
+        for polyline in path.flatten(tolerance) {
+            let points: Vec<Point> = polyline.points.iter().map(|&p| self.transform.apply(p)).collect();
+            for [a, b, c] in triangulate(&points) {
+                draw_triangle(to_vec2(points[a]), to_vec2(points[b]), to_vec2(points[c]), color);
+            }
+        }
+    }
+
+    fn stroke_path(&mut self, path: &Path, stroke: &Stroke) {
+        let color = to_color(stroke.color, 1.0);
+        let scale = self.transform.scale_factor();
+        let tolerance = FLATTEN_TOLERANCE / scale.max(f32::EPSILON);
+        for polyline in path.flatten(tolerance) {
+            let points: Vec<Vec2> = polyline.points.iter().map(|&p| self.to_screen(p)).collect();
+            draw_polyline(&points, polyline.closed, stroke.width * scale, color);
+        }
+    }
+
+    /// Every family draws with macroquad's built in font, spaced by the
+    /// layout's advances. Bold draws each glyph twice, a little apart, and
+    /// italic stays upright since glyphs can't be slanted here.
+    fn draw_text(&mut self, text: &str, position: Point, font: &Font, color: Color) {
+        let scale = self.transform.scale_factor();
+        let size = font.size * scale;
+        if size < MIN_TEXT_SIZE {
+            return;
+        }
+        // glyphs are cached for every pixel size, so zooming far in scales
+        // the largest one instead of filling the atlas
+        let raster_size = size.round().min(MAX_GLYPH_SIZE);
+        let (rotation, mirrored) = self.orientation();
+        let params = TextParams {
+            font_size: raster_size as u16,
+            font_scale: size / raster_size,
+            font_scale_aspect: if mirrored { -1.0 } else { 1.0 },
+            rotation,
+            color: to_color(color, 1.0),
+            ..Default::default()
+        };
+        // in world units, at least a pixel apart
+        let bold_shift = (BOLD_SHIFT * size).max(1.0) / scale;
+        let shifts: &[f32] = if font.bold { &[0.0, bold_shift] } else { &[0.0] };
+        let mut buffer = [0; 4];
+        for (c, offset) in font.glyphs(text) {
+            for shift in shifts {
+                // draw_text positions on the baseline, which turns with the text
+                let baseline = self.to_screen(position + canvas_document::point(offset + shift, font.size));
+                draw_text_ex(c.encode_utf8(&mut buffer), baseline.x, baseline.y, params);
+            }
+        }
+    }
+
+    fn draw_image(&mut self, image: &Image, dest: Rect) {
+        let max_texture_size = self.max_texture_size;
+        let texture = *self.textures.entry(image.id()).or_insert_with(|| {
+            let fitted = fit_within(image, max_texture_size);
+            let image = fitted.as_ref().unwrap_or(image);
+            let side = |length: u32| u16::try_from(length).expect("fits within the texture size");
+            Texture2D::from_rgba8(side(image.width()), side(image.height()), image.pixels())
+        });
+        // macroquad turns the texture around the center of its unrotated rect
+        let axis_length = |x: f32, y: f32| self.transform.apply_vector(canvas_document::point(x, y)).length();
+        let size = to_vec2(dest.size()) * Vec2::new(axis_length(1.0, 0.0), axis_length(0.0, 1.0));
+        let top_left = self.to_screen(dest.center()) - size * 0.5;
+        let (rotation, flip_x) = self.orientation();
+        draw_texture_ex(
+            texture,
+            top_left.x,
+            top_left.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(size),
+                rotation,
+                flip_x,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// The GPU's texture size limit, at most what macroquad's `u16` sizes hold.
+fn max_texture_size() -> u32 {
+    let mut size = 0;
+    // macroquad has made its GL context current before `main` runs
+    unsafe { glGetIntegerv(GL_MAX_TEXTURE_SIZE, &mut size) };
+    match u32::try_from(size) {
+        Ok(size) if size > 0 => size.min(u16::MAX as u32),
+        _ => FALLBACK_TEXTURE_SIZE,
+    }
+}
+
+/// Connected line segments with round joins so thick strokes don't show gaps.
+fn draw_polyline(points: &[Vec2], closed: bool, thickness: f32, color: macroquad::color::Color) {
+    if let [only] = points {
+        draw_circle(only.x, only.y, thickness * 0.5, color);
+        return;
+    }
+    if let Some(first) = points.first() {
+        draw_circle(first.x, first.y, thickness * 0.5, color);
+    }
+    for pair in points.windows(2) {
+        draw_line(pair[0].x, pair[0].y, pair[1].x, pair[1].y, thickness, color);
+        draw_circle(pair[1].x, pair[1].y, thickness * 0.5, color);
+    }
+    if let (true, Some(first), Some(last)) = (closed, points.first(), points.last()) {
+        draw_line(last.x, last.y, first.x, first.y, thickness, color);
+    }
+}
@@ -1,5 +1,6 @@
+use canvas_document::{point, Color, Rect, Renderer};
 use macroquad::{
-    color::Color, input::{is_key_down, mouse_position, KeyCode}, math::Vec2, shapes::draw_rectangle, window::{screen_height, screen_width}
+    input::{is_key_down, mouse_position, KeyCode}, math::Vec2, window::{screen_height, screen_width}
 };
 
 use crate::camera::Camera;
@@ -29,7 +30,7 @@ impl ScrollBarConfig {
 
 /// The scrollbar has to display relative to how much in an infinite plane the user has scrolled
 /// 
-pub fn draw_scrollbar(scroll_bar_config: &ScrollBarConfig, camera: &Camera) {
+pub fn draw_scrollbar(renderer: &mut impl Renderer, scroll_bar_config: &ScrollBarConfig, camera: &Camera) {
     let scrollbar_width: f32 = scroll_bar_config.scrollbar_width;
     let background_color: Color = scroll_bar_config.background_color;
     let scrollbar_foreground_color: Color = scroll_bar_config.foreground_color;
@@ -41,18 +42,18 @@ pub fn draw_scrollbar(scroll_bar_config: &ScrollBarConfig, camera: &Camera) {
         (camera.position.y % visible_area_height + visible_area_height) % visible_area_height;
     let scrollbar_position: f32 =
         (normalized_scroll_pos / scroll_range) * (screen_height() - scrollbar_height);
-    draw_rectangle(
-        screen_width() - scrollbar_width,
-        0.0,
-        scrollbar_width,
-        screen_height(),
+    renderer.fill_rect(
+        Rect::from_origin_size(
+            point(screen_width() - scrollbar_width, 0.0),
+            point(scrollbar_width, screen_height()),
+        ),
         background_color,
     );
-    draw_rectangle(
-        screen_width() - scrollbar_width,
-        scrollbar_position,
-        scrollbar_width,
-        scrollbar_height,
+    renderer.fill_rect(
+        Rect::from_origin_size(
+            point(screen_width() - scrollbar_width, scrollbar_position),
+            point(scrollbar_width, scrollbar_height),
+        ),
         scrollbar_foreground_color,
     );
 }
@@ -0,0 +1,596 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use canvas_document::{
+    arrow::Arrowhead,
+    eraser::{erase, EraseMode},
+    frame::{adopt_contained, next_frame_name, place_in_frames, FRAME_SIZE},
+    freehand,
+    group::{group_elements, ungroup},
+    geometry::{constrain_angle, constrain_square, Affine},
+    image::{crop_to, is_image},
+    selection::{resize_bounds, rotation_angle, Handle, MarqueeMode},
+    snap::Snapper,
+    sticky::STICKY_SIZE,
+    style::{BLACK, WHITE},
+    text::TextCursor,
+    CanvasSettings, Color, Document, Element, ElementId, History, Point, Rect, Shape, Style,
+};
+use macroquad::{
+    input::{
+        is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, mouse_position, KeyCode,
+        MouseButton,
+    },
+    math::Vec2,
+    rand,
+    time::get_time,
+};
+
+use crate::{
+    camera::Camera,
+    canvas_state::{CanvasState, ShapeDrag},
+    document_view::{to_point, to_vec2},
+    keyboard::{is_alt_down, is_ctrl_down, is_shift_down},
+    text_tool::{begin_text_editing, edit_text_at_mouse},
+};
+
+/// Width and height of shapes placed with a click, in world units.
+const MIN_SHAPE_SIZE: f32 = 60.0;
+/// Size of the selection handles in screen pixels, they don't scale with zoom.
+pub const HANDLE_SIZE: f32 = 8.0;
+/// Distance of the rotation handle above the selection, in screen pixels.
+pub const ROTATE_HANDLE_OFFSET: f32 = 24.0;
+/// How far from a thin shape a click still picks it, in screen pixels.
+pub const PICK_TOLERANCE: f32 = 4.0;
+/// Pen stroke width in world units.
+const PEN_WIDTH: f32 = 2.0;
+/// Radius of the eraser in screen pixels.
+pub const ERASER_RADIUS: f32 = 8.0;
+/// Line, arrow and connector width in world units.
+pub const LINE_WIDTH: f32 = 2.0;
+/// Outline of new frames.
+const FRAME_STROKE: Color = Color::new(0.8, 0.8, 0.8, 1.0);
+/// How far the mouse has to move, in screen pixels, before a press becomes a drag.
+pub const DRAG_THRESHOLD: f32 = 3.0;
+/// Longest time between the clicks of a double click, in seconds.
+const DOUBLE_CLICK_TIME: f64 = 0.4;
+
+/// Snaps to the elements on screen other than the `dragged` ones, and to the
+/// grid when that is on. Holding Ctrl bypasses both.
+pub fn snapper(camera: &Camera, settings: &CanvasSettings, document: &Document, dragged: &[ElementId]) -> Snapper {
+    if is_ctrl_down() {
+        return Snapper::disabled();
+    }
+    Snapper::new(settings.snap_to_grid, camera.zoom).with_guides(document, &camera.visible_world_rect(), dragged)
+}
+
+fn mouse_world_position(camera: &Camera) -> Vec2 {
+    camera.screen_to_world(mouse_position().into())
+}
+
+/// The selected elements, to edit from while a handle is dragged.
+fn selected_elements(state: &CanvasState, document: &Document) -> Vec<Element> {
+    state
+        .selection
+        .ids()
+        .iter()
+        .filter_map(|&id| document.get(id))
+        .cloned()
+        .collect()
+}
+
+/// Whether this press is the second click of a double click.
+pub fn is_double_click(state: &mut CanvasState) -> bool {
+    let (now, position) = (get_time(), Vec2::from(mouse_position()));
+    let double = state.last_click.is_some_and(|(time, last)| {
+        now - time <= DOUBLE_CLICK_TIME && last.distance(position) <= DRAG_THRESHOLD
+    });
+    // a third click starts over
+    state.last_click = (!double).then_some((now, position));
+    double
+}
+
+/// Click picks the topmost shape and Shift-click toggles it. Dragging moves the
+/// selection, its handles resize (Alt from the center, Shift keeping the aspect
+/// ratio) or rotate it, and dragging on empty space draws a marquee. Moves and
+/// resizes snap to other elements and the grid unless Ctrl is held.
+/// Elements dropped on a frame join it and ones dragged off leave it.
+/// Double-clicking a group enters it so its children can be picked, clicking
+/// outside of it or Escape leaves it again. Double-clicking text or a sticky
+/// note edits it, double-clicking an image crops it: its handles then move
+/// its edges over the picture until Escape or a click elsewhere.
+pub fn handle_select(
+    camera: &Camera,
+    settings: &CanvasSettings,
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+) {
+    let mouse_world = mouse_world_position(camera);
+    let bounds = state.selection.bounds(document);
+
+    if state.cropping.is_some_and(|id| state.selection.ids() != [id]) {
+        state.cropping = None;
+    }
+    if is_key_pressed(KeyCode::Escape) && state.cropping.take().is_none() && !state.selection.leave() {
+        state.selection.clear();
+    }
+
+    if is_mouse_button_pressed(MouseButton::Left) {
+        let double_click = is_double_click(state);
+        let frame = state.selection.frame(document);
+        let handle = frame.and_then(|frame| {
+            frame.handle_at(
+                to_point(mouse_world),
+                HANDLE_SIZE / camera.zoom,
+                ROTATE_HANDLE_OFFSET / camera.zoom,
+            )
+        });
+        if handle.is_none() {
+            state.cropping = None;
+        }
+        state.shape_drag = match (handle, frame) {
+            (Some(Handle::Rotate), Some(frame)) => ShapeDrag::Rotating {
+                center: frame.center(),
+                start: to_point(mouse_world),
+                elements: selected_elements(state, document),
+            },
+            (Some(handle), Some(frame)) => ShapeDrag::Resizing {
+                handle,
+                frame,
+                elements: selected_elements(state, document),
+            },
+            _ => match pick(state, document, to_point(mouse_world), PICK_TOLERANCE / camera.zoom, double_click) {
+                Some(id) if double_click && edit_text_at_mouse(camera, state, document, history, id) => return,
+                Some(id) if double_click && is_image(document, id) => {
+                    state.selection.set(id);
+                    state.cropping = Some(id);
+                    return;
+                }
+                Some(id) => {
+                    if is_shift_down() {
+                        state.selection.toggle(id);
+                    } else if !state.selection.contains(id) {
+                        state.selection.set(id);
+                    }
+                    match state.selection.bounds(document) {
+                        Some(bounds) if state.selection.contains(id) => ShapeDrag::Moving {
+                            grab_offset: mouse_world - to_vec2(bounds.min),
+                        },
+                        _ => ShapeDrag::None,
+                    }
+                }
+                None => {
+                    if !is_shift_down() {
+                        state.selection.clear();
+                    }
+                    ShapeDrag::Marquee {
+                        start: mouse_world,
+                        base: state.selection.ids().to_vec(),
+                    }
+                }
+            },
+        };
+        history.begin_gesture();
+    }
+
+    if is_mouse_button_down(MouseButton::Left) {
+        match &state.shape_drag {
+            ShapeDrag::Moving { grab_offset } => {
+                if let Some(bounds) = bounds {
+                    let snapper = snapper(camera, settings, document, state.selection.ids());
+                    let snapped = snapper.snap_translation(&bounds, to_point(mouse_world - *grab_offset));
+                    if snapped.value != Point::ZERO {
+                        history.translate(document, state.selection.ids(), snapped.value);
+                    }
+                    state.guides = snapped.guides;
+                }
+            }
+            ShapeDrag::Resizing { handle, frame, elements } => {
+                // handles are dragged in the frame's own coordinates, so a
+                // rotated element stretches along its own axes
+                let snapper = snapper(camera, settings, document, state.selection.ids());
+                let pointer = frame.to_local(snapper.point(to_point(mouse_world)));
+                let target = resize_bounds(&frame.rect, *handle, pointer, is_alt_down(), is_shift_down());
+                // guides only make sense along the world axes
+                state.guides = match frame.transform.is_identity() {
+                    true => snapper.guides(&target),
+                    false => Vec::new(),
+                };
+                let stretch = Affine::rect_to_rect(&frame.rect, &target);
+                for original in elements {
+                    let mut resized = original.clone();
+                    match elements.len() {
+                        1 if state.cropping == Some(original.id) => crop_to(&mut resized.shape, target),
+                        1 => resized.shape.fit_to(target),
+                        _ => resized.transform_by(&stretch),
+                    }
+                    history.update(document, original.id, |element| *element = resized);
+                }
+            }
+            ShapeDrag::Rotating { center, start, elements } => {
+                let angle = rotation_angle(*center, *start, to_point(mouse_world), is_shift_down());
+                let turn = Affine::around(*center, &Affine::rotate(angle));
+                for original in elements {
+                    let mut rotated = original.clone();
+                    rotated.transform_by(&turn);
+                    history.update(document, original.id, |element| *element = rotated);
+                }
+            }
+            ShapeDrag::Marquee { start, base } => {
+                let (start, end) = (to_point(*start), to_point(mouse_world));
+                let marquee = Rect::from_corners(start, end);
+                state.selection.select_marquee(document, base, &marquee, MarqueeMode::for_drag(start, end));
+            }
+            ShapeDrag::Drawing { .. }
+            | ShapeDrag::Lasso { .. }
+            | ShapeDrag::Erasing { .. }
+            | ShapeDrag::Creating { .. }
+            | ShapeDrag::Connecting { .. }
+            | ShapeDrag::None => {}
+        }
+    } else {
+        if matches!(state.shape_drag, ShapeDrag::Moving { .. }) {
+            place_in_frames(history, document, state.selection.ids());
+        }
+        state.shape_drag = ShapeDrag::None;
+        state.guides.clear();
+        history.end_gesture(document);
+    }
+
+    if is_key_pressed(KeyCode::Delete) || is_key_pressed(KeyCode::Backspace) {
+        history.begin_gesture();
+        for &id in state.selection.ids() {
+            history.remove(document, id);
+        }
+        history.end_gesture(document);
+        state.selection.clear();
+    }
+}
+
+/// Element a click at `p` picks. Clicking outside of the entered group leaves
+/// it, double-clicking a group enters it and picks the child under `p`.
+fn pick(state: &mut CanvasState, document: &Document, p: Point, tolerance: f32, double_click: bool) -> Option<ElementId> {
+    let mut hit = state.selection.element_at(document, p, tolerance);
+    while hit.is_none() && state.selection.leave() {
+        hit = state.selection.element_at(document, p, tolerance);
+    }
+    if let Some(group) = hit.filter(|&id| double_click && document.get(id).is_some_and(|e| e.is_group())) {
+        state.selection.enter(group);
+        hit = state.selection.element_at(document, p, tolerance);
+    }
+    hit
+}
+
+/// Ctrl+G groups the selection, Ctrl+Shift+G breaks the selected groups up again.
+pub fn handle_group_shortcuts(state: &mut CanvasState, document: &mut Document, history: &mut History) {
+    if !is_ctrl_down() || !is_key_pressed(KeyCode::G) {
+        return;
+    }
+    if is_shift_down() {
+        let groups: Vec<ElementId> = state.selection.ids().to_vec();
+        state.selection.clear();
+        for group in groups {
+            for child in ungroup(history, document, group) {
+                state.selection.add(child);
+            }
+        }
+    } else if let Some(group) = group_elements(history, document, state.selection.ids()) {
+        state.selection.set(group);
+    }
+}
+
+fn random_fill() -> Style {
+    Style::filled(Color::new(
+        rand::gen_range(0.0, 1.0),
+        rand::gen_range(0.0, 1.0),
+        rand::gen_range(0.0, 1.0),
+        0.5,
+    ))
+}
+
+/// Shapes drawn by dragging from one corner or end to the other.
+#[derive(Clone, Copy)]
+pub enum CreateTool {
+    Rectangle,
+    Ellipse,
+    Line,
+    Arrow,
+    Sticky,
+    Frame,
+}
+
+impl CreateTool {
+    /// Shape spanning from `anchor` to `current`. `constrain` makes rects
+    /// square and snaps lines to multiples of 45 degrees. New frames are
+    /// named by `handle_create`.
+    fn shape(self, anchor: Point, current: Point, constrain: bool, state: &CanvasState) -> Shape {
+        match self {
+            CreateTool::Rectangle | CreateTool::Ellipse | CreateTool::Sticky | CreateTool::Frame => {
+                let corner = if constrain { constrain_square(anchor, current) } else { current };
+                let rect = Rect::from_corners(anchor, corner);
+                match self {
+                    CreateTool::Rectangle => Shape::Rectangle { rect },
+                    CreateTool::Ellipse => Shape::Ellipse { rect },
+                    CreateTool::Sticky => Shape::Sticky {
+                        rect,
+                        content: String::new(),
+                        color: state.sticky_color,
+                    },
+                    _ => Shape::frame(rect, ""),
+                }
+            }
+            CreateTool::Line | CreateTool::Arrow => {
+                let end = if constrain { constrain_angle(anchor, current, FRAC_PI_4) } else { current };
+                let (start_head, end_head) = match self {
+                    CreateTool::Arrow => state.arrowheads,
+                    _ => (Arrowhead::None, Arrowhead::None),
+                };
+                Shape::Line {
+                    start: anchor,
+                    end,
+                    start_head,
+                    end_head,
+                }
+            }
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            CreateTool::Rectangle | CreateTool::Ellipse => random_fill(),
+            CreateTool::Line | CreateTool::Arrow => Style::stroked(BLACK, LINE_WIDTH),
+            // notes paint their own color
+            CreateTool::Sticky => Style { fill: None, stroke: None, ..Style::default() },
+            CreateTool::Frame => Style {
+                fill: Some(WHITE),
+                ..Style::stroked(FRAME_STROKE, 1.0)
+            },
+        }
+    }
+
+    /// Size of the shape a click places, lines need a drag.
+    fn click_size(self) -> Option<Point> {
+        match self {
+            CreateTool::Rectangle | CreateTool::Ellipse => {
+                Some(canvas_document::point(MIN_SHAPE_SIZE, MIN_SHAPE_SIZE))
+            }
+            CreateTool::Sticky => Some(canvas_document::point(STICKY_SIZE, STICKY_SIZE)),
+            CreateTool::Frame => Some(FRAME_SIZE),
+            CreateTool::Line | CreateTool::Arrow => None,
+        }
+    }
+}
+
+/// Adds the new `shape`, a frame gets the next free name.
+fn add_created(history: &mut History, document: &mut Document, tool: CreateTool, mut shape: Shape) -> ElementId {
+    if let Shape::Frame { name, .. } = &mut shape {
+        *name = next_frame_name(document);
+    }
+    history.add(document, shape, tool.style())
+}
+
+/// Sets the new element `id` into the board: a frame takes in what it was
+/// drawn around, anything else joins the frame it was drawn on and a sticky
+/// note is typed into right away. Ends the gesture unless typing goes on.
+fn finish_created(
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+    tool: CreateTool,
+    id: ElementId,
+) {
+    match tool {
+        CreateTool::Frame => adopt_contained(history, document, id),
+        _ => place_in_frames(history, document, &[id]),
+    }
+    if let CreateTool::Sticky = tool {
+        begin_text_editing(state, history, id, TextCursor::new(0));
+        return;
+    }
+    state.selection.set(id);
+    history.end_gesture(document);
+}
+
+/// Dragging draws a new shape, Shift constrains it. A click without a drag
+/// places a rectangle, ellipse, sticky note or frame of the default size.
+/// Both ends snap to other elements and the grid unless Ctrl is held.
+pub fn handle_create(
+    camera: &Camera,
+    settings: &CanvasSettings,
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+    tool: CreateTool,
+) {
+    let mouse_world = mouse_world_position(camera);
+    if is_mouse_button_pressed(MouseButton::Left) {
+        state.shape_drag = ShapeDrag::Creating { anchor: mouse_world, id: None };
+    }
+    let ShapeDrag::Creating { anchor, id } = state.shape_drag else {
+        return;
+    };
+    let snapper = snapper(camera, settings, document, id.as_slice());
+
+    if is_mouse_button_down(MouseButton::Left) {
+        let start = snapper.point(to_point(anchor));
+        let end = snapper.snap_point(to_point(mouse_world));
+        state.guides = end.guides;
+        let end = end.value;
+        let mut shape = tool.shape(start, end, is_shift_down(), state);
+        // a frame keeps the name it was added with
+        if let (Shape::Frame { name, .. }, Some(Shape::Frame { name: added, .. })) =
+            (&mut shape, id.and_then(|id| document.get(id)).map(|element| &element.shape))
+        {
+            name.clone_from(added);
+        }
+        match id {
+            Some(id) if document.get(id).is_some_and(|element| element.shape != shape) => {
+                history.update(document, id, |element| element.shape = shape);
+            }
+            None if anchor.distance(mouse_world) * camera.zoom >= DRAG_THRESHOLD => {
+                history.begin_gesture();
+                let id = add_created(history, document, tool, shape);
+                state.selection.set(id);
+                state.shape_drag = ShapeDrag::Creating { anchor, id: Some(id) };
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    state.shape_drag = ShapeDrag::None;
+    state.guides.clear();
+    match (id, tool.click_size()) {
+        (Some(id), _) => finish_created(state, document, history, tool, id),
+        (None, Some(size)) => {
+            let corner = snapper.point(to_point(anchor));
+            let shape = tool.shape(corner, corner + size, false, state);
+            history.begin_gesture();
+            let id = add_created(history, document, tool, shape);
+            finish_created(state, document, history, tool, id);
+        }
+        (None, None) => {}
+    }
+}
+
+/// `[` and `]` cycle the arrowhead at the start and the end, for new arrows
+/// and connectors and for the selected line or connector.
+pub fn handle_arrowhead_shortcuts(state: &mut CanvasState, document: &mut Document, history: &mut History) {
+    if is_ctrl_down() {
+        return;
+    }
+    let start = is_key_pressed(KeyCode::LeftBracket);
+    let end = is_key_pressed(KeyCode::RightBracket);
+    if !start && !end {
+        return;
+    }
+    let selected_heads = state.selection.single().and_then(|id| document.get(id)).and_then(|element| match element.shape {
+        Shape::Line { start_head, end_head, .. } | Shape::Connector { start_head, end_head, .. } => {
+            Some((element.id, (start_head, end_head)))
+        }
+        _ => None,
+    });
+    let (mut start_head, mut end_head) = selected_heads.map_or(state.arrowheads, |(_, heads)| heads);
+    if start {
+        start_head = start_head.next();
+    }
+    if end {
+        end_head = end_head.next();
+    }
+    state.arrowheads = (start_head, end_head);
+    if let Some((id, _)) = selected_heads {
+        history.update(document, id, |element| {
+            if let Shape::Line {
+                start_head: s,
+                end_head: e,
+                ..
+            }
+            | Shape::Connector {
+                start_head: s,
+                end_head: e,
+                ..
+            } = &mut element.shape
+            {
+                *s = start_head;
+                *e = end_head;
+            }
+        });
+    }
+}
+
+/// Shift+H and Shift+V flip the selection horizontally and vertically,
+/// Shift+R turns it 90 degrees clockwise and Alt+Shift+R counter-clockwise.
+pub fn handle_transform_shortcuts(state: &CanvasState, document: &mut Document, history: &mut History) {
+    if is_ctrl_down() || !is_shift_down() {
+        return;
+    }
+    let transform = if is_key_pressed(KeyCode::H) {
+        Affine::scale_xy(-1.0, 1.0)
+    } else if is_key_pressed(KeyCode::V) {
+        Affine::scale_xy(1.0, -1.0)
+    } else if is_key_pressed(KeyCode::R) {
+        // y points down, so a positive angle turns clockwise on screen
+        Affine::rotate(if is_alt_down() { -FRAC_PI_2 } else { FRAC_PI_2 })
+    } else {
+        return;
+    };
+    let Some(bounds) = state.selection.bounds(document) else {
+        return;
+    };
+    history.begin_gesture();
+    history.transform(document, state.selection.ids(), &Affine::around(bounds.center(), &transform));
+    history.end_gesture(document);
+}
+
+/// Dragging draws a freehand stroke, simplified when the button is released.
+pub fn handle_pen(camera: &Camera, state: &mut CanvasState, document: &mut Document, history: &mut History) {
+    let mouse_world = to_point(mouse_world_position(camera));
+    if is_mouse_button_pressed(MouseButton::Left) {
+        let id = freehand::begin_stroke(history, document, mouse_world, None, Style::stroked(BLACK, PEN_WIDTH));
+        state.shape_drag = ShapeDrag::Drawing { id };
+    }
+    if let ShapeDrag::Drawing { id } = state.shape_drag {
+        if is_mouse_button_down(MouseButton::Left) {
+            freehand::extend_stroke(history, document, id, mouse_world, None, camera.zoom);
+        } else {
+            freehand::finish_stroke(history, document, id, camera.zoom);
+            state.shape_drag = ShapeDrag::None;
+        }
+    }
+}
+
+/// Dragging draws a loop and selects what it holds, or with Alt everything
+/// it touches, updating as it grows. Shift adds to the selection.
+pub fn handle_lasso(camera: &Camera, state: &mut CanvasState, document: &Document) {
+    let mouse_world = to_point(mouse_world_position(camera));
+    if is_mouse_button_pressed(MouseButton::Left) {
+        if !is_shift_down() {
+            state.selection.clear();
+        }
+        state.shape_drag = ShapeDrag::Lasso {
+            points: vec![mouse_world],
+            base: state.selection.ids().to_vec(),
+        };
+    }
+    if let ShapeDrag::Lasso { points, base } = &mut state.shape_drag {
+        if is_mouse_button_down(MouseButton::Left) {
+            freehand::append_point(points, mouse_world, camera.zoom);
+            let mode = match is_alt_down() {
+                true => MarqueeMode::Intersecting,
+                false => MarqueeMode::Contained,
+            };
+            state.selection.select_lasso(document, base, points, mode);
+        } else {
+            state.shape_drag = ShapeDrag::None;
+        }
+    }
+}
+
+/// Dragging erases the pen strokes under the eraser, whole or cut where it
+/// passes depending on `mode`. Each drag is one undo step.
+pub fn handle_erase(
+    camera: &Camera,
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+    mode: EraseMode,
+) {
+    let mouse_world = to_point(mouse_world_position(camera));
+    if is_mouse_button_pressed(MouseButton::Left) {
+        history.begin_gesture();
+        // the first call erases under the press itself
+        state.shape_drag = ShapeDrag::Erasing { last: mouse_world };
+        erase(history, document, mouse_world, mouse_world, ERASER_RADIUS / camera.zoom, mode);
+    }
+    if let ShapeDrag::Erasing { last } = state.shape_drag {
+        if is_mouse_button_down(MouseButton::Left) {
+            if last != mouse_world {
+                erase(history, document, last, mouse_world, ERASER_RADIUS / camera.zoom, mode);
+                state.shape_drag = ShapeDrag::Erasing { last: mouse_world };
+            }
+        } else {
+            history.end_gesture(document);
+            state.shape_drag = ShapeDrag::None;
+        }
+    }
+}
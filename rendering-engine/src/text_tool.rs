@@ -0,0 +1,350 @@
+use canvas_document::{
+    geometry::Affine,
+    style::BLACK,
+    text::{word_range, Motion, TextCursor, TextLayout},
+    CanvasSettings, Document, Element, ElementId, History, Point, Rect, Shape, Style,
+};
+use macroquad::{
+    input::{
+        is_mouse_button_down, is_mouse_button_pressed, mouse_position,
+        utils::{register_input_subscriber, repeat_all_miniquad_input},
+        KeyCode, MouseButton,
+    },
+    math::Vec2,
+    miniquad::{self, KeyMods},
+    time::get_time,
+    window::get_internal_gl,
+};
+
+use crate::{
+    camera::Camera,
+    canvas_state::{CanvasState, ShapeDrag, TextEdit},
+    document_view::to_point,
+    keyboard::is_shift_down,
+    shape_tools::{is_double_click, snapper, DRAG_THRESHOLD, PICK_TOLERANCE},
+};
+
+/// Font size of new text in screen pixels, at the zoom it is placed at.
+const NEW_TEXT_SIZE: f32 = 20.0;
+
+/// A key or character typed since the last frame.
+#[derive(Clone, Copy)]
+pub enum Typed {
+    Char(char, KeyMods),
+    Key(KeyCode, KeyMods),
+}
+
+/// Typing as it happened, in order and with key repeat, which macroquad's
+/// polling functions don't keep.
+pub struct TextInput {
+    subscriber: usize,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        TextInput {
+            subscriber: register_input_subscriber(),
+        }
+    }
+
+    /// What was typed since the last call. Has to be called every frame.
+    pub fn take(&self) -> Vec<Typed> {
+        let mut collector = Collector(Vec::new());
+        repeat_all_miniquad_input(&mut collector, self.subscriber);
+        collector.0
+    }
+}
+
+struct Collector(Vec<Typed>);
+
+impl miniquad::EventHandler for Collector {
+    fn update(&mut self, _ctx: &mut miniquad::Context) {}
+
+    fn draw(&mut self, _ctx: &mut miniquad::Context) {}
+
+    fn char_event(&mut self, _ctx: &mut miniquad::Context, character: char, keymods: KeyMods, _repeat: bool) {
+        self.0.push(Typed::Char(character, keymods));
+    }
+
+    fn key_down_event(&mut self, _ctx: &mut miniquad::Context, keycode: KeyCode, keymods: KeyMods, _repeat: bool) {
+        self.0.push(Typed::Key(keycode, keymods));
+    }
+}
+
+fn clipboard_get() -> Option<String> {
+    unsafe { get_internal_gl() }.quad_context.clipboard_get()
+}
+
+fn clipboard_set(text: &str) {
+    unsafe { get_internal_gl() }.quad_context.clipboard_set(text);
+}
+
+/// The text or sticky note `id` as laid out, with the transform from its text box to the world.
+fn text_of(document: &Document, id: ElementId) -> Option<(String, TextLayout, Affine)> {
+    let element = document.get(id)?;
+    let content = element.shape.text_content()?;
+    let block = element.shape.text_block()?;
+    let to_world = Affine::translate(block.origin).then(&element.transform);
+    Some((content.to_string(), block.layout, to_world))
+}
+
+/// Lays out `content` the way the element `id` would show it.
+fn relayout(document: &Document, id: ElementId, content: &str) -> Option<TextLayout> {
+    let mut shape = document.get(id)?.shape.clone();
+    *shape.text_content_mut()? = content.to_string();
+    shape.text_block().map(|block| block.layout)
+}
+
+/// Starts typing into the text element `id`. Everything typed until editing
+/// finishes is one undo step.
+pub fn begin_text_editing(state: &mut CanvasState, history: &mut History, id: ElementId, cursor: TextCursor) {
+    history.begin_gesture();
+    state.selection.clear();
+    state.shape_drag = ShapeDrag::None;
+    state.text_edit = Some(TextEdit {
+        id,
+        cursor,
+        selecting: false,
+        moved_at: get_time(),
+    });
+}
+
+/// Stops typing, removing text again when nothing was typed, and selects it.
+/// Empty sticky notes stay.
+pub fn finish_text_editing(state: &mut CanvasState, document: &mut Document, history: &mut History) {
+    let Some(edit) = state.text_edit.take() else {
+        return;
+    };
+    let is_text = document.get(edit.id).is_some_and(|element| matches!(element.shape, Shape::Text { .. }));
+    match text_of(document, edit.id) {
+        Some((content, ..)) if is_text && content.trim().is_empty() => {
+            history.remove(document, edit.id);
+        }
+        Some(_) => state.selection.set(edit.id),
+        None => {}
+    }
+    history.end_gesture(document);
+}
+
+/// Caret index under the mouse in the text box, if it is over it.
+fn index_under_mouse(camera: &Camera, content: &str, layout: &TextLayout, to_world: &Affine) -> Option<usize> {
+    let mouse_world = to_point(camera.screen_to_world(mouse_position().into()));
+    let local = to_world.inverse()?.apply(mouse_world);
+    let tolerance = PICK_TOLERANCE / camera.zoom;
+    let text_box = Rect::from_origin_size(Point::ZERO, layout.size).inflate(tolerance);
+    text_box.contains(local).then(|| layout.index_at(content, local))
+}
+
+/// Typing, caret movement and selection in the text being edited. Arrows
+/// move the caret, by words with Ctrl or Alt, and Shift selects. Ctrl+A
+/// selects everything, Ctrl+C, Ctrl+X and Ctrl+V go through the clipboard and
+/// Ctrl+B and Ctrl+I make the text bold or italic. Escape or a click outside
+/// of the text finishes editing.
+///
+/// Returns whether the text is still being edited, the tools leave the mouse
+/// alone then. A click that finishes editing goes on to the tools.
+pub fn handle_text_editing(
+    camera: &Camera,
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+    typed: &[Typed],
+    pointer: bool,
+) -> bool {
+    let Some(edit) = &mut state.text_edit else {
+        return false;
+    };
+    let Some((mut content, mut layout, to_world)) = text_of(document, edit.id) else {
+        // undone or deleted from elsewhere
+        state.text_edit = None;
+        return false;
+    };
+    edit.cursor.clamp(&content);
+
+    if pointer && is_mouse_button_pressed(MouseButton::Left) {
+        let Some(index) = index_under_mouse(camera, &content, &layout, &to_world) else {
+            finish_text_editing(state, document, history);
+            return false;
+        };
+        if is_double_click(state) {
+            let edit = state.text_edit.as_mut().unwrap();
+            edit.cursor.select(word_range(&content, index));
+            edit.moved_at = get_time();
+            return true;
+        }
+        let edit = state.text_edit.as_mut().unwrap();
+        edit.cursor.set(index, is_shift_down());
+        edit.selecting = true;
+        edit.moved_at = get_time();
+        return true;
+    }
+    let edit = state.text_edit.as_mut().unwrap();
+    if edit.selecting {
+        if !is_mouse_button_down(MouseButton::Left) {
+            edit.selecting = false;
+        } else if let Some(index) = index_under_mouse(camera, &content, &layout, &to_world) {
+            edit.cursor.set(index, true);
+        }
+    }
+
+    let original = content.clone();
+    let (mut finished, mut toggle_bold, mut toggle_italic) = (false, false, false);
+    for &event in typed {
+        let cursor = &mut edit.cursor;
+        match event {
+            // shortcuts come through as characters too
+            Typed::Char(c, mods) if !c.is_control() && !mods.ctrl && !mods.logo => {
+                cursor.insert(&mut content, c.encode_utf8(&mut [0; 4]));
+            }
+            Typed::Char(..) => continue,
+            Typed::Key(key, mods) => {
+                let command = mods.ctrl || mods.logo;
+                let by_word = command || mods.alt;
+                let motion = match key {
+                    KeyCode::Left if by_word => Some(Motion::WordLeft),
+                    KeyCode::Left => Some(Motion::Left),
+                    KeyCode::Right if by_word => Some(Motion::WordRight),
+                    KeyCode::Right => Some(Motion::Right),
+                    KeyCode::Up => Some(Motion::Up),
+                    KeyCode::Down => Some(Motion::Down),
+                    KeyCode::Home if command => Some(Motion::Start),
+                    KeyCode::Home => Some(Motion::LineStart),
+                    KeyCode::End if command => Some(Motion::End),
+                    KeyCode::End => Some(Motion::LineEnd),
+                    _ => None,
+                };
+                if let Some(motion) = motion {
+                    cursor.move_caret(&content, &layout, motion, mods.shift);
+                } else {
+                    match key {
+                        KeyCode::Backspace => {
+                            let motion = if by_word { Motion::WordLeft } else { Motion::Left };
+                            cursor.delete(&mut content, &layout, motion);
+                        }
+                        KeyCode::Delete => {
+                            let motion = if by_word { Motion::WordRight } else { Motion::Right };
+                            cursor.delete(&mut content, &layout, motion);
+                        }
+                        KeyCode::Enter | KeyCode::KpEnter => cursor.insert(&mut content, "\n"),
+                        KeyCode::Escape => finished = true,
+                        KeyCode::A if command => *cursor = TextCursor::all(&content),
+                        KeyCode::B if command => toggle_bold = !toggle_bold,
+                        KeyCode::I if command => toggle_italic = !toggle_italic,
+                        KeyCode::C | KeyCode::X if command && cursor.has_selection() => {
+                            clipboard_set(cursor.selected(&content));
+                            if key == KeyCode::X {
+                                cursor.insert(&mut content, "");
+                            }
+                        }
+                        KeyCode::V if command => {
+                            if let Some(pasted) = clipboard_get() {
+                                cursor.insert(&mut content, &pasted.replace("\r\n", "\n"));
+                            }
+                        }
+                        _ => continue,
+                    }
+                }
+            }
+        }
+        edit.moved_at = get_time();
+        if content != original {
+            layout = relayout(document, edit.id, &content).unwrap_or(layout);
+        }
+    }
+    if content != original || toggle_bold || toggle_italic {
+        history.update(document, edit.id, |element| {
+            if let Some(text) = element.shape.text_content_mut() {
+                *text = content;
+            }
+            // notes size their own text
+            if let Shape::Text { font, .. } = &mut element.shape {
+                font.bold ^= toggle_bold;
+                font.italic ^= toggle_italic;
+            }
+        });
+    }
+    if finished {
+        finish_text_editing(state, document, history);
+    }
+    true
+}
+
+/// Clicking on text edits it, clicking anywhere else starts a new text that
+/// grows with what is typed, and dragging sets the width new text wraps at.
+pub fn handle_text(
+    camera: &Camera,
+    settings: &CanvasSettings,
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+) {
+    let mouse_world: Vec2 = camera.screen_to_world(mouse_position().into());
+    if is_mouse_button_pressed(MouseButton::Left) {
+        let p = to_point(mouse_world);
+        let hit = state
+            .selection
+            .element_at(document, p, PICK_TOLERANCE / camera.zoom)
+            .filter(|&id| text_of(document, id).is_some());
+        if let Some(id) = hit {
+            let index = text_of(document, id)
+                .and_then(|(content, layout, to_world)| index_under_mouse(camera, &content, &layout, &to_world));
+            begin_text_editing(state, history, id, TextCursor::new(index.unwrap_or(0)));
+            return;
+        }
+        state.shape_drag = ShapeDrag::Creating {
+            anchor: mouse_world,
+            id: None,
+        };
+    }
+    let ShapeDrag::Creating { anchor, .. } = state.shape_drag else {
+        return;
+    };
+    if is_mouse_button_down(MouseButton::Left) {
+        return;
+    }
+    state.shape_drag = ShapeDrag::None;
+    let snapper = snapper(camera, settings, document, &[]);
+    let start = snapper.point(to_point(anchor));
+    let end = snapper.point(to_point(mouse_world));
+    let width = ((end.x - start.x).abs() * camera.zoom >= DRAG_THRESHOLD).then(|| (end.x - start.x).abs());
+    let position = canvas_document::point(start.x.min(end.x), start.y);
+    let mut shape = Shape::text(position, "", NEW_TEXT_SIZE / camera.zoom);
+    if let Shape::Text { width: wrap, .. } = &mut shape {
+        *wrap = width;
+    }
+    history.begin_gesture();
+    let id = history.add(document, shape, Style::filled(BLACK));
+    begin_text_editing(state, history, id, TextCursor::new(0));
+}
+
+/// Double-clicking a text element or sticky note in select mode edits it at the click.
+pub fn edit_text_at_mouse(
+    camera: &Camera,
+    state: &mut CanvasState,
+    document: &Document,
+    history: &mut History,
+    id: ElementId,
+) -> bool {
+    let Some((content, layout, to_world)) = text_of(document, id) else {
+        return false;
+    };
+    let index = index_under_mouse(camera, &content, &layout, &to_world).unwrap_or(content.len());
+    begin_text_editing(state, history, id, TextCursor::new(index));
+    true
+}
+
+/// The text being edited, or else the selected text elements, for the text
+/// controls in the toolbar.
+pub fn text_targets(state: &CanvasState, document: &Document) -> Vec<ElementId> {
+    if let Some(edit) = &state.text_edit {
+        return vec![edit.id];
+    }
+    let is_text = |element: &Element| matches!(element.shape, Shape::Text { .. });
+    state
+        .selection
+        .ids()
+        .iter()
+        .copied()
+        .filter(|&id| document.get(id).is_some_and(is_text))
+        .collect()
+}
@@ -0,0 +1,288 @@
+use canvas_document::align::{align, distribute, tidy_up, Alignment, Distribution};
+use canvas_document::connector::Routing;
+use canvas_document::sticky::StickyColor;
+use canvas_document::text::{Font, FontFamily, TextAlign};
+use canvas_document::{Document, ElementId, History, Shape};
+use egui_macroquad::egui;
+use macroquad::input::{is_key_pressed, KeyCode};
+
+use crate::canvas_state::CanvasState;
+use crate::keyboard::{is_alt_down, is_ctrl_down, is_shift_down};
+use crate::text_tool::text_targets;
+
+const ALIGNMENTS: [(Alignment, &str, &str); 6] = [
+    (Alignment::Left, "⇤", "Align left (Alt+A)"),
+    (Alignment::Center, "↔", "Align centers horizontally (Alt+H)"),
+    (Alignment::Right, "⇥", "Align right (Alt+D)"),
+    (Alignment::Top, "⤒", "Align top (Alt+W)"),
+    (Alignment::Middle, "↕", "Align centers vertically (Alt+V)"),
+    (Alignment::Bottom, "⤓", "Align bottom (Alt+S)"),
+];
+
+const TEXT_ALIGNMENTS: [(TextAlign, &str, &str); 3] = [
+    (TextAlign::Left, "L", "Align text left"),
+    (TextAlign::Center, "C", "Center text"),
+    (TextAlign::Right, "R", "Align text right"),
+];
+
+/// Sizes offered for text, in world units.
+const FONT_SIZES: [f32; 10] = [10.0, 12.0, 14.0, 16.0, 20.0, 24.0, 32.0, 48.0, 64.0, 96.0];
+
+/// Buttons laying out the selection, enabled once enough is selected, the
+/// font of the selected or edited text, the color of sticky notes and the
+/// routing of selected connectors.
+pub fn show_toolbar(ctx: &egui::Context, state: &mut CanvasState, document: &mut Document, history: &mut History) {
+    let selected = state.selection.ids().to_vec();
+    let selected = selected.as_slice();
+    egui::Window::new("Arrange")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 8.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(selected.len() > 1, |ui| {
+                    for (alignment, icon, hint) in ALIGNMENTS {
+                        if ui.button(icon).on_hover_text(hint).clicked() {
+                            align(history, document, selected, alignment);
+                        }
+                    }
+                });
+                ui.separator();
+                ui.add_enabled_ui(selected.len() > 2, |ui| {
+                    if ui.button("⋯").on_hover_text("Distribute horizontally (Ctrl+Alt+H)").clicked() {
+                        distribute(history, document, selected, Distribution::Horizontal);
+                    }
+                    if ui.button("⋮").on_hover_text("Distribute vertically (Ctrl+Alt+V)").clicked() {
+                        distribute(history, document, selected, Distribution::Vertical);
+                    }
+                });
+                ui.add_enabled_ui(selected.len() > 1, |ui| {
+                    if ui.button("▦").on_hover_text("Tidy up into a grid (Ctrl+Alt+T)").clicked() {
+                        tidy_up(history, document, selected);
+                    }
+                });
+                let texts = text_targets(state, document);
+                if !texts.is_empty() {
+                    ui.separator();
+                    text_controls(ui, document, history, &texts);
+                }
+                let connectors: Vec<ElementId> = selected
+                    .iter()
+                    .copied()
+                    .filter(|&id| document.get(id).is_some_and(|e| matches!(e.shape, Shape::Connector { .. })))
+                    .collect();
+                if !connectors.is_empty() {
+                    ui.separator();
+                    routing_controls(ui, state, document, history, &connectors);
+                }
+                let stickies = sticky_targets(state, document);
+                if !stickies.is_empty() {
+                    ui.separator();
+                    sticky_controls(ui, state, document, history, &stickies);
+                }
+            });
+        });
+}
+
+/// Font, size, style, alignment and wrapping of `texts`, showing the first one.
+fn text_controls(ui: &mut egui::Ui, document: &mut Document, history: &mut History, texts: &[ElementId]) {
+    let Some(Shape::Text { font, align, width, .. }) = document.get(texts[0]).map(|element| element.shape.clone())
+    else {
+        return;
+    };
+    let mut changed = font;
+    egui::ComboBox::from_id_source("font family")
+        .selected_text(font.family.name())
+        .width(64.0)
+        .show_ui(ui, |ui| {
+            for family in FontFamily::ALL {
+                ui.selectable_value(&mut changed.family, family, family.name());
+            }
+        });
+    egui::ComboBox::from_id_source("font size")
+        .selected_text(format!("{}", font.size.round()))
+        .width(48.0)
+        .show_ui(ui, |ui| {
+            for size in FONT_SIZES {
+                ui.selectable_value(&mut changed.size, size, format!("{size}"));
+            }
+        });
+    ui.toggle_value(&mut changed.bold, egui::RichText::new("B").strong())
+        .on_hover_text("Bold (Ctrl+B)");
+    ui.toggle_value(&mut changed.italic, egui::RichText::new("I").italics())
+        .on_hover_text("Italic (Ctrl+I)");
+    if changed != font {
+        restyle_text(history, document, texts, |font, _, _| {
+            // only what was just changed, the texts may differ otherwise
+            if changed.family != font.family {
+                font.family = changed.family;
+            }
+            if changed.size != font.size {
+                font.size = changed.size;
+            }
+            if changed.bold != font.bold {
+                font.bold = changed.bold;
+            }
+            if changed.italic != font.italic {
+                font.italic = changed.italic;
+            }
+        });
+    }
+
+    let mut new_align = align;
+    for (value, icon, hint) in TEXT_ALIGNMENTS {
+        ui.selectable_value(&mut new_align, value, icon).on_hover_text(hint);
+    }
+    if new_align != align {
+        restyle_text(history, document, texts, |_, align, _| *align = new_align);
+    }
+    let mut wraps = width.is_some();
+    if ui.toggle_value(&mut wraps, "↩").on_hover_text("Wrap at a fixed width").changed() {
+        let widths: Vec<f32> = texts
+            .iter()
+            .map(|&id| document.get(id).map_or(0.0, |element| element.shape.bounds().width()))
+            .collect();
+        let mut widths = widths.into_iter();
+        restyle_text(history, document, texts, |font, _, width| {
+            let current = widths.next().unwrap_or(0.0);
+            *width = wraps.then(|| current.max(font.size));
+        });
+    }
+}
+
+/// Routing of `connectors`, showing the first one. The choice also goes to
+/// the connectors drawn next.
+fn routing_controls(
+    ui: &mut egui::Ui,
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+    connectors: &[ElementId],
+) {
+    let Some(Shape::Connector { routing, .. }) = document.get(connectors[0]).map(|element| &element.shape) else {
+        return;
+    };
+    let mut new_routing = *routing;
+    for value in Routing::ALL {
+        ui.selectable_value(&mut new_routing, value, value.name());
+    }
+    if new_routing == *routing {
+        return;
+    }
+    state.routing = new_routing;
+    history.step(document, |history, document| {
+        for &id in connectors {
+            history.update(document, id, |element| {
+                if let Shape::Connector { routing, .. } = &mut element.shape {
+                    *routing = new_routing;
+                }
+            });
+        }
+    });
+}
+
+/// The sticky note being typed on, or else the selected ones.
+fn sticky_targets(state: &CanvasState, document: &Document) -> Vec<ElementId> {
+    let editing = state.text_edit.as_ref().map(|edit| vec![edit.id]);
+    editing
+        .unwrap_or_else(|| state.selection.ids().to_vec())
+        .into_iter()
+        .filter(|&id| document.get(id).is_some_and(|e| matches!(e.shape, Shape::Sticky { .. })))
+        .collect()
+}
+
+/// Palette recoloring `stickies`, showing the color of the first one. The
+/// choice also goes to the notes placed next.
+fn sticky_controls(
+    ui: &mut egui::Ui,
+    state: &mut CanvasState,
+    document: &mut Document,
+    history: &mut History,
+    stickies: &[ElementId],
+) {
+    let Some(Shape::Sticky { color, .. }) = document.get(stickies[0]).map(|element| &element.shape) else {
+        return;
+    };
+    let current = *color;
+    let mut new_color = current;
+    for value in StickyColor::ALL {
+        let c = value.color();
+        let swatch = egui::Color32::from_rgb((c.r * 255.0) as u8, (c.g * 255.0) as u8, (c.b * 255.0) as u8);
+        let stroke = match value == current {
+            true => egui::Stroke::new(2.0, egui::Color32::DARK_GRAY),
+            false => egui::Stroke::new(1.0, egui::Color32::GRAY),
+        };
+        let button = egui::Button::new("  ").fill(swatch).stroke(stroke);
+        if ui.add(button).on_hover_text(value.name()).clicked() {
+            new_color = value;
+        }
+    }
+    if new_color == current {
+        return;
+    }
+    state.sticky_color = new_color;
+    history.step(document, |history, document| {
+        for &id in stickies {
+            history.update(document, id, |element| {
+                if let Shape::Sticky { color, .. } = &mut element.shape {
+                    *color = new_color;
+                }
+            });
+        }
+    });
+}
+
+/// Changes the font, alignment and wrap width of every text as one undo step,
+/// or as part of the editing going on.
+fn restyle_text(
+    history: &mut History,
+    document: &mut Document,
+    texts: &[ElementId],
+    mut restyle: impl FnMut(&mut Font, &mut TextAlign, &mut Option<f32>),
+) {
+    history.step(document, |history, document| {
+        for &id in texts {
+            history.update(document, id, |element| {
+                if let Shape::Text { font, align, width, .. } = &mut element.shape {
+                    restyle(font, align, width);
+                }
+            });
+        }
+    });
+}
+
+/// Alt with A, H, D, W, V or S aligns the selection left, to the horizontal
+/// center, right, top, vertical middle or bottom. Ctrl+Alt+H and Ctrl+Alt+V
+/// distribute it and Ctrl+Alt+T tidies it up into a grid.
+pub fn handle_align_shortcuts(state: &CanvasState, document: &mut Document, history: &mut History) {
+    if !is_alt_down() || is_shift_down() || state.selection.ids().len() < 2 {
+        return;
+    }
+    let selected = state.selection.ids();
+    if is_ctrl_down() {
+        if is_key_pressed(KeyCode::H) {
+            distribute(history, document, selected, Distribution::Horizontal);
+        } else if is_key_pressed(KeyCode::V) {
+            distribute(history, document, selected, Distribution::Vertical);
+        } else if is_key_pressed(KeyCode::T) {
+            tidy_up(history, document, selected);
+        }
+        return;
+    }
+    let alignment = if is_key_pressed(KeyCode::A) {
+        Alignment::Left
+    } else if is_key_pressed(KeyCode::H) {
+        Alignment::Center
+    } else if is_key_pressed(KeyCode::D) {
+        Alignment::Right
+    } else if is_key_pressed(KeyCode::W) {
+        Alignment::Top
+    } else if is_key_pressed(KeyCode::V) {
+        Alignment::Middle
+    } else if is_key_pressed(KeyCode::S) {
+        Alignment::Bottom
+    } else {
+        return;
+    };
+    align(history, document, selected, alignment);
+}
@@ -1,20 +1,106 @@
-use macroquad::{input::{is_mouse_button_down, mouse_position, MouseButton}, math::Vec2};
+use canvas_document::{eraser::EraseMode, CanvasSettings, Document, History};
+use macroquad::{input::{is_key_pressed, is_mouse_button_down, mouse_position, KeyCode, MouseButton}, math::Vec2};
 
-use crate::{camera::Camera, canvas_state::CanvasState};
+use crate::{camera::Camera, canvas_state::CanvasState, keyboard::{is_alt_down, is_ctrl_down, is_shift_down}, connector_tool::handle_connect, shape_tools::{handle_create, handle_erase, handle_lasso, handle_pen, handle_select, CreateTool}, text_tool::handle_text};
 
 
 pub enum UserActionMode {
     /// user is dragging canvas background
-    DRAG
+    Drag,
+    /// user is picking, moving and resizing shapes
+    Select,
+    /// dragging a loop selects what it holds
+    Lasso,
+    /// dragging draws a rectangle, clicking places one of the default size
+    Rectangle,
+    /// dragging draws an ellipse, clicking places one of the default size
+    Ellipse,
+    /// dragging draws a straight line
+    Line,
+    /// dragging draws a line with arrowheads
+    Arrow,
+    /// dragging links two elements with a connector that follows them
+    Connector,
+    /// dragging draws freehand strokes
+    Pen,
+    /// dragging erases pen strokes, whole or only where the eraser passes
+    Eraser(EraseMode),
+    /// clicking places or edits text, dragging sets the width it wraps at
+    Text,
+    /// clicking places a sticky note to type on, dragging sizes it
+    Sticky,
+    /// dragging draws a frame around what it should hold, clicking places one of the default size
+    Frame,
 }
 
-pub fn observe_user_action(camera: &mut Camera, mode: &UserActionMode, mut state: CanvasState) -> CanvasState {
+/// H pans, V selects, Q selects with a lasso, R and O draw rectangles and ellipses, L lines, A arrows,
+/// C connectors, P draws, E erases, pressed again only where the eraser passes,
+/// T writes text, S places sticky notes and F frames.
+/// With Shift the letters flip and rotate instead, see `handle_transform_shortcuts`,
+/// and with Alt they align, see `handle_align_shortcuts`.
+/// The mode stays put while the mouse is held, so the tool that started a drag also finishes it.
+pub fn handle_mode_shortcuts(mode: &mut UserActionMode) {
+    if is_ctrl_down() || is_shift_down() || is_alt_down() || is_mouse_button_down(MouseButton::Left) {
+        return;
+    }
+    if is_key_pressed(KeyCode::H) {
+        *mode = UserActionMode::Drag;
+    } else if is_key_pressed(KeyCode::V) {
+        *mode = UserActionMode::Select;
+    } else if is_key_pressed(KeyCode::Q) {
+        *mode = UserActionMode::Lasso;
+    } else if is_key_pressed(KeyCode::R) {
+        *mode = UserActionMode::Rectangle;
+    } else if is_key_pressed(KeyCode::O) {
+        *mode = UserActionMode::Ellipse;
+    } else if is_key_pressed(KeyCode::L) {
+        *mode = UserActionMode::Line;
+    } else if is_key_pressed(KeyCode::A) {
+        *mode = UserActionMode::Arrow;
+    } else if is_key_pressed(KeyCode::C) {
+        *mode = UserActionMode::Connector;
+    } else if is_key_pressed(KeyCode::P) {
+        *mode = UserActionMode::Pen;
+    } else if is_key_pressed(KeyCode::E) {
+        *mode = match mode {
+            UserActionMode::Eraser(EraseMode::Stroke) => UserActionMode::Eraser(EraseMode::Precise),
+            _ => UserActionMode::Eraser(EraseMode::Stroke),
+        };
+    } else if is_key_pressed(KeyCode::T) {
+        *mode = UserActionMode::Text;
+    } else if is_key_pressed(KeyCode::S) {
+        *mode = UserActionMode::Sticky;
+    } else if is_key_pressed(KeyCode::F) {
+        *mode = UserActionMode::Frame;
+    }
+}
+
+pub fn observe_user_action(
+    camera: &mut Camera,
+    mode: &UserActionMode,
+    settings: &CanvasSettings,
+    mut state: CanvasState,
+    document: &mut Document,
+    history: &mut History,
+) -> CanvasState {
     match mode {
-        UserActionMode::DRAG => {
+        UserActionMode::Drag => {
             let (is_dragging, last_mouse_position) = handle_dragging(camera, state.is_dragging, state.last_mouse_position);
             state.is_dragging = is_dragging;
             state.last_mouse_position = last_mouse_position;
         }
+        UserActionMode::Select => handle_select(camera, settings, &mut state, document, history),
+        UserActionMode::Lasso => handle_lasso(camera, &mut state, document),
+        UserActionMode::Rectangle => handle_create(camera, settings, &mut state, document, history, CreateTool::Rectangle),
+        UserActionMode::Ellipse => handle_create(camera, settings, &mut state, document, history, CreateTool::Ellipse),
+        UserActionMode::Line => handle_create(camera, settings, &mut state, document, history, CreateTool::Line),
+        UserActionMode::Arrow => handle_create(camera, settings, &mut state, document, history, CreateTool::Arrow),
+        UserActionMode::Connector => handle_connect(camera, &mut state, document, history),
+        UserActionMode::Pen => handle_pen(camera, &mut state, document, history),
+        UserActionMode::Eraser(erase_mode) => handle_erase(camera, &mut state, document, history, *erase_mode),
+        UserActionMode::Text => handle_text(camera, settings, &mut state, document, history),
+        UserActionMode::Sticky => handle_create(camera, settings, &mut state, document, history, CreateTool::Sticky),
+        UserActionMode::Frame => handle_create(camera, settings, &mut state, document, history, CreateTool::Frame),
     }
     state
 }
@@ -33,4 +119,4 @@ pub fn handle_dragging(camera: &mut Camera, mut is_dragging: bool, mut last_mous
         is_dragging = false;
     }
     (is_dragging, last_mouse_position)
-}
\ No newline at end of file
+}
@@ -1,37 +1,294 @@
-use std::rc::Rc;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 
+use canvas_document::{
+    decode::DecodeError,
+    eraser::{erase, EraseMode},
+    format::FormatError,
+    freehand,
+    image::add_image,
+    point,
+    render::{Path, Stroke},
+    selection::{MarqueeMode, Selection},
+    style::BLACK,
+    svg::{self, ExportArea, SvgOptions},
+    scene::draw_board,
+    CanvasSettings, Color, Document, ElementId, History, Point, Renderer, SavedCanvas, Style,
+};
 use web_sys::CanvasRenderingContext2d;
 
-use crate::mouse::MouseInfo;
+use crate::{camera::Camera, mouse::MouseInfo, render::Canvas2dRenderer};
+
+/// Width of pen strokes in world units, so they grow and shrink with zoom.
+const STROKE_WIDTH: f32 = 2.0;
+/// How far from an element a click still picks it, in screen pixels.
+const PICK_TOLERANCE: f32 = 4.0;
+/// Radius of the eraser in screen pixels.
+const ERASER_RADIUS: f32 = 8.0;
+/// Largest share of the canvas a newly placed image covers.
+const MAX_IMAGE_SHARE: f32 = 0.8;
+/// Outlines of selected elements and the lasso.
+const SELECTION_COLOR: Color = Color::new(0.9, 0.16, 0.22, 1.0);
+
+/// What dragging on the canvas does.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Pen,
+    /// erases strokes, whole or only where the eraser passes
+    Eraser(EraseMode),
+    /// draws a loop selecting what it holds, or everything it touches
+    Lasso(MarqueeMode),
+}
 
 
 
 pub(crate) struct State {
     pub context: Rc<CanvasRenderingContext2d>,
-    pub mouse_info: Rc<MouseInfo>
+    renderer: RefCell<Canvas2dRenderer>,
+    pub mouse_info: Rc<MouseInfo>,
+    pub document: RefCell<Document>,
+    pub settings: RefCell<CanvasSettings>,
+    pub history: RefCell<History>,
+    pub camera: RefCell<Camera>,
+    /// space is held, dragging pans instead of drawing
+    pub space_held: Cell<bool>,
+    /// what dragging does, changed through `set_tool`
+    tool: Cell<Tool>,
+    pub selection: RefCell<Selection>,
+    /// stroke currently being drawn, if the pointer is held down
+    active_stroke: Cell<Option<ElementId>>,
+    /// last world position of the eraser while erasing
+    erase_anchor: Cell<Option<Point>>,
+    /// world points of the lasso being drawn, empty while there is none
+    lasso: RefCell<Vec<Point>>,
+    /// last screen position while panning
+    pan_anchor: Cell<Option<Point>>,
 }
 impl State {
     pub fn new(context: Rc<CanvasRenderingContext2d>) -> State {
         State {
+            renderer: RefCell::new(Canvas2dRenderer::new(Rc::clone(&context))),
             context,
-            mouse_info: Rc::new(MouseInfo::new())
+            mouse_info: Rc::new(MouseInfo::new()),
+            document: RefCell::new(Document::new()),
+            settings: RefCell::new(CanvasSettings::default()),
+            history: RefCell::new(History::default()),
+            camera: RefCell::new(Camera::new()),
+            space_held: Cell::new(false),
+            tool: Cell::new(Tool::Pen),
+            selection: RefCell::new(Selection::new()),
+            active_stroke: Cell::new(None),
+            erase_anchor: Cell::new(None),
+            lasso: RefCell::new(Vec::new()),
+            pan_anchor: Cell::new(None),
+        }
+    }
+
+    fn mouse_screen_position(&self) -> Point {
+        point(
+            *self.mouse_info.x_coord.borrow() as f32,
+            *self.mouse_info.y_coord.borrow() as f32,
+        )
+    }
+
+    /// Drags the canvas along with the mouse.
+    pub fn pan_with_mouse(&self) {
+        let mouse = self.mouse_screen_position();
+        if let Some(anchor) = self.pan_anchor.get() {
+            self.camera.borrow_mut().pan_by_screen(mouse - anchor);
+        }
+        self.pan_anchor.set(Some(mouse));
+    }
+
+    pub fn end_pan(&self) {
+        self.pan_anchor.set(None);
+    }
+
+    /// Appends the current pointer position to the stroke being drawn, starting one if needed.
+    /// Points are stored in world space so the stroke stays put when panning and zooming.
+    /// Pen strokes also record the pressure, which varies their width.
+    pub fn extend_stroke(&self) {
+        let world = self.camera.borrow().screen_to_world(self.mouse_screen_position());
+        let pressure = *self.mouse_info.pressure.borrow();
+        let zoom = self.camera.borrow().zoom;
+        let mut document = self.document.borrow_mut();
+        let mut history = self.history.borrow_mut();
+
+        match self.active_stroke.get() {
+            Some(id) => freehand::extend_stroke(&mut history, &mut document, id, world, pressure, zoom),
+            None => {
+                // the whole stroke is undone in one step
+                let style = Style::stroked(BLACK, STROKE_WIDTH);
+                let id = freehand::begin_stroke(&mut history, &mut document, world, pressure, style);
+                self.active_stroke.set(Some(id));
+            }
+        }
+    }
+
+    pub fn end_stroke(&self) {
+        if let Some(id) = self.active_stroke.take() {
+            let zoom = self.camera.borrow().zoom;
+            freehand::finish_stroke(&mut self.history.borrow_mut(), &mut self.document.borrow_mut(), id, zoom);
+        }
+    }
+
+    /// Erases along the way the pointer moved since the last frame, the
+    /// whole drag is undone in one step.
+    pub fn erase_with_mouse(&self, mode: EraseMode) {
+        let (world, radius) = {
+            let camera = self.camera.borrow();
+            (camera.screen_to_world(self.mouse_screen_position()), ERASER_RADIUS / camera.zoom)
+        };
+        let mut document = self.document.borrow_mut();
+        let mut history = self.history.borrow_mut();
+        let from = self.erase_anchor.get().unwrap_or_else(|| {
+            history.begin_gesture();
+            world
+        });
+        erase(&mut history, &mut document, from, world, radius, mode);
+        self.erase_anchor.set(Some(world));
+    }
+
+    pub fn end_erase(&self) {
+        if self.erase_anchor.take().is_some() {
+            self.history.borrow_mut().end_gesture(&self.document.borrow());
+        }
+    }
+
+    /// Extends the lasso to the pointer and selects what it picks so far.
+    pub fn lasso_with_mouse(&self, mode: MarqueeMode) {
+        let (world, zoom) = {
+            let camera = self.camera.borrow();
+            (camera.screen_to_world(self.mouse_screen_position()), camera.zoom)
+        };
+        let mut lasso = self.lasso.borrow_mut();
+        freehand::append_point(&mut lasso, world, zoom);
+        self.selection.borrow_mut().select_lasso(&self.document.borrow(), &[], &lasso, mode);
+    }
+
+    pub fn end_lasso(&self) {
+        self.lasso.borrow_mut().clear();
+    }
+
+    pub fn tool(&self) -> Tool {
+        self.tool.get()
+    }
+
+    /// Finishes whatever the current tool is doing before switching, so a
+    /// stroke or erase in progress is its own undo step.
+    pub fn set_tool(&self, tool: Tool) {
+        self.end_stroke();
+        self.end_erase();
+        self.end_lasso();
+        self.tool.set(tool);
+    }
+
+    /// Selected elements that still exist, undo may have removed some.
+    pub fn selected(&self) -> Vec<ElementId> {
+        let document = self.document.borrow();
+        let selection = self.selection.borrow();
+        selection.ids().iter().copied().filter(|&id| document.get(id).is_some()).collect()
+    }
+
+    pub fn undo(&self) {
+        self.end_stroke();
+        self.end_erase();
+        self.history.borrow_mut().undo(&mut self.document.borrow_mut());
+    }
+
+    pub fn redo(&self) {
+        self.end_stroke();
+        self.end_erase();
+        self.history.borrow_mut().redo(&mut self.document.borrow_mut());
+    }
+
+    /// Runs an undoable edit, finishing the stroke being drawn or erased first.
+    pub fn edit<T>(&self, f: impl FnOnce(&mut History, &mut Document) -> T) -> T {
+        self.end_stroke();
+        self.end_erase();
+        f(&mut self.history.borrow_mut(), &mut self.document.borrow_mut())
+    }
+
+    /// Places a PNG or JPEG file centered on a screen position, one file
+    /// pixel per screen pixel unless that wouldn't fit on the canvas.
+    pub fn import_image(&self, bytes: Vec<u8>, screen_pos: Point) -> Result<ElementId, DecodeError> {
+        let (center, pixel_size, visible) = {
+            let camera = self.camera.borrow();
+            (camera.screen_to_world(screen_pos), 1.0 / camera.zoom, camera.visible_world_rect())
+        };
+        let max_size = point(visible.width(), visible.height()) * MAX_IMAGE_SHARE;
+        self.edit(|history, document| add_image(history, document, bytes, center, pixel_size, max_size))
+    }
+
+    /// Topmost visible and unlocked element under a screen position.
+    pub fn element_at(&self, screen_pos: Point) -> Option<ElementId> {
+        let camera = self.camera.borrow();
+        let world = camera.screen_to_world(screen_pos);
+        self.document.borrow().element_at(world, PICK_TOLERANCE / camera.zoom)
+    }
+
+    pub fn save(&self) -> Result<String, FormatError> {
+        SavedCanvas {
+            document: self.document.borrow().clone(),
+            camera: self.camera.borrow().state(),
+            settings: self.settings.borrow().clone(),
         }
+        .to_json()
     }
 
-    pub fn draw_line(&self) {
-        let x = *self.mouse_info.x_coord.borrow();
-        let y = *self.mouse_info.y_coord.borrow();
-        let mut last_x = self.mouse_info.last_x.borrow_mut();
-        let mut last_y = self.mouse_info.last_y.borrow_mut();
-        
-        if let (Some(lx), Some(ly)) = (*last_x, *last_y) {
-            self.context.begin_path();
-            self.context.move_to(lx, ly);
-            self.context.line_to(x, y);
-            self.context.stroke();
+    pub fn export_svg(&self, visible_only: bool, transparent: bool) -> String {
+        let area = if visible_only {
+            ExportArea::Viewport(self.camera.borrow().visible_world_rect())
+        } else {
+            ExportArea::Board
+        };
+        let options = SvgOptions {
+            area,
+            background: (!transparent).then(|| self.settings.borrow().background),
+            ..SvgOptions::default()
+        };
+        svg::export_svg(&self.document.borrow(), &options)
+    }
+
+    pub fn load(&self, json: &str) -> Result<(), FormatError> {
+        let saved = SavedCanvas::from_json(json)?;
+        self.end_stroke();
+        self.end_erase();
+        self.end_lasso();
+        self.selection.borrow_mut().clear();
+        *self.document.borrow_mut() = saved.document;
+        self.history.borrow_mut().clear();
+        *self.settings.borrow_mut() = saved.settings;
+        self.camera.borrow_mut().restore(&saved.camera);
+        Ok(())
+    }
+
+    pub fn render(&self) {
+        let canvas = self.context.canvas().unwrap();
+        let mut camera = self.camera.borrow_mut();
+        camera.set_viewport(canvas.width() as f64, canvas.height() as f64);
+        let mut renderer = self.renderer.borrow_mut();
+        let document = self.document.borrow();
+        draw_board(&mut *renderer, &document, &self.settings.borrow(), &camera.state(), camera.viewport);
+
+        // selection outlines and the lasso, one pixel wide at any zoom
+        renderer.save();
+        renderer.transform(&camera.state().view_transform(camera.viewport));
+        let thin = Stroke {
+            color: SELECTION_COLOR,
+            width: 1.0 / camera.zoom,
+        };
+        for element in self.selection.borrow().ids().iter().filter_map(|&id| document.get(id)) {
+            renderer.stroke_path(&Path::polygon(&element.outline()), &thin);
+        }
+        let lasso = self.lasso.borrow();
+        if !lasso.is_empty() {
+            let path = Path::polygon(&lasso);
+            renderer.fill_path(&path, SELECTION_COLOR.with_alpha(0.08));
+            renderer.stroke_path(&path, &thin);
         }
-        
-        *last_x = Some(x);
-        *last_y = Some(y);
+        renderer.restore();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,65 @@
+use canvas_document::{point, CameraState, Point, Rect};
+
+pub const MIN_ZOOM: f32 = 0.1;
+pub const MAX_ZOOM: f32 = 8.0;
+const ZOOM_SPEED: f32 = 0.001;
+
+/// Same camera as `rendering-engine`'s, `position` is the world point at the
+/// center of the canvas. The canvas size is stored since there is no global
+/// `screen_width()` in the browser.
+pub struct Camera {
+    pub position: Point,
+    pub zoom: f32,
+    pub viewport: Point,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Camera {
+            position: Point::ZERO,
+            zoom: 1.0,
+            viewport: Point::ZERO,
+        }
+    }
+
+    pub fn screen_to_world(&self, screen_pos: Point) -> Point {
+        (screen_pos - self.viewport * 0.5) / self.zoom + self.position
+    }
+
+    pub fn visible_world_rect(&self) -> Rect {
+        Rect::from_corners(
+            self.screen_to_world(Point::ZERO),
+            self.screen_to_world(self.viewport),
+        )
+    }
+
+    /// Zooms while keeping the world point under `screen_pos` in place.
+    pub fn zoom_around(&mut self, screen_pos: Point, wheel_delta: f32) {
+        let before = self.screen_to_world(screen_pos);
+        let factor = (-wheel_delta * ZOOM_SPEED).exp();
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let after = self.screen_to_world(screen_pos);
+        self.position += before - after;
+    }
+
+    /// Moves the view by a screen space offset, e.g. a wheel scroll or a drag.
+    pub fn pan_by_screen(&mut self, delta: Point) {
+        self.position -= delta / self.zoom;
+    }
+
+    pub fn state(&self) -> CameraState {
+        CameraState {
+            position: self.position,
+            zoom: self.zoom,
+        }
+    }
+
+    pub fn restore(&mut self, state: &CameraState) {
+        self.position = state.position;
+        self.zoom = state.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    pub fn set_viewport(&mut self, width: f64, height: f64) {
+        self.viewport = point(width as f32, height as f32);
+    }
+}
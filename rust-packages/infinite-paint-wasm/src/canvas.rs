@@ -4,6 +4,7 @@ use std::cell::Cell;
 use std::rc::Rc;
 
 #[wasm_bindgen]
+#[allow(dead_code)] // context and pressed are kept for the upcoming drawing modes
 pub struct Canvas {
     canvas: HtmlCanvasElement,
     context: Rc<CanvasRenderingContext2d>,
@@ -0,0 +1,60 @@
+use std::rc::Rc;
+
+use canvas_document::point;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{console, Blob, DragEvent, HtmlCanvasElement};
+
+use crate::appstate::State;
+
+/// The bytes of a `File` or `Blob`.
+pub async fn read_blob(blob: &Blob) -> Result<Vec<u8>, JsValue> {
+    let buffer = JsFuture::from(blob.array_buffer()).await?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+// Image files dropped on the canvas are placed where they were dropped.
+
+fn create_dragover_handler() -> Closure<dyn FnMut(DragEvent)> {
+    Closure::wrap(Box::new(move |event: DragEvent| {
+        // the browser only fires drop on targets that cancel dragover
+        event.prevent_default();
+    }) as Box<dyn FnMut(DragEvent)>)
+}
+
+fn create_drop_handler(state: Rc<State>) -> Closure<dyn FnMut(DragEvent)> {
+    Closure::wrap(Box::new(move |event: DragEvent| {
+        // instead of the browser opening the file
+        event.prevent_default();
+        let Some(files) = event.data_transfer().and_then(|data| data.files()) else {
+            return;
+        };
+        let position = point(event.offset_x() as f32, event.offset_y() as f32);
+        for file in (0..files.length()).filter_map(|index| files.get(index)) {
+            let state = state.clone();
+            spawn_local(async move {
+                let placed = read_blob(&file).await.and_then(|bytes| {
+                    state
+                        .import_image(bytes, position)
+                        .map_err(|err| JsValue::from_str(&format!("{}: {}", file.name(), err)))
+                });
+                if let Err(err) = placed {
+                    console::error_1(&err);
+                }
+            });
+        }
+    }) as Box<dyn FnMut(DragEvent)>)
+}
+
+pub fn add_drop_event_listeners(canvas: &HtmlCanvasElement, state: Rc<State>) -> Result<(), JsValue> {
+    let dragover_handler = create_dragover_handler();
+    let drop_handler = create_drop_handler(state);
+
+    canvas.add_event_listener_with_callback("dragover", dragover_handler.as_ref().unchecked_ref())?;
+    canvas.add_event_listener_with_callback("drop", drop_handler.as_ref().unchecked_ref())?;
+
+    dragover_handler.forget();
+    drop_handler.forget();
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::rc::Rc;
+
+use canvas_document::{eraser::EraseMode, selection::MarqueeMode};
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{KeyboardEvent, Window};
+
+use crate::appstate::{State, Tool};
+
+// Event Handlers for keyboard shortcuts
+
+fn create_keydown_handler(state: Rc<State>) -> Closure<dyn FnMut(KeyboardEvent)> {
+    Closure::wrap(Box::new(move |event: KeyboardEvent| {
+        if event.code() == "Space" {
+            // keep the page from scrolling while space is used for panning
+            event.prevent_default();
+            state.space_held.set(true);
+            return;
+        }
+        let ctrl = event.ctrl_key() || event.meta_key();
+        if !ctrl && !event.alt_key() {
+            // P draws, E erases strokes and pressed again only where the eraser passes,
+            // Q selects with a lasso and pressed again also what the lasso touches
+            let tool = match (event.key().to_ascii_lowercase().as_str(), state.tool()) {
+                ("p", _) => Some(Tool::Pen),
+                ("e", Tool::Eraser(EraseMode::Stroke)) => Some(Tool::Eraser(EraseMode::Precise)),
+                ("e", _) => Some(Tool::Eraser(EraseMode::Stroke)),
+                ("q", Tool::Lasso(MarqueeMode::Contained)) => Some(Tool::Lasso(MarqueeMode::Intersecting)),
+                ("q", _) => Some(Tool::Lasso(MarqueeMode::Contained)),
+                _ => None,
+            };
+            if let Some(tool) = tool {
+                state.set_tool(tool);
+            }
+        }
+        if ctrl && event.key().eq_ignore_ascii_case("z") {
+            event.prevent_default();
+            if event.shift_key() {
+                state.redo();
+            } else {
+                state.undo();
+            }
+        }
+    }) as Box<dyn FnMut(KeyboardEvent)>)
+}
+
+fn create_keyup_handler(state: Rc<State>) -> Closure<dyn FnMut(KeyboardEvent)> {
+    Closure::wrap(Box::new(move |event: KeyboardEvent| {
+        if event.code() == "Space" {
+            state.space_held.set(false);
+        }
+    }) as Box<dyn FnMut(KeyboardEvent)>)
+}
+
+/// Keys are listened for on the window since the canvas element never has focus.
+pub fn add_keyboard_event_listeners(window: &Window, state: Rc<State>) -> Result<(), JsValue> {
+    let keydown_handler = create_keydown_handler(state.clone());
+    let keyup_handler = create_keyup_handler(state);
+    window.add_event_listener_with_callback("keydown", keydown_handler.as_ref().unchecked_ref())?;
+    window.add_event_listener_with_callback("keyup", keyup_handler.as_ref().unchecked_ref())?;
+    keydown_handler.forget();
+    keyup_handler.forget();
+    Ok(())
+}
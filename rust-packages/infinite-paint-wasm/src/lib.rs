@@ -1,47 +1,315 @@
 mod utils;
 mod canvas;
 
-use mouse::add_mouse_event_listeners;
+use canvas_document::eraser::EraseMode;
+use canvas_document::selection::MarqueeMode;
+use canvas_document::layer::{self, LayerId, ZOrder};
+use canvas_document::{point, ElementId};
+use mouse::add_pointer_event_listeners;
 use wasm_bindgen::prelude::*;
-use web_sys::{window, CanvasRenderingContext2d};
+use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement};
 use std::cell::RefCell; 
 use std::rc::Rc;
 
 mod appstate;
-use appstate::State;
+use appstate::{State, Tool};
+mod camera;
 mod mouse;
+mod keyboard;
+use keyboard::add_keyboard_event_listeners;
+mod drop;
+use drop::add_drop_event_listeners;
+mod render;
 
+type AnimationFrameCallback = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
+
+thread_local! {
+    // the exported functions below need the state created in `start`
+    static STATE: RefCell<Option<Rc<State>>> = const { RefCell::new(None) };
+}
+
+fn with_state<T>(f: impl FnOnce(&State) -> Result<T, JsValue>) -> Result<T, JsValue> {
+    STATE.with(|state| match state.borrow().as_ref() {
+        Some(state) => f(state),
+        None => Err(JsValue::from_str("canvas is not initialized")),
+    })
+}
+
+/// Serializes the drawing to the versioned canvas JSON format.
+#[wasm_bindgen]
+pub fn save() -> Result<String, JsValue> {
+    with_state(|state| state.save().map_err(|err| JsValue::from_str(&err.to_string())))
+}
+
+/// SVG of the whole drawing, or only of what is on screen when `visible_only` is set.
+/// A transparent export leaves out the background color.
+#[wasm_bindgen]
+pub fn export_svg(visible_only: bool, transparent: bool) -> Result<String, JsValue> {
+    with_state(|state| Ok(state.export_svg(visible_only, transparent)))
+}
+
+/// Replaces the drawing with one previously returned by `save`.
+#[wasm_bindgen]
+pub fn load(json: &str) -> Result<(), JsValue> {
+    with_state(|state| state.load(json).map_err(|err| JsValue::from_str(&err.to_string())))
+}
+
+/// Layers bottom to top as `{ id, name, hidden, locked, active }` objects.
+#[wasm_bindgen]
+pub fn layers() -> Result<js_sys::Array, JsValue> {
+    with_state(|state| {
+        let document = state.document.borrow();
+        let layers = js_sys::Array::new();
+        for layer in document.layers() {
+            let object = js_sys::Object::new();
+            js_sys::Reflect::set(&object, &"id".into(), &js_id(layer.id.0)?.into())?;
+            js_sys::Reflect::set(&object, &"name".into(), &layer.name.as_str().into())?;
+            js_sys::Reflect::set(&object, &"hidden".into(), &layer.hidden.into())?;
+            js_sys::Reflect::set(&object, &"locked".into(), &layer.locked.into())?;
+            js_sys::Reflect::set(&object, &"active".into(), &(layer.id == document.active_layer()).into())?;
+            layers.push(&object);
+        }
+        Ok(layers)
+    })
+}
+
+/// Adds a layer above the active one, makes it active and returns its id.
+#[wasm_bindgen]
+pub fn add_layer(name: &str) -> Result<f64, JsValue> {
+    with_state(|state| js_id(state.edit(|history, document| layer::add_layer(history, document, name)).0))
+}
+
+/// Deletes a layer and everything on it. Returns false for the last layer.
+#[wasm_bindgen]
+pub fn remove_layer(id: f64) -> Result<bool, JsValue> {
+    let id = layer_id(id)?;
+    with_state(|state| Ok(state.edit(|history, document| layer::remove_layer(history, document, id))))
+}
+
+#[wasm_bindgen]
+pub fn rename_layer(id: f64, name: &str) -> Result<(), JsValue> {
+    let id = layer_id(id)?;
+    with_state(|state| {
+        state.edit(|history, document| layer::rename_layer(history, document, id, name));
+        Ok(())
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_layer_hidden(id: f64, hidden: bool) -> Result<(), JsValue> {
+    let id = layer_id(id)?;
+    with_state(|state| {
+        state.edit(|history, document| layer::set_layer_hidden(history, document, id, hidden));
+        Ok(())
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_layer_locked(id: f64, locked: bool) -> Result<(), JsValue> {
+    let id = layer_id(id)?;
+    with_state(|state| {
+        state.edit(|history, document| layer::set_layer_locked(history, document, id, locked));
+        Ok(())
+    })
+}
+
+/// Moves a layer to `index` in the bottom to top order.
+#[wasm_bindgen]
+pub fn move_layer(id: f64, index: usize) -> Result<(), JsValue> {
+    let id = layer_id(id)?;
+    with_state(|state| {
+        state.edit(|history, document| layer::move_layer(history, document, id, index));
+        Ok(())
+    })
+}
+
+/// Picks the layer new strokes go to.
+#[wasm_bindgen]
+pub fn set_active_layer(id: f64) -> Result<(), JsValue> {
+    let id = layer_id(id)?;
+    with_state(|state| {
+        state.document.borrow_mut().set_active_layer(id);
+        Ok(())
+    })
+}
+
+#[wasm_bindgen]
+pub fn bring_forward(ids: Vec<f64>) -> Result<(), JsValue> {
+    arrange(&ids, ZOrder::Forward)
+}
+
+#[wasm_bindgen]
+pub fn send_backward(ids: Vec<f64>) -> Result<(), JsValue> {
+    arrange(&ids, ZOrder::Backward)
+}
+
+#[wasm_bindgen]
+pub fn bring_to_front(ids: Vec<f64>) -> Result<(), JsValue> {
+    arrange(&ids, ZOrder::Front)
+}
+
+#[wasm_bindgen]
+pub fn send_to_back(ids: Vec<f64>) -> Result<(), JsValue> {
+    arrange(&ids, ZOrder::Back)
+}
+
+fn arrange(ids: &[f64], order: ZOrder) -> Result<(), JsValue> {
+    let ids = element_ids(ids)?;
+    with_state(|state| {
+        state.edit(|history, document| layer::arrange(history, document, &ids, order));
+        Ok(())
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_element_hidden(ids: Vec<f64>, hidden: bool) -> Result<(), JsValue> {
+    let ids = element_ids(&ids)?;
+    with_state(|state| {
+        state.edit(|history, document| layer::set_hidden(history, document, &ids, hidden));
+        Ok(())
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_element_locked(ids: Vec<f64>, locked: bool) -> Result<(), JsValue> {
+    let ids = element_ids(&ids)?;
+    with_state(|state| {
+        state.edit(|history, document| layer::set_locked(history, document, &ids, locked));
+        Ok(())
+    })
+}
+
+/// Puts elements on top of another layer.
+#[wasm_bindgen]
+pub fn move_to_layer(ids: Vec<f64>, layer: f64) -> Result<(), JsValue> {
+    let (ids, layer) = (element_ids(&ids)?, layer_id(layer)?);
+    with_state(|state| {
+        state.edit(|history, document| layer::move_to_layer(history, document, &ids, layer));
+        Ok(())
+    })
+}
+
+/// Switches what dragging does: "pen" draws, "eraser" deletes the strokes
+/// it touches and "precise-eraser" only cuts away the parts it passes over.
+/// "lasso" selects what a drawn loop holds and "touch-lasso" everything the
+/// loop touches.
+#[wasm_bindgen]
+pub fn set_tool(tool: &str) -> Result<(), JsValue> {
+    let tool = match tool {
+        "pen" => Tool::Pen,
+        "eraser" => Tool::Eraser(EraseMode::Stroke),
+        "precise-eraser" => Tool::Eraser(EraseMode::Precise),
+        "lasso" => Tool::Lasso(MarqueeMode::Contained),
+        "touch-lasso" => Tool::Lasso(MarqueeMode::Intersecting),
+        _ => return Err(JsValue::from_str(&format!("unknown tool {}", tool))),
+    };
+    with_state(|state| {
+        state.set_tool(tool);
+        Ok(())
+    })
+}
+
+/// Ids of the selected elements.
+#[wasm_bindgen]
+pub fn selection() -> Result<Vec<f64>, JsValue> {
+    with_state(|state| state.selected().into_iter().map(|id| js_id(id.0)).collect())
+}
+
+/// Places a PNG or JPEG `File` or `Blob`, from a file input or the
+/// clipboard, centered on a screen position. Resolves to the new element's id.
+#[wasm_bindgen]
+pub async fn import_image(blob: web_sys::Blob, x: f32, y: f32) -> Result<f64, JsValue> {
+    let bytes = drop::read_blob(&blob).await?;
+    with_state(|state| {
+        let id = state.import_image(bytes, point(x, y)).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        js_id(id.0)
+    })
+}
+
+/// Id of the topmost visible and unlocked element at a screen position.
+#[wasm_bindgen]
+pub fn element_at(x: f32, y: f32) -> Result<Option<f64>, JsValue> {
+    with_state(|state| state.element_at(point(x, y)).map(|id| js_id(id.0)).transpose())
+}
+
+/// Ids go to JS as numbers, which hold integers exactly up to 2^53 - 1.
+const MAX_JS_ID: u64 = (1 << 53) - 1;
+
+fn js_id(id: u64) -> Result<f64, JsValue> {
+    match id <= MAX_JS_ID {
+        true => Ok(id as f64),
+        false => Err(JsValue::from_str(&format!("id {} does not fit in a JS number", id))),
+    }
+}
+
+fn rust_id(id: f64) -> Result<u64, JsValue> {
+    match id >= 0.0 && id <= MAX_JS_ID as f64 && id.fract() == 0.0 {
+        true => Ok(id as u64),
+        false => Err(JsValue::from_str(&format!("{} is not an id", id))),
+    }
+}
+
+fn layer_id(id: f64) -> Result<LayerId, JsValue> {
+    rust_id(id).map(LayerId)
+}
+
+fn element_ids(ids: &[f64]) -> Result<Vec<ElementId>, JsValue> {
+    ids.iter().map(|&id| rust_id(id).map(ElementId)).collect()
+}
 
 #[wasm_bindgen(start)]
 fn start() -> Result<(), JsValue> { // indicates succeed w JsValue fail with no meaninful return value
+    utils::set_panic_hook();
     // initialize the canvas element
     let document = web_sys::window().unwrap().document().unwrap();
     let canvas = document
         .create_element("canvas")?
         .dyn_into::<web_sys::HtmlCanvasElement>()?;
     let _ = document.body().unwrap().append_child(&canvas);
-    canvas.set_width(500);
-    canvas.set_height(500);
-    canvas.style().set_property("border", "solid")?; // this is like a CSS portion
+    canvas.style().set_property("display", "block")?; // this is like a CSS portion
+    fit_canvas_to_window(&canvas);
+    {
+        let canvas = canvas.clone();
+        let closure = Closure::<dyn FnMut()>::new(move || fit_canvas_to_window(&canvas));
+        window().unwrap().add_event_listener_with_callback("resize", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
     let context = canvas
         .get_context("2d")?
         .unwrap()
         .dyn_into::<CanvasRenderingContext2d>()?;
 
     let state = Rc::new(State::new(context.into()));
+    STATE.with(|global| *global.borrow_mut() = Some(state.clone()));
 
-    add_mouse_event_listeners(&canvas, state.clone())?;
+    add_pointer_event_listeners(&canvas, state.clone())?;
+    add_keyboard_event_listeners(&window().unwrap(), state.clone())?;
+    add_drop_event_listeners(&canvas, state.clone())?;
 
     // Render Loop
     {
-        let f: Rc<RefCell<Option<Closure<dyn FnMut()>>>> = Rc::new(RefCell::new(None));
+        let f: AnimationFrameCallback = Rc::new(RefCell::new(None));
         let g = f.clone();
         *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
             let state = state.clone();
             if *state.mouse_info.left_click.borrow() {
                 // TODO: there will be deeper functionality here based on user action mode
-                state.draw_line();
+                if state.space_held.get() {
+                    state.pan_with_mouse();
+                } else {
+                    match state.tool() {
+                        Tool::Pen => state.extend_stroke(),
+                        Tool::Eraser(mode) => state.erase_with_mouse(mode),
+                        Tool::Lasso(mode) => state.lasso_with_mouse(mode),
+                    }
+                }
+            } else {
+                state.end_stroke();
+                state.end_erase();
+                state.end_lasso();
+                state.end_pan();
             }
+            state.render();
     
             request_animation_frame(f.borrow().as_ref().unwrap());
         }) as Box<dyn FnMut()>));
@@ -52,6 +320,15 @@ fn start() -> Result<(), JsValue> { // indicates succeed w JsValue fail with no
     Ok(())
 }
 
+/// The canvas covers the whole window, the camera makes the drawing area infinite.
+fn fit_canvas_to_window(canvas: &HtmlCanvasElement) {
+    let window = window().unwrap();
+    let width = window.inner_width().ok().and_then(|w| w.as_f64()).unwrap_or(500.0);
+    let height = window.inner_height().ok().and_then(|h| h.as_f64()).unwrap_or(500.0);
+    canvas.set_width(width as u32);
+    canvas.set_height(height as u32);
+}
+
 fn request_animation_frame(f: &Closure<dyn FnMut()>) -> i32 {
     window().unwrap().request_animation_frame(f.as_ref().unchecked_ref())
         .expect("should register `requestAnimationFrame` OK")
@@ -1,7 +1,8 @@
 use std::{cell::RefCell, rc::Rc};
 
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
-use web_sys::{HtmlCanvasElement, MouseEvent};
+use canvas_document::point;
+use web_sys::{AddEventListenerOptions, HtmlCanvasElement, PointerEvent, WheelEvent};
 
 use crate::appstate::State;
 
@@ -11,8 +12,8 @@ pub(crate) struct MouseInfo {
     pub left_click: Rc<RefCell<bool>>,
     pub x_coord: Rc<RefCell<f64>>,
     pub y_coord: Rc<RefCell<f64>>,
-    pub last_x: Rc<RefCell<Option<f64>>>,
-    pub last_y: Rc<RefCell<Option<f64>>>
+    /// pen pressure between 0 and 1, None for mice and touch which don't report a real one
+    pub pressure: Rc<RefCell<Option<f32>>>,
 }
 
 impl MouseInfo {
@@ -21,62 +22,101 @@ impl MouseInfo {
             left_click: Rc::new(RefCell::new(false)),
             x_coord: Rc::new(RefCell::new(0.0)),
             y_coord: Rc::new(RefCell::new(0.0)),
-            last_x: Rc::new(RefCell::new(None)),
-            last_y: Rc::new(RefCell::new(None)),
+            pressure: Rc::new(RefCell::new(None)),
         }
     }
 
     pub fn pressed(&self) {
         *self.left_click.borrow_mut() = true;
-        *self.last_x.borrow_mut() = None;
-        *self.last_y.borrow_mut() = None;
     }
 
     pub fn released(&self) {
         *self.left_click.borrow_mut() = false;
-        *self.last_x.borrow_mut() = None;
-        *self.last_y.borrow_mut() = None;
     }
 
     pub fn update_position(&self, x: f64, y:f64) {
         *self.x_coord.borrow_mut() = x;
         *self.y_coord.borrow_mut() = y;
     }
+
+    pub fn update_pressure(&self, pressure: Option<f32>) {
+        *self.pressure.borrow_mut() = pressure;
+    }
 }
 
-// Event Handlers for mouse actions
+// Event Handlers for pointer actions. Pointer events cover mice, pens and touch alike.
+
+/// Updates position and pressure from any pointer event.
+fn track_pointer(state: &State, event: &PointerEvent) {
+    state.mouse_info.update_position(event.offset_x() as f64, event.offset_y() as f64);
+    // mice report a fixed 0.5 while a button is down, only pens measure it
+    let pressure = (event.pointer_type() == "pen").then(|| event.pressure());
+    state.mouse_info.update_pressure(pressure);
+}
 
-fn create_mousedown_handler(state: Rc<State>) -> Closure<dyn FnMut(MouseEvent)> {
-    Closure::wrap(Box::new(move |event: MouseEvent| {
+fn create_pointerdown_handler(state: Rc<State>, canvas: HtmlCanvasElement) -> Closure<dyn FnMut(PointerEvent)> {
+    Closure::wrap(Box::new(move |event: PointerEvent| {
+        // keep receiving moves and the release when the pointer leaves the canvas
+        let _ = canvas.set_pointer_capture(event.pointer_id());
         state.mouse_info.pressed();
-        state.mouse_info.update_position(event.offset_x() as f64, event.offset_y() as f64);
-    }) as Box<dyn FnMut(MouseEvent)>)
+        track_pointer(&state, &event);
+    }) as Box<dyn FnMut(PointerEvent)>)
 }
 
-fn create_mousemove_handler(state: Rc<State>) -> Closure<dyn FnMut(MouseEvent)> {
-    Closure::wrap(Box::new(move |event: MouseEvent| {
-        state.mouse_info.update_position(event.offset_x() as f64, event.offset_y() as f64);
-    }) as Box<dyn FnMut(MouseEvent)>)
+fn create_pointermove_handler(state: Rc<State>) -> Closure<dyn FnMut(PointerEvent)> {
+    Closure::wrap(Box::new(move |event: PointerEvent| {
+        track_pointer(&state, &event);
+    }) as Box<dyn FnMut(PointerEvent)>)
 }
 
-fn create_mouseup_handler(state: Rc<State>) -> Closure<dyn FnMut(MouseEvent)> {
-    Closure::wrap(Box::new(move |_event: MouseEvent| {
+fn create_pointerup_handler(state: Rc<State>) -> Closure<dyn FnMut(PointerEvent)> {
+    Closure::wrap(Box::new(move |_event: PointerEvent| {
         state.mouse_info.released();
-    }) as Box<dyn FnMut(MouseEvent)>)
+    }) as Box<dyn FnMut(PointerEvent)>)
 }
 
-pub fn add_mouse_event_listeners(canvas: &HtmlCanvasElement, state: Rc<State>) -> Result<(), JsValue> {
-    let mousedown_handler = create_mousedown_handler(state.clone());
-    let mousemove_handler = create_mousemove_handler(state.clone());
-    let mouseup_handler = create_mouseup_handler(state.clone());
-
-    canvas.add_event_listener_with_callback("mousedown", mousedown_handler.as_ref().unchecked_ref())?;
-    canvas.add_event_listener_with_callback("mousemove", mousemove_handler.as_ref().unchecked_ref())?;
-    canvas.add_event_listener_with_callback("mouseup", mouseup_handler.as_ref().unchecked_ref())?;
+/// Ctrl + wheel (and trackpad pinch, which browsers report the same way) zooms
+/// around the cursor, the plain wheel pans.
+fn create_wheel_handler(state: Rc<State>) -> Closure<dyn FnMut(WheelEvent)> {
+    Closure::wrap(Box::new(move |event: WheelEvent| {
+        event.prevent_default();
+        let mut camera = state.camera.borrow_mut();
+        if event.ctrl_key() || event.meta_key() {
+            let cursor = point(event.offset_x() as f32, event.offset_y() as f32);
+            camera.zoom_around(cursor, event.delta_y() as f32);
+        } else {
+            camera.pan_by_screen(point(-event.delta_x() as f32, -event.delta_y() as f32));
+        }
+    }) as Box<dyn FnMut(WheelEvent)>)
+}
 
-    mousedown_handler.forget();
-    mousemove_handler.forget();
-    mouseup_handler.forget();
+pub fn add_pointer_event_listeners(canvas: &HtmlCanvasElement, state: Rc<State>) -> Result<(), JsValue> {
+    // touches would otherwise scroll and zoom the page instead of drawing
+    canvas.style().set_property("touch-action", "none")?;
+
+    let pointerdown_handler = create_pointerdown_handler(state.clone(), canvas.clone());
+    let pointermove_handler = create_pointermove_handler(state.clone());
+    let pointerup_handler = create_pointerup_handler(state.clone());
+
+    canvas.add_event_listener_with_callback("pointerdown", pointerdown_handler.as_ref().unchecked_ref())?;
+    canvas.add_event_listener_with_callback("pointermove", pointermove_handler.as_ref().unchecked_ref())?;
+    canvas.add_event_listener_with_callback("pointerup", pointerup_handler.as_ref().unchecked_ref())?;
+    canvas.add_event_listener_with_callback("pointercancel", pointerup_handler.as_ref().unchecked_ref())?;
+
+    // not passive so the page itself doesn't scroll or zoom
+    let wheel_handler = create_wheel_handler(state.clone());
+    let options = AddEventListenerOptions::new();
+    options.set_passive(false);
+    canvas.add_event_listener_with_callback_and_add_event_listener_options(
+        "wheel",
+        wheel_handler.as_ref().unchecked_ref(),
+        &options,
+    )?;
+    wheel_handler.forget();
+
+    pointerdown_handler.forget();
+    pointermove_handler.forget();
+    pointerup_handler.forget();
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    rc::Rc,
+};
+
+use canvas_document::{
+    render::{Image, Path, PathSegment, Stroke},
+    text::Font,
+    Affine, Color, Point, Rect, Renderer,
+};
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+fn css_color(color: Color) -> String {
+    format!(
+        "rgba({}, {}, {}, {})",
+        (color.r * 255.0).round(),
+        (color.g * 255.0).round(),
+        (color.b * 255.0).round(),
+        color.a
+    )
+}
+
+/// Draws onto a 2D canvas context. Transform and clip map directly onto the
+/// context's own state stack.
+pub struct Canvas2dRenderer {
+    context: Rc<CanvasRenderingContext2d>,
+    /// images uploaded to offscreen canvases, by image id
+    images: HashMap<u64, HtmlCanvasElement>,
+}
+
+impl Canvas2dRenderer {
+    pub fn new(context: Rc<CanvasRenderingContext2d>) -> Self {
+        Canvas2dRenderer {
+            context,
+            images: HashMap::new(),
+        }
+    }
+
+    /// Replaces the context's current path with `path`.
+    fn set_path(&self, path: &Path) {
+        let context = &self.context;
+        context.begin_path();
+        for segment in &path.segments {
+            match *segment {
+                PathSegment::MoveTo(p) => context.move_to(p.x as f64, p.y as f64),
+                PathSegment::LineTo(p) => context.line_to(p.x as f64, p.y as f64),
+                PathSegment::CubicTo { control1, control2, to } => context.bezier_curve_to(
+                    control1.x as f64,
+                    control1.y as f64,
+                    control2.x as f64,
+                    control2.y as f64,
+                    to.x as f64,
+                    to.y as f64,
+                ),
+                PathSegment::Close => context.close_path(),
+            }
+        }
+    }
+}
+
+impl Renderer for Canvas2dRenderer {
+    fn save(&mut self) {
+        self.context.save();
+    }
+
+    fn restore(&mut self) {
+        self.context.restore();
+    }
+
+    fn transform(&mut self, t: &Affine) {
+        let _ = self
+            .context
+            .transform(t.a as f64, t.b as f64, t.c as f64, t.d as f64, t.e as f64, t.f as f64);
+    }
+
+    fn clip_rect(&mut self, rect: Rect) {
+        self.set_path(&Path::rect(rect));
+        self.context.clip();
+    }
+
+    fn clear(&mut self, color: Color) {
+        let context = &self.context;
+        let Some(canvas) = context.canvas() else {
+            return;
+        };
+        context.save();
+        let _ = context.reset_transform();
+        context.set_fill_style_str(&css_color(color));
+        context.fill_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
+        context.restore();
+    }
+
+    fn fill_path(&mut self, path: &Path, color: Color) {
+        self.set_path(path);
+        self.context.set_fill_style_str(&css_color(color));
+        self.context.fill();
+    }
+
+    fn stroke_path(&mut self, path: &Path, stroke: &Stroke) {
+        self.set_path(path);
+        let context = &self.context;
+        context.set_line_width(stroke.width as f64);
+        context.set_line_cap("round");
+        context.set_line_join("round");
+        context.set_stroke_style_str(&css_color(stroke.color));
+        context.stroke();
+    }
+
+    fn draw_text(&mut self, text: &str, position: Point, font: &Font, color: Color) {
+        let context = &self.context;
+        context.set_fill_style_str(&css_color(color));
+        context.set_font(&font.css());
+        context.set_text_baseline("top");
+        let _ = context.fill_text(text, position.x as f64, position.y as f64);
+    }
+
+    fn draw_image(&mut self, image: &Image, dest: Rect) {
+        let canvas = match self.images.entry(image.id()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match image_canvas(image) {
+                Some(canvas) => entry.insert(canvas),
+                None => return,
+            },
+        };
+        let _ = self.context.draw_image_with_html_canvas_element_and_dw_and_dh(
+            canvas,
+            dest.min.x as f64,
+            dest.min.y as f64,
+            dest.width() as f64,
+            dest.height() as f64,
+        );
+    }
+}
+
+/// Copies the image's pixels onto a new offscreen canvas, which can be drawn
+/// with any transform.
+fn image_canvas(image: &Image) -> Option<HtmlCanvasElement> {
+    let canvas: HtmlCanvasElement = web_sys::window()?
+        .document()?
+        .create_element("canvas")
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    canvas.set_width(image.width());
+    canvas.set_height(image.height());
+    let context: CanvasRenderingContext2d = canvas.get_context("2d").ok()??.dyn_into().ok()?;
+    let data =
+        ImageData::new_with_u8_clamped_array_and_sh(Clamped(image.pixels()), image.width(), image.height()).ok()?;
+    context.put_image_data(&data, 0.0, 0.0).ok()?;
+    Some(canvas)
+}
@@ -2,16 +2,17 @@ mod utils;
 
 use wasm_bindgen::prelude::*;
 use web_sys::CanvasRenderingContext2d;
-use std::cell::{self, Cell}; // concurrency primative that allows for interior mutability
+use std::cell::Cell; // concurrency primative that allows for interior mutability
 use std::rc::Rc; // a way to have multiple owners of the same data
 
 #[wasm_bindgen(start)]
 fn start() -> Result<(), JsValue> { // indicates succeed w JsValue fail with no meaninful return value
+    utils::set_panic_hook();
     let document = web_sys::window().unwrap().document().unwrap();
     let canvas = document
         .create_element("canvas")?
         .dyn_into::<web_sys::HtmlCanvasElement>()?;
-    document.body().unwrap().append_child(&canvas);
+    document.body().unwrap().append_child(&canvas)?;
     canvas.set_width(640);
     canvas.set_height(480);
     canvas.style().set_property("border", "solid")?; // this is like a CSS portion
@@ -25,22 +26,30 @@ fn start() -> Result<(), JsValue> { // indicates succeed w JsValue fail with no
     // threaded use with Cell (RefCell is multithreadded)
     let pressed = Rc::new(Cell::new(false)); // lets you clone without moving
 
+    // touches would otherwise scroll the page instead of drawing
+    canvas.style().set_property("touch-action", "none")?;
+    context.set_line_cap("round");
+
     /* 
     This is a block expression to group related statements and declarations together.
     It creates a closure 🤢🥲 that creates a pointer to a function that performs some action
-        when a direction key is pressed.
-    An event listener with callback is added to the "mousedown" action
+        when the pointer is pressed.
+    An event listener with callback is added to the "pointerdown" action.
+    Pointer events are sent for mice, pens and touch alike.
     */
     {
         // in a closure its better to copy variables getting picked up from outside
         let context = context.clone();
         let pressed = pressed.clone();
-        let closure = Closure::<dyn FnMut(_)>::new(move |event: web_sys::MouseEvent| {
+        let canvas_ref = canvas.clone();
+        let closure = Closure::<dyn FnMut(_)>::new(move |event: web_sys::PointerEvent| {
+            // keeps the moves and the release coming when the pointer leaves the canvas
+            let _ = canvas_ref.set_pointer_capture(event.pointer_id());
             context.begin_path();
             context.move_to(event.offset_x() as f64, event.offset_y() as f64);
             pressed.set(true);
         });
-        canvas.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref())?;
+        canvas.add_event_listener_with_callback("pointerdown", closure.as_ref().unchecked_ref())?;
         closure.forget();
     }
 
@@ -49,28 +58,39 @@ fn start() -> Result<(), JsValue> { // indicates succeed w JsValue fail with no
     {
         let context = context.clone();
         let pressed = pressed.clone();
-        let closure = Closure::<dyn FnMut(_)>::new(move |event: web_sys::MouseEvent| {
+        let closure = Closure::<dyn FnMut(_)>::new(move |event: web_sys::PointerEvent| {
             if pressed.get() {
+                context.set_line_width(line_width(&event));
                 context.line_to(event.offset_x() as f64, event.offset_y() as f64);
                 context.stroke();
                 context.begin_path();
                 context.move_to(event.offset_x() as f64, event.offset_y() as f64);
             }
         });
-        canvas.add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref())?;
+        canvas.add_event_listener_with_callback("pointermove", closure.as_ref().unchecked_ref())?;
         closure.forget();
     }
 
     {
-        let closure = Closure::<dyn FnMut(_)>::new(move |event: web_sys::MouseEvent| {
-            pressed .set(false);
-            context.line_to(event.offset_x() as f64, event.offset_y() as f64);
-            context.stroke();
+        let closure = Closure::<dyn FnMut(_)>::new(move |event: web_sys::PointerEvent| {
+            if pressed.replace(false) {
+                context.line_to(event.offset_x() as f64, event.offset_y() as f64);
+                context.stroke();
+            }
         });
-        canvas.add_event_listener_with_callback("mouseup", closure.as_ref().unchecked_ref())?;
+        canvas.add_event_listener_with_callback("pointerup", closure.as_ref().unchecked_ref())?;
+        canvas.add_event_listener_with_callback("pointercancel", closure.as_ref().unchecked_ref())?;
         closure.forget();
     }
 
     Ok(())
 }
 
+/// Pens draw thicker the harder they press, mice and fingers draw a fixed width.
+fn line_width(event: &web_sys::PointerEvent) -> f64 {
+    if event.pointer_type() == "pen" {
+        1.0 + 7.0 * event.pressure() as f64
+    } else {
+        1.0
+    }
+}